@@ -0,0 +1,723 @@
+//! Patterns parsed at runtime, for when the pattern comes from the user
+//! rather than the source code.
+
+use std::fmt;
+
+use crate::{Captures, Names};
+
+/// A pattern parsed at runtime, with the same syntax as the macros.
+///
+/// Segments that refer to Rust code can't be used, so `#name` segments,
+/// guards, typed captures, presets and groups are errors. Everything else
+/// works: literals and their repetitions, `_`, byte captures, ranges, `[name]`
+/// captures anywhere (`lazy` ones too), `..`, `#ws`, runs like `digit+`,
+/// a leading `min_len(N);`,
+/// `#utf8char`, `peek(..)` and `not_ahead(..)`.
+///
+/// Where the macros generate code for a pattern, a `Pattern` is interpreted,
+/// so prefer the macros for patterns known at compile time.
+///
+/// ```rust
+/// use strmatch_runtime::Pattern;
+///
+/// let filter = Pattern::parse(r#""GET " [path] " HTTP/1." _"#).unwrap();
+/// let captures = filter.captures(b"GET /index.html HTTP/1.1").unwrap();
+/// assert_eq!(captures.name("path"), Some(&b"/index.html"[..]));
+///
+/// let error = Pattern::parse("#sep [rest]").unwrap_err();
+/// assert_eq!(error.offset(), 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+    names: Vec<String>,
+    min_len: usize,
+}
+
+/// A pattern that couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+    offset: usize,
+}
+
+impl ParseError {
+    /// Where in the pattern the problem is, in bytes.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Literals repeated past this many bytes are refused rather than allocated
+const MAX_LITERAL: usize = 1 << 20;
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Literal(Vec<u8>),
+    /// A single byte in `lo..=hi`, bound to the slot if there is one
+    Byte {
+        lo: u8,
+        hi: u8,
+        slot: Option<usize>,
+    },
+    /// A bracketed capture, trying longer matches first unless `lazy`
+    Slice {
+        slot: Option<usize>,
+        lazy: bool,
+    },
+    /// A run of bytes in a class, always taken whole
+    Run {
+        class: Class,
+        optional: bool,
+        slot: Option<usize>,
+    },
+    Utf8Char {
+        slot: Option<usize>,
+    },
+    Peek {
+        bytes: Vec<u8>,
+        negated: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Class {
+    /// One of the named classes, like `digit`
+    Named(fn(&u8) -> bool),
+    Range(u8, u8),
+}
+
+impl Class {
+    fn contains(self, byte: &u8) -> bool {
+        match self {
+            Class::Named(class) => class(byte),
+            Class::Range(lo, hi) => (lo..=hi).contains(byte),
+        }
+    }
+}
+
+impl Pattern {
+    /// Parse a pattern.
+    pub fn parse(pattern: &str) -> Result<Pattern, ParseError> {
+        let mut parser = Parser {
+            src: pattern,
+            at: 0,
+            names: vec![],
+        };
+        let min_len = parser.min_len()?;
+        let mut segments = vec![];
+        loop {
+            parser.skip_whitespace();
+            if parser.at == pattern.len() {
+                break;
+            }
+            segments.push(parser.segment()?);
+        }
+        Ok(Pattern {
+            segments,
+            names: parser.names,
+            min_len,
+        })
+    }
+
+    /// Whether the pattern matches `input`.
+    pub fn is_match(&self, input: &[u8]) -> bool {
+        let mut slots = vec![(0, 0); self.names.len()];
+        input.len() >= self.min_len
+            && matches(&self.segments, input, 0, &mut slots)
+    }
+
+    /// What the pattern's captures matched, if it matches `input`.
+    pub fn captures<'a>(&'a self, input: &'a [u8]) -> Option<Captures<'a>> {
+        let mut slots = vec![(0, 0); self.names.len()];
+        if input.len() < self.min_len
+            || !matches(&self.segments, input, 0, &mut slots)
+        {
+            return None;
+        }
+        Some(Captures {
+            names: Names::Owned(&self.names),
+            values: slots
+                .into_iter()
+                .map(|(start, end)| &input[start..end])
+                .collect(),
+        })
+    }
+
+    /// The names the pattern binds, in order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+// Whether `segments` match all of `input` from `at` on, filling in the
+// slots of the captures they bind
+fn matches(
+    segments: &[Segment],
+    input: &[u8],
+    at: usize,
+    slots: &mut [(usize, usize)],
+) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return at == input.len();
+    };
+    let left = &input[at..];
+    let mut bind = |slot: &Option<usize>, len: usize| {
+        if let Some(slot) = slot {
+            slots[*slot] = (at, at + len);
+        }
+    };
+    match segment {
+        Segment::Literal(bytes) => {
+            left.starts_with(bytes)
+                && matches(rest, input, at + bytes.len(), slots)
+        }
+        Segment::Byte { lo, hi, slot } => match left.first() {
+            Some(byte) if (lo..=hi).contains(&byte) => {
+                bind(slot, 1);
+                matches(rest, input, at + 1, slots)
+            }
+            _ => false,
+        },
+        Segment::Slice { slot, lazy } => {
+            let mut try_len = |len: usize| {
+                if let Some(slot) = slot {
+                    slots[*slot] = (at, at + len);
+                }
+                matches(rest, input, at + len, slots)
+            };
+            if *lazy {
+                (0..=left.len()).any(&mut try_len)
+            } else {
+                (0..=left.len()).rev().any(&mut try_len)
+            }
+        }
+        Segment::Run {
+            class,
+            optional,
+            slot,
+        } => {
+            let len = left
+                .iter()
+                .position(|b| !class.contains(b))
+                .unwrap_or(left.len());
+            if len == 0 && !optional {
+                return false;
+            }
+            bind(slot, len);
+            matches(rest, input, at + len, slots)
+        }
+        Segment::Utf8Char { slot } => {
+            let width = match left.first() {
+                Some(0x00..=0x7f) => 1,
+                Some(0xc2..=0xdf) => 2,
+                Some(0xe0..=0xef) => 3,
+                Some(0xf0..=0xf4) => 4,
+                _ => return false,
+            };
+            if left.len() < width
+                || std::str::from_utf8(&left[..width]).is_err()
+            {
+                return false;
+            }
+            bind(slot, width);
+            matches(rest, input, at + width, slots)
+        }
+        Segment::Peek { bytes, negated } => {
+            left.starts_with(bytes) != *negated
+                && matches(rest, input, at, slots)
+        }
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    at: usize,
+    names: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, at: usize, message: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            message: message.to_string(),
+            offset: at,
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.at..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.at += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.at += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        self.at += len;
+        Some(&self.src[self.at - len..self.at])
+    }
+
+    // `min_len(N);` at the start of the pattern, or 0 if there isn't one
+    fn min_len(&mut self) -> Result<usize, ParseError> {
+        let start = self.at;
+        if self.ident() != Some("min_len") || !self.eat("(") {
+            self.at = start;
+            return Ok(0);
+        }
+        self.skip_whitespace();
+        let at = self.at;
+        let digits = self.rest().find(|c: char| !c.is_ascii_digit());
+        let len = digits.unwrap_or(self.rest().len());
+        let min_len = self.rest()[..len].parse::<usize>();
+        self.at += len;
+        match min_len {
+            Ok(min_len) if self.eat(")") && self.eat(";") => Ok(min_len),
+            _ => self.error(at, "expected `min_len(N);`"),
+        }
+    }
+
+    fn slot(&mut self, name: &str) -> Option<usize> {
+        if name == "_" {
+            return None;
+        }
+        self.names.push(name.to_string());
+        Some(self.names.len() - 1)
+    }
+
+    fn segment(&mut self) -> Result<Segment, ParseError> {
+        let start = self.at;
+        let rest = self.rest();
+        if self.eat("#") {
+            return match self.pound(None) {
+                Some(segment) => Ok(segment),
+                None => self.error(
+                    start,
+                    "`#` segments refer to Rust values, which a runtime \
+                     pattern can't",
+                ),
+            };
+        }
+        if rest.starts_with('(') {
+            return self.error(start, "groups aren't supported at runtime");
+        }
+        if rest.starts_with('[') {
+            return self.slice();
+        }
+        if self.eat("..") {
+            return Ok(Segment::Slice {
+                slot: None,
+                lazy: false,
+            });
+        }
+        if rest.starts_with(['"', '\'', 'b', 'r'])
+            || rest.starts_with(|c: char| c.is_ascii_digit())
+        {
+            if let Some(literal) = self.literal()? {
+                return self.after_literal(literal, None, start);
+            }
+        }
+        let Some(ident) = self.ident() else {
+            return self.error(start, "expected a segment");
+        };
+        let ident = ident.to_string();
+        if let Some(segment) = self.keyword(&ident, None, start)? {
+            return Ok(segment);
+        }
+        if !self.eat("@") {
+            let slot = self.slot(&ident);
+            return Ok(Segment::Byte {
+                lo: 0,
+                hi: 255,
+                slot,
+            });
+        }
+        let slot = self.slot(&ident);
+        self.skip_whitespace();
+        let at = self.at;
+        if self.eat("..") {
+            return Ok(Segment::Slice { slot, lazy: false });
+        }
+        if let Some(literal) = self.literal()? {
+            return self.after_literal(literal, slot, at);
+        }
+        if self.eat("#") {
+            if let Some(segment) = self.pound(slot) {
+                return Ok(segment);
+            }
+            return self.error(at, "expected a segment to bind after `@`");
+        }
+        match self.ident().map(str::to_string) {
+            Some(keyword) => match self.keyword(&keyword, slot, at)? {
+                Some(segment) => Ok(segment),
+                None => self.error(at, "expected a segment to bind after `@`"),
+            },
+            None => self.error(at, "expected a segment to bind after `@`"),
+        }
+    }
+
+    // What follows a `#`, which can only be a keyword like `#utf8char`,
+    // since the rest refer to Rust values
+    fn pound(&mut self, slot: Option<usize>) -> Option<Segment> {
+        match self.ident()? {
+            "utf8char" => Some(Segment::Utf8Char { slot }),
+            "ws" => Some(Segment::Run {
+                class: Class::Named(u8::is_ascii_whitespace),
+                optional: self.eat("?"),
+                slot,
+            }),
+            _ => None,
+        }
+    }
+
+    // Runs like `digit+`, `peek(..)` and `not_ahead(..)`
+    fn keyword(
+        &mut self,
+        keyword: &str,
+        slot: Option<usize>,
+        start: usize,
+    ) -> Result<Option<Segment>, ParseError> {
+        let class: fn(&u8) -> bool = match keyword {
+            "peek" | "not_ahead" if self.eat("(") => {
+                let mut bytes = vec![];
+                while !self.eat(")") {
+                    self.skip_whitespace();
+                    match self.literal()? {
+                        Some(literal) => bytes.extend(literal),
+                        None => {
+                            return self.error(
+                                self.at,
+                                "lookahead only takes literals",
+                            )
+                        }
+                    }
+                }
+                return Ok(Some(Segment::Peek {
+                    bytes,
+                    negated: keyword == "not_ahead",
+                }));
+            }
+            "digit" => u8::is_ascii_digit,
+            "alpha" => u8::is_ascii_alphabetic,
+            "alnum" => u8::is_ascii_alphanumeric,
+            "hex" => u8::is_ascii_hexdigit,
+            "upper" => u8::is_ascii_uppercase,
+            "lower" => u8::is_ascii_lowercase,
+            "punct" => u8::is_ascii_punctuation,
+            _ if self.rest().trim_start().starts_with(['+', '*']) => {
+                return self.error(start, "unknown class of bytes");
+            }
+            _ => return Ok(None),
+        };
+        let optional = if self.eat("+") {
+            false
+        } else if self.eat("*") {
+            true
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(Segment::Run {
+            class: Class::Named(class),
+            optional,
+            slot,
+        }))
+    }
+
+    // `[name]`, `[_]` or `[name lazy]`, or an array of bytes like
+    // `[0x89, b'P']`
+    fn slice(&mut self) -> Result<Segment, ParseError> {
+        let start = self.at;
+        self.at += 1;
+        self.skip_whitespace();
+        if let Some(first) = self.literal()? {
+            let mut bytes = vec![];
+            let mut element = Some(first);
+            let mut at = start + 1;
+            loop {
+                match element.as_deref() {
+                    Some([byte]) => bytes.push(*byte),
+                    _ => return self.error(at, "expected a single byte"),
+                }
+                let comma = self.eat(",");
+                if self.eat("]") {
+                    break;
+                }
+                if !comma {
+                    return self.error(self.at, "expected `,` or `]`");
+                }
+                self.skip_whitespace();
+                at = self.at;
+                element = self.literal()?;
+            }
+            return self.after_literal(bytes, None, start);
+        }
+        let name = match self.ident() {
+            Some(name) => name.to_string(),
+            None => return self.error(self.at, "expected a name or `_`"),
+        };
+        let slot = self.slot(&name);
+        let mut lazy = false;
+        loop {
+            if self.eat("]") {
+                return Ok(Segment::Slice { slot, lazy });
+            }
+            self.skip_whitespace();
+            let at = self.at;
+            match self.ident() {
+                Some("lazy") => lazy = true,
+                Some("greedy") => lazy = false,
+                _ if self.src[at..].starts_with(':') => {
+                    return self.error(
+                        at,
+                        "typed captures aren't supported at runtime",
+                    )
+                }
+                Some("if") => {
+                    return self.error(at, "guards aren't supported at runtime")
+                }
+                _ => return self.error(at, "expected `]`"),
+            }
+        }
+    }
+
+    // A literal's repetitions, then a range if it starts one
+    fn after_literal(
+        &mut self,
+        literal: Vec<u8>,
+        slot: Option<usize>,
+        start: usize,
+    ) -> Result<Segment, ParseError> {
+        if self.eat("..=") {
+            self.skip_whitespace();
+            let end = self.at;
+            let (lo, hi) =
+                match (literal.as_slice(), self.literal()?.as_deref()) {
+                    ([lo], Some([hi])) if lo <= hi => (*lo, *hi),
+                    ([_], Some([_])) => {
+                        return self.error(end, "this range is empty")
+                    }
+                    _ => {
+                        return self
+                            .error(start, "a range goes between single bytes")
+                    }
+                };
+            if self.eat("+") || self.rest().trim_start().starts_with('*') {
+                return Ok(Segment::Run {
+                    class: Class::Range(lo, hi),
+                    optional: self.eat("*"),
+                    slot,
+                });
+            }
+            return Ok(Segment::Byte { lo, hi, slot });
+        }
+        if let Some(slot) = slot {
+            return match literal.as_slice() {
+                [byte] => Ok(Segment::Byte {
+                    lo: *byte,
+                    hi: *byte,
+                    slot: Some(slot),
+                }),
+                _ => self.error(start, "only a single byte can be bound"),
+            };
+        }
+        let reps = if self.eat("*") {
+            self.skip_whitespace();
+            let at = self.at;
+            let digits = self.rest().find(|c: char| !c.is_ascii_digit());
+            let len = digits.unwrap_or(self.rest().len());
+            let reps = self.rest()[..len].parse::<usize>().ok();
+            self.at += len;
+            if len == 0 {
+                return self.error(self.at, "expected a number of repetitions");
+            }
+            self.checked_reps(at, literal.len(), reps)?
+        } else {
+            1
+        };
+        Ok(Segment::Literal(literal.repeat(reps)))
+    }
+
+    // A string, char, byte string or byte literal, raw or not, or an integer
+    // standing for a byte, with an `x2` suffix
+    fn literal(&mut self) -> Result<Option<Vec<u8>>, ParseError> {
+        let start = self.at;
+        let rest = self.rest();
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let byte = self.int_byte()?;
+            return Ok(Some(vec![byte; self.reps(1)?]));
+        }
+        let prefix = usize::from(rest.starts_with("br")) + 1;
+        if let Some(raw) = rest.strip_prefix("r").or(rest.strip_prefix("br")) {
+            // No escapes, up to a quote followed by as many `#`s as came
+            // before the opening one
+            let hashes = raw.len() - raw.trim_start_matches('#').len();
+            let Some(body) = raw[hashes..].strip_prefix('"') else {
+                return Ok(None);
+            };
+            let close = format!("\"{}", "#".repeat(hashes));
+            let Some(end) = body.find(&close) else {
+                return self.error(start, "unclosed literal");
+            };
+            self.at += prefix + hashes + 1 + end + close.len();
+            let value = body.as_bytes()[..end].to_vec();
+            return Ok(Some(value.repeat(self.reps(value.len())?)));
+        }
+        let (quote, bytes) = if let Some(rest) = rest.strip_prefix('b') {
+            match rest.chars().next() {
+                Some(q @ ('"' | '\'')) => (q, true),
+                _ => return Ok(None),
+            }
+        } else {
+            match rest.chars().next() {
+                Some(q @ ('"' | '\'')) => (q, false),
+                _ => return Ok(None),
+            }
+        };
+        self.at += usize::from(bytes) + 1;
+        let mut value = vec![];
+        loop {
+            let Some(c) = self.rest().chars().next() else {
+                return self.error(start, "unclosed literal");
+            };
+            self.at += c.len_utf8();
+            match c {
+                c if c == quote => break,
+                '\\' => value.extend(self.escape(bytes)?),
+                c => {
+                    let mut buf = [0; 4];
+                    value.extend(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        Ok(Some(value.repeat(self.reps(value.len())?)))
+    }
+
+    // An integer literal like `0x7f` or `10`, with an optional `u8` suffix
+    fn int_byte(&mut self) -> Result<u8, ParseError> {
+        let start = self.at;
+        let rest = self.rest();
+        let (radix, prefix) = match rest.get(..2) {
+            Some("0x") => (16, 2),
+            Some("0o") => (8, 2),
+            Some("0b") => (2, 2),
+            _ => (10, 0),
+        };
+        let len = rest[prefix..]
+            .find(|c: char| !(c.is_digit(radix) || c == '_'))
+            .unwrap_or(rest.len() - prefix);
+        let digits = rest[prefix..prefix + len].replace('_', "");
+        self.at += prefix + len;
+        if self.rest().starts_with("u8") {
+            self.at += 2;
+        }
+        match u32::from_str_radix(&digits, radix).map(u8::try_from) {
+            Ok(Ok(byte)) => Ok(byte),
+            Ok(Err(_)) => {
+                self.error(start, "integer literal doesn't fit in a byte")
+            }
+            Err(_) => self.error(start, "invalid integer literal"),
+        }
+    }
+
+    // The repetitions in an `x2` suffix on a literal `len` bytes long
+    fn reps(&mut self, len: usize) -> Result<usize, ParseError> {
+        let Some(rest) = self.rest().strip_prefix('x') else {
+            return Ok(1);
+        };
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Ok(1);
+        }
+        let start = self.at;
+        self.at += digits + 1;
+        self.checked_reps(start, len, rest[..digits].parse().ok())
+    }
+
+    // Repetitions that keep a literal `len` bytes long under `MAX_LITERAL`
+    fn checked_reps(
+        &self,
+        at: usize,
+        len: usize,
+        reps: Option<usize>,
+    ) -> Result<usize, ParseError> {
+        let fits = |reps: &usize| {
+            len.checked_mul(*reps).is_some_and(|len| len <= MAX_LITERAL)
+        };
+        match reps.filter(fits) {
+            Some(reps) => Ok(reps),
+            None => self.error(at, "too many repetitions"),
+        }
+    }
+
+    fn escape(&mut self, bytes: bool) -> Result<Vec<u8>, ParseError> {
+        let start = self.at - 1;
+        let Some(c) = self.rest().chars().next() else {
+            return self.error(start, "unclosed literal");
+        };
+        self.at += c.len_utf8();
+        let byte = match c {
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            '0' => b'\0',
+            '\\' | '\'' | '"' => c as u8,
+            'x' => {
+                let hex = self.rest().get(..2).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) if bytes || byte < 0x80 => {
+                        self.at += 2;
+                        byte
+                    }
+                    _ => return self.error(start, "invalid `\\x` escape"),
+                }
+            }
+            'u' if !bytes => {
+                let hex = self
+                    .rest()
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.find('}').map(|end| &rest[..end]));
+                let c = hex
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32);
+                match (hex, c) {
+                    (Some(hex), Some(c)) => {
+                        self.at += hex.len() + 2;
+                        let mut buf = [0; 4];
+                        return Ok(c.encode_utf8(&mut buf).as_bytes().to_vec());
+                    }
+                    _ => return self.error(start, "invalid `\\u` escape"),
+                }
+            }
+            _ => return self.error(start, "unknown escape"),
+        };
+        Ok(vec![byte])
+    }
+}
@@ -0,0 +1,378 @@
+//! Runtime support for `strmatch`, for when a pattern has to be passed
+//! around as a value rather than written inline.
+//!
+//! [`strmatcher!`] compiles a pattern, with the same syntax as
+//! `strmatch::strextract!`, into a [`Matcher`], which can go in a `static`:
+//!
+//! ```rust
+//! use strmatch_runtime::{strmatcher, Matcher};
+//!
+//! static LINE: Matcher = strmatcher!("ts=" [ts lazy] " level=" [level]);
+//!
+//! assert!(LINE.is_match(b"ts=12 level=warn"));
+//! let captures = LINE.captures(b"ts=12 level=warn").unwrap();
+//! assert_eq!(captures.name("level"), Some(&b"warn"[..]));
+//! assert_eq!(captures.get(0), Some(&b"12"[..]));
+//! ```
+//!
+//! A [`Stream`] feeds a `Matcher` input as it arrives, a chunk at a time,
+//! for protocols read off a socket.
+//!
+//! With the `bytes` feature, matching against a `bytes::Bytes` can hand back
+//! captures as `Bytes` that share its buffer, through
+//! [`Matcher::captures_bytes`] or `Captures::to_bytes`.
+
+use std::fmt;
+
+#[doc(hidden)]
+pub use strmatch::__strmatch_sub;
+pub use strmatch::strmatcher;
+
+// `strmatcher!` refers to `::strmatch_runtime`, which has to work in here too
+extern crate self as strmatch_runtime;
+
+#[cfg(feature = "dynamic")]
+mod dynamic;
+#[cfg(feature = "bytes")]
+mod shared;
+mod stream;
+
+#[cfg(feature = "dynamic")]
+pub use dynamic::{ParseError, Pattern};
+#[cfg(feature = "bytes")]
+pub use shared::SharedCaptures;
+pub use stream::{Progress, Stream};
+
+/// A pattern compiled by [`strmatcher!`].
+///
+/// The matching code is generated at compile time, just like for the other
+/// macros; a `Matcher` only holds on to it. Each capture is handed back as
+/// the bytes it matched, so the pattern can only bind bytes and byte slices,
+/// not typed captures.
+///
+/// ```rust
+/// use strmatch_runtime::{strmatcher, Matcher};
+///
+/// static ROUTES: [(Matcher, &str); 2] = [
+///     (strmatcher!("GET /users/" [id]), "user"),
+///     (strmatcher!("GET /" sep @ '!'..='~' [_]), "other"),
+/// ];
+///
+/// fn route(request: &[u8]) -> Option<&'static str> {
+///     ROUTES
+///         .iter()
+///         .find(|(matcher, _)| matcher.is_match(request))
+///         .map(|(_, name)| *name)
+/// }
+/// assert_eq!(route(b"GET /users/7"), Some("user"));
+/// assert_eq!(route(b"GET /index"), Some("other"));
+/// assert_eq!(route(b"POST /"), None);
+///
+/// let (other, _) = &ROUTES[1];
+/// let captures = other.captures(b"GET /x").unwrap();
+/// assert_eq!(captures.iter().collect::<Vec<_>>(), [("sep", &b"x"[..])]);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Matcher {
+    pattern: &'static str,
+    names: &'static [&'static str],
+    is_match: fn(&[u8]) -> bool,
+    captures: for<'a> fn(&'a [u8]) -> Option<Vec<&'a [u8]>>,
+    viable: fn(&[u8]) -> bool,
+}
+
+impl Matcher {
+    #[doc(hidden)]
+    pub const fn __new(
+        pattern: &'static str,
+        names: &'static [&'static str],
+        is_match: fn(&[u8]) -> bool,
+        captures: for<'a> fn(&'a [u8]) -> Option<Vec<&'a [u8]>>,
+        viable: fn(&[u8]) -> bool,
+    ) -> Matcher {
+        Matcher {
+            pattern,
+            names,
+            is_match,
+            captures,
+            viable,
+        }
+    }
+
+    /// Whether the pattern matches `input`.
+    pub fn is_match(&self, input: &[u8]) -> bool {
+        (self.is_match)(input)
+    }
+
+    /// What the pattern's captures matched, if it matches `input`.
+    pub fn captures<'a>(&self, input: &'a [u8]) -> Option<Captures<'a>> {
+        (self.captures)(input).map(|values| Captures {
+            names: Names::Static(self.names),
+            values,
+        })
+    }
+
+    /// The pattern, as it was written.
+    pub fn pattern(&self) -> &'static str {
+        self.pattern
+    }
+
+    /// The names the pattern binds, in order.
+    pub fn names(&self) -> &'static [&'static str] {
+        self.names
+    }
+}
+
+impl fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Matcher").field(&self.pattern).finish()
+    }
+}
+
+/// The bytes each capture of a [`Matcher`] (or a runtime `Pattern`) matched.
+#[derive(Clone)]
+pub struct Captures<'a> {
+    names: Names<'a>,
+    values: Vec<&'a [u8]>,
+}
+
+// The names of a `Matcher`'s captures are known at compile time, and a
+// runtime `Pattern`'s aren't
+#[derive(Clone, Copy)]
+enum Names<'a> {
+    Static(&'static [&'static str]),
+    #[cfg_attr(not(feature = "dynamic"), allow(dead_code))]
+    Owned(&'a [String]),
+}
+
+impl<'a> Names<'a> {
+    fn get(self, i: usize) -> Option<&'a str> {
+        match self {
+            Names::Static(names) => names.get(i).copied(),
+            Names::Owned(names) => names.get(i).map(String::as_str),
+        }
+    }
+}
+
+impl<'a> Captures<'a> {
+    /// The bytes the `i`th capture matched.
+    pub fn get(&self, i: usize) -> Option<&'a [u8]> {
+        self.values.get(i).copied()
+    }
+
+    /// The bytes the capture called `name` matched.
+    pub fn name(&self, name: &str) -> Option<&'a [u8]> {
+        let i = (0..self.len()).find(|i| self.names.get(*i) == Some(name))?;
+        self.get(i)
+    }
+
+    /// How many captures there are.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the pattern has no captures.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Each capture's name along with the bytes it matched.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a [u8])> + '_ {
+        let names = self.names;
+        (0..self.len()).filter_map(move |i| Some((names.get(i)?, self.get(i)?)))
+    }
+}
+
+// Captures are the same if they have the same names and values, whether
+// they came from a `Matcher` or a `Pattern`
+impl PartialEq for Captures<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Captures<'_> {}
+
+impl fmt::Debug for Captures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Turns a single binding into the bytes it matched.
+#[doc(hidden)]
+pub trait IntoCapture<'a> {
+    fn into_capture(self) -> &'a [u8];
+}
+
+impl<'a> IntoCapture<'a> for &'a u8 {
+    fn into_capture(self) -> &'a [u8] {
+        std::slice::from_ref(self)
+    }
+}
+
+impl<'a> IntoCapture<'a> for &'a [u8] {
+    fn into_capture(self) -> &'a [u8] {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strmatcher, Matcher, Pattern, Progress, Stream};
+
+    static KV: Matcher = strmatcher!([key lazy] #ws? '=' #ws? [value]);
+
+    #[test]
+    fn matchers() {
+        let captures = KV.captures(b"a = 1").unwrap();
+        assert_eq!(captures.name("key"), Some(&b"a"[..]));
+        assert_eq!(captures.get(1), Some(&b"1"[..]));
+        assert_eq!(captures.get(2), None);
+        assert!(!KV.is_match(b"a"));
+        assert_eq!(KV.names(), ["key", "value"]);
+
+        // Sub-patterns are spliced in through this crate
+        strmatch::strpattern!(EQ = #ws? '=' #ws?);
+        let kv = strmatcher!([key lazy] @sub(EQ) [value]);
+        assert_eq!(kv.captures(b"a = 1"), KV.captures(b"a = 1"));
+    }
+
+    #[test]
+    fn streams() {
+        static LINE: Matcher = strmatcher!("+" [n until '\r'] "\r\n");
+        let mut stream = Stream::new(LINE);
+        assert_eq!(stream.push(b"+1"), Progress::Partial);
+        assert_eq!(stream.push(b"2\r"), Progress::Partial);
+        assert_eq!(
+            stream.push(b"\n+3\r\n"),
+            Progress::Matched(LINE.captures(b"+12\r\n").unwrap())
+        );
+        // What was left after a match is checked on the next push
+        assert_eq!(
+            stream.push(b""),
+            Progress::Matched(LINE.captures(b"+3\r\n").unwrap())
+        );
+        assert_eq!(stream.buffered(), b"");
+
+        // A byte the pattern's start can't have fails straight away
+        assert_eq!(stream.push(b"-"), Progress::Failed);
+        assert_eq!(stream.push(b"+1\r\n"), Progress::Failed);
+        stream.reset();
+        assert_eq!(
+            stream.push(b"+1\r\n"),
+            Progress::Matched(LINE.captures(b"+1\r\n").unwrap())
+        );
+
+        // And so does going over the limit
+        let mut stream = Stream::with_limit(LINE, 4);
+        assert_eq!(stream.push(b"+123"), Progress::Failed);
+        let mut stream = Stream::new(LINE);
+        assert_eq!(stream.push(&[b'1'; 4096]), Progress::Failed);
+        let mut stream = Stream::new(LINE);
+        assert_eq!(stream.push(&[b'+'; 4096]), Progress::Partial);
+        assert_eq!(stream.push(&[b'+'; 4096]), Progress::Failed);
+    }
+
+    #[test]
+    fn shared_captures() {
+        let input = bytes::Bytes::from_static(b"a = 1");
+        let captures = KV.captures_bytes(&input).unwrap();
+        assert_eq!(captures.name("key").unwrap(), "a");
+        assert_eq!(captures.get(1).unwrap(), "1");
+        // The captures point into the input's buffer
+        assert_eq!(captures.get(1).unwrap().as_ptr(), input[4..].as_ptr());
+        assert!(KV
+            .captures_bytes(&bytes::Bytes::from_static(b"a"))
+            .is_none());
+
+        let kv = Pattern::parse("[key lazy] #ws? '=' #ws? [value]").unwrap();
+        let shared = kv.captures(&input).unwrap().to_bytes(&input);
+        assert_eq!(shared, captures);
+    }
+
+    #[test]
+    fn runtime_patterns() {
+        // The same pattern works the same at runtime
+        let kv = Pattern::parse("[key lazy] #ws? '=' #ws? [value]").unwrap();
+        for input in [&b"a = 1"[..], b"a=1", b"a", b"=", b"a =\t"] {
+            assert_eq!(kv.captures(input), KV.captures(input));
+        }
+        // Without the `#`, it's a byte bound to a name
+        let p = Pattern::parse("'a' ws 'b'").unwrap();
+        assert!(p.is_match(b"a-b") && !p.is_match(b"a  b"));
+
+        let p = Pattern::parse(
+            r#"b'\x01' "ab"x2 '-' * 2 first @ 'a'..='z' n @ digit+ [_]"#,
+        )
+        .unwrap();
+        let captures = p.captures(b"\x01abab--q42!").unwrap();
+        assert_eq!(
+            captures.iter().collect::<Vec<_>>(),
+            [("first", &b"q"[..]), ("n", &b"42"[..])]
+        );
+        assert!(!p.is_match(b"\x01abab-q42"));
+
+        // Raw literals have no escapes, with or without `#`s
+        let p = Pattern::parse(r##"r"C:\" br#"a"b"# r"\"x2 [_]"##).unwrap();
+        assert!(p.is_match(br#"C:\a"b\\"#));
+        assert!(!p.is_match(br#"C:\a"b\"#));
+        assert!(Pattern::parse("'a'x3").unwrap().is_match(b"aaa"));
+        assert!(Pattern::parse("rest @ ..").unwrap().is_match(b"r"));
+
+        let p =
+            Pattern::parse("0x7f 10x2 0x30..=0x39+ n @ 0b1 0u8 * 2").unwrap();
+        assert!(p.is_match(b"\x7f\n\n42\x01\0\0"));
+        let p = Pattern::parse("[0x89, b'P', 'N',] [0] * 2 [rest]").unwrap();
+        assert_eq!(
+            p.captures(b"\x89PN\0\0!").unwrap().name("rest"),
+            Some(&b"!"[..])
+        );
+
+        let p = Pattern::parse("min_len(4); 'a' [rest]").unwrap();
+        assert!(p.is_match(b"abcd") && !p.is_match(b"abc"));
+        assert!(p.captures(b"abc").is_none());
+
+        let p = Pattern::parse("'0' peek('x') .. not_ahead('z') ..").unwrap();
+        assert!(p.is_match(b"0x1"));
+        assert!(!p.is_match(b"01"));
+
+        let p = Pattern::parse("c @ #utf8char hex* tail @ ..").unwrap();
+        let captures = p.captures("ébeefz".as_bytes()).unwrap();
+        assert_eq!(captures.name("c"), Some("é".as_bytes()));
+        assert_eq!(captures.name("tail"), Some(&b"z"[..]));
+        // Without the `#`, it's a byte bound to a name
+        let p = Pattern::parse("utf8char 'b'").unwrap();
+        assert!(p.is_match(b"ab") && !p.is_match("éb".as_bytes()));
+
+        let p = Pattern::parse("[a lazy] ',' [b] ',' [c]").unwrap();
+        let captures = p.captures(b"1,2,3,4").unwrap();
+        assert_eq!(captures.name("b"), Some(&b"2,3"[..]));
+
+        let p = Pattern::parse(r#""\u{41}"x3"#).unwrap();
+        assert!(p.is_match(b"AAA") && !p.is_match(b"AA"));
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let offset = |pattern| Pattern::parse(pattern).unwrap_err().offset();
+        assert_eq!(offset("'a' #sep"), 4);
+        assert_eq!(offset("[n: u32]"), 2);
+        assert_eq!(offset("[n if n.len() > 2]"), 3);
+        assert_eq!(offset("'a' (\"b\")*"), 4);
+        assert_eq!(offset("\"abc"), 0);
+        assert_eq!(offset("'z'..='a'"), 6);
+        assert_eq!(offset("word+"), 0);
+        assert_eq!(offset("'a' 0x100"), 4);
+        assert_eq!(offset("[1, \"ab\"]"), 4);
+        assert_eq!(offset("\"\\u{41\""), 1);
+        assert_eq!(offset("\"ab\"x99999999999999999999"), 4);
+        assert_eq!(offset("\"ab\"x4000000"), 4);
+        assert_eq!(offset("'a' * 18446744073709551615"), 6);
+        let error = Pattern::parse("x @ #y").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a segment to bind after `@` at offset 4"
+        );
+    }
+}
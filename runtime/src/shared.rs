@@ -0,0 +1,112 @@
+//! [`SharedCaptures`], captures as [`Bytes`] that share the input's buffer,
+//! behind the `bytes` feature.
+
+use std::fmt;
+
+use bytes::Bytes;
+
+use crate::{Captures, Matcher, Names};
+
+/// The captures of a match against a [`Bytes`], each a `Bytes` pointing into
+/// the same buffer as the input rather than a copy of it.
+///
+/// Unlike [`Captures`], these don't borrow from the input, so they can be
+/// kept after it's dropped, like when a field parsed off a socket is stored
+/// for later. Cloning a capture only bumps a reference count.
+///
+/// ```rust
+/// use bytes::Bytes;
+/// use strmatch_runtime::{strmatcher, Matcher};
+///
+/// static HEADER: Matcher = strmatcher!([name until ':'] ": " [value]);
+///
+/// let line = Bytes::from_static(b"Host: example.com");
+/// let captures = HEADER.captures_bytes(&line).unwrap();
+/// let host: Bytes = captures.name("value").unwrap();
+/// drop(line);
+/// assert_eq!(host, "example.com");
+/// ```
+#[derive(Clone)]
+pub struct SharedCaptures<'n> {
+    names: Names<'n>,
+    values: Vec<Bytes>,
+}
+
+impl<'n> SharedCaptures<'n> {
+    /// What the `i`th capture matched.
+    pub fn get(&self, i: usize) -> Option<Bytes> {
+        self.values.get(i).cloned()
+    }
+
+    /// What the capture called `name` matched.
+    pub fn name(&self, name: &str) -> Option<Bytes> {
+        let i = (0..self.len()).find(|i| self.names.get(*i) == Some(name))?;
+        self.get(i)
+    }
+
+    /// How many captures there are.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the pattern has no captures.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Each capture's name along with what it matched.
+    pub fn iter(&self) -> impl Iterator<Item = (&'n str, Bytes)> + '_ {
+        let names = self.names;
+        (0..self.len()).filter_map(move |i| Some((names.get(i)?, self.get(i)?)))
+    }
+}
+
+impl<'a> Captures<'a> {
+    /// These captures as parts of `input`, which has to be what they were
+    /// matched against.
+    ///
+    /// # Panics
+    ///
+    /// If a capture isn't part of `input`.
+    pub fn to_bytes(&self, input: &Bytes) -> SharedCaptures<'a> {
+        SharedCaptures {
+            names: self.names,
+            values: self
+                .values
+                .iter()
+                .map(|value| input.slice_ref(value))
+                .collect(),
+        }
+    }
+}
+
+impl Matcher {
+    /// What the pattern's captures matched, if it matches `input`, as parts
+    /// of `input`.
+    pub fn captures_bytes(
+        &self,
+        input: &Bytes,
+    ) -> Option<SharedCaptures<'static>> {
+        (self.captures)(input).map(|values| SharedCaptures {
+            names: Names::Static(self.names),
+            values: values
+                .into_iter()
+                .map(|value| input.slice_ref(value))
+                .collect(),
+        })
+    }
+}
+
+impl PartialEq for SharedCaptures<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for SharedCaptures<'_> {}
+
+impl fmt::Debug for SharedCaptures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
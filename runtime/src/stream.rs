@@ -0,0 +1,141 @@
+//! [`Stream`], which matches a [`Matcher`] against input that arrives a
+//! chunk at a time.
+
+use crate::{Captures, Matcher};
+
+/// How many bytes a [`Stream`] buffers by default before giving up. Waiting
+/// for a match costs time quadratic in what's buffered, so it's kept small.
+const DEFAULT_LIMIT: usize = 8 * 1024;
+
+/// Matches a [`Matcher`] against input fed to it in chunks, like reads off a
+/// socket, where a whole message isn't available all at once.
+///
+/// Each [`push`](Stream::push) adds a chunk and says how matching stands:
+/// [`Progress::Matched`] once the shortest start of what's buffered matches,
+/// [`Progress::Failed`] once no more input could make it match, and
+/// [`Progress::Partial`] while it might still. A match takes its bytes out of
+/// the buffer on the next push, so one `Stream` can read message after
+/// message; any bytes after a match are kept for the next one, and pushing
+/// an empty chunk checks them.
+///
+/// ```rust
+/// use strmatch_runtime::{strmatcher, Matcher, Progress, Stream};
+///
+/// static GREETING: Matcher = strmatcher!("HELO " [host until '\r'] "\r\n");
+///
+/// let mut stream = Stream::new(GREETING);
+/// assert_eq!(stream.push(b"HELO mail.exa"), Progress::Partial);
+/// match stream.push(b"mple.com\r\nQUIT") {
+///     Progress::Matched(captures) => {
+///         assert_eq!(captures.name("host"), Some(&b"mail.example.com"[..]));
+///     }
+///     other => panic!("{:?}", other),
+/// }
+/// assert_eq!(stream.push(b"\r\n"), Progress::Failed);
+/// assert_eq!(stream.buffered(), b"QUIT\r\n");
+/// ```
+///
+/// Only the fixed-width start of a pattern, up to its first segment that can
+/// match a varying number of bytes, is checked for bytes that rule out a
+/// match. Past that, a `Stream` waits for a match until it has buffered its
+/// limit, 8 KiB unless set with [`with_limit`](Stream::with_limit). Each
+/// push matches the pattern against every length of input it adds, so
+/// waiting for a message of `n` bytes costs on the order of `n * n` steps.
+/// Messages should be short, or the pattern should end in a delimiter that's
+/// quick to rule out, and a larger limit is worth it only for both.
+#[derive(Clone, Debug)]
+pub struct Stream {
+    matcher: Matcher,
+    buf: Vec<u8>,
+    limit: usize,
+    // How many bytes at the front of `buf` the last match took, which go on
+    // the next push
+    consumed: usize,
+    // How many of the shortest starts of `buf` are known not to match
+    tested: usize,
+    failed: bool,
+}
+
+/// Where matching stands after a [`Stream::push`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Progress<'a> {
+    /// The input so far could still turn into a match.
+    Partial,
+    /// The start of the input matched, with these captures.
+    Matched(Captures<'a>),
+    /// No more input can make the pattern match, or there's more of it than
+    /// the limit. Pushing more keeps failing until [`Stream::reset`].
+    Failed,
+}
+
+impl Stream {
+    /// A `Stream` for `matcher`, buffering up to 8 KiB.
+    pub fn new(matcher: Matcher) -> Stream {
+        Stream::with_limit(matcher, DEFAULT_LIMIT)
+    }
+
+    /// A `Stream` for `matcher`, buffering up to `limit` bytes before
+    /// failing.
+    pub fn with_limit(matcher: Matcher, limit: usize) -> Stream {
+        Stream {
+            matcher,
+            buf: Vec::new(),
+            limit,
+            consumed: 0,
+            tested: 0,
+            failed: false,
+        }
+    }
+
+    /// Add `chunk` to the input, and check whether the start of it matches.
+    pub fn push(&mut self, chunk: &[u8]) -> Progress<'_> {
+        self.buf.drain(..self.consumed);
+        self.consumed = 0;
+        if self.failed {
+            return Progress::Failed;
+        }
+        self.buf.extend_from_slice(chunk);
+        // A match has to take at least a byte, or it would never move on.
+        // Lengths tried before are known not to match, and once the start
+        // rules out a match, no longer input can have one either.
+        let start = self.tested.max(1);
+        let mut matched = None;
+        for len in start..=self.buf.len().min(self.limit) {
+            let input = &self.buf[..len];
+            if !(self.matcher.viable)(input) {
+                break;
+            }
+            if self.matcher.is_match(input) {
+                matched = Some(len);
+                break;
+            }
+        }
+        if let Some(len) = matched {
+            self.consumed = len;
+            self.tested = 0;
+            return match self.matcher.captures(&self.buf[..len]) {
+                Some(captures) => Progress::Matched(captures),
+                None => unreachable!("the matcher just matched"),
+            };
+        }
+        self.tested = self.buf.len() + 1;
+        if self.buf.len() >= self.limit || !(self.matcher.viable)(&self.buf) {
+            self.failed = true;
+            return Progress::Failed;
+        }
+        Progress::Partial
+    }
+
+    /// The input that hasn't been matched yet.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf[self.consumed..]
+    }
+
+    /// Drop everything buffered, and start over after a failure.
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.consumed = 0;
+        self.tested = 0;
+        self.failed = false;
+    }
+}
@@ -0,0 +1,357 @@
+//! Parsing and expansion for `strmatch_match!`, which wraps a whole `match`
+//! so that arms can use segments needing guards.
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, Attribute, Expr, Ident, Token};
+
+use crate::engine::{coerce_input, hidden, matcher, with_helpers, Codegen};
+use crate::pattern::Pattern;
+use crate::shadow::{first_bytes, reorder, shadowing};
+
+/// `scrutinee { arms }`, mirroring an ordinary `match`, optionally preceded
+/// by `#[reorder]` and `#[codegen(..)]` attributes.
+pub(crate) struct MatchInput {
+    codegen: Codegen,
+    /// Whether to try arms starting with longer literals first
+    reorder: bool,
+    scrutinee: Expr,
+    arms: Vec<Arm>,
+}
+
+impl Parse for MatchInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut codegen = Codegen::default();
+        let mut reorder = false;
+        for attr in input.call(Attribute::parse_outer)? {
+            if attr.path.is_ident("codegen") {
+                codegen = Codegen::from_attr(&attr)?;
+            } else if attr.path.is_ident("reorder") && attr.tokens.is_empty() {
+                reorder = true;
+            } else {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected `#[reorder]` or `#[codegen(..)]`",
+                ));
+            }
+        }
+        let scrutinee = Expr::parse_without_eager_brace(input)?;
+        let inner;
+        let _ = syn::braced!(inner in input);
+        let mut arms = vec![];
+        while !inner.is_empty() {
+            if let Some(Arm {
+                patterns: None,
+                guard: None,
+                ..
+            }) = arms.last()
+            {
+                return Err(inner.error("arms after a `_` arm can never match"));
+            }
+            arms.push(inner.parse()?);
+        }
+        Ok(MatchInput {
+            codegen,
+            reorder,
+            scrutinee,
+            arms,
+        })
+    }
+}
+
+/// A single `(pattern) | (pattern) if guard => body` arm. The patterns are
+/// `None` for a catch-all `_` arm.
+struct Arm {
+    patterns: Option<Vec<Pattern>>,
+    guard: Option<Expr>,
+    body: Expr,
+    /// Where the arm's first pattern is, or its `_`
+    span: Span,
+}
+
+impl Parse for Arm {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.span();
+        let patterns = if input.peek(Token![_]) {
+            input.parse::<Token![_]>()?;
+            None
+        } else {
+            let mut patterns = vec![];
+            loop {
+                let inner;
+                let _ = parenthesized!(inner in input);
+                patterns.push(inner.parse()?);
+                if !input.peek(Token![|]) {
+                    break;
+                }
+                input.parse::<Token![|]>()?;
+            }
+            Some(patterns)
+        };
+        let guard = if input.peek(Token![if]) {
+            input.parse::<Token![if]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        input.parse::<Token![=>]>()?;
+        let body = input.parse()?;
+        // Like in a `match`, block bodies don't need a trailing comma
+        if input.is_empty() || matches!(body, Expr::Block(_)) {
+            let _ = input.parse::<Option<Token![,]>>()?;
+        } else {
+            input.parse::<Token![,]>()?;
+        }
+        if let Some(patterns) = &patterns {
+            check_bindings(patterns);
+        }
+        Ok(Arm {
+            patterns,
+            guard,
+            body,
+            span,
+        })
+    }
+}
+
+// Every alternative of an arm must bind the same names so that the body can
+// use them no matter which alternative matched.
+pub(crate) fn check_bindings(patterns: &[Pattern]) {
+    let mut expected = patterns[0].bindings();
+    expected.sort();
+    for pattern in &patterns[1..] {
+        let mut bindings = pattern.bindings();
+        bindings.sort();
+        if bindings != expected {
+            let names = expected
+                .iter()
+                .map(|ident| format!("`{ident}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            match pattern.literals.first() {
+                Some(first) => abort!(
+                    first.span(),
+                    "every alternative of an arm must bind the same names";
+                    note = "expected bindings: {}", names
+                ),
+                None => abort!(
+                    proc_macro2::Span::call_site(),
+                    "every alternative of an arm must bind the same names"
+                ),
+            }
+        }
+    }
+}
+
+// `1st`, `2nd`, `3rd`, `4th`, ...
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+// How many arms with patterns it takes for a table of which arms can match
+// input starting with each byte to be worth it
+const DISPATCH_ARMS: usize = 4;
+
+impl ToTokens for MatchInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        // Arms share the helper functions and consts of `shared_helpers` and
+        // `const_array` matchers
+        tokens.extend(with_helpers(|| self.expand()));
+    }
+}
+
+impl MatchInput {
+    fn expand(&self) -> TokenStream {
+        let input = hidden("input");
+        let arms = self
+            .arms
+            .iter()
+            .map(|arm| (arm.patterns.as_deref(), arm.guard.is_some()))
+            .collect::<Vec<_>>();
+        // Stable Rust has no way for a macro to emit a warning, but using a
+        // deprecated item does warn, with its note, wherever it's used
+        let warnings = shadowing(&arms).into_iter().zip(&self.arms).filter_map(
+            |(by, arm)| {
+                let note = format!(
+                    "this arm can never match, since the {} arm matches \
+                     everything it does first",
+                    ordinal(by? + 1)
+                );
+                let warning = Ident::new(
+                    "__strmatch_unreachable_arm",
+                    Span::mixed_site().located_at(arm.span),
+                );
+                Some(quote! {{
+                    #[deprecated(note = #note)]
+                    fn #warning() {}
+                    #warning();
+                }})
+            },
+        );
+        let order = if self.reorder {
+            let patterns = self
+                .arms
+                .iter()
+                .map(|arm| arm.patterns.as_deref())
+                .collect::<Vec<_>>();
+            reorder(&patterns).unwrap_or_else(|(moved, passed)| {
+                abort!(
+                    self.arms[moved].span,
+                    "`#[reorder]` would try this arm before the {} arm, which \
+                     can match some of the same inputs",
+                    ordinal(passed + 1);
+                    help = "move this arm up yourself if it should win, or \
+                            drop `#[reorder]`"
+                )
+            })
+        } else {
+            (0..self.arms.len()).collect()
+        };
+        let candidates = hidden("candidates");
+        let dispatch = self.dispatch(&input, &candidates);
+        let mut chain = TokenStream::new();
+        for (i, arm) in order.iter().map(|&i| (i, &self.arms[i])) {
+            let body = &arm.body;
+            // Arms that can't match input starting with its first byte are
+            // skipped without running their matchers
+            let gated = |patterns, guard| {
+                let matcher = matcher(&input, patterns, guard, self.codegen);
+                match dispatch {
+                    Some(_) => quote! {
+                        (if (#candidates >> #i) & 1 == 1 {
+                            #matcher
+                        } else {
+                            ::core::option::Option::None
+                        })
+                    },
+                    None => matcher,
+                }
+            };
+            let branch = match (&arm.patterns, &arm.guard) {
+                (Some(patterns), None)
+                    if !patterns.iter().any(Pattern::has_guards) =>
+                {
+                    let bindings = patterns[0].bindings();
+                    let matcher = gated(patterns, None);
+                    quote! {
+                        if let ::core::option::Option::Some((#(#bindings,)*)) = #matcher {
+                            #body
+                        }
+                    }
+                }
+                (Some(patterns), guard) => {
+                    // The guards already used the captures, so the body
+                    // doesn't have to
+                    let bindings = patterns[0].bindings();
+                    let captures = hidden("captures");
+                    let matcher = gated(patterns, guard.as_ref());
+                    quote! {
+                        if let ::core::option::Option::Some(#captures) = #matcher {
+                            #[allow(unused_variables)]
+                            let (#(#bindings,)*) = #captures;
+                            #body
+                        }
+                    }
+                }
+                (None, Some(guard)) => quote!(if #guard { #body }),
+                (None, None) => quote!({ #body }),
+            };
+            if !chain.is_empty() {
+                chain.extend(quote!(else));
+            }
+            chain.extend(branch);
+        }
+        let scrutinee = &self.scrutinee;
+        let coerce = coerce_input(&input, scrutinee);
+        quote! {
+            match #scrutinee {
+                #input => {
+                    #coerce
+                    #(#warnings)*
+                    #dispatch
+                    #chain
+                }
+            }
+        }
+    }
+
+    // With enough arms, a table of which arms can match input starting with
+    // each byte, as bits of a `u64`, which `candidates` is set to for the
+    // input. `None` when there are too few or too many arms, or the table
+    // wouldn't rule any out.
+    fn dispatch(
+        &self,
+        input: &Ident,
+        candidates: &Ident,
+    ) -> Option<TokenStream> {
+        let patterned = self.arms.iter().filter(|arm| arm.patterns.is_some());
+        if patterned.count() < DISPATCH_ARMS || self.arms.len() > 64 {
+            return None;
+        }
+        let firsts = self
+            .arms
+            .iter()
+            .map(|arm| arm.patterns.as_deref().map(first_bytes))
+            .collect::<Vec<_>>();
+        // Arms without a pattern are never skipped, so they don't need bits
+        let mask = |byte: Option<u8>| {
+            firsts.iter().enumerate().fold(0u64, |mask, (i, first)| {
+                let can = match (first, byte) {
+                    (None, _) => false,
+                    (Some((set, _)), Some(byte)) => {
+                        (set[usize::from(byte / 64)] >> (byte % 64)) & 1 == 1
+                    }
+                    (Some((_, empty)), None) => *empty,
+                };
+                mask | u64::from(can) << i
+            })
+        };
+        let masks = (0..=u8::MAX)
+            .map(|byte| mask(Some(byte)))
+            .collect::<Vec<_>>();
+        let patterned = firsts
+            .iter()
+            .enumerate()
+            .filter(|(_, first)| first.is_some())
+            .fold(0u64, |mask, (i, _)| mask | 1 << i);
+        if masks.iter().all(|&mask| mask == patterned) {
+            return None;
+        }
+        // Only the bytes whose arms differ from the most common set are
+        // listed
+        let mut counts = std::collections::BTreeMap::new();
+        for mask in &masks {
+            *counts.entry(mask).or_insert(0) += 1;
+        }
+        let common = **counts.iter().max_by_key(|(_, count)| **count)?.0;
+        let (bytes, overrides): (Vec<_>, Vec<_>) = masks
+            .iter()
+            .enumerate()
+            .filter(|(_, mask)| **mask != common)
+            .map(|(byte, mask)| (byte, *mask))
+            .unzip();
+        let empty = mask(None);
+        let table = hidden("table");
+        Some(quote! {
+            #[allow(non_upper_case_globals)]
+            const #table: [u64; 256] = {
+                let mut table = [#common; 256];
+                #(table[#bytes] = #overrides;)*
+                table
+            };
+            let #candidates: u64 = match #input.first() {
+                ::core::option::Option::Some(byte) => #table[usize::from(*byte)],
+                ::core::option::Option::None => #empty,
+            };
+        })
+    }
+}
@@ -0,0 +1,174 @@
+//! Parsing and expansion for `assert_strmatches!`, which asserts that a
+//! pattern matches, and shows where the input went wrong if it doesn't.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Ident, Lifetime, Token};
+
+use crate::engine::{coerce_input, hidden, matcher, Codegen};
+use crate::pattern::{Capture, Pattern};
+
+/// `input, pattern`
+pub(crate) struct AssertInput {
+    input: Expr,
+    pattern: Pattern,
+    /// The pattern as written, for the failure message
+    printed: String,
+}
+
+impl Parse for AssertInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let printed = input.fork().parse::<TokenStream>()?.to_string();
+        let printed = printed.split_whitespace().collect::<Vec<_>>().join(" ");
+        let pattern = input.parse()?;
+        Ok(AssertInput {
+            input: expr,
+            pattern,
+            printed,
+        })
+    }
+}
+
+impl AssertInput {
+    // Code finding the first byte the pattern can't match, which breaks out
+    // of `label` with its offset. Only the fixed-width start of the pattern
+    // is checked, up to the first segment that can't be checked a byte at a
+    // time; `at` is left at how far that got.
+    fn divergence(
+        &self,
+        input: &Ident,
+        at: &Ident,
+        label: &Lifetime,
+    ) -> TokenStream {
+        let mut checks = TokenStream::new();
+        let byte = hidden("byte");
+        let check = |in_byte: TokenStream| {
+            quote! {
+                match #input.get(#at) {
+                    ::core::option::Option::Some(#byte) if #in_byte => #at += 1,
+                    _ => break #label ::core::option::Option::Some(#at),
+                }
+            }
+        };
+        for capture in &self.pattern.literals {
+            if let Some(bytes) = capture.literal_bytes() {
+                for b in bytes {
+                    checks.extend(check(quote!(*#byte == #b)));
+                }
+                continue;
+            }
+            match capture {
+                Capture::Ident(_) | Capture::Underscore(_) => {
+                    checks.extend(check(quote!(true)))
+                }
+                Capture::Const { path, .. } => {
+                    checks.extend(check(quote!(*#byte == #path)))
+                }
+                Capture::NoCase { literal, .. } => {
+                    for b in literal.literal_bytes().unwrap() {
+                        checks.extend(check(
+                            quote!(#byte.eq_ignore_ascii_case(&#b)),
+                        ));
+                    }
+                }
+                Capture::ByteRange { start, end, .. } => {
+                    let lo = start.literal_bytes().unwrap()[0];
+                    let hi = end
+                        .as_ref()
+                        .map_or(lo, |end| end.literal_bytes().unwrap()[0]);
+                    checks.extend(check(quote!((#lo..=#hi).contains(#byte))));
+                }
+                _ => return checks,
+            }
+        }
+        // Everything was checked, so what's left is input the pattern
+        // doesn't have room for
+        if self.pattern.end.is_none() {
+            checks.extend(quote! {
+                if #at < #input.len() {
+                    break #label ::core::option::Option::Some(#at);
+                }
+            });
+        }
+        checks
+    }
+}
+
+impl ToTokens for AssertInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let at = hidden("at");
+        let hexdump = hidden("hexdump");
+        let label = Lifetime::new("'__strmatch", Span::mixed_site());
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            Codegen::default(),
+        );
+        let divergence = self.divergence(&input, &at, &label);
+        let printed = &self.printed;
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    if #matcher.is_none() {
+                        // Sixteen bytes a row, with the printable ones on
+                        // the right
+                        fn #hexdump(input: &[u8]) -> ::std::string::String {
+                            let mut dump = ::std::string::String::new();
+                            for (row, bytes) in input.chunks(16).enumerate() {
+                                let hex = bytes
+                                    .iter()
+                                    .map(|b| ::std::format!("{:02x} ", b))
+                                    .collect::<::std::string::String>();
+                                let text = bytes
+                                    .iter()
+                                    .map(|&b| match b {
+                                        b' '..=b'~' => b as char,
+                                        _ => '.',
+                                    })
+                                    .collect::<::std::string::String>();
+                                dump.push_str(&::std::format!(
+                                    "\n  {:08x}  {:<48} |{}|",
+                                    row * 16,
+                                    hex,
+                                    text
+                                ));
+                            }
+                            dump
+                        }
+                        #[allow(unused_mut)]
+                        let mut #at = 0usize;
+                        #[allow(unreachable_code)]
+                        let divergence: ::core::option::Option<usize> = #label: {
+                            #divergence
+                            ::core::option::Option::None
+                        };
+                        let divergence = match divergence {
+                            ::core::option::Option::Some(at) => {
+                                ::std::format!("first difference at byte {}", at)
+                            }
+                            ::core::option::Option::None => ::std::format!(
+                                "the first {} bytes match, the difference is after them",
+                                #at
+                            ),
+                        };
+                        ::core::panic!(
+                            "assertion failed: input doesn't match `{}`\n  {}\n  input ({} bytes):{}",
+                            #printed,
+                            divergence,
+                            #input.len(),
+                            #hexdump(#input)
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
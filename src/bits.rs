@@ -0,0 +1,154 @@
+//! Parsing and expansion for `bitmatch!`, which matches the bits of an
+//! unsigned integer with a mask-and-compare and pulls fields out with shifts.
+
+use proc_macro2::{Literal, Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{format_ident, quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Ident, LitInt, Token};
+
+use crate::engine::hidden;
+
+/// A run of bits, written from the most significant bit down.
+enum Bits {
+    /// `0b101` (or a lone `0` or `1`), bits that have to match exactly
+    Literal { value: u128, width: u32 },
+    /// `_`, a single bit that can be anything
+    Ignore,
+    /// `name` or `name: width`, bits that get bound
+    Field { name: Ident, width: u32 },
+}
+
+impl Bits {
+    fn width(&self) -> u32 {
+        match self {
+            Bits::Literal { width, .. } | Bits::Field { width, .. } => *width,
+            Bits::Ignore => 1,
+        }
+    }
+}
+
+impl Parse for Bits {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(LitInt) {
+            let lit = input.parse::<LitInt>()?;
+            // Each digit stands for a bit, so only binary literals make sense
+            let repr = lit.to_string();
+            let digits = match repr.strip_prefix("0b") {
+                Some(digits) if lit.suffix().is_empty() => {
+                    digits.replace('_', "")
+                }
+                // A lone `0` or `1` is a single bit
+                None if repr == "0" || repr == "1" => repr.clone(),
+                _ => abort!(
+                    lit,
+                    "bits have to be written as a binary literal";
+                    help = "like `0b101`, which matches three bits"
+                ),
+            };
+            Ok(Bits::Literal {
+                value: lit.base10_parse()?,
+                width: digits.len() as u32,
+            })
+        } else if lookahead.peek(Token![_]) {
+            input.parse::<Token![_]>()?;
+            Ok(Bits::Ignore)
+        } else if lookahead.peek(Ident) {
+            let name = input.parse()?;
+            let width = if input.peek(Token![:]) {
+                input.parse::<Token![:]>()?;
+                let width = input.parse::<LitInt>()?;
+                match width.base10_parse()? {
+                    0 => {
+                        abort!(width, "a field has to be at least one bit wide")
+                    }
+                    width => width,
+                }
+            } else {
+                1
+            };
+            Ok(Bits::Field { name, width })
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+/// `input, bits`
+pub(crate) struct BitInput {
+    input: Expr,
+    bits: Vec<Bits>,
+}
+
+impl Parse for BitInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let mut bits = vec![];
+        while !input.is_empty() {
+            bits.push(input.parse()?);
+        }
+        Ok(BitInput { input: expr, bits })
+    }
+}
+
+impl ToTokens for BitInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let width = self.bits.iter().map(Bits::width).sum::<u32>();
+        let ty = match width {
+            8 | 16 | 32 | 64 | 128 => format_ident!("u{}", width),
+            _ => abort!(
+                Span::call_site(),
+                "the bits add up to {} bits, but have to fill an unsigned \
+                 integer", width;
+                note = "that's 8, 16, 32, 64 or 128 bits"
+            ),
+        };
+        let input = hidden("input");
+        let ones = |width: u32| u128::MAX >> (128 - width);
+        // Going from the most significant bit down, gather the bits that
+        // have to match and where each field sits
+        let (mut mask, mut value, mut shift) = (0u128, 0u128, width);
+        let mut fields = vec![];
+        for bits in &self.bits {
+            shift -= bits.width();
+            match bits {
+                Bits::Literal { value: bits, width } => {
+                    mask |= ones(*width) << shift;
+                    value |= bits << shift;
+                }
+                Bits::Ignore => {}
+                Bits::Field { name, width } => {
+                    let field = Literal::u128_unsuffixed(ones(*width));
+                    fields.push((name, quote!((#input >> #shift) & #field)));
+                }
+            }
+        }
+        let mask = Literal::u128_unsuffixed(mask);
+        let value = Literal::u128_unsuffixed(value);
+        // A lone field is handed back as is rather than in a 1-tuple
+        let names = fields.iter().map(|(name, _)| name);
+        let result = match fields.as_slice() {
+            [(name, _)] => quote!(#name),
+            _ => quote!((#(#names,)*)),
+        };
+        let binds = fields
+            .iter()
+            .map(|(name, field)| quote!(let #name = #field;));
+        let expr = &self.input;
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    let #input: #ty = #input;
+                    if #input & #mask == #value {
+                        #(#binds)*
+                        ::core::option::Option::Some(#result)
+                    } else {
+                        ::core::option::Option::None
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,136 @@
+//! Parsing and expansion for `strbuild!`, which writes the bytes a pattern
+//! stands for into a `Vec<u8>`, with `{expr}` holes for what's only known at
+//! runtime.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{braced, token, Expr, LitByteStr};
+
+use crate::engine::hidden;
+use crate::pattern::Capture;
+
+/// A part of what's built
+enum Piece {
+    Bytes(LitByteStr),
+    /// `#byte`, a `u8`
+    Byte(Box<Expr>),
+    /// `{expr}`, anything that's `AsRef<[u8]>`
+    Hole(Box<Expr>),
+    /// `pad(' ', count)`, the byte repeated a number of times
+    Pad(u8, Box<Expr>),
+}
+
+/// The segments of a `strbuild!`
+pub(crate) struct BuildInput {
+    pieces: Vec<Piece>,
+}
+
+impl Parse for BuildInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut pieces = vec![];
+        while !input.is_empty() {
+            if input.peek(token::Brace) {
+                let inner;
+                braced!(inner in input);
+                pieces.push(Piece::Hole(Box::new(inner.parse()?)));
+                continue;
+            }
+            let capture = input.parse::<Capture>()?;
+            let span = capture.span();
+            let bytes = match capture {
+                // A line ending is written the Unix way, as `Display` impls
+                // write it
+                Capture::Newline { .. } => b"\n".to_vec(),
+                Capture::Var { expr, .. } => {
+                    pieces.push(Piece::Byte(expr));
+                    continue;
+                }
+                Capture::Pad { fill, count, .. } => {
+                    let byte = fill.literal_bytes().unwrap()[0];
+                    pieces.push(Piece::Pad(byte, count));
+                    continue;
+                }
+                // A case-insensitive literal is written as it's spelled
+                Capture::NoCase { literal, .. } => {
+                    literal.literal_bytes().unwrap()
+                }
+                capture => match capture.literal_bytes() {
+                    Some(bytes) => bytes,
+                    None => abort!(
+                        capture.span(),
+                        "this segment can't be built, since it doesn't say \
+                         what to write";
+                        help = "use literals, `#nl`, `#byte`, `pad(..)` and \
+                                `{expr}` holes"
+                    ),
+                },
+            };
+            // Runs of literals are written in one go
+            match pieces.last_mut() {
+                Some(Piece::Bytes(last)) => {
+                    let mut joined = last.value();
+                    joined.extend(bytes);
+                    *last = LitByteStr::new(&joined, last.span());
+                }
+                _ => pieces.push(Piece::Bytes(LitByteStr::new(&bytes, span))),
+            }
+        }
+        Ok(BuildInput { pieces })
+    }
+}
+
+impl ToTokens for BuildInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let out = hidden("out");
+        // Holes are evaluated once, in order, before anything is written,
+        // so the buffer can be allocated at its final size
+        let mut holes = vec![];
+        let mut lens = vec![];
+        let mut writes = vec![];
+        for (i, piece) in self.pieces.iter().enumerate() {
+            match piece {
+                Piece::Bytes(bytes) => {
+                    let len = bytes.value().len();
+                    lens.push(quote!(#len));
+                    writes.push(quote!(#out.extend_from_slice(#bytes);));
+                }
+                Piece::Byte(expr) => {
+                    let byte = hidden(&format!("byte{i}"));
+                    // Spanned so a byte of the wrong type is pointed out
+                    holes.push(
+                        quote_spanned!(expr.span()=> let #byte: u8 = #expr;),
+                    );
+                    lens.push(quote!(1));
+                    writes.push(quote!(#out.push(#byte);));
+                }
+                Piece::Hole(expr) => {
+                    let hole = hidden(&format!("hole{i}"));
+                    // Borrowed, so building doesn't use up a `String`
+                    holes.push(quote!(let #hole = &(#expr);));
+                    let bytes = quote_spanned! {expr.span()=>
+                        ::core::convert::AsRef::<[u8]>::as_ref(#hole)
+                    };
+                    lens.push(quote!(#bytes.len()));
+                    writes.push(quote!(#out.extend_from_slice(#bytes);));
+                }
+                Piece::Pad(byte, count) => {
+                    let len = hidden(&format!("len{i}"));
+                    holes.push(quote_spanned!(count.span()=> let #len: usize = #count;));
+                    lens.push(quote!(#len));
+                    writes.push(quote! {
+                        #out.resize(#out.len() + #len, #byte);
+                    });
+                }
+            }
+        }
+        tokens.extend(quote! {{
+            #(#holes)*
+            let mut #out = ::std::vec::Vec::<u8>::with_capacity(0 #(+ #lens)*);
+            #(#writes)*
+            #out
+        }});
+    }
+}
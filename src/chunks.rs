@@ -0,0 +1,134 @@
+//! Parsing and expansion for `strmatch_chunks!`, which walks fixed-size
+//! records, as wide as the pattern, and yields the captures of each one.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Expr, Token};
+
+use crate::engine::{captured, coerce_input, hidden, matcher, Codegen};
+use crate::pattern::Pattern;
+
+/// `input, pattern`, optionally preceded by `#[strict]` and a
+/// `#[codegen(..)]` attribute
+pub(crate) struct ChunksInput {
+    codegen: Codegen,
+    /// Whether records that don't match are yielded as errors rather than
+    /// skipped.
+    strict: bool,
+    input: Expr,
+    pattern: Pattern,
+}
+
+impl Parse for ChunksInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut codegen = Codegen::default();
+        let mut strict = false;
+        for attr in input.call(Attribute::parse_outer)? {
+            if attr.path.is_ident("codegen") {
+                codegen = Codegen::from_attr(&attr)?;
+            } else if attr.path.is_ident("strict") && attr.tokens.is_empty() {
+                strict = true;
+            } else {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected `#[strict]` or `#[codegen(..)]`",
+                ));
+            }
+        }
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let pattern = input.parse()?;
+        Ok(ChunksInput {
+            codegen,
+            strict,
+            input: expr,
+            pattern,
+        })
+    }
+}
+
+impl ToTokens for ChunksInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        // Every record is exactly as wide as the pattern
+        let mut len = 0;
+        for capture in &self.pattern.literals {
+            match capture.width() {
+                Some(width) => len += width,
+                None => abort!(
+                    capture.span(),
+                    "`strmatch_chunks!` splits its input into records as \
+                     wide as the pattern, so every segment has to match a \
+                     fixed number of bytes"
+                ),
+            }
+        }
+        if let Some(end) = &self.pattern.end {
+            abort!(
+                end.bracket.span,
+                "`strmatch_chunks!` splits its input into records as wide as \
+                 the pattern, so it can't end in a capture of the rest"
+            );
+        }
+        if len == 0 {
+            abort!(
+                proc_macro2::Span::call_site(),
+                "the pattern has to match at least one byte"
+            );
+        }
+
+        let input = hidden("input");
+        let chunk = hidden("chunk");
+        let chunks = hidden("chunks");
+        let matcher = matcher(
+            &chunk,
+            std::slice::from_ref(&self.pattern),
+            None,
+            self.codegen,
+        );
+        let bindings = self.pattern.bindings();
+        let captures = captured(&bindings);
+        let iter = if self.strict {
+            // Records that don't match, and a partial one at the end, are
+            // errors holding the offset they start at
+            let i = hidden("i");
+            let tail = hidden("tail");
+            quote! {
+                let #tail = (!#chunks.remainder().is_empty())
+                    .then(|| #input.len() - #chunks.remainder().len());
+                #chunks
+                    .enumerate()
+                    .map(|(#i, #chunk)| match #matcher {
+                        ::core::option::Option::Some((#(#bindings,)*)) => {
+                            ::core::result::Result::Ok(#captures)
+                        }
+                        ::core::option::Option::None => {
+                            ::core::result::Result::Err(#i * #len)
+                        }
+                    })
+                    .chain(#tail.map(::core::result::Result::Err))
+            }
+        } else {
+            quote! {
+                #chunks.filter_map(|#chunk| match #matcher {
+                    ::core::option::Option::Some((#(#bindings,)*)) => {
+                        ::core::option::Option::Some(#captures)
+                    }
+                    ::core::option::Option::None => ::core::option::Option::None,
+                })
+            }
+        };
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    let #chunks = #input.chunks_exact(#len);
+                    #iter
+                }
+            }
+        });
+    }
+}
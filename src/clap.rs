@@ -0,0 +1,107 @@
+//! Parsing and expansion for `strmatch_clap!`, which turns a pattern into a
+//! `clap` value parser.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, Expr, Ident, Token, Type};
+
+use crate::engine::{hidden, matcher, Codegen};
+use crate::pattern::Pattern;
+
+/// `Type, (pattern) => value`, optionally preceded by a `#[codegen(..)]`
+/// attribute
+pub(crate) struct ClapInput {
+    codegen: Codegen,
+    ty: Type,
+    pattern: Pattern,
+    /// The pattern as written, for the error message
+    printed: String,
+    value: Expr,
+}
+
+impl Parse for ClapInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let ty = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let inner;
+        parenthesized!(inner in input);
+        let printed = inner.fork().parse::<TokenStream>()?.to_string();
+        let printed = printed.split_whitespace().collect::<Vec<_>>().join(" ");
+        let pattern = inner.parse()?;
+        input.parse::<Token![=>]>()?;
+        let value = input.parse()?;
+        let _ = input.parse::<Option<Token![,]>>()?;
+        Ok(ClapInput {
+            codegen,
+            ty,
+            pattern,
+            printed,
+            value,
+        })
+    }
+}
+
+impl ToTokens for ClapInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ClapInput { ty, value, .. } = self;
+        let input = hidden("input");
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            self.codegen,
+        );
+        let bindings = self.pattern.bindings();
+        let expected = format!("expected the format `{}`", self.printed);
+        let parser = hidden("Parser");
+        let cmd = Ident::new("cmd", Span::mixed_site());
+        let arg = Ident::new("arg", Span::mixed_site());
+        let os = Ident::new("value", Span::mixed_site());
+        tokens.extend(quote! {{
+            #[derive(Clone, Copy)]
+            struct #parser;
+
+            impl ::clap::builder::TypedValueParser for #parser {
+                type Value = #ty;
+
+                fn parse_ref(
+                    &self,
+                    #cmd: &::clap::Command,
+                    #arg: ::core::option::Option<&::clap::Arg>,
+                    #os: &::std::ffi::OsStr,
+                ) -> ::core::result::Result<#ty, ::clap::Error> {
+                    let #input: &[u8] = #os.as_encoded_bytes();
+                    match #matcher {
+                        ::core::option::Option::Some((#(#bindings,)*)) => {
+                            ::core::result::Result::Ok(#value)
+                        }
+                        ::core::option::Option::None => {
+                            let value = #os.to_string_lossy();
+                            let message = match #arg {
+                                ::core::option::Option::Some(arg) => ::std::format!(
+                                    "invalid value '{}' for '{}': {}\n",
+                                    value, arg, #expected,
+                                ),
+                                ::core::option::Option::None => ::std::format!(
+                                    "invalid value '{}': {}\n",
+                                    value, #expected,
+                                ),
+                            };
+                            ::core::result::Result::Err(
+                                ::clap::Error::raw(
+                                    ::clap::error::ErrorKind::ValueValidation,
+                                    message,
+                                )
+                                .with_cmd(#cmd),
+                            )
+                        }
+                    }
+                }
+            }
+
+            #parser
+        }});
+    }
+}
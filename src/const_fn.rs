@@ -0,0 +1,87 @@
+//! Parsing and expansion for `strmatch_const_fn!`, which turns a pattern
+//! into a `const fn`. Const fns can't call the matcher macros' scanning code,
+//! so this only takes patterns that `strmatch!` could turn into a slice
+//! pattern.
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Ident, Token, Visibility};
+
+use crate::engine::hidden;
+use crate::pattern::Pattern;
+use crate::slice_pattern;
+
+/// `#[attrs] vis fn name = pattern`
+pub(crate) struct ConstFnInput {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    pattern: Pattern,
+}
+
+impl Parse for ConstFnInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![fn]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let pattern = input.parse()?;
+        let _ = input.parse::<Option<Token![;]>>()?;
+        Ok(ConstFnInput {
+            attrs,
+            vis,
+            name,
+            pattern,
+        })
+    }
+}
+
+impl ToTokens for ConstFnInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ConstFnInput {
+            attrs,
+            vis,
+            name,
+            pattern,
+        } = self;
+        let input = hidden("input");
+        let slice_pattern = slice_pattern(pattern);
+        // In a slice pattern, the whole match and the tail bind subslices,
+        // and everything else binds a single byte
+        let bindings = pattern.bindings();
+        let mut types = vec![];
+        if pattern.whole.is_some() {
+            types.push(quote!(&[u8]));
+        }
+        for capture in &pattern.literals {
+            types.extend(capture.bindings().iter().map(|_| quote!(&u8)));
+        }
+        if let Some(end) = &pattern.end {
+            types.extend(end.name.iter().map(|_| quote!(&[u8])));
+        }
+        let (output, matched) = match (bindings.as_slice(), types.as_slice()) {
+            ([], _) => {
+                tokens.extend(quote! {
+                    #(#attrs)*
+                    #vis const fn #name(#input: &[u8]) -> bool {
+                        ::core::matches!(#input, #slice_pattern)
+                    }
+                });
+                return;
+            }
+            ([binding], [ty]) => (quote!(#ty), quote!(#binding)),
+            _ => (quote!((#(#types,)*)), quote!((#(#bindings,)*))),
+        };
+        tokens.extend(quote! {
+            #(#attrs)*
+            #vis const fn #name(#input: &[u8]) -> ::core::option::Option<#output> {
+                match #input {
+                    #slice_pattern => ::core::option::Option::Some(#matched),
+                    _ => ::core::option::Option::None,
+                }
+            }
+        });
+    }
+}
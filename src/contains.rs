@@ -0,0 +1,123 @@
+//! Parsing and expansion for `strmatch_contains!`, which checks whether a
+//! pattern matches anywhere in its input.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Token};
+
+use crate::engine::{coerce_input, hidden, matcher, with_helpers, Codegen};
+use crate::find::position;
+use crate::pattern::Pattern;
+
+/// `input, pattern`
+pub(crate) struct ContainsInput {
+    input: Expr,
+    pattern: Pattern,
+    /// The fixed bytes every match starts with, which are searched for
+    prefix: Vec<u8>,
+    /// Whether the prefix is all there is to the pattern
+    literal: bool,
+}
+
+impl Parse for ContainsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let mut pattern: Pattern = input.parse()?;
+        let prefix = pattern
+            .literals
+            .iter()
+            .map_while(|capture| capture.literal_bytes())
+            .flatten()
+            .collect::<Vec<_>>();
+        let literal = pattern.end.is_none()
+            && pattern.whole.is_none()
+            && pattern.min_len.is_none()
+            && pattern
+                .literals
+                .iter()
+                .all(|capture| capture.literal_bytes().is_some());
+        // A match only has to be at the start of where it's tried
+        pattern.capture_rest(hidden("rest"), Span::call_site());
+        Ok(ContainsInput {
+            input: expr,
+            pattern,
+            prefix,
+            literal,
+        })
+    }
+}
+
+impl ToTokens for ContainsInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(with_helpers(|| self.expand()));
+    }
+}
+
+impl ContainsInput {
+    fn expand(&self) -> TokenStream {
+        let input = hidden("input");
+        let coerce = coerce_input(&input, &self.input);
+        let expr = &self.input;
+        let search = hidden("search");
+        // Nothing to match after the search
+        if self.literal {
+            let position = position(&search, &self.prefix);
+            return quote! {
+                match #expr {
+                    #input => {
+                        #coerce
+                        let #search: &[u8] = #input;
+                        (#position).is_some()
+                    }
+                }
+            };
+        }
+        let left = hidden("left");
+        let matcher = matcher(
+            &left,
+            std::slice::from_ref(&self.pattern),
+            None,
+            Codegen::default(),
+        );
+        let start = hidden("start");
+        let at = hidden("at");
+        // Where to try the pattern next, at or after `start`. Without a
+        // prefix to search for, that's every offset, including the end,
+        // where it might match nothing.
+        let find = if self.prefix.is_empty() {
+            quote! {
+                if #start > #input.len() {
+                    break false;
+                }
+                let #at = #start;
+            }
+        } else {
+            let position = position(&search, &self.prefix);
+            quote! {
+                let #search: &[u8] = &#input[#start..];
+                let ::core::option::Option::Some(#at) = (#position) else {
+                    break false;
+                };
+                let #at = #start + #at;
+            }
+        };
+        quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    let mut #start = 0usize;
+                    loop {
+                        #find
+                        let #left: &[u8] = &#input[#at..];
+                        if #matcher.is_some() {
+                            break true;
+                        }
+                        #start = #at + 1;
+                    }
+                }
+            }
+        }
+    }
+}
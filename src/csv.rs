@@ -0,0 +1,244 @@
+//! Parsing and expansion for `strcsv!`, which splits a CSV row into fields
+//! and matches each one against its own pattern.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, token, Expr, Lifetime, Token};
+
+use crate::engine::{bind_slice, coerce_input, hidden, matcher, Codegen};
+use crate::pattern::{Pattern, SliceCapture};
+
+/// `input, field, field, ..`
+pub(crate) struct CsvInput {
+    input: Expr,
+    fields: Vec<Field>,
+    /// Whether a trailing `..` allows more fields than were listed.
+    open: bool,
+}
+
+/// How a single field is matched.
+enum Field {
+    /// `_`: anything
+    Skip,
+    /// `[name]` or `[name: Type]`, optionally with a guard: bind the
+    /// (unescaped) field
+    Capture(SliceCapture),
+    /// `(pattern)`: the field must match the pattern
+    Pattern(Pattern),
+}
+
+impl Parse for CsvInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        let mut fields = vec![];
+        let mut open = false;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            if open {
+                return Err(input.error("`..` has to be the last field"));
+            }
+            let lookahead = input.lookahead1();
+            if lookahead.peek(Token![..]) {
+                input.parse::<Token![..]>()?;
+                open = true;
+            } else if lookahead.peek(Token![_]) {
+                input.parse::<Token![_]>()?;
+                fields.push(Field::Skip);
+            } else if lookahead.peek(token::Bracket) {
+                let capture = input.parse::<SliceCapture>()?;
+                if capture.lazy || capture.until.is_some() {
+                    abort!(
+                        capture.bracket.span,
+                        "fields always span up to the next `,`, so `lazy`, \
+                         `greedy` and `until` don't apply"
+                    );
+                }
+                fields.push(Field::Capture(capture));
+            } else if lookahead.peek(token::Paren) {
+                let inner;
+                let paren = parenthesized!(inner in input);
+                let pattern = inner.parse::<Pattern>()?;
+                if !pattern.bindings().is_empty() {
+                    abort!(
+                        paren.span,
+                        "patterns on fields can't bind names, since quoted \
+                         fields may have to be unescaped into a temporary \
+                         buffer";
+                        help = "bind the whole field with `[name]` instead"
+                    );
+                }
+                fields.push(Field::Pattern(pattern));
+            } else {
+                return Err(lookahead.error());
+            }
+        }
+        Ok(CsvInput {
+            input: expr,
+            fields,
+            open,
+        })
+    }
+}
+
+impl ToTokens for CsvInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let rest = hidden("rest");
+        let next_field = hidden("next_field");
+        let label =
+            Lifetime::new("'__strmatch", proc_macro2::Span::mixed_site());
+        let result = hidden("result");
+
+        let bindings = self
+            .fields
+            .iter()
+            .filter_map(|field| match field {
+                Field::Capture(capture) => capture.name.clone(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let captures = match bindings.as_slice() {
+            [binding] => quote!(#binding),
+            _ => quote!((#(#bindings,)*)),
+        };
+        let check_rest = (!self.open).then(|| quote!(if #rest.is_none()));
+        let mut next = quote! {
+            #check_rest {
+                break #label ::core::option::Option::Some(#captures);
+            }
+        };
+        // Build the matching code inside out, so that each field wraps the
+        // code for the fields after it
+        for (i, field) in self.fields.iter().enumerate().rev() {
+            let value = hidden(&format!("field{i}"));
+            let inner = match field {
+                Field::Skip => next,
+                Field::Capture(capture) => {
+                    let next = match &capture.guard {
+                        Some(guard) => quote!(if #guard { #next }),
+                        None => next,
+                    };
+                    match (&capture.name, &capture.ty) {
+                        (_, Some(_)) => {
+                            bind_slice(capture, i, quote!(&#value[..]), next)
+                        }
+                        _ if !capture.steps.is_empty() => {
+                            bind_slice(capture, i, quote!(&#value[..]), next)
+                        }
+                        // Trimming keeps a borrowed field borrowed
+                        (Some(name), None) if capture.trim => quote! {
+                            let #name = match #value {
+                                ::std::borrow::Cow::Borrowed(v) => {
+                                    ::std::borrow::Cow::Borrowed(<[u8]>::trim_ascii(v))
+                                }
+                                ::std::borrow::Cow::Owned(v) => ::std::borrow::Cow::Owned(
+                                    <[u8]>::trim_ascii(&v).to_vec(),
+                                ),
+                            };
+                            #next
+                        },
+                        (Some(name), None) => quote!(let #name = #value; #next),
+                        (None, None) => next,
+                    }
+                }
+                Field::Pattern(pattern) => {
+                    let matcher = matcher(
+                        &value,
+                        std::slice::from_ref(pattern),
+                        None,
+                        Codegen::default(),
+                    );
+                    quote! {
+                        let #value: &[u8] = &#value[..];
+                        if #matcher.is_some() {
+                            #next
+                        }
+                    }
+                }
+            };
+            next = quote! {
+                if let ::core::option::Option::Some(#value) = #next_field(&mut #rest) {
+                    #inner
+                }
+            };
+        }
+
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    // Splits the next field off the row, unescaping doubled
+                    // quotes in quoted fields. `rest` becomes `None` after
+                    // the last field.
+                    fn #next_field<'a>(
+                        rest: &mut ::core::option::Option<&'a [u8]>,
+                    ) -> ::core::option::Option<::std::borrow::Cow<'a, [u8]>> {
+                        let input = rest.take()?;
+                        if input.first() != ::core::option::Option::Some(&b'"') {
+                            return ::core::option::Option::Some(
+                                match input.iter().position(|b| *b == b',') {
+                                    ::core::option::Option::Some(i) => {
+                                        *rest = ::core::option::Option::Some(&input[i + 1..]);
+                                        ::std::borrow::Cow::Borrowed(&input[..i])
+                                    }
+                                    ::core::option::Option::None => {
+                                        ::std::borrow::Cow::Borrowed(input)
+                                    }
+                                },
+                            );
+                        }
+                        let mut owned: ::core::option::Option<::std::vec::Vec<u8>> =
+                            ::core::option::Option::None;
+                        let mut start = 1;
+                        let mut i = 1;
+                        loop {
+                            match input.get(i)? {
+                                b'"' if input.get(i + 1) == ::core::option::Option::Some(&b'"') => {
+                                    owned
+                                        .get_or_insert_with(::std::vec::Vec::new)
+                                        .extend_from_slice(&input[start..=i]);
+                                    i += 2;
+                                    start = i;
+                                }
+                                b'"' => break,
+                                _ => i += 1,
+                            }
+                        }
+                        match input.get(i + 1) {
+                            ::core::option::Option::None => {}
+                            ::core::option::Option::Some(b',') => {
+                                *rest = ::core::option::Option::Some(&input[i + 2..]);
+                            }
+                            // Junk after the closing quote
+                            ::core::option::Option::Some(_) => return ::core::option::Option::None,
+                        }
+                        ::core::option::Option::Some(match owned {
+                            ::core::option::Option::Some(mut owned) => {
+                                owned.extend_from_slice(&input[start..i]);
+                                ::std::borrow::Cow::Owned(owned)
+                            }
+                            ::core::option::Option::None => {
+                                ::std::borrow::Cow::Borrowed(&input[1..i])
+                            }
+                        })
+                    }
+
+                    #[allow(unreachable_code, unused_mut)]
+                    let #result = #label: {
+                        let mut #rest = ::core::option::Option::Some(#input);
+                        #next
+                        ::core::option::Option::None
+                    };
+                    #result
+                }
+            }
+        });
+    }
+}
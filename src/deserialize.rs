@@ -0,0 +1,121 @@
+//! Expansion for `#[strmatch_deserialize]`, which implements serde's
+//! `Deserialize` for a struct by matching the string it's given against a
+//! pattern whose captures are named after its fields.
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{format_ident, quote};
+use syn::{Fields, GenericParam, Ident, ItemStruct, Lifetime, LifetimeDef};
+
+use crate::engine::{hidden, matcher, Codegen};
+use crate::from_str::{named_fields, typed_fields};
+use crate::pattern::Pattern;
+
+/// Expand to the struct's `Deserialize` impl.
+pub(crate) fn deserialize(
+    mut pattern: Pattern,
+    item: &ItemStruct,
+) -> TokenStream {
+    let name = &item.ident;
+    // A newtype's one field is whatever the pattern's one capture is called
+    let fields = match &item.fields {
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            let bindings = pattern.bindings();
+            let [binding] = bindings.as_slice() else {
+                abort!(
+                    unnamed,
+                    "a newtype is deserialized from a pattern with exactly one \
+                     capture, but this one has {}",
+                    bindings.len()
+                );
+            };
+            vec![(binding.clone(), unnamed.unnamed[0].ty.clone())]
+        }
+        _ => named_fields(item, "strmatch_deserialize"),
+    };
+    typed_fields(&mut pattern, &fields, item);
+    let bindings = pattern.bindings();
+    let value = match &item.fields {
+        Fields::Unit => quote!(#name),
+        Fields::Unnamed(_) => quote!(#name(#(#bindings),*)),
+        Fields::Named(_) => quote!(#name { #(#bindings,)* }),
+    };
+
+    let input = hidden("input");
+    let matcher = matcher(
+        &input,
+        std::slice::from_ref(&pattern),
+        None,
+        Codegen::default(),
+    );
+    let de = Lifetime::new("'__strmatch_de", Span::call_site());
+    let mut generics = item.generics.clone();
+    generics
+        .params
+        .insert(0, GenericParam::Lifetime(LifetimeDef::new(de.clone())));
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (visitor_generics, ty_generics, where_clause) =
+        item.generics.split_for_impl();
+    let visitor = format_ident!("{}Visitor", name, span = Span::mixed_site());
+    let expecting = format!("a string in the format of `{name}`");
+    let s = Ident::new("s", Span::mixed_site());
+    let error = Ident::new("E", Span::mixed_site());
+    let deserializer = Ident::new("D", Span::mixed_site());
+    quote! {
+        impl #impl_generics ::serde::Deserialize<#de> for #name #ty_generics #where_clause {
+            fn deserialize<#deserializer>(
+                deserializer: #deserializer,
+            ) -> ::core::result::Result<Self, #deserializer::Error>
+            where
+                #deserializer: ::serde::Deserializer<#de>,
+            {
+                struct #visitor #visitor_generics (
+                    ::core::marker::PhantomData<fn() -> #name #ty_generics>,
+                ) #where_clause;
+
+                impl #impl_generics ::serde::de::Visitor<#de> for #visitor #ty_generics #where_clause {
+                    type Value = #name #ty_generics;
+
+                    fn expecting(
+                        &self,
+                        f: &mut ::core::fmt::Formatter<'_>,
+                    ) -> ::core::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_str<#error: ::serde::de::Error>(
+                        self,
+                        #s: &str,
+                    ) -> ::core::result::Result<Self::Value, #error> {
+                        let #input: &[u8] = #s.as_bytes();
+                        match #matcher {
+                            ::core::option::Option::Some((#(#bindings,)*)) => {
+                                ::core::result::Result::Ok(#value)
+                            }
+                            ::core::option::Option::None => ::core::result::Result::Err(
+                                #error::invalid_value(::serde::de::Unexpected::Str(#s), &self),
+                            ),
+                        }
+                    }
+
+                    fn visit_bytes<#error: ::serde::de::Error>(
+                        self,
+                        #s: &[u8],
+                    ) -> ::core::result::Result<Self::Value, #error> {
+                        let #input: &[u8] = #s;
+                        match #matcher {
+                            ::core::option::Option::Some((#(#bindings,)*)) => {
+                                ::core::result::Result::Ok(#value)
+                            }
+                            ::core::option::Option::None => ::core::result::Result::Err(
+                                #error::invalid_value(::serde::de::Unexpected::Bytes(#s), &self),
+                            ),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_str(#visitor(::core::marker::PhantomData))
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Expansion for `#[strmatch_display]`, which implements `Display` for a
+//! struct by writing its fields back into a pattern's literal skeleton.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::quote;
+use syn::{Ident, ItemStruct};
+
+use crate::from_str::named_fields;
+use crate::pattern::{Capture, Pattern};
+
+// The field a capture binds, if it's one that can be written back: a
+// bracketed capture, a `#utf8char` or a `#grapheme`.
+fn field(capture: &Capture) -> Option<&Ident> {
+    match capture {
+        Capture::Slice(slice) => slice.name.as_ref(),
+        Capture::Utf8Char { binding, .. }
+        | Capture::Grapheme { binding, .. } => binding.as_ref(),
+        Capture::Guarded { capture, .. } => field(capture),
+        _ => None,
+    }
+}
+
+/// Expand to the struct's `Display` impl.
+pub(crate) fn display(pattern: Pattern, item: &ItemStruct) -> TokenStream {
+    let fields = named_fields(item, "strmatch_display");
+    if let Some(whole) = &pattern.whole {
+        abort!(
+            whole,
+            "`{} @ (..)` can't be written back, since it's the whole string",
+            whole
+        );
+    }
+    let mut writes = vec![];
+    let end = pattern.end.map(Capture::Slice);
+    for capture in pattern.literals.iter().chain(&end) {
+        // Lookahead doesn't match anything, so there's nothing to write
+        if let Capture::Peek { .. } = capture {
+            continue;
+        }
+        // A line ending is written the Unix way
+        if let Capture::Newline { .. } = capture {
+            writes.push(quote!(f.write_str("\n")?;));
+            continue;
+        }
+        // A case-insensitive literal is written as it's spelled
+        let literal = match capture {
+            Capture::NoCase { literal, .. } => literal,
+            capture => capture,
+        };
+        if let Some(bytes) = literal.literal_bytes() {
+            match String::from_utf8(bytes) {
+                Ok(text) => writes.push(quote!(f.write_str(#text)?;)),
+                Err(_) => abort!(
+                    capture.span(),
+                    "this literal isn't UTF-8, so a `Display` impl can't \
+                     write it"
+                ),
+            }
+            continue;
+        }
+        match field(capture) {
+            Some(name) if fields.iter().any(|(field, _)| field == name) => {
+                writes.push(quote!(::core::fmt::Display::fmt(&self.#name, f)?;))
+            }
+            Some(name) => {
+                abort!(name, "`{}` isn't a field of `{}`", name, item.ident)
+            }
+            None => abort!(
+                capture.span(),
+                "this segment can't be written back, since it doesn't say \
+                 what to write";
+                help = "only literals and captures of fields, like `[name]` \
+                        or `name @ #utf8char`, can be written back"
+            ),
+        }
+    }
+
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        item.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #(#writes)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    }
+}
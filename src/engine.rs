@@ -0,0 +1,1034 @@
+//! Code generation for matchers: expressions that run a pattern against a
+//! `&[u8]` and evaluate to `Option<(captures,)>`. Unlike the bare slice
+//! patterns emitted by `strmatch!`, matchers can check guards, so they back
+//! the macros that need more than a pattern can express.
+
+use std::cell::RefCell;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::parse::ParseStream;
+use syn::spanned::Spanned;
+use syn::{Attribute, Expr, Ident, Lifetime, LitByteStr};
+
+use crate::find::position;
+use crate::pattern::{Capture, Class, Pattern, SliceCapture, Step};
+use crate::presets::PresetArg;
+
+/// An identifier only visible to generated code. These use mixed-site
+/// hygiene so they can never clash with the user's bindings.
+pub(crate) fn hidden(name: &str) -> Ident {
+    format_ident!("__strmatch_{}", name, span = Span::mixed_site())
+}
+
+/// How a matcher tests runs of fixed-width captures.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Codegen {
+    /// As a slice pattern, `if let [b'a', x, rest @ ..] = input`
+    #[default]
+    SlicePattern,
+    /// As a length check and comparisons, `if input.len() >= 2 && input[0]
+    /// == b'a'`, for when a backend optimizes those better
+    IfChain,
+    /// Like `IfChain`, but comparing each literal of more than one byte with
+    /// a call to an `#[inline(never)]` function, shared by every use of the
+    /// same literal in the macro, to keep the generated code small
+    SharedHelpers,
+    /// Like `IfChain`, but comparing each literal of more than one byte
+    /// against a `const` array, shared like `SharedHelpers`' functions, with
+    /// slice equality
+    ConstArray,
+}
+
+impl Codegen {
+    /// Parse the `#[codegen(..)]` attributes a matcher macro can start with.
+    pub(crate) fn parse_attrs(input: ParseStream) -> syn::Result<Codegen> {
+        let mut codegen = Codegen::default();
+        for attr in input.call(Attribute::parse_outer)? {
+            if !attr.path.is_ident("codegen") {
+                return Err(syn::Error::new_spanned(
+                    attr.path,
+                    "expected `codegen`",
+                ));
+            }
+            codegen = Codegen::from_attr(&attr)?;
+        }
+        Ok(codegen)
+    }
+
+    /// Parse the mode out of a single `#[codegen(..)]` attribute.
+    pub(crate) fn from_attr(attr: &Attribute) -> syn::Result<Codegen> {
+        let mode = attr.parse_args::<Ident>()?;
+        match mode.to_string().as_str() {
+            "slice_pattern" => Ok(Codegen::SlicePattern),
+            "if_chain" => Ok(Codegen::IfChain),
+            "shared_helpers" => Ok(Codegen::SharedHelpers),
+            "const_array" => Ok(Codegen::ConstArray),
+            _ => Err(syn::Error::new(
+                mode.span(),
+                "expected `slice_pattern`, `if_chain`, `shared_helpers` or \
+                 `const_array`",
+            )),
+        }
+    }
+}
+
+// A literal a matcher compares against out of line, with the mode that
+// compares it
+type Helper = (Vec<u8>, Codegen);
+
+thread_local! {
+    // The literals that the `SharedHelpers` and `ConstArray` matchers of the
+    // macro being expanded compare against, or `None` outside of
+    // `with_helpers`
+    static HELPERS: RefCell<Option<Vec<Helper>>> = const { RefCell::new(None) };
+}
+
+/// Run `expand`, then define the helper functions and consts that the
+/// matchers in what it expanded to use, around it. Macros with more than one
+/// matcher, like one per arm, wrap all of them in this so the arms share their
+/// helpers.
+pub(crate) fn with_helpers(
+    expand: impl FnOnce() -> TokenStream,
+) -> TokenStream {
+    // Already inside another call, which defines the helpers
+    if HELPERS.with(|helpers| helpers.borrow().is_some()) {
+        return expand();
+    }
+    // Cleared on the way out even if `expand` aborts, so the next macro
+    // doesn't think it's inside this one
+    struct Clear;
+    impl Drop for Clear {
+        fn drop(&mut self) {
+            HELPERS.with(|helpers| helpers.borrow_mut().take());
+        }
+    }
+    let _clear = Clear;
+    HELPERS.with(|helpers| *helpers.borrow_mut() = Some(vec![]));
+    let tokens = expand();
+    let literals = HELPERS.with(|helpers| helpers.borrow_mut().take());
+    let helpers = literals.unwrap_or_default().into_iter().enumerate().map(
+        |(i, (bytes, codegen))| {
+            let helper = hidden(&format!("literal{i}"));
+            let n = bytes.len();
+            match codegen {
+                Codegen::ConstArray => quote! {
+                    #[allow(non_upper_case_globals)]
+                    const #helper: [u8; #n] = [#(#bytes),*];
+                },
+                _ => quote! {
+                    #[inline(never)]
+                    fn #helper(bytes: &[u8]) -> bool {
+                        bytes == [#(#bytes),*]
+                    }
+                },
+            }
+        },
+    );
+    quote!({ #(#helpers)* #tokens })
+}
+
+// The helper function or const that `codegen` compares against `bytes`
+// with, made if it's the first use of them
+fn helper(bytes: &[u8], codegen: Codegen) -> Ident {
+    HELPERS.with(|helpers| {
+        let mut helpers = helpers.borrow_mut();
+        let helpers = helpers.as_mut().expect("matchers make helpers");
+        let helper = (bytes.to_vec(), codegen);
+        let i = match helpers.iter().position(|other| *other == helper) {
+            Some(i) => i,
+            None => {
+                helpers.push(helper);
+                helpers.len() - 1
+            }
+        };
+        hidden(&format!("literal{i}"))
+    })
+}
+
+/// Rebind `input`, which holds the scrutinee `expr`, as a `&[u8]`. A
+/// scrutinee of the wrong type gets pointed out rather than the whole macro.
+pub(crate) fn coerce_input(input: &Ident, expr: &Expr) -> TokenStream {
+    let scrutinee = Ident::new(
+        &input.to_string(),
+        Span::mixed_site().located_at(expr.span()),
+    );
+    quote!(let #input: &[u8] = #scrutinee;)
+}
+
+/// What a macro hands back for the names a pattern binds. As in
+/// `strextract!`, a lone capture is handed back as is rather than in a
+/// 1-tuple.
+pub(crate) fn captured(bindings: &[Ident]) -> TokenStream {
+    match bindings {
+        [binding] => quote!(#binding),
+        bindings => quote!((#(#bindings,)*)),
+    }
+}
+
+/// Expands to an expression of type `Option<(captures,)>` that tries each
+/// alternative against `input` in order. All alternatives must bind the
+/// same names; the tuple holds them in the order the first alternative
+/// binds them.
+pub(crate) fn matcher(
+    input: &Ident,
+    alternatives: &[Pattern],
+    guard: Option<&Expr>,
+    codegen: Codegen,
+) -> TokenStream {
+    // Named after the input so that nested matchers don't shadow each other
+    let label = Lifetime::new(&format!("'{input}"), Span::mixed_site());
+    let result = hidden("result");
+    let bindings = alternatives
+        .first()
+        .map(|pattern| pattern.bindings())
+        .unwrap_or_default();
+    let done =
+        quote!(break #label ::core::option::Option::Some((#(#bindings,)*)));
+    let done = match guard {
+        Some(guard) => quote!(if #guard { #done; }),
+        None => quote!(#done;),
+    };
+    let warnings = alternatives
+        .iter()
+        .flat_map(Pattern::bindings)
+        .filter(looks_like_const)
+        .map(|name| {
+            let note = format!(
+                "`{name}` binds a new name rather than comparing against a \
+                 const; write `{{{name}}}` to compare against the const's value"
+            );
+            // Stable Rust has no way for a macro to emit a warning, but
+            // using a deprecated item does warn, with its note
+            let warning = Ident::new(
+                "__strmatch_const_like_capture",
+                Span::mixed_site().located_at(name.span()),
+            );
+            quote! {{
+                #[deprecated(note = #note)]
+                fn #warning() {}
+                #warning();
+            }}
+        });
+    with_helpers(|| {
+        let alternatives = alternatives
+            .iter()
+            .map(|pattern| emit(pattern, input, &done, codegen));
+        quote! {{
+            #(#warnings)*
+            #[allow(unreachable_code, irrefutable_let_patterns, unused_parens)]
+            let #result = #label: {
+                #(#alternatives)*
+                ::core::option::Option::None
+            };
+            #result
+        }}
+    })
+}
+
+// Whether a capture's name is SCREAMING_CASE, like a const's, which is most
+// likely a const the pattern was meant to compare against. Writing it as
+// `r#NAME` or `_NAME` says the new binding is meant.
+fn looks_like_const(name: &Ident) -> bool {
+    let name = name.to_string();
+    !name.starts_with("r#")
+        && !name.starts_with('_')
+        && name.len() > 1
+        && name.chars().any(|c| c.is_ascii_uppercase())
+        && !name.chars().any(|c| c.is_lowercase())
+}
+
+/// Emit the code matching `pattern` against `input`, running `done` once
+/// everything (including any guards) has matched.
+fn emit(
+    pattern: &Pattern,
+    input: &Ident,
+    done: &TokenStream,
+    codegen: Codegen,
+) -> TokenStream {
+    let next = emit_from(pattern, 0, input, done, codegen);
+    let next = match pattern.min_len {
+        Some((min_len, _)) => {
+            pattern.check_min_len();
+            quote!(if #input.len() >= #min_len { #next })
+        }
+        None => next,
+    };
+    match &pattern.whole {
+        // A pattern always matches all of its input
+        Some(whole) => quote!({ let #whole = #input; #next }),
+        None => next,
+    }
+}
+
+// Emit the code matching the captures of `pattern` from index `start`
+// onwards. Runs of fixed-width captures are tested all at once, and each
+// variable-width capture wraps the code matching everything after it.
+fn emit_from(
+    pattern: &Pattern,
+    start: usize,
+    input: &Ident,
+    done: &TokenStream,
+    codegen: Codegen,
+) -> TokenStream {
+    let captures = &pattern.literals[start..];
+    let fixed = captures.iter().take_while(|c| !c.is_variable()).count();
+    let variable = captures.get(fixed);
+    let next = match variable {
+        Some(variable) => emit_variable(
+            variable,
+            start + fixed,
+            input,
+            emit_from(pattern, start + fixed + 1, input, done, codegen),
+            codegen,
+        ),
+        None => done.clone(),
+    };
+    match codegen {
+        Codegen::SlicePattern => slice_pattern(
+            &captures[..fixed],
+            start,
+            pattern,
+            variable,
+            input,
+            next,
+        ),
+        Codegen::IfChain | Codegen::SharedHelpers | Codegen::ConstArray => {
+            if_chain(
+                &captures[..fixed],
+                pattern,
+                variable,
+                input,
+                next,
+                codegen,
+            )
+        }
+    }
+}
+
+// Match a run of fixed-width captures with a slice pattern, running `next`
+// on what's left after them. Without a `variable` capture after the run,
+// the run has to take everything up to the pattern's end capture.
+fn slice_pattern(
+    captures: &[Capture],
+    start: usize,
+    pattern: &Pattern,
+    variable: Option<&Capture>,
+    input: &Ident,
+    next: TokenStream,
+) -> TokenStream {
+    let mut elems = TokenStream::new();
+    let mut guards = vec![];
+    for (i, capture) in captures.iter().enumerate() {
+        match capture {
+            Capture::Var { expr, .. } => {
+                let var = hidden(&format!("var{}", start + i));
+                elems.extend(quote!(#var,));
+                // Spanned so a type mismatch points at the expression
+                guards.push(quote_spanned!(expr.span()=> *#var == (#expr)));
+            }
+            // Compared in a guard, so a name that isn't a const is an error
+            // rather than a binding
+            Capture::Const { path, .. } => {
+                let var = hidden(&format!("var{}", start + i));
+                elems.extend(quote!(#var,));
+                guards.push(quote_spanned!(path.span()=> *#var == #path));
+            }
+            Capture::Guarded { capture, guard } => {
+                capture.to_tokens(&mut elems);
+                guards.push(quote!((#guard)));
+            }
+            other => other.to_tokens(&mut elems),
+        }
+    }
+    let next = if guards.is_empty() {
+        next
+    } else {
+        quote!(if #(#guards)&&* { #next })
+    };
+
+    if variable.is_none() {
+        // Everything left has to match exactly, up to the end capture
+        let end = &pattern.end;
+        return quote!(if let [#elems #end] = #input { #next });
+    }
+    if elems.is_empty() {
+        return next;
+    }
+    let rest = hidden("rest");
+    quote! {
+        if let [#elems #rest @ ..] = #input {
+            let #input = #rest;
+            #next
+        }
+    }
+}
+
+// Match a run of fixed-width captures with a length check and a chain of
+// comparisons, indexing into the input, in the same way as `slice_pattern`.
+fn if_chain(
+    captures: &[Capture],
+    pattern: &Pattern,
+    variable: Option<&Capture>,
+    input: &Ident,
+    next: TokenStream,
+    codegen: Codegen,
+) -> TokenStream {
+    let mut at = 0;
+    let mut checks = vec![];
+    let mut binds = vec![];
+    let mut guards = vec![];
+    for capture in captures {
+        let capture = match capture {
+            Capture::Guarded { capture, guard } => {
+                guards.push(quote!((#guard)));
+                capture
+            }
+            capture => capture,
+        };
+        match capture {
+            Capture::Var { expr, .. } => checks
+                .push(quote_spanned!(expr.span()=> #input[#at] == (#expr))),
+            Capture::Const { path, .. } => {
+                checks.push(quote_spanned!(path.span()=> #input[#at] == #path))
+            }
+            Capture::Ident(ident) => {
+                binds.push(quote!(let #ident = &#input[#at];))
+            }
+            Capture::Underscore(_) => {}
+            Capture::ByteRange {
+                binding,
+                start,
+                end,
+            } => {
+                let byte =
+                    |literal: &Capture| literal.literal_bytes().unwrap()[0];
+                let lo = byte(start);
+                match end {
+                    Some(end) => {
+                        let hi = byte(end);
+                        checks.push(quote!((#lo..=#hi).contains(&#input[#at])));
+                    }
+                    None => checks.push(quote!(#input[#at] == #lo)),
+                }
+                if let Some(binding) = binding {
+                    binds.push(quote!(let #binding = &#input[#at];));
+                }
+            }
+            Capture::NoCase { literal, .. } => {
+                let bytes = literal.literal_bytes().unwrap();
+                let end = at + bytes.len();
+                checks.push(quote! {
+                    #input[#at..#end].eq_ignore_ascii_case(&[#(#bytes),*])
+                });
+            }
+            // `#hexbyte`, the only fixed-width preset
+            Capture::Preset { .. } => checks.push(quote! {
+                #input[#at].is_ascii_hexdigit()
+                    && #input[#at + 1].is_ascii_hexdigit()
+            }),
+            literal
+                if matches!(
+                    codegen,
+                    Codegen::SharedHelpers | Codegen::ConstArray
+                ) && literal
+                    .literal_bytes()
+                    .is_some_and(|bytes| bytes.len() > 1) =>
+            {
+                let bytes = literal.literal_bytes().unwrap();
+                let helper = helper(&bytes, codegen);
+                let end = at + bytes.len();
+                checks.push(match codegen {
+                    Codegen::ConstArray => quote!(#input[#at..#end] == #helper),
+                    _ => quote!(#helper(&#input[#at..#end])),
+                });
+            }
+            literal => {
+                let bytes = literal.literal_bytes().unwrap_or_default();
+                checks.extend(bytes.iter().enumerate().map(|(i, byte)| {
+                    let i = at + i;
+                    quote!(#input[#i] == #byte)
+                }));
+            }
+        }
+        at += capture.width().unwrap_or(0);
+    }
+    let next = if guards.is_empty() {
+        next
+    } else {
+        quote!(if #(#guards)&&* { #next })
+    };
+
+    let len = match (variable, &pattern.end) {
+        // Everything left has to match exactly, up to the end capture
+        (None, None) => Some(quote!(#input.len() == #at)),
+        // Nothing to check before an end capture or a variable-width one
+        (Some(_), _) if at == 0 => return next,
+        (None, Some(_)) if at == 0 => None,
+        _ => Some(quote!(#input.len() >= #at)),
+    };
+    match (variable, &pattern.end) {
+        (
+            None,
+            Some(SliceCapture {
+                name: Some(name), ..
+            }),
+        ) => binds.push(quote!(let #name = &#input[#at..];)),
+        (Some(_), _) => binds.push(quote!(let #input = &#input[#at..];)),
+        _ => {}
+    }
+    let checks = len.into_iter().chain(checks).collect::<Vec<_>>();
+    if checks.is_empty() {
+        return quote!({ #(#binds)* #next });
+    }
+    quote! {
+        if #(#checks)&&* {
+            #(#binds)*
+            #next
+        }
+    }
+}
+
+// Emit the code matching a single variable-width capture at the start of
+// `input`, running `next` on whatever is left after it.
+fn emit_variable(
+    capture: &Capture,
+    index: usize,
+    input: &Ident,
+    next: TokenStream,
+    codegen: Codegen,
+) -> TokenStream {
+    match capture {
+        Capture::Guarded { capture, guard } => emit_variable(
+            capture,
+            index,
+            input,
+            quote!(if #guard { #next }),
+            codegen,
+        ),
+        Capture::Group { count, pattern, .. } => {
+            // Match the group for as long as it matches and makes progress,
+            // collecting its captures
+            // Named after the input too, so nested groups get names of
+            // their own
+            let group = format_ident!(
+                "{}_group{}",
+                input,
+                index,
+                span = Span::mixed_site()
+            );
+            let mut bindings = pattern.bindings();
+            let left = bindings.pop().expect("groups capture what's left");
+            let collected = (0..bindings.len())
+                .map(|i| {
+                    format_ident!("{}_{}", group, i, span = Span::mixed_site())
+                })
+                .collect::<Vec<_>>();
+            let matcher =
+                matcher(&group, std::slice::from_ref(pattern), None, codegen);
+            let (count, bump) = match count {
+                Some(count) => {
+                    (quote!(let mut #count: usize = 0;), quote!(#count += 1;))
+                }
+                None => (TokenStream::new(), TokenStream::new()),
+            };
+            quote! {
+                let mut #input = #input;
+                #count
+                #(let mut #collected = ::std::vec::Vec::new();)*
+                loop {
+                    let #group: &[u8] = #input;
+                    match #matcher {
+                        ::core::option::Option::Some((#(#bindings,)* #left,))
+                            if #left.len() < #input.len() =>
+                        {
+                            #bump
+                            #(#collected.push(#bindings);)*
+                            #input = #left;
+                        }
+                        _ => break,
+                    }
+                }
+                #(let #bindings = #collected;)*
+                #next
+            }
+        }
+        Capture::Utf8Char { binding, .. } => {
+            // The width is known from the first byte, then `from_utf8`
+            // rejects bad continuation bytes, overlong forms and surrogates
+            let width = hidden(&format!("width{index}"));
+            let str = hidden(&format!("str{index}"));
+            let char = hidden(&format!("char{index}"));
+            let bind =
+                binding.as_ref().map(|ident| quote!(let #ident = #char;));
+            quote! {
+                let #width = match #input.first() {
+                    ::core::option::Option::Some(0x00..=0x7f) => 1,
+                    ::core::option::Option::Some(0xc2..=0xdf) => 2,
+                    ::core::option::Option::Some(0xe0..=0xef) => 3,
+                    ::core::option::Option::Some(0xf0..=0xf4) => 4,
+                    _ => 0,
+                };
+                if #width != 0 && #input.len() >= #width {
+                    if let ::core::result::Result::Ok(#str) =
+                        ::core::str::from_utf8(&#input[..#width])
+                    {
+                        if let ::core::option::Option::Some(#char) = #str.chars().next() {
+                            #bind
+                            let #input = &#input[#width..];
+                            #next
+                        }
+                    }
+                }
+            }
+        }
+        Capture::Grapheme { binding, .. } => {
+            // Segmenting needs text, but validating all of what's left for
+            // every cluster would be quadratic, so only a window at the start
+            // is validated, doubling it while the cluster could go on past it
+            let window = hidden(&format!("window{index}"));
+            let chunk = hidden(&format!("chunk{index}"));
+            let text = hidden(&format!("text{index}"));
+            let ends = hidden(&format!("ends{index}"));
+            let cluster = hidden(&format!("cluster{index}"));
+            let bind =
+                binding.as_ref().map(|ident| quote!(let #ident = #cluster;));
+            quote! {
+                let mut #window = 16;
+                let #cluster = loop {
+                    let #chunk = &#input[..#input.len().min(#window)];
+                    let (#text, #ends) = match ::core::str::from_utf8(#chunk) {
+                        ::core::result::Result::Ok(#text) => {
+                            (#text, #chunk.len() == #input.len())
+                        }
+                        // Either the window cut a character short, or there's
+                        // a byte that can't be UTF-8, which ends the text
+                        ::core::result::Result::Err(error) => (
+                            ::core::str::from_utf8(&#chunk[..error.valid_up_to()]).unwrap(),
+                            error.error_len().is_some() || #chunk.len() == #input.len(),
+                        ),
+                    };
+                    let #cluster =
+                        ::unicode_segmentation::UnicodeSegmentation::graphemes(#text, true)
+                            .next();
+                    if #ends || #cluster.map_or(0, str::len) < #text.len() {
+                        break #cluster;
+                    }
+                    #window *= 2;
+                };
+                if let ::core::option::Option::Some(#cluster) = #cluster {
+                    #bind
+                    let #input = &#input[#cluster.len()..];
+                    #next
+                }
+            }
+        }
+        Capture::Whitespace {
+            optional, binding, ..
+        } => {
+            let byte = hidden("byte");
+            let in_run = quote!(#byte.is_ascii_whitespace());
+            let scan = scan_bytes(input, &byte, in_run);
+            emit_run(index, input, scan, *optional, binding, next)
+        }
+        Capture::OneOf { options, .. } => {
+            // Each literal is tried in turn, going on with the rest of the
+            // pattern after it, so a shorter one that's a prefix of a longer
+            // one doesn't hide it
+            let option = hidden(&format!("option{index}"));
+            let options = options.iter().map(|option| {
+                let bytes = option.literal_bytes().unwrap();
+                LitByteStr::new(&bytes, option.span())
+            });
+            quote! {
+                for #option in [#(&#options[..]),*] {
+                    if #input.starts_with(#option) {
+                        let #input = &#input[#option.len()..];
+                        #next
+                    }
+                }
+            }
+        }
+        Capture::Newline { eol, .. } => {
+            // The line endings can't both be at the start, so whichever is
+            // there is the only way to go on
+            let width = hidden(&format!("width{index}"));
+            let end =
+                eol.then(|| quote!([] => ::core::option::Option::Some(0),));
+            quote! {
+                let #width = match #input {
+                    [b'\n', ..] => ::core::option::Option::Some(1),
+                    [b'\r', b'\n', ..] => ::core::option::Option::Some(2),
+                    #end
+                    _ => ::core::option::Option::None,
+                };
+                if let ::core::option::Option::Some(#width) = #width {
+                    let #input = &#input[#width..];
+                    #next
+                }
+            }
+        }
+        Capture::Run {
+            class: Class::Unicode(category),
+            optional,
+            binding,
+            ..
+        } => {
+            let scan = category.scan(index, input);
+            emit_run(index, input, scan, *optional, binding, next)
+        }
+        Capture::Run {
+            class,
+            optional,
+            binding,
+            ..
+        } => {
+            let byte = hidden("byte");
+            // Long runs of classes made of several ranges are tested a byte
+            // at a time against a table, rather than against each range
+            let in_run = match class.bitmap() {
+                Some(set) => quote! {{
+                    static CLASS: [u64; 4] = [#(#set),*];
+                    (CLASS[usize::from(*#byte / 64)] >> (*#byte % 64)) & 1 == 1
+                }},
+                None => class.contains(&byte),
+            };
+            let scan = scan_bytes(input, &byte, in_run);
+            emit_run(index, input, scan, *optional, binding, next)
+        }
+        Capture::Pad { fill, count, .. } => {
+            let byte = fill.literal_bytes().unwrap()[0];
+            let len = hidden(&format!("len{index}"));
+            // Spanned so a count of the wrong type is pointed out
+            let count = quote_spanned!(count.span()=> #count);
+            quote! {
+                let #len: usize = #count;
+                if #input.len() >= #len && #input[..#len].iter().all(|b| *b == #byte) {
+                    let #input = &#input[#len..];
+                    #next
+                }
+            }
+        }
+        Capture::Peek { bytes, negated, .. } => {
+            let not = negated.then(|| quote!(!));
+            quote! {
+                if #not #input.starts_with(&[#(#bytes),*]) {
+                    #next
+                }
+            }
+        }
+        Capture::Preset {
+            preset,
+            binding,
+            args,
+            ..
+        } => {
+            let len = hidden(&format!("len{index}"));
+            let bind = binding
+                .as_ref()
+                .map(|ident| quote!(let #ident = &#input[..#len];));
+            let mut outputs = vec![];
+            let mut checks = vec![];
+            let mut binds = vec![];
+            for (i, arg) in args.iter().enumerate() {
+                let output = hidden(&format!("output{index}_{i}"));
+                match arg {
+                    PresetArg::Skip => outputs.push(quote!(_)),
+                    PresetArg::Bind(ident) => {
+                        binds.push(quote!(let #ident = #output;));
+                        outputs.push(output.into_token_stream());
+                    }
+                    PresetArg::Bytes(bytes, span) => {
+                        let lit = LitByteStr::new(bytes, *span);
+                        checks.push(quote!(#output == #lit));
+                        outputs.push(output.into_token_stream());
+                    }
+                    PresetArg::Value(lit) => {
+                        checks.push(quote!(#output == #lit));
+                        outputs.push(output.into_token_stream());
+                    }
+                }
+            }
+            let outputs = if args.is_empty() {
+                quote!(_)
+            } else {
+                quote!((#(#outputs,)*))
+            };
+            let checks = (!checks.is_empty()).then(|| quote!(if #(#checks)&&*));
+            let scan = preset.scan(input);
+            quote! {
+                if let ::core::option::Option::Some((#len, #outputs)) = #scan {
+                    #checks {
+                        #bind
+                        #(#binds)*
+                        let #input = &#input[#len..];
+                        #next
+                    }
+                }
+            }
+        }
+        Capture::Slice(slice) => {
+            let len = hidden(&format!("len{index}"));
+            let next = bind_slice(
+                slice,
+                index,
+                quote!(&#input[..#len]),
+                quote! {
+                    let #input = &#input[#len..];
+                    #next
+                },
+            );
+            match &slice.until {
+                Some(delimiter) => {
+                    // Split right before the first occurrence of the
+                    // delimiter
+                    let position = position(input, delimiter);
+                    quote! {
+                        if let ::core::option::Option::Some(#len) = #position {
+                            #next
+                        }
+                    }
+                }
+                None if slice.is_float() || slice.is_signed() => {
+                    let scan = if slice.is_float() {
+                        float_len(input)
+                    } else {
+                        int_len(input)
+                    };
+                    quote! {
+                        if let ::core::option::Option::Some(#len) = #scan {
+                            #next
+                        }
+                    }
+                }
+                None => {
+                    // Try every length the capture could have, backtracking
+                    // until the rest of the pattern matches
+                    let lens = if slice.lazy {
+                        quote!(0..=#input.len())
+                    } else {
+                        quote!((0..=#input.len()).rev())
+                    };
+                    quote!(for #len in #lens { #next })
+                }
+            }
+        }
+        _ => unreachable!("only variable-width captures are emitted here"),
+    }
+}
+
+// The length of the float at the start of `input`, if there is one: an
+// optional sign, digits with an optional fraction (at least one digit in all,
+// so `1.` and `.5` count), then an optional exponent. It's taken whole, and
+// always parses as an `f32` or `f64`.
+fn float_len(input: &Ident) -> TokenStream {
+    let float_len = hidden("float_len");
+    quote! {{
+        fn #float_len(input: &[u8]) -> ::core::option::Option<usize> {
+            let digits = |at: usize| {
+                input
+                    .get(at..)
+                    .map_or(0, |rest| rest.iter().take_while(|b| b.is_ascii_digit()).count())
+            };
+            let mut at = usize::from(::core::matches!(input.first(), Some(b'+' | b'-')));
+            let int = digits(at);
+            at += int;
+            let dot = input.get(at) == ::core::option::Option::Some(&b'.');
+            let frac = if dot { digits(at + 1) } else { 0 };
+            if int + frac == 0 {
+                return ::core::option::Option::None;
+            }
+            if dot {
+                at += 1 + frac;
+            }
+            if ::core::matches!(input.get(at), Some(b'e' | b'E')) {
+                let sign = usize::from(::core::matches!(input.get(at + 1), Some(b'+' | b'-')));
+                let exponent = digits(at + 1 + sign);
+                if exponent > 0 {
+                    at += 1 + sign + exponent;
+                }
+            }
+            ::core::option::Option::Some(at)
+        }
+        #float_len(#input)
+    }}
+}
+
+// The length of the signed integer at the start of `input`, if there is one:
+// an optional sign, then at least one digit
+fn int_len(input: &Ident) -> TokenStream {
+    quote! {{
+        let sign = usize::from(::core::matches!(#input.first(), Some(b'+' | b'-')));
+        match #input[sign..].iter().take_while(|b| b.is_ascii_digit()).count() {
+            0 => ::core::option::Option::None,
+            digits => ::core::option::Option::Some(sign + digits),
+        }
+    }}
+}
+
+// How many bytes at the start of `input` `in_run` accepts, as an expression
+fn scan_bytes(input: &Ident, byte: &Ident, in_run: TokenStream) -> TokenStream {
+    quote! {
+        #input
+            .iter()
+            .position(|#byte| !(#in_run))
+            .unwrap_or(#input.len())
+    }
+}
+
+// Take the whole run at the start of `input`, `scan` bytes long, binding it
+// to `binding`. Since the run is taken whole, there's nothing to backtrack
+// over.
+fn emit_run(
+    index: usize,
+    input: &Ident,
+    scan: TokenStream,
+    optional: bool,
+    binding: &Option<Ident>,
+    next: TokenStream,
+) -> TokenStream {
+    let len = hidden(&format!("len{index}"));
+    let bind = binding
+        .as_ref()
+        .map(|ident| quote!(let #ident = &#input[..#len];));
+    let check = (!optional).then(|| quote!(if #len > 0));
+    quote! {
+        let #len = #scan;
+        #check {
+            #bind
+            let #input = &#input[#len..];
+            #next
+        }
+    }
+}
+
+// Run `value` through each step of a pipeline, binding what comes out of the
+// last one to `name` if none of them fail.
+fn pipeline(
+    steps: &[Step],
+    name: &Ident,
+    value: TokenStream,
+    next: TokenStream,
+) -> TokenStream {
+    let step = Ident::new("__StrmatchStep", Span::mixed_site());
+    let bytes = quote!(::core::convert::AsRef::<[u8]>::as_ref(#name));
+    let mut code = next;
+    for each in steps.iter().rev() {
+        let some = quote!(::core::option::Option::Some);
+        let (ok, output) = match each {
+            Step::Trim => {
+                (some.clone(), quote!(#some(<[u8]>::trim_ascii(#bytes))))
+            }
+            Step::Utf8 => (
+                quote!(::core::result::Result::Ok),
+                quote!(::core::str::from_utf8(#bytes)),
+            ),
+            Step::BStr => {
+                (some.clone(), quote!(#some(::bstr::BStr::new(#bytes))))
+            }
+            Step::Parse(ty) => {
+                // Spanned so a type that can't be parsed is pointed out
+                let parse = quote_spanned!(ty.span()=> <#ty as ::core::str::FromStr>::from_str);
+                let output = quote! {
+                    ::core::str::from_utf8(#bytes).ok().and_then(|s| #parse(s).ok())
+                };
+                (some.clone(), output)
+            }
+            Step::Call(call) => {
+                let call = quote_spanned!(call.span()=> #call(#name));
+                (some.clone(), quote!(#step::step(#call)))
+            }
+        };
+        code = quote! {
+            if let #ok(#name) = #output {
+                #code
+            }
+        };
+    }
+    // Functions can fail with an `Option` or a `Result`, which this turns
+    // into the same thing
+    let calls = steps.iter().any(|step| matches!(step, Step::Call(_)));
+    let trait_def = calls.then(|| {
+        quote! {
+            trait #step<T> {
+                fn step(self) -> ::core::option::Option<T>;
+            }
+            impl<T> #step<T> for ::core::option::Option<T> {
+                fn step(self) -> ::core::option::Option<T> {
+                    self
+                }
+            }
+            impl<T, E> #step<T> for ::core::result::Result<T, E> {
+                fn step(self) -> ::core::option::Option<T> {
+                    self.ok()
+                }
+            }
+        }
+    });
+    quote! {
+        #trait_def
+        let #name = #value;
+        #code
+    }
+}
+
+/// Bind the bytes a bracketed capture matched, `value`, and run `next`. A
+/// typed capture only runs `next` if the bytes parse into its type.
+pub(crate) fn bind_slice(
+    slice: &SliceCapture,
+    index: usize,
+    value: TokenStream,
+    next: TokenStream,
+) -> TokenStream {
+    let name = slice
+        .name
+        .clone()
+        .unwrap_or_else(|| hidden(&format!("value{index}")));
+    let value = if slice.trim {
+        quote!(<[u8]>::trim_ascii(#value))
+    } else {
+        value
+    };
+    if !slice.steps.is_empty() {
+        return pipeline(&slice.steps, &name, value, next);
+    }
+    match &slice.ty {
+        None if slice.name.is_none() => next,
+        None => quote! {
+            let #name = #value;
+            #next
+        },
+        Some(ty) => {
+            // Spanned so a type that can't be parsed is pointed out
+            let parse = quote_spanned!(ty.span()=> <#ty as ::core::str::FromStr>::from_str);
+            let parsed = if slice.saturating {
+                // Only overflow is clamped, anything else still fails
+                quote! {
+                    match #parse(s) {
+                        ::core::result::Result::Ok(n) => ::core::option::Option::Some(n),
+                        ::core::result::Result::Err(e) => match e.kind() {
+                            ::core::num::IntErrorKind::PosOverflow => {
+                                ::core::option::Option::Some(<#ty>::MAX)
+                            }
+                            ::core::num::IntErrorKind::NegOverflow => {
+                                ::core::option::Option::Some(<#ty>::MIN)
+                            }
+                            _ => ::core::option::Option::None,
+                        },
+                    }
+                }
+            } else {
+                quote!(#parse(s).ok())
+            };
+            quote! {
+                if let ::core::option::Option::Some(#name) =
+                    ::core::str::from_utf8(#value)
+                        .ok()
+                        .and_then(|s| #parsed)
+                {
+                    #next
+                }
+            }
+        }
+    }
+}
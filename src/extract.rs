@@ -0,0 +1,138 @@
+//! Parsing and expansion for `strextract!`, which runs a single pattern as
+//! an expression and hands back its captures, and `strextract_owned!`, which
+//! hands back owned copies of them.
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Token};
+
+use crate::engine::{coerce_input, hidden, matcher, Codegen};
+use crate::pattern::{Capture, Pattern, SliceCapture};
+
+/// `input, pattern`, optionally preceded by a `#[codegen(..)]` attribute
+pub(crate) struct ExtractInput {
+    codegen: Codegen,
+    input: Expr,
+    pattern: Pattern,
+    /// Whether to hand back owned copies of the captures
+    pub(crate) owned: bool,
+}
+
+impl Parse for ExtractInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let pattern = input.parse()?;
+        Ok(ExtractInput {
+            codegen,
+            input: expr,
+            pattern,
+            owned: false,
+        })
+    }
+}
+
+impl ToTokens for ExtractInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            self.codegen,
+        );
+        let bindings = self.pattern.bindings();
+        let outputs: Vec<_> = if self.owned {
+            bindings
+                .iter()
+                .zip(depths(&self.pattern, 0))
+                .map(|(binding, depth)| to_owned(binding, depth))
+                .collect()
+        } else {
+            bindings.iter().map(|binding| quote!(#binding)).collect()
+        };
+        // A lone capture is handed back as is rather than in a 1-tuple
+        let matcher = match outputs.as_slice() {
+            [output] => quote! {
+                match #matcher {
+                    ::core::option::Option::Some((#(#bindings,)*)) => {
+                        ::core::option::Option::Some(#output)
+                    }
+                    ::core::option::Option::None => ::core::option::Option::None,
+                }
+            },
+            _ if self.owned => quote! {
+                match #matcher {
+                    ::core::option::Option::Some((#(#bindings,)*)) => {
+                        ::core::option::Option::Some((#(#outputs,)*))
+                    }
+                    ::core::option::Option::None => ::core::option::Option::None,
+                }
+            },
+            _ => matcher,
+        };
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        // The input is bound by a `match` rather than a `let`: temporaries in
+        // it, like the `String` in `line.to_string().as_bytes()`, then live
+        // until the end of the whole expression, and the captures borrow
+        // from what the input points at rather than from a local, so they
+        // can be returned from a function taking `line`
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    #matcher
+                }
+            }
+        });
+    }
+}
+
+// An owned copy of a capture `depth` groups deep, which is that many `Vec`s
+// around the capture. `ToOwned` turns `&[u8]` into `Vec<u8>`, `&str` into
+// `String` and `&u8` into `u8`, and leaves values that are already owned,
+// like parsed numbers, as they are.
+fn to_owned(binding: &Ident, depth: usize) -> TokenStream {
+    match depth {
+        // A method call, so a reference is followed to what it points at
+        0 => quote!(#binding.to_owned()),
+        _ => {
+            let item = hidden("item");
+            let inner = to_owned(&item, depth - 1);
+            quote! {
+                ::core::iter::IntoIterator::into_iter(#binding)
+                    .map(|#item| #inner)
+                    .collect::<::std::vec::Vec<_>>()
+            }
+        }
+    }
+}
+
+// How many groups deep each of the pattern's bindings is, in the order that
+// `Pattern::bindings` gives them in
+fn depths(pattern: &Pattern, depth: usize) -> Vec<usize> {
+    let mut depths = vec![depth; pattern.whole.iter().count()];
+    for capture in &pattern.literals {
+        depths.extend(capture_depths(capture, depth));
+    }
+    if let Some(SliceCapture { name: Some(_), .. }) = &pattern.end {
+        depths.push(depth);
+    }
+    depths
+}
+
+fn capture_depths(capture: &Capture, depth: usize) -> Vec<usize> {
+    match capture {
+        Capture::Guarded { capture, .. } => capture_depths(capture, depth),
+        Capture::Group { count, pattern, .. } => {
+            // Leaving out the hidden capture of what's left
+            let mut inner = depths(pattern, depth + 1);
+            inner.pop();
+            count.iter().map(|_| depth).chain(inner).collect()
+        }
+        capture => vec![depth; capture.bindings().len()],
+    }
+}
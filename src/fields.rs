@@ -0,0 +1,189 @@
+//! Parsing and expansion for `strmatch_fields!`, which splits its input on a
+//! delimiter and matches each field against its own pattern.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, token, Expr, Lifetime, Token};
+
+use crate::engine::{
+    captured, coerce_input, hidden, matcher, with_helpers, Codegen,
+};
+use crate::pattern::{Capture, Pattern, SliceCapture};
+
+/// `input, delimiter; field, field, ..`, optionally preceded by a
+/// `#[codegen(..)]` attribute
+pub(crate) struct FieldsInput {
+    codegen: Codegen,
+    input: Expr,
+    delimiter: Vec<u8>,
+    /// `None` for a `_` field, which matches anything
+    fields: Vec<Option<Pattern>>,
+    /// Whether a trailing `..` allows more fields than were listed.
+    open: bool,
+}
+
+impl Parse for FieldsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let delimiter = input.parse::<Capture>()?;
+        let delimiter = match delimiter.literal_bytes() {
+            Some(bytes) if !bytes.is_empty() => bytes,
+            _ => abort!(
+                delimiter.span(),
+                "expected a non-empty literal to split fields on"
+            ),
+        };
+        input.parse::<Token![;]>()?;
+        let mut fields = vec![];
+        let mut open = false;
+        while !input.is_empty() {
+            if open {
+                return Err(input.error("`..` has to be the last field"));
+            }
+            let lookahead = input.lookahead1();
+            if lookahead.peek(Token![..]) {
+                input.parse::<Token![..]>()?;
+                open = true;
+            } else if lookahead.peek(Token![_]) {
+                input.parse::<Token![_]>()?;
+                fields.push(None);
+            } else if lookahead.peek(token::Bracket) {
+                // `[name]` is short for `(name @ ..)`, and typed captures
+                // work the same way
+                let capture = input.parse::<SliceCapture>()?;
+                let pattern = if capture.is_plain() {
+                    Pattern {
+                        whole: None,
+                        min_len: None,
+                        literals: vec![],
+                        end: Some(capture),
+                    }
+                } else {
+                    Pattern {
+                        whole: None,
+                        min_len: None,
+                        literals: vec![Capture::Slice(capture)],
+                        end: None,
+                    }
+                };
+                fields.push(Some(pattern));
+            } else if lookahead.peek(token::Paren) {
+                let inner;
+                parenthesized!(inner in input);
+                fields.push(Some(inner.parse()?));
+            } else {
+                return Err(lookahead.error());
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(FieldsInput {
+            codegen,
+            input: expr,
+            delimiter,
+            fields,
+            open,
+        })
+    }
+}
+
+impl ToTokens for FieldsInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        // Fields share the helper functions and consts of `shared_helpers` and
+        // `const_array` matchers
+        tokens.extend(with_helpers(|| self.expand()));
+    }
+}
+
+impl FieldsInput {
+    fn expand(&self) -> TokenStream {
+        let input = hidden("input");
+        let rest = hidden("rest");
+        let label =
+            Lifetime::new("'__strmatch", proc_macro2::Span::mixed_site());
+        let result = hidden("result");
+
+        let bindings = self
+            .fields
+            .iter()
+            .flatten()
+            .flat_map(Pattern::bindings)
+            .collect::<Vec<_>>();
+        let captures = captured(&bindings);
+        let check_rest = (!self.open).then(|| quote!(if #rest.is_none()));
+        let mut next = quote! {
+            #check_rest {
+                break #label ::core::option::Option::Some(#captures);
+            }
+        };
+        // Build the matching code inside out, so that each field wraps the
+        // code for the fields after it
+        for (i, field) in self.fields.iter().enumerate().rev() {
+            let value = hidden(&format!("field{i}"));
+            let inner = match field {
+                None => next,
+                Some(pattern) => {
+                    let bindings = pattern.bindings();
+                    let matcher = matcher(
+                        &value,
+                        std::slice::from_ref(pattern),
+                        None,
+                        self.codegen,
+                    );
+                    quote! {
+                        if let ::core::option::Option::Some((#(#bindings,)*)) = #matcher {
+                            #next
+                        }
+                    }
+                }
+            };
+            let len = self.delimiter.len();
+            let position = match self.delimiter.as_slice() {
+                [byte] => quote!(#value.iter().position(|b| *b == #byte)),
+                bytes => quote! {
+                    #value.windows(#len).position(|w| w == [#(#bytes),*])
+                },
+            };
+            next = quote! {
+                if let ::core::option::Option::Some(#value) = #rest {
+                    let #value: &[u8] = #value;
+                    // Split the field off; `rest` becomes `None` after the
+                    // last one
+                    let (#value, #rest) = match #position {
+                        ::core::option::Option::Some(i) => (
+                            &#value[..i],
+                            ::core::option::Option::Some(&#value[i + #len..]),
+                        ),
+                        ::core::option::Option::None => {
+                            (#value, ::core::option::Option::None)
+                        }
+                    };
+                    #inner
+                }
+            };
+        }
+
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    #[allow(unreachable_code, irrefutable_let_patterns)]
+                    let #result = #label: {
+                        let #rest = ::core::option::Option::Some(#input);
+                        #next
+                        ::core::option::Option::None
+                    };
+                    #result
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Parsing and expansion for `strmatch_find!`, which finds a literal in its
+//! input with a search table built at compile time, `strmatch_split_once!`,
+//! which splits its input around one, and `strmatch_count!`, which counts
+//! them. The same search is used for the delimiters of `until` captures.
+
+use std::collections::BTreeMap;
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Ident, Lit, Token};
+
+use crate::engine::{coerce_input, hidden};
+use crate::pattern::Pattern;
+
+/// `input, "needle"`
+pub(crate) struct FindInput {
+    input: Expr,
+    needle: Vec<u8>,
+}
+
+impl Parse for FindInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let needle = match input.parse::<Lit>()? {
+            Lit::Str(lit) => lit.value().into_bytes(),
+            Lit::ByteStr(lit) => lit.value(),
+            Lit::Char(lit) => lit.value().to_string().into_bytes(),
+            Lit::Byte(lit) => vec![lit.value()],
+            lit => abort!(
+                lit,
+                "expected a string, byte string, char or byte literal to find"
+            ),
+        };
+        Ok(FindInput {
+            input: expr,
+            needle,
+        })
+    }
+}
+
+impl ToTokens for FindInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let coerce = coerce_input(&input, &self.input);
+        let position = position(&input, &self.needle);
+        let expr = &self.input;
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    #position
+                }
+            }
+        });
+    }
+}
+
+/// `input, delimiter`, where the delimiter is a pattern of fixed bytes, like
+/// `"::"` or `'\r' '\n'`
+pub(crate) struct SplitOnceInput {
+    input: Expr,
+    delimiter: Vec<u8>,
+}
+
+impl Parse for SplitOnceInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        Ok(SplitOnceInput {
+            input: expr,
+            delimiter: fixed_bytes(input)?,
+        })
+    }
+}
+
+// The bytes of a pattern that only matches fixed bytes, like `"::"` or
+// `'\r' '\n'`, which can't be empty
+fn fixed_bytes(input: ParseStream) -> syn::Result<Vec<u8>> {
+    let span = input.span();
+    let pattern = input.parse::<Pattern>()?;
+    let mut bytes = vec![];
+    for capture in &pattern.literals {
+        match capture.literal_bytes() {
+            Some(literal) => bytes.extend(literal),
+            None => {
+                abort!(capture.span(), "expected fixed bytes, like a literal")
+            }
+        }
+    }
+    if pattern.whole.is_some()
+        || pattern.min_len.is_some()
+        || pattern.end.is_some()
+    {
+        abort!(span, "expected fixed bytes, like a literal");
+    }
+    if bytes.is_empty() {
+        abort!(span, "expected at least one byte to look for");
+    }
+    Ok(bytes)
+}
+
+impl ToTokens for SplitOnceInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let coerce = coerce_input(&input, &self.input);
+        let position = position(&input, &self.delimiter);
+        let len = self.delimiter.len();
+        let at = hidden("at");
+        let expr = &self.input;
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    match #position {
+                        ::core::option::Option::Some(#at) => ::core::option::Option::Some(
+                            (&#input[..#at], &#input[#at + #len..]),
+                        ),
+                        ::core::option::Option::None => ::core::option::Option::None,
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// `input, pattern`, where the pattern is fixed bytes like for
+/// `strmatch_split_once!`
+pub(crate) struct CountInput {
+    input: Expr,
+    needle: Vec<u8>,
+}
+
+impl Parse for CountInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        Ok(CountInput {
+            input: expr,
+            needle: fixed_bytes(input)?,
+        })
+    }
+}
+
+impl ToTokens for CountInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let coerce = coerce_input(&input, &self.input);
+        let rest = hidden("rest");
+        let position = position(&rest, &self.needle);
+        let len = self.needle.len();
+        let count = hidden("count");
+        let at = hidden("at");
+        let expr = &self.input;
+        // Each search starts after the last occurrence, so they can't overlap
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    let mut #rest = #input;
+                    let mut #count = 0usize;
+                    while let ::core::option::Option::Some(#at) = #position {
+                        #count += 1;
+                        #rest = &#rest[#at + #len..];
+                    }
+                    #count
+                }
+            }
+        });
+    }
+}
+
+/// An expression giving the offset of the first occurrence of `needle` in
+/// `haystack`, an `Option<usize>`.
+///
+/// Longer needles use Horspool's algorithm: the last byte of each window is
+/// looked up in a table of how far the window can move before that byte
+/// could line up with the same byte in the needle, which is worked out here
+/// rather than at runtime.
+pub(crate) fn position(haystack: &Ident, needle: &[u8]) -> TokenStream {
+    let n = needle.len();
+    let (last, init) = match needle.split_last() {
+        None => return quote!(::core::option::Option::Some(0usize)),
+        Some((byte, [])) => {
+            return quote!(#haystack.iter().position(|b| *b == #byte));
+        }
+        Some(split) => split,
+    };
+    // The later occurrence of a byte wins, being the shorter shift
+    let shifts = init
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| (byte, n - 1 - i))
+        .collect::<BTreeMap<_, _>>();
+    let (bytes, shifts) = shifts.into_iter().unzip::<_, _, Vec<_>, Vec<_>>();
+    let at = hidden("at");
+    let window = hidden("window");
+    quote! {{
+        const NEEDLE: [u8; #n] = [#(#needle),*];
+        const SHIFT: [usize; 256] = {
+            let mut shift = [#n; 256];
+            #(shift[#bytes as usize] = #shifts;)*
+            shift
+        };
+        let mut #at = 0usize;
+        loop {
+            let ::core::option::Option::Some(#window) = #haystack.get(#at..#at + #n) else {
+                break ::core::option::Option::None;
+            };
+            if #window[#n - 1] == #last && #window == NEEDLE {
+                break ::core::option::Option::Some(#at);
+            }
+            #at += SHIFT[usize::from(#window[#n - 1])];
+        }
+    }}
+}
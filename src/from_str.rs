@@ -0,0 +1,223 @@
+//! Expansion for `#[strmatch_from_str]`, which derives `FromStr` for a
+//! struct from a pattern whose captures are named after its fields.
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{format_ident, quote};
+use syn::{Fields, Ident, ItemStruct, LitStr, Type};
+
+use crate::engine::{hidden, matcher, Codegen};
+use crate::pattern::{Capture, Pattern};
+
+/// Turn a format string like `"{x: i32},{y}"` into the pattern
+/// `[x: i32] "," [y]`. `{{` and `}}` stand for literal braces.
+pub(crate) fn format_pattern(format: &LitStr) -> TokenStream {
+    let span = format.span();
+    let mut tokens = TokenStream::new();
+    let mut text = String::new();
+    let format_value = format.value();
+    let mut chars = format_value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => abort!(
+                            span,
+                            "unclosed `{{` in format string";
+                            help = "write `{{{{` for a literal `{{`"
+                        ),
+                    }
+                }
+                if !text.is_empty() {
+                    let lit = LitStr::new(&std::mem::take(&mut text), span);
+                    tokens.extend(quote!(#lit));
+                }
+                let (name, ty) = match placeholder.split_once(':') {
+                    Some((name, ty)) => (name, Some(ty)),
+                    None => (placeholder.as_str(), None),
+                };
+                let name = match syn::parse_str::<Ident>(name.trim()) {
+                    Ok(name) => Ident::new(&name.to_string(), span),
+                    Err(_) => abort!(
+                        span,
+                        "expected a field name in `{{{}}}`",
+                        placeholder
+                    ),
+                };
+                match ty.map(syn::parse_str::<Type>) {
+                    Some(Ok(ty)) => tokens.extend(quote!([#name: #ty])),
+                    Some(Err(_)) => {
+                        abort!(span, "expected a type in `{{{}}}`", placeholder)
+                    }
+                    None => tokens.extend(quote!([#name])),
+                }
+            }
+            '}' => abort!(
+                span,
+                "unmatched `}}` in format string";
+                help = "write `}}}}` for a literal `}}`"
+            ),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        let lit = LitStr::new(&text, span);
+        tokens.extend(quote!(#lit));
+    }
+    tokens
+}
+
+// Bracketed captures named after a field, and without a type of their own,
+// get parsed into the field's type.
+fn fill_types(capture: &mut Capture, fields: &[(Ident, Type)]) {
+    match capture {
+        Capture::Slice(slice)
+            if slice.ty.is_none() && slice.steps.is_empty() =>
+        {
+            let field = fields
+                .iter()
+                .find(|(name, _)| Some(name) == slice.name.as_ref());
+            if let Some((_, ty)) = field {
+                slice.ty = Some(Box::new(ty.clone()));
+            }
+        }
+        Capture::Guarded { capture, .. } => fill_types(capture, fields),
+        _ => {}
+    }
+}
+
+/// The names and types of a struct's fields, which `attribute` refers to by
+/// name.
+pub(crate) fn named_fields(
+    item: &ItemStruct,
+    attribute: &str,
+) -> Vec<(Ident, Type)> {
+    match &item.fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|field| (field.ident.clone().unwrap(), field.ty.clone()))
+            .collect(),
+        Fields::Unit => vec![],
+        Fields::Unnamed(unnamed) => abort!(
+            unnamed,
+            "`#[{}]` refers to fields by name, so it needs a struct with \
+             named fields",
+            attribute
+        ),
+    }
+}
+
+/// Give the captures of `pattern` named after `fields` their field's type.
+pub(crate) fn fill_field_types(
+    pattern: &mut Pattern,
+    fields: &[(Ident, Type)],
+) {
+    // The tail capture of a pattern is a plain slice, so one that needs a
+    // type has to become a capture like any other
+    if let Some(end) = pattern.end.take_if(|end| {
+        fields
+            .iter()
+            .any(|(field, _)| Some(field) == end.name.as_ref())
+    }) {
+        pattern.literals.push(Capture::Slice(end));
+    }
+    for capture in &mut pattern.literals {
+        fill_types(capture, fields);
+    }
+}
+
+/// Give the captures of `pattern` named after `fields` their field's type,
+/// checking that it binds every field and nothing else.
+pub(crate) fn typed_fields(
+    pattern: &mut Pattern,
+    fields: &[(Ident, Type)],
+    item: &ItemStruct,
+) {
+    fill_field_types(pattern, fields);
+    let bindings = pattern.bindings();
+    for (field, _) in fields {
+        if !bindings.contains(field) {
+            abort!(
+                field,
+                "the pattern never binds `{}`", field;
+                help = "add a capture like `[{}]` to the pattern", field
+            );
+        }
+    }
+    if let Some(extra) = bindings
+        .iter()
+        .find(|b| !fields.iter().any(|(f, _)| f == *b))
+    {
+        abort!(extra, "`{}` isn't a field of `{}`", extra, item.ident);
+    }
+}
+
+/// Expand to an error type for the struct and its `FromStr` impl.
+pub(crate) fn from_str(mut pattern: Pattern, item: &ItemStruct) -> TokenStream {
+    let fields = named_fields(item, "strmatch_from_str");
+    typed_fields(&mut pattern, &fields, item);
+    let bindings = pattern.bindings();
+
+    let name = &item.ident;
+    let vis = &item.vis;
+    let error = format_ident!("Parse{}Error", name);
+    let error_doc =
+        format!("The error returned when parsing a `{name}` fails.");
+    let message = format!("string doesn't match the format of `{name}`");
+    let (impl_generics, ty_generics, where_clause) =
+        item.generics.split_for_impl();
+    let input = hidden("input");
+    let matcher = matcher(
+        &input,
+        std::slice::from_ref(&pattern),
+        None,
+        Codegen::default(),
+    );
+    let value = match &item.fields {
+        Fields::Unit => quote!(#name),
+        _ => quote!(#name { #(#bindings,)* }),
+    };
+    let s = Ident::new("s", Span::mixed_site());
+    quote! {
+        #[doc = #error_doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #vis struct #error;
+
+        impl ::core::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(#message)
+            }
+        }
+
+        impl ::std::error::Error for #error {}
+
+        impl #impl_generics ::core::str::FromStr for #name #ty_generics #where_clause {
+            type Err = #error;
+
+            fn from_str(#s: &str) -> ::core::result::Result<Self, Self::Err> {
+                let #input: &[u8] = #s.as_bytes();
+                match #matcher {
+                    ::core::option::Option::Some((#(#bindings,)*)) => {
+                        ::core::result::Result::Ok(#value)
+                    }
+                    ::core::option::Option::None => {
+                        ::core::result::Result::Err(#error)
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,884 @@
+//! Generating inputs that match a pattern, for `strmatch_arbitrary!` and
+//! `strmatch_proptest!`. The
+//! generated code makes each of its random choices with an expression the
+//! backend supplies, which picks a `usize` in a range, and writes what it
+//! picked into a `Vec<u8>`.
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitByteStr, Type};
+
+use crate::engine::hidden;
+use crate::pattern::{
+    Alternatives, Capture, Class, Pattern, SliceCapture, Step,
+};
+use crate::presets::{Preset, PresetArg};
+
+/// How long runs and captures of varying length get, and how many times
+/// repeated groups repeat
+const MAX_LEN: usize = 8;
+const MAX_REPS: usize = 4;
+
+/// How many bytes `strmatch_proptest!` draws its choices from
+const MAX_CHOICES: usize = 512;
+
+/// A part of what a preset matches, written the same way each time
+enum Part {
+    Lit(&'static [u8]),
+    /// Between `min` and `max` bytes, each one of `set`
+    Run(&'static [u8], usize, usize),
+    /// A decimal number up to `max`
+    Number(usize),
+}
+
+const DIGITS: &[u8] = b"0123456789";
+const HEX: &[u8] = b"0123456789abcdefABCDEF";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const ALPHA: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const ALNUM: &[u8] =
+    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const WORD: &[u8] =
+    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_";
+const WORD_START: &[u8] =
+    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_";
+const WHITESPACE: &[u8] = b" \t\n\x0c\r";
+
+/// Which of the 256 bytes something can start with, or would go on taking
+type Bytes = [bool; 256];
+
+// One way of spelling what a preset matches
+fn preset_parts(preset: Preset) -> Vec<Part> {
+    use Part::*;
+    match preset {
+        Preset::HexByte => vec![Run(HEX, 2, 2)],
+        Preset::U16 => vec![Number(u16::MAX as usize)],
+        Preset::Ident => vec![Run(WORD_START, 1, 1), Run(WORD, 0, MAX_LEN)],
+        Preset::Ipv4 => vec![
+            Number(255),
+            Lit(b"."),
+            Number(255),
+            Lit(b"."),
+            Number(255),
+            Lit(b"."),
+            Number(255),
+        ],
+        Preset::Ipv6 => {
+            let mut parts = vec![Run(HEX, 1, 4)];
+            for _ in 0..7 {
+                parts.extend([Lit(b":"), Run(HEX, 1, 4)]);
+            }
+            parts
+        }
+        Preset::HexRun { len: Some(len), .. } => {
+            vec![Run(HEX, 2 * len, 2 * len)]
+        }
+        Preset::HexRun { len: None, .. } => {
+            vec![Run(HEX, 2, 2), Run(HEX, 2, 2)]
+        }
+        Preset::Base64Run { .. } => {
+            vec![Run(ALNUM, 4, 4), Run(ALNUM, 2, 2), Lit(b"==")]
+        }
+        Preset::Kv => {
+            vec![Run(ALNUM, 1, MAX_LEN), Lit(b" = "), Run(ALNUM, 0, MAX_LEN)]
+        }
+        Preset::HttpRequest => vec![
+            Run(UPPER, 1, 7),
+            Lit(b" /"),
+            Run(ALNUM, 0, MAX_LEN),
+            Lit(b" HTTP/1."),
+            Run(DIGITS, 1, 1),
+        ],
+        Preset::Semver => {
+            vec![Number(99), Lit(b"."), Number(99), Lit(b"."), Number(99)]
+        }
+        Preset::Uuid => vec![
+            Run(HEX, 8, 8),
+            Lit(b"-"),
+            Run(HEX, 4, 4),
+            Lit(b"-"),
+            Run(HEX, 4, 4),
+            Lit(b"-"),
+            Run(HEX, 4, 4),
+            Lit(b"-"),
+            Run(HEX, 12, 12),
+        ],
+        Preset::Mac => {
+            let mut parts = vec![Run(HEX, 2, 2)];
+            for _ in 0..5 {
+                parts.extend([Lit(b":"), Run(HEX, 2, 2)]);
+            }
+            parts
+        }
+        Preset::Url => vec![
+            Lit(b"https://"),
+            Run(ALNUM, 1, MAX_LEN),
+            Lit(b".com/"),
+            Run(ALNUM, 0, MAX_LEN),
+        ],
+        Preset::Email => vec![
+            Run(ALNUM, 1, MAX_LEN),
+            Lit(b"@"),
+            Run(ALNUM, 1, MAX_LEN),
+            Lit(b".com"),
+        ],
+    }
+}
+
+// The bytes a preset's scan would go on taking after what `preset_parts`
+// spells, or that would make it stop matching
+fn preset_tail(preset: Preset) -> Bytes {
+    match preset {
+        Preset::HexByte
+        | Preset::Base64Run { .. }
+        | Preset::HttpRequest
+        | Preset::Uuid
+        | Preset::Mac => [false; 256],
+        Preset::U16 | Preset::Ipv4 => of(DIGITS),
+        Preset::Ident => of(WORD),
+        Preset::Ipv6 | Preset::HexRun { .. } => of(HEX),
+        Preset::Semver => {
+            let mut tail = of(DIGITS);
+            union(&mut tail, &of(b"-+"));
+            tail
+        }
+        Preset::Kv => {
+            let mut tail = [true; 256];
+            tail[usize::from(b'\n')] = false;
+            tail
+        }
+        Preset::Url => of(&(b'!'..=b'~')
+            .filter(|byte| !b"\"<>".contains(byte))
+            .collect::<Vec<_>>()),
+        Preset::Email => {
+            let mut tail = of(ALNUM);
+            union(&mut tail, &of(b"-."));
+            tail
+        }
+    }
+}
+
+fn of(bytes: &[u8]) -> Bytes {
+    let mut set = [false; 256];
+    for byte in bytes {
+        set[usize::from(*byte)] = true;
+    }
+    set
+}
+
+fn union(set: &mut Bytes, other: &Bytes) {
+    for (byte, other) in set.iter_mut().zip(other) {
+        *byte |= other;
+    }
+}
+
+fn overlaps(set: &Bytes, other: &Bytes) -> bool {
+    set.iter().zip(other).any(|(byte, other)| *byte && *other)
+}
+
+// The bytes the UTF-8 of a character in one of `ranges` can start with
+fn lead_bytes(ranges: &[(u32, u32)]) -> Bytes {
+    let lead = |code: u32| {
+        let c = char::from_u32(code).unwrap();
+        c.encode_utf8(&mut [0; 4]).as_bytes()[0]
+    };
+    let mut set = [false; 256];
+    for (lo, hi) in ranges {
+        union(&mut set, &of(&(lead(*lo)..=lead(*hi)).collect::<Vec<_>>()));
+    }
+    set
+}
+
+// The bytes what's generated for `captures`, then for `after` if they can
+// all come out empty, can start with
+fn starts(captures: &[Capture], after: &Bytes) -> Bytes {
+    let mut starts = [false; 256];
+    for capture in captures {
+        let (first, empty) = first(capture);
+        union(&mut starts, &first);
+        if !empty {
+            return starts;
+        }
+    }
+    union(&mut starts, after);
+    starts
+}
+
+// The bytes what's generated for a capture can start with, and whether it
+// can come out empty. Captures that pick their first byte around what came
+// before them, like `_` or `[rest]`, start with nothing in particular, as do
+// ones that can't be generated at all.
+fn first(capture: &Capture) -> (Bytes, bool) {
+    if let Some(bytes) = capture.literal_bytes() {
+        return (of(&bytes[..bytes.len().min(1)]), bytes.is_empty());
+    }
+    match capture {
+        Capture::Const { .. } | Capture::Utf8Char { .. } => {
+            ([true; 256], false)
+        }
+        Capture::NoCase { literal, .. } => {
+            let bytes = literal.literal_bytes().unwrap();
+            let cases = bytes.first().map(|byte| {
+                [byte.to_ascii_lowercase(), byte.to_ascii_uppercase()]
+            });
+            (
+                of(cases.as_ref().map_or(&[][..], |cases| cases)),
+                cases.is_none(),
+            )
+        }
+        Capture::Grapheme { .. } => (of(ALPHA), false),
+        Capture::Whitespace { optional, .. } => (of(WHITESPACE), *optional),
+        Capture::OneOf { options, .. } => {
+            let mut starts = [false; 256];
+            let mut empty = false;
+            for option in options {
+                let (first, can_be_empty) = first(option);
+                union(&mut starts, &first);
+                empty |= can_be_empty;
+            }
+            (starts, empty)
+        }
+        Capture::Newline { .. } => (of(b"\r\n"), false),
+        Capture::Run {
+            class: Class::Unicode(category),
+            optional,
+            ..
+        } => (lead_bytes(&category.ranges()), *optional),
+        Capture::Run {
+            class, optional, ..
+        } => (of(&class.bytes().unwrap()), *optional),
+        Capture::Pad { fill, .. } => (first(fill).0, true),
+        Capture::Preset { preset, .. } => {
+            let mut starts = [false; 256];
+            for part in preset_parts(*preset) {
+                let (first, empty) = match part {
+                    Part::Lit(bytes) => (of(&bytes[..1]), false),
+                    Part::Run(set, min, _) => (of(set), min == 0),
+                    Part::Number(_) => (of(DIGITS), false),
+                };
+                union(&mut starts, &first);
+                if !empty {
+                    break;
+                }
+            }
+            (starts, false)
+        }
+        Capture::Slice(slice) => slice_first(slice),
+        Capture::Group { pattern, .. } => {
+            (starts(&pattern.literals, &[false; 256]), true)
+        }
+        _ => ([false; 256], false),
+    }
+}
+
+// What a bracketed capture's generated bytes can start with. Only typed
+// ones start with anything in particular.
+fn slice_first(slice: &SliceCapture) -> (Bytes, bool) {
+    let name = match slice_type(slice).0 {
+        Some(Type::Path(path)) => {
+            path.path.get_ident().map(ToString::to_string)
+        }
+        _ => None,
+    };
+    match name.as_deref() {
+        Some(
+            "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "f32" | "f64",
+        ) => (of(DIGITS), false),
+        Some("i8" | "i16" | "i32" | "i64" | "i128" | "isize") => {
+            let mut starts = of(DIGITS);
+            starts[usize::from(b'-')] = true;
+            (starts, false)
+        }
+        Some("String") => (of(ALNUM), true),
+        _ => ([false; 256], false),
+    }
+}
+
+// The type a bracketed capture's bytes parse into, and whether they have to
+// be text
+fn slice_type(slice: &SliceCapture) -> (Option<&Type>, bool) {
+    let mut ty = slice.ty.as_deref();
+    let mut text = false;
+    for step in &slice.steps {
+        match step {
+            Step::Utf8 => text = true,
+            Step::Parse(parsed) => ty = Some(parsed),
+            _ => {}
+        }
+    }
+    (ty, text)
+}
+
+// The bytes a capture would go on taking if they came right after what was
+// generated for it, since runs and presets never give any back
+fn tail(capture: &Capture) -> Bytes {
+    match capture {
+        Capture::Run {
+            class: Class::Unicode(category),
+            ..
+        } => lead_bytes(&category.ranges()),
+        Capture::Run { class, .. } => of(&class.bytes().unwrap()),
+        Capture::Whitespace { .. } => of(WHITESPACE),
+        // Combining marks and the like join the letter's cluster
+        Capture::Grapheme { .. } => of(&(0x80..=u8::MAX).collect::<Vec<_>>()),
+        Capture::Preset { preset, .. } => preset_tail(*preset),
+        Capture::Group { pattern, .. } => {
+            trailing(&pattern.literals, &[false; 256])
+        }
+        _ => [false; 256],
+    }
+}
+
+// What the captures before the next one would go on taking, starting from
+// `before`: the tails of the last captures, back to one that can't be empty
+fn trailing(captures: &[Capture], before: &Bytes) -> Bytes {
+    let mut trailing = *before;
+    for capture in captures {
+        let tail = tail(capture);
+        match first(capture).1 {
+            true => union(&mut trailing, &tail),
+            false => trailing = tail,
+        }
+    }
+    trailing
+}
+
+/// Writes the code generating inputs for patterns, given how to pick a
+/// number: `pick(lo, hi)` has to be a `usize` expression in `lo..=hi`.
+pub(crate) struct Generator<'a> {
+    pub(crate) out: Ident,
+    pub(crate) pick: &'a dyn Fn(TokenStream, TokenStream) -> TokenStream,
+}
+
+impl Generator<'_> {
+    fn pick(&self, lo: impl ToTokens, hi: impl ToTokens) -> TokenStream {
+        (self.pick)(lo.into_token_stream(), hi.into_token_stream())
+    }
+
+    /// Code writing an input that one of `patterns` matches.
+    pub(crate) fn alternatives(&self, patterns: &[Pattern]) -> TokenStream {
+        if let [pattern] = patterns {
+            return self.pattern(pattern);
+        }
+        let last = patterns.len() - 1;
+        let pick = self.pick(0usize, last);
+        let arms = patterns.iter().enumerate().map(|(i, pattern)| {
+            let body = self.pattern(pattern);
+            match i == last {
+                true => quote!(_ => { #body }),
+                false => quote!(#i => { #body }),
+            }
+        });
+        quote!(match #pick { #(#arms)* })
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> TokenStream {
+        let after = pattern
+            .end
+            .as_ref()
+            .map_or([false; 256], |end| slice_first(end).0);
+        let mut before = [false; 256];
+        let captures = self.captures(&pattern.literals, &after, &mut before);
+        let end = match (&pattern.end, pattern.min_len) {
+            (end, None) => end.as_ref().map(|end| self.slice(end, 0, &before)),
+            // The capture of the rest makes up for however short the rest
+            // of the pattern came out
+            (Some(end), Some((min_len, _))) if end.is_plain() => {
+                Some(self.slice(end, min_len, &before))
+            }
+            (_, Some((_, span))) => abort!(
+                span,
+                "only a pattern ending in a plain capture of the rest, like \
+                 `[rest]`, can be generated with a `min_len`";
+                help = "the capture of the rest is what makes it long enough"
+            ),
+        };
+        quote!(#(#captures)* #end)
+    }
+
+    // The code for each of `captures`, followed by `after`. A run or preset
+    // never gives back bytes, so one followed by something that can start
+    // with a byte it would take is turned down. `before` is what the
+    // captures so far would take, for the ones after them to steer clear of.
+    fn captures(
+        &self,
+        captures: &[Capture],
+        after: &Bytes,
+        before: &mut Bytes,
+    ) -> Vec<TokenStream> {
+        let mut code = vec![];
+        for (i, capture) in captures.iter().enumerate() {
+            let next = starts(&captures[i + 1..], after);
+            if overlaps(&tail(capture), &next) {
+                abort!(
+                    capture.span(),
+                    "this takes all the bytes it can, and what follows it can \
+                     start with one of them, so an input generated for the \
+                     pattern wouldn't always match it";
+                    help = "follow it with something it can't match, or with \
+                            a capture like `[rest]`"
+                );
+            }
+            code.push(self.capture(capture, before, &next));
+            *before = trailing(std::slice::from_ref(capture), before);
+        }
+        code
+    }
+
+    // A first byte from `set` that what came before wouldn't take, if it
+    // would take some of them
+    fn lead(
+        &self,
+        set: &Bytes,
+        before: &Bytes,
+        span: Span,
+    ) -> Option<TokenStream> {
+        if !overlaps(set, before) {
+            return None;
+        }
+        let allowed = (0..=u8::MAX)
+            .filter(|byte| {
+                set[usize::from(*byte)] && !before[usize::from(*byte)]
+            })
+            .collect::<Vec<_>>();
+        if allowed.is_empty() {
+            abort!(
+                span,
+                "what comes before this would take any byte it could start \
+                 with, so an input generated for the pattern wouldn't match it"
+            );
+        }
+        Some(self.one_of(&allowed))
+    }
+
+    // A byte from `set`
+    fn one_of(&self, set: &[u8]) -> TokenStream {
+        let out = &self.out;
+        let n = set.len();
+        let pick = self.pick(0usize, n - 1);
+        quote!({
+            const SET: [u8; #n] = [#(#set),*];
+            #out.push(SET[#pick]);
+        })
+    }
+
+    // Between `min` and `max` bytes from `set`
+    fn run(&self, set: &[u8], min: usize, max: usize) -> TokenStream {
+        let one = self.one_of(set);
+        if min == max {
+            return quote!(for _ in 0..#min { #one });
+        }
+        let len = self.pick(min, max);
+        quote!(for _ in 0..#len { #one })
+    }
+
+    // Between `min` and `max` bytes of any value
+    fn bytes(&self, min: impl ToTokens, max: impl ToTokens) -> TokenStream {
+        let out = &self.out;
+        let len = self.pick(min, max);
+        let byte = self.pick(0usize, 255usize);
+        quote!(for _ in 0..#len { #out.push(#byte as u8); })
+    }
+
+    // The code for a capture, which comes after what would take the bytes in
+    // `before` and is followed by something starting with one in `next`
+    fn capture(
+        &self,
+        capture: &Capture,
+        before: &Bytes,
+        next: &Bytes,
+    ) -> TokenStream {
+        let out = &self.out;
+        if let Some(bytes) = capture.literal_bytes() {
+            let lit = LitByteStr::new(&bytes, capture.span());
+            return quote!(#out.extend_from_slice(#lit););
+        }
+        match capture {
+            Capture::Ident(_) | Capture::Underscore(_) => {
+                if let Some(lead) =
+                    self.lead(&[true; 256], before, capture.span())
+                {
+                    return lead;
+                }
+                let byte = self.pick(0usize, 255usize);
+                quote!(#out.push(#byte as u8);)
+            }
+            Capture::Const { path, .. } => quote!(#out.push(#path);),
+            Capture::ByteRange { start, end, .. } => {
+                let lo = start.literal_bytes().unwrap()[0];
+                let hi = end
+                    .as_ref()
+                    .map_or(lo, |end| end.literal_bytes().unwrap()[0]);
+                let range = of(&(lo..=hi).collect::<Vec<_>>());
+                if let Some(lead) = self.lead(&range, before, capture.span()) {
+                    return lead;
+                }
+                let byte = self.pick(lo as usize, hi as usize);
+                quote!(#out.push(#byte as u8);)
+            }
+            Capture::NoCase { literal, .. } => {
+                let writes =
+                    literal.literal_bytes().unwrap().into_iter().map(|byte| {
+                        let (lower, upper) = (
+                            byte.to_ascii_lowercase(),
+                            byte.to_ascii_uppercase(),
+                        );
+                        if lower == upper {
+                            return quote!(#out.push(#byte););
+                        }
+                        self.one_of(&[lower, upper])
+                    });
+                quote!(#(#writes)*)
+            }
+            Capture::Utf8Char { .. } => {
+                // Any code point, stepping over the surrogates
+                let code = self.pick(0usize, 0x10ffff - 0x800usize);
+                quote!({
+                    let code = #code as u32;
+                    let code = if code >= 0xd800 { code + 0x800 } else { code };
+                    let c = ::core::char::from_u32(code).unwrap();
+                    #out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                })
+            }
+            // A letter is always a cluster of its own, unless what comes
+            // after it combines with it
+            Capture::Grapheme { .. } => self.one_of(ALPHA),
+            Capture::Whitespace { optional, .. } => {
+                self.run(WHITESPACE, usize::from(!optional), MAX_LEN)
+            }
+            Capture::OneOf { options, .. } => {
+                let last = options.len() - 1;
+                let pick = self.pick(0usize, last);
+                let arms = options.iter().enumerate().map(|(i, option)| {
+                    let bytes = option.literal_bytes().unwrap();
+                    let lit = LitByteStr::new(&bytes, option.span());
+                    match i == last {
+                        true => quote!(_ => #lit.as_slice(),),
+                        false => quote!(#i => #lit.as_slice(),),
+                    }
+                });
+                quote!(#out.extend_from_slice(match #pick { #(#arms)* });)
+            }
+            Capture::Newline { .. } => {
+                let pick = self.pick(0usize, 1usize);
+                quote! {
+                    #out.extend_from_slice(match #pick {
+                        0 => b"\n".as_slice(),
+                        _ => b"\r\n".as_slice(),
+                    });
+                }
+            }
+            Capture::Run {
+                class: Class::Unicode(category),
+                optional,
+                ..
+            } => {
+                let ranges = category.ranges();
+                let n = ranges.len();
+                let ranges = ranges.iter().map(|(lo, hi)| quote!((#lo, #hi)));
+                let len = self.pick(usize::from(!optional), MAX_LEN);
+                let range = self.pick(0usize, n - 1);
+                let offset = self.pick(0usize, quote!((hi - lo) as usize));
+                quote! {
+                    for _ in 0..#len {
+                        static RANGES: [(u32, u32); #n] = [#(#ranges),*];
+                        let (lo, hi) = RANGES[#range];
+                        let c = ::core::char::from_u32(lo + #offset as u32).unwrap();
+                        #out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                }
+            }
+            Capture::Run {
+                class, optional, ..
+            } => {
+                let set = class.bytes().unwrap();
+                self.run(&set, usize::from(!optional), MAX_LEN)
+            }
+            Capture::Pad { fill, count, .. } => {
+                let byte = fill.literal_bytes().unwrap()[0];
+                quote!(#out.resize(#out.len() + (#count), #byte);)
+            }
+            Capture::Preset {
+                name, preset, args, ..
+            } => {
+                if args.iter().any(|arg| {
+                    matches!(arg, PresetArg::Bytes(..) | PresetArg::Value(_))
+                }) {
+                    abort!(
+                        name,
+                        "a preset whose outputs are checked can't be generated";
+                        help = "bind the outputs or ignore them with `_`"
+                    );
+                }
+                let parts =
+                    preset_parts(*preset).into_iter().map(|part| match part {
+                        Part::Lit(bytes) => {
+                            let lit = LitByteStr::new(bytes, name.span());
+                            quote!(#out.extend_from_slice(#lit);)
+                        }
+                        Part::Run(set, min, max) => self.run(set, min, max),
+                        Part::Number(max) => self.number(max),
+                    });
+                quote!(#(#parts)*)
+            }
+            Capture::Slice(slice) => self.slice(slice, 0, before),
+            Capture::Group { paren, pattern, .. } => {
+                // The group repeats as many times as it can, so what follows
+                // can't start the group again
+                let own = starts(&pattern.literals, &[false; 256]);
+                if overlaps(&own, next) {
+                    abort!(
+                        paren.span,
+                        "this group repeats as many times as it can, and what \
+                         follows it can start the group again, so an input \
+                         generated for the pattern wouldn't always match it";
+                        help = "follow it with something it can't start with"
+                    );
+                }
+                // The group's pattern ends in a hidden capture of the rest,
+                // which is whatever comes after the group. Each repetition
+                // comes after the last one, or after what came before.
+                let mut again = own;
+                union(&mut again, next);
+                let mut inner = trailing(&pattern.literals, before);
+                union(&mut inner, before);
+                let captures =
+                    self.captures(&pattern.literals, &again, &mut inner);
+                let reps = self.pick(0usize, MAX_REPS);
+                quote!(for _ in 0..#reps { #(#captures)* })
+            }
+            Capture::Var { pound, .. } => abort!(
+                pound.span,
+                "a variable is only known where the pattern is matched, so \
+                 it can't be generated";
+                help = "use a const, as in `{{NAME}}`, instead"
+            ),
+            Capture::Peek { keyword, .. } => abort!(
+                keyword,
+                "`{}` can't be generated, since it constrains what the \
+                 segments after it match",
+                keyword
+            ),
+            Capture::Guarded { capture, .. } => abort!(
+                capture.span(),
+                "a capture with a guard can't be generated, since the guard \
+                 could reject anything"
+            ),
+            _ => unreachable!("literals are written above"),
+        }
+    }
+
+    // A decimal number up to `max`
+    fn number(&self, max: usize) -> TokenStream {
+        let out = &self.out;
+        let n = self.pick(0usize, max);
+        quote!(#out.extend_from_slice((#n).to_string().as_bytes());)
+    }
+
+    // What goes in a bracketed capture, at least `min` bytes of it, never
+    // starting with a byte in `before`
+    fn slice(
+        &self,
+        slice: &SliceCapture,
+        min: usize,
+        before: &Bytes,
+    ) -> TokenStream {
+        let out = &self.out;
+        if slice.guard.is_some() {
+            abort!(
+                slice.bracket.span,
+                "a capture with a guard can't be generated, since the guard \
+                 could reject anything"
+            );
+        }
+        for step in &slice.steps {
+            if let Step::Call(call) = step {
+                abort!(
+                    call,
+                    "a capture with a function in its pipeline can't be \
+                     generated, since the function could reject anything"
+                );
+            }
+        }
+        let span = slice.bracket.span;
+        let (ty, text) = slice_type(slice);
+        if let Some(ty) = ty {
+            return self.typed(ty);
+        }
+        if text {
+            let lead = self.lead(&of(ALNUM), before, span);
+            let min = min.saturating_sub(usize::from(lead.is_some()));
+            let run = self.run(ALNUM, min, min + MAX_LEN);
+            return quote!(#lead #run);
+        }
+        match &slice.until {
+            // Never the delimiter's first byte, so it can't turn up early
+            Some(until) => {
+                let skip = until[0];
+                let mut set = [true; 256];
+                set[usize::from(skip)] = false;
+                let lead = self.lead(&set, before, span);
+                let len = self.pick(0usize, MAX_LEN);
+                let byte = self.pick(0usize, 254usize);
+                quote! {
+                    #lead
+                    for _ in 0..#len {
+                        let byte = #byte as u8;
+                        #out.push(if byte >= #skip { byte + 1 } else { byte });
+                    }
+                }
+            }
+            None => {
+                let need = hidden("need");
+                let lead = self.lead(&[true; 256], before, span);
+                let bytes = self.bytes(quote!(#need), quote!(#need + #MAX_LEN));
+                quote! {
+                    #lead
+                    let #need = #min.saturating_sub(#out.len());
+                    #bytes
+                }
+            }
+        }
+    }
+
+    // What parses into a `ty`
+    fn typed(&self, ty: &Type) -> TokenStream {
+        let out = &self.out;
+        let name = match ty {
+            Type::Path(path) => path.path.get_ident().map(ToString::to_string),
+            _ => None,
+        };
+        match name.as_deref() {
+            Some(
+                "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16"
+                | "i32" | "i64" | "i128" | "isize",
+            ) => {
+                let byte = self.pick(0usize, 255usize);
+                quote!({
+                    let mut bytes = [0u8; ::core::mem::size_of::<#ty>()];
+                    for byte in &mut bytes {
+                        *byte = #byte as u8;
+                    }
+                    let n = <#ty>::from_le_bytes(bytes);
+                    #out.extend_from_slice(n.to_string().as_bytes());
+                })
+            }
+            Some("f32" | "f64") => {
+                let whole = self.number(9999);
+                let fraction = self.number(99);
+                quote!(#whole #out.push(b'.'); #fraction)
+            }
+            Some("String") => self.run(ALNUM, 0, MAX_LEN),
+            _ => abort!(
+                ty,
+                "only integer, float and `String` captures can be generated"
+            ),
+        }
+    }
+}
+
+/// `pattern | pattern | ..`
+pub(crate) struct ArbitraryInput {
+    alternatives: Vec<Pattern>,
+}
+
+impl Parse for ArbitraryInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.span();
+        if !cfg!(feature = "arbitrary") {
+            abort!(
+                span,
+                "`strmatch_arbitrary!` needs the `arbitrary` feature of \
+                 strmatch";
+                help = "the generated code uses `arbitrary::Unstructured`, so \
+                        the crate using it needs `arbitrary` as a dependency"
+            );
+        }
+        let Alternatives(alternatives) = input.parse()?;
+        Ok(ArbitraryInput { alternatives })
+    }
+}
+
+impl ToTokens for ArbitraryInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let u = hidden("u");
+        let out = hidden("out");
+        let pick = |lo, hi| quote!(#u.int_in_range::<usize>((#lo)..=(#hi))?);
+        let generator = Generator {
+            out: out.clone(),
+            pick: &pick,
+        };
+        let body = generator.alternatives(&self.alternatives);
+        let generate = hidden("generate");
+        tokens.extend(quote! {{
+            fn #generate(
+                #u: &mut ::arbitrary::Unstructured<'_>,
+            ) -> ::arbitrary::Result<::std::vec::Vec<u8>> {
+                let mut #out = ::std::vec::Vec::new();
+                #body
+                ::core::result::Result::Ok(#out)
+            }
+            #generate
+        }});
+    }
+}
+
+/// `pattern | pattern | ..`
+pub(crate) struct ProptestInput {
+    alternatives: Vec<Pattern>,
+}
+
+impl Parse for ProptestInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.span();
+        if !cfg!(feature = "proptest") {
+            abort!(
+                span,
+                "`strmatch_proptest!` needs the `proptest` feature of \
+                 strmatch";
+                help = "the generated code builds a `proptest` strategy, so \
+                        the crate using it needs `proptest` as a dependency"
+            );
+        }
+        let Alternatives(alternatives) = input.parse()?;
+        Ok(ProptestInput { alternatives })
+    }
+}
+
+impl ToTokens for ProptestInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let choices = hidden("choices");
+        let pick = hidden("pick");
+        let out = hidden("out");
+        let pick_in = |lo, hi| quote!(#pick(#lo, #hi));
+        let generator = Generator {
+            out: out.clone(),
+            pick: &pick_in,
+        };
+        let body = generator.alternatives(&self.alternatives);
+        // The strategy makes a list of bytes, and each choice is made by
+        // taking as many of them as its range needs, or zeros once they run
+        // out. Shrinking the list toward fewer, smaller bytes then shrinks
+        // the input toward the smallest choices.
+        tokens.extend(quote! {
+            ::proptest::strategy::Strategy::prop_map(
+                ::proptest::collection::vec(::proptest::arbitrary::any::<u8>(), 0..=#MAX_CHOICES),
+                |#choices: ::std::vec::Vec<u8>| {
+                    let mut #choices = #choices.into_iter();
+                    let mut #pick = |lo: usize, hi: usize| -> usize {
+                        let (mut n, mut span) = (0usize, hi - lo);
+                        while span > 0 {
+                            n = (n << 8) | usize::from(#choices.next().unwrap_or(0));
+                            span >>= 8;
+                        }
+                        lo + n % (hi - lo + 1)
+                    };
+                    let mut #out = ::std::vec::Vec::new();
+                    #body
+                    #out
+                },
+            )
+        });
+    }
+}
@@ -0,0 +1,184 @@
+//! Parsing and expansion for `strglob!`, which matches its input against a
+//! glob like `*.rs` by turning the glob into a pattern.
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{token, Expr, Ident, LitStr, Token};
+
+use crate::engine::{coerce_input, hidden, matcher, Codegen};
+use crate::pattern::{Capture, Pattern, SliceCapture};
+
+/// `input, "glob"`
+pub(crate) struct GlobInput {
+    input: Expr,
+    pattern: Pattern,
+}
+
+impl Parse for GlobInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let glob = input.parse::<LitStr>()?;
+        Ok(GlobInput {
+            input: expr,
+            pattern: translate(&glob),
+        })
+    }
+}
+
+// Turn a glob into the pattern it stands for: `*` is a lazy `[_]`, `?` is
+// `#utf8char`, a class like `[a-z]` is a byte with a guard, and everything
+// else is a literal. A backslash makes the next character a literal too.
+fn translate(glob: &LitStr) -> Pattern {
+    let span = glob.span();
+    let value = glob.value();
+    let mut literals = vec![];
+    let mut literal = String::new();
+    let flush = |literal: &mut String, literals: &mut Vec<Capture>| {
+        if !literal.is_empty() {
+            literals.push(Capture::Str {
+                lit: LitStr::new(literal, span),
+                reps: 1,
+            });
+            literal.clear();
+        }
+    };
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                flush(&mut literal, &mut literals);
+                // `**` matches the same as `*`
+                while chars.next_if_eq(&'*').is_some() {}
+                literals.push(Capture::Slice(SliceCapture {
+                    bracket: token::Bracket(span),
+                    name: None,
+                    ty: None,
+                    lazy: true,
+                    until: None,
+                    saturating: false,
+                    trim: false,
+                    steps: vec![],
+                    guard: None,
+                }));
+            }
+            '?' => {
+                flush(&mut literal, &mut literals);
+                literals.push(Capture::Utf8Char {
+                    keyword: Ident::new("utf8char", span),
+                    binding: None,
+                });
+            }
+            '[' => {
+                flush(&mut literal, &mut literals);
+                let byte = hidden(&format!("class{}", literals.len()));
+                let guard = class(&mut chars, &byte, span);
+                literals.push(Capture::Guarded {
+                    capture: Box::new(Capture::Ident(byte)),
+                    guard: Box::new(guard),
+                });
+            }
+            '\\' => match chars.next() {
+                Some(c) => literal.push(c),
+                None => abort!(span, "the glob ends in a lone `\\`"),
+            },
+            c => literal.push(c),
+        }
+    }
+    flush(&mut literal, &mut literals);
+    // A trailing `*` grabs whatever is left, which a slice pattern can do
+    let end = match literals.pop() {
+        Some(Capture::Slice(end)) => Some(end),
+        Some(other) => {
+            literals.push(other);
+            None
+        }
+        None => None,
+    };
+    Pattern {
+        whole: None,
+        min_len: None,
+        literals,
+        end,
+    }
+}
+
+// The guard checking that `byte` is in the class after a `[`: ASCII
+// characters and ranges like `a-z`, all of them negated by a leading `!` or
+// `^`. As in shell globs, a `]` right at the start is part of the class.
+fn class(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    byte: &Ident,
+    span: Span,
+) -> Expr {
+    let negated = chars.next_if(|c| matches!(c, '!' | '^')).is_some();
+    let mut ranges = vec![];
+    let mut first = true;
+    loop {
+        let lo = match chars.next() {
+            Some(']') if !first => break,
+            Some(c) => c,
+            None => abort!(span, "the glob has a `[` without a matching `]`"),
+        };
+        first = false;
+        let hi = match chars.next_if_eq(&'-') {
+            // A `-` right before the `]` is just a `-`
+            Some(_) if chars.peek() == Some(&']') => {
+                ranges.push(('-', '-'));
+                lo
+            }
+            Some(_) => match chars.next() {
+                Some(hi) => hi,
+                None => {
+                    abort!(span, "the glob has a `[` without a matching `]`")
+                }
+            },
+            None => lo,
+        };
+        if !lo.is_ascii() || !hi.is_ascii() {
+            abort!(
+                span,
+                "`[..]` in a glob only matches ASCII characters";
+                help = "use `?` to match any character"
+            );
+        }
+        if lo > hi {
+            abort!(span, "the range `{}-{}` in the glob is empty", lo, hi);
+        }
+        ranges.push((lo, hi));
+    }
+    let ranges = ranges.iter().map(|&(lo, hi)| {
+        let (lo, hi) = (lo as u8, hi as u8);
+        quote!(#lo..=#hi)
+    });
+    let matches = quote!(::core::matches!(*#byte, #(#ranges)|*));
+    if negated {
+        syn::parse_quote!(!#matches)
+    } else {
+        syn::parse_quote!(#matches)
+    }
+}
+
+impl ToTokens for GlobInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            Codegen::default(),
+        );
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    #matcher.is_some()
+                }
+            }
+        });
+    }
+}
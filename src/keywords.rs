@@ -0,0 +1,244 @@
+//! Parsing and expansion for `strkeywords!`, which defines an enum with a
+//! variant per keyword and a classifier picking the variant for some bytes.
+
+use std::collections::BTreeMap;
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Ident, Lit, LitByteStr, Token, Visibility};
+
+use crate::engine::hidden;
+
+/// `#[attrs] vis enum Name { Variant = "keyword", .. }`, where the attributes
+/// can include `#[phf]`
+pub(crate) struct KeywordsInput {
+    attrs: Vec<Attribute>,
+    /// Whether to look keywords up in a `phf::Map` rather than a `match`
+    phf: bool,
+    vis: Visibility,
+    name: Ident,
+    keywords: Vec<Keyword>,
+}
+
+/// `#[attrs] Variant = "keyword"`
+struct Keyword {
+    attrs: Vec<Attribute>,
+    variant: Ident,
+    bytes: LitByteStr,
+}
+
+impl Parse for Keyword {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let variant = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let bytes = match input.parse::<Lit>()? {
+            Lit::Str(lit) => {
+                LitByteStr::new(lit.value().as_bytes(), lit.span())
+            }
+            Lit::ByteStr(lit) => lit,
+            lit => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "expected a string or byte string literal",
+                ))
+            }
+        };
+        if bytes.value().is_empty() {
+            return Err(syn::Error::new(
+                bytes.span(),
+                "a keyword can't be empty",
+            ));
+        }
+        Ok(Keyword {
+            attrs,
+            variant,
+            bytes,
+        })
+    }
+}
+
+impl Parse for KeywordsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (phfs, attrs): (Vec<_>, _) = input
+            .call(Attribute::parse_outer)?
+            .into_iter()
+            .partition(|attr| attr.path.is_ident("phf"));
+        let mut phf = false;
+        for attr in phfs {
+            if !attr.tokens.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    attr.tokens,
+                    "`#[phf]` doesn't take arguments",
+                ));
+            }
+            if !cfg!(feature = "phf") {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[phf]` needs the `phf` feature of strmatch",
+                ));
+            }
+            phf = true;
+        }
+        let vis = input.parse()?;
+        input.parse::<Token![enum]>()?;
+        let name = input.parse()?;
+        let inner;
+        let _ = syn::braced!(inner in input);
+        let keywords =
+            Punctuated::<Keyword, Token![,]>::parse_terminated(&inner)?
+                .into_iter()
+                .collect::<Vec<_>>();
+        let mut seen = BTreeMap::new();
+        for keyword in &keywords {
+            if let Some(first) =
+                seen.insert(keyword.bytes.value(), &keyword.variant)
+            {
+                return Err(syn::Error::new(
+                    keyword.bytes.span(),
+                    format!("this keyword is already `{first}`"),
+                ));
+            }
+        }
+        Ok(KeywordsInput {
+            attrs,
+            phf,
+            vis,
+            name,
+            keywords,
+        })
+    }
+}
+
+impl ToTokens for KeywordsInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let KeywordsInput {
+            attrs,
+            vis,
+            name,
+            keywords,
+            ..
+        } = self;
+        let variants = keywords.iter().map(|keyword| {
+            let attrs = &keyword.attrs;
+            let variant = &keyword.variant;
+            quote!(#(#attrs)* #variant)
+        });
+        let bytes = hidden("bytes");
+        let classify = self.classify(&bytes);
+        let spellings = keywords.iter().map(|keyword| {
+            let variant = &keyword.variant;
+            let literal = &keyword.bytes;
+            quote!(#name::#variant => #literal,)
+        });
+        let all = keywords.iter().map(|keyword| &keyword.variant);
+        let count = keywords.len();
+        tokens.extend(quote! {
+            #(#attrs)*
+            #vis enum #name {
+                #(#variants,)*
+            }
+
+            impl #name {
+                /// Every keyword, in the order they were listed.
+                #vis const ALL: [#name; #count] = [#(#name::#all),*];
+
+                /// The keyword spelled `bytes`, if there is one.
+                #vis fn from_bytes(#bytes: &[u8]) -> ::core::option::Option<#name> {
+                    #classify
+                }
+
+                /// How the keyword is spelled.
+                #vis fn as_bytes(&self) -> &'static [u8] {
+                    match self {
+                        #(#spellings)*
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl KeywordsInput {
+    // The body of `from_bytes`, which classifies `bytes`
+    fn classify(&self, bytes: &Ident) -> TokenStream {
+        // Without the feature, parsing has already turned `#[phf]` down
+        if self.phf {
+            #[cfg(feature = "phf")]
+            return self.phf(bytes);
+        }
+        self.by_len(bytes)
+    }
+
+    fn by_len(&self, bytes: &Ident) -> TokenStream {
+        let KeywordsInput { name, keywords, .. } = self;
+        // Dispatch on the length first, then let rustc build a decision tree
+        // over the bytes of the keywords of that length
+        let mut by_len = BTreeMap::<usize, Vec<&Keyword>>::new();
+        for keyword in keywords {
+            by_len
+                .entry(keyword.bytes.value().len())
+                .or_default()
+                .push(keyword);
+        }
+        let lens = by_len.iter().map(|(len, keywords)| {
+            let arms = keywords.iter().map(|keyword| {
+                let variant = &keyword.variant;
+                let literal = &keyword.bytes;
+                quote! {
+                    #literal => ::core::option::Option::Some(#name::#variant),
+                }
+            });
+            quote! {
+                #len => match #bytes {
+                    #(#arms)*
+                    _ => ::core::option::Option::None,
+                },
+            }
+        });
+        quote! {
+            match #bytes.len() {
+                #(#lens)*
+                _ => ::core::option::Option::None,
+            }
+        }
+    }
+
+    // A perfect hash of the keywords is found now, so the lookup is a hash,
+    // an index and one comparison however many keywords there are
+    #[cfg(feature = "phf")]
+    fn phf(&self, bytes: &Ident) -> TokenStream {
+        let KeywordsInput { name, keywords, .. } = self;
+        let owned = keywords
+            .iter()
+            .map(|keyword| keyword.bytes.value())
+            .collect::<Vec<_>>();
+        // Hashed as slices, since `phf_shared` only implements `PhfHash` for
+        // `Vec<u8>` with its `std` feature, which a plain build doesn't turn on
+        let spellings: Vec<&[u8]> = owned.iter().map(Vec::as_slice).collect();
+        let state = phf_generator::generate_hash(&spellings);
+        let key = state.key;
+        let disps = state.disps.iter().map(|(a, b)| quote!((#a, #b)));
+        let entries = state.map.iter().map(|&i| {
+            let literal = &keywords[i].bytes;
+            quote!((#literal, #i))
+        });
+        let arms = keywords.iter().enumerate().map(|(i, keyword)| {
+            let variant = &keyword.variant;
+            quote!(::core::option::Option::Some(#i) => ::core::option::Option::Some(#name::#variant),)
+        });
+        quote! {
+            static KEYWORDS: ::phf::Map<&'static [u8], usize> = ::phf::Map {
+                key: #key,
+                disps: &[#(#disps),*],
+                entries: &[#(#entries),*],
+            };
+            match KEYWORDS.get(#bytes).copied() {
+                #(#arms)*
+                _ => ::core::option::Option::None,
+            }
+        }
+    }
+}
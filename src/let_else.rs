@@ -0,0 +1,84 @@
+//! Parsing and expansion for `strmatch_let!`, which binds a pattern's
+//! captures with a `let`-`else`, for where a refutable pattern can't go.
+
+use proc_macro2::{Spacing, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Block, Expr, Token};
+
+use crate::engine::{coerce_input, hidden, matcher, Codegen};
+use crate::pattern::Pattern;
+
+/// `pattern = input else { .. }`, optionally preceded by a `#[codegen(..)]`
+/// attribute
+pub(crate) struct LetInput {
+    codegen: Codegen,
+    pattern: Pattern,
+    input: Expr,
+    otherwise: Block,
+}
+
+impl Parse for LetInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        // The pattern is everything up to the `=`, which can't be part of a
+        // pattern unless it's in `..=`, `==` and the like, or in brackets
+        let pattern = input.step(|cursor| {
+            let mut tokens = TokenStream::new();
+            let mut rest = *cursor;
+            let mut joined = false;
+            while let Some((token, next)) = rest.token_tree() {
+                if let TokenTree::Punct(punct) = &token {
+                    let alone = punct.spacing() == Spacing::Alone;
+                    if punct.as_char() == '=' && alone && !joined {
+                        return Ok((tokens, rest));
+                    }
+                    joined = !alone;
+                } else {
+                    joined = false;
+                }
+                tokens.extend([token]);
+                rest = next;
+            }
+            Err(cursor
+                .error("expected `= input else { .. }` after the pattern"))
+        })?;
+        input.parse::<Token![=]>()?;
+        let expr = Expr::parse_without_eager_brace(input)?;
+        input.parse::<Token![else]>()?;
+        let otherwise = input.parse()?;
+        Ok(LetInput {
+            codegen,
+            pattern: syn::parse2(pattern)?,
+            input: expr,
+            otherwise,
+        })
+    }
+}
+
+impl ToTokens for LetInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let bindings = self.pattern.bindings();
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            self.codegen,
+        );
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        let otherwise = &self.otherwise;
+        let result = hidden("result");
+        // The matcher is a block, which can't come right before `else`
+        tokens.extend(quote! {
+            let #input = #expr;
+            #coerce
+            let #result = #matcher;
+            // The `else` block is the user's, so it's not for us to say it
+            // could be a `?`
+            #[allow(clippy::question_mark)]
+            let ::core::option::Option::Some((#(#bindings,)*)) = #result else #otherwise;
+        });
+    }
+}
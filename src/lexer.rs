@@ -0,0 +1,160 @@
+//! Parsing and expansion for `strlexer!`, which splits its input into tokens
+//! with a list of `pattern => token` arms.
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, Expr, Token};
+
+use crate::arms::check_bindings;
+use crate::engine::{hidden, matcher, with_helpers, Codegen};
+use crate::pattern::Pattern;
+
+/// `input { arms }`, optionally preceded by a `#[codegen(..)]` attribute
+pub(crate) struct LexerInput {
+    codegen: Codegen,
+    input: Expr,
+    arms: Vec<LexArm>,
+}
+
+impl Parse for LexerInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let expr = Expr::parse_without_eager_brace(input)?;
+        let inner;
+        let _ = syn::braced!(inner in input);
+        let mut arms = vec![];
+        while !inner.is_empty() {
+            arms.push(inner.parse()?);
+        }
+        if arms.is_empty() {
+            return Err(inner.error("a lexer needs at least one arm"));
+        }
+        Ok(LexerInput {
+            codegen,
+            input: expr,
+            arms,
+        })
+    }
+}
+
+/// A single `(pattern) | (pattern) => token` arm. The token is `None` for
+/// `=> _`, which skips what the arm matches.
+struct LexArm {
+    patterns: Vec<Pattern>,
+    token: Option<Expr>,
+}
+
+impl Parse for LexArm {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut patterns = vec![];
+        loop {
+            let inner;
+            let paren = parenthesized!(inner in input);
+            let mut pattern: Pattern = inner.parse()?;
+            // Each arm matches the start of what's left, and hands back the
+            // rest so the lexer knows how much it took
+            pattern.capture_rest(hidden("rest"), paren.span);
+            patterns.push(pattern);
+            if !input.peek(Token![|]) {
+                break;
+            }
+            input.parse::<Token![|]>()?;
+        }
+        input.parse::<Token![=>]>()?;
+        let token = if input.peek(Token![_]) {
+            input.parse::<Token![_]>()?;
+            None
+        } else {
+            Some(input.parse()?)
+        };
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        check_bindings(&patterns);
+        Ok(LexArm { patterns, token })
+    }
+}
+
+impl ToTokens for LexerInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        // Arms share the helper functions and consts of `shared_helpers` and
+        // `const_array` matchers
+        tokens.extend(with_helpers(|| self.expand()));
+    }
+}
+
+impl LexerInput {
+    fn expand(&self) -> TokenStream {
+        let input = hidden("input");
+        let left = hidden("left");
+        let at = hidden("at");
+        let best = hidden("best");
+        let len = hidden("len");
+        let arm = hidden("arm");
+        let start = hidden("start");
+        // How much of what's left each arm takes, keeping the longest, or the
+        // first of the longest
+        let lengths = self.arms.iter().enumerate().map(|(i, arm)| {
+            let matcher = matcher(&left, &arm.patterns, None, self.codegen);
+            quote! {
+                if let ::core::option::Option::Some((.., #len,)) = #matcher {
+                    let #len = #left.len() - #len.len();
+                    if #len > #best.map_or(0, |(len, _)| len) {
+                        #best = ::core::option::Option::Some((#len, #i));
+                    }
+                }
+            }
+        });
+        // Then the arm that won is run again to bind its captures
+        let bodies = self.arms.iter().enumerate().map(|(i, arm)| {
+            let mut bindings = arm.patterns[0].bindings();
+            let rest = bindings.pop();
+            let matcher = matcher(&left, &arm.patterns, None, self.codegen);
+            let emit = match &arm.token {
+                Some(token) => quote! {
+                    return ::core::option::Option::Some(
+                        ::core::result::Result::Ok((#token, #start..#at)),
+                    );
+                },
+                None => quote!(continue;),
+            };
+            quote! {
+                #i => {
+                    #[allow(unused_variables)]
+                    let ::core::option::Option::Some((#(#bindings,)* #rest,)) = (#matcher) else {
+                        ::core::unreachable!()
+                    };
+                    let #start = #at;
+                    #at += #len;
+                    #emit
+                }
+            }
+        });
+        let expr = &self.input;
+        quote! {{
+            let #input: &[u8] = #expr;
+            let mut #at = 0usize;
+            ::core::iter::from_fn(move || loop {
+                let #left: &[u8] = &#input[#at..];
+                if #left.is_empty() {
+                    return ::core::option::Option::None;
+                }
+                let mut #best = ::core::option::Option::None;
+                #(#lengths)*
+                let ::core::option::Option::Some((#len, #arm)) = #best else {
+                    // No arm takes the next byte, so it's skipped and its
+                    // offset handed back
+                    #at += 1;
+                    return ::core::option::Option::Some(
+                        ::core::result::Result::Err(#at - 1),
+                    );
+                };
+                match #arm {
+                    #(#bodies)*
+                    _ => ::core::unreachable!(),
+                }
+            })
+        }}
+    }
+}
@@ -1,10 +1,75 @@
-use std::vec;
-
 use proc_macro_error::{abort, proc_macro_error};
-use quote::TokenStreamExt;
 use quote::{quote, ToTokens};
-use syn::{bracketed, parse_macro_input};
-use syn::{parse::Parse, Ident, LitByte, LitByteStr, LitChar, LitStr, Token};
+use syn::parse_macro_input;
+
+mod arms;
+mod assert;
+mod bits;
+mod build;
+mod chunks;
+mod clap;
+mod const_fn;
+mod contains;
+mod csv;
+mod deserialize;
+mod display;
+mod engine;
+mod extract;
+mod fields;
+mod find;
+mod from_str;
+mod generate;
+mod glob;
+mod keywords;
+mod let_else;
+mod lexer;
+#[cfg(feature = "runtime")]
+mod matcher;
+mod multi;
+mod nom;
+mod path;
+mod pattern;
+mod presets;
+mod record;
+mod regex;
+mod regex_str;
+mod replace;
+mod shadow;
+mod sniff;
+mod sub;
+mod try_from;
+mod unicode;
+mod winnow;
+
+use arms::MatchInput;
+use assert::AssertInput;
+use bits::BitInput;
+use build::BuildInput;
+use chunks::ChunksInput;
+use clap::ClapInput;
+use const_fn::ConstFnInput;
+use contains::ContainsInput;
+use csv::CsvInput;
+use extract::ExtractInput;
+use fields::FieldsInput;
+use find::{CountInput, FindInput, SplitOnceInput};
+use generate::{ArbitraryInput, ProptestInput};
+use glob::GlobInput;
+use keywords::KeywordsInput;
+use let_else::LetInput;
+use lexer::LexerInput;
+#[cfg(feature = "runtime")]
+use matcher::MatcherInput;
+use multi::MultiInput;
+use nom::NomInput;
+use path::PathInput;
+use pattern::{Alternatives, Capture, Pattern};
+use regex::RegexInput;
+use regex_str::RegexStrInput;
+use replace::ReplaceInput;
+use sniff::SniffInput;
+use sub::{PatternDef, SubInput};
+use winnow::WinnowInput;
 
 /// `strmatch!` makes validating and extracting parts of
 /// strings easier. It works by converting your query into a slice pattern,
@@ -13,6 +78,8 @@ use syn::{parse::Parse, Ident, LitByte, LitByteStr, LitChar, LitStr, Token};
 /// # Usage:
 ///
 /// ```rust
+/// # use strmatch::strmatch;
+/// # const COLON: u8 = b':';
 /// // Convert to bytes so we can use slice pattern matching.
 /// let str = "one twotwo threethreethree";
 ///
@@ -32,17 +99,58 @@ use syn::{parse::Parse, Ident, LitByte, LitByteStr, LitChar, LitStr, Token};
 ///     strmatch!('x') => {}
 ///     strmatch!("xyz") => {}
 ///
+///     // Or any byte in a range, binding it too if we want
+///     strmatch!('0'..='9' _) => {}
+///     strmatch!(first @ 'a'..='z' sep @ ':') => {}
+///
+///     // Or the value of a `u8` const, which a bare name would bind instead
+///     strmatch!("key" {COLON} [value]) => {}
+///
 ///     // And match repeats!
 ///     strmatch!("one" _ "two"x2  _ "three"x3) => {}
 ///
+///     // Repeats can also be spelled with a `*`, whitespace allowed.
+///     strmatch!("one" _ "two" * 2 _ "three" * 3) => {}
+///
+///     // Or by the length in bytes of another literal, to keep them in sync
+///     strmatch!("one" '-' x len("two")) => {}
+///
+///     // `pad(byte, N)` spells out N copies of a byte
+///     strmatch!("one" pad(' ', 4) ..) => {}
+///
+///     // A literal can ignore ASCII case while the rest stays exact
+///     strmatch!(nocase("ONE") ' ' ..) => {}
+///
+///     // Or be one of several, as in a shell's brace expansion
+///     strmatch!({"one", "uno"} ' ' ..) => {}
+///
 ///     // Bracketed patterns can be the last term of a pattern.
 ///     // Ignore everything past "one"
 ///     strmatch!("one" [_]) => {}
+///     // Which can also be spelled like in a slice pattern
+///     strmatch!("one" ..) => {}
+///     // Or be the first, when there's none at the end
+///     strmatch!([_] "three") => {}
 ///
 ///     // Or give it a name :)
 ///     strmatch!("one" _ [hellooo]) => {
 ///         assert_eq!(hellooo, b"twotwo threethreethree");
 ///     }
+///     strmatch!("one" _ hellooo @ ..) => {
+///         assert_eq!(hellooo, b"twotwo threethreethree");
+///     }
+///
+///     // Several whole patterns can share an arm, if they bind the same
+///     // names
+///     strmatch!("yes" [_] | "y" [_] | "true") => {}
+///
+///     // Ask for a minimum length up front, without padding with `_`s
+///     strmatch!(min_len(20); "one" ..) => {}
+///
+///     // Bind the whole match too, along with its parts
+///     strmatch!(line @ ("one " [rest])) => {
+///         assert_eq!(line, b"one twotwo threethreethree");
+///     }
 ///
 ///     // We can combine patterns however we want!
 ///     strmatch!("one" ' ' "two"x2 space "three"x2 [rest]) => {
@@ -53,205 +161,1974 @@ use syn::{parse::Parse, Ident, LitByte, LitByteStr, LitChar, LitStr, Token};
 ///     _ => println!("Macros are fun :p"),
 /// }
 /// ```
+///
+/// # Refutability
+///
+/// Almost every pattern can fail to match, so `strmatch!` can't go where
+/// Rust needs a pattern that always matches, like a plain `let` or a
+/// function parameter. A macro can't tell where it's being used, so rustc
+/// reports this itself, as a "refutable pattern" error listing the slices
+/// the pattern doesn't cover. Use it in a `match`, `if let` or `matches!`,
+/// or bind its captures with `strmatch_let!`, which takes an `else` block
+/// for when the pattern doesn't match.
+///
+/// ```compile_fail
+/// # use strmatch::strmatch;
+/// let strmatch!("GET " [path]) = b"GET /".as_slice();
+/// ```
 #[proc_macro]
 #[proc_macro_error]
 pub fn strmatch(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch", &tokens) {
+        return splice;
+    }
     if tokens.is_empty() {
         return quote!([]).into();
     }
 
-    let macro_input = parse_macro_input!(tokens as MacroInput);
-    let end = macro_input.end;
-    let literals = macro_input.literals;
-    if let Some(end) = end {
-        quote!([#(#literals)* #end]).into()
-    } else {
-        quote!([#(#literals)*]).into()
+    let Alternatives(alternatives) = parse_macro_input!(tokens as Alternatives);
+    alternatives_pattern(&alternatives, slice_pattern).into()
+}
+
+/// `bstrmatch!` is `strmatch!` for binary data: it only accepts byte and
+/// byte string literals, so text can't sneak into a pattern by accident.
+/// `str` and `char` literals are an error, since what bytes they stand for
+/// depends on an encoding. Integer literals up to 255, like `0x89`, stand for
+/// a single byte, so values can be copied straight from a protocol's tables.
+///
+/// ```rust
+/// # use strmatch::bstrmatch;
+/// let png = b"\x89PNG\r\n\x1a\n...";
+/// assert!(matches!(png.as_slice(), bstrmatch!(b'\x89' b"PNG" [_])));
+/// assert!(matches!(png.as_slice(), bstrmatch!(0x89 b"PNG" 0x0d 0x0a [_])));
+/// ```
+///
+/// ```compile_fail
+/// # use strmatch::bstrmatch;
+/// let header = b"\x01\x00";
+/// assert!(matches!(header.as_slice(), bstrmatch!(0x100)));
+/// ```
+///
+/// ```compile_fail
+/// # use strmatch::bstrmatch;
+/// let header = b"GIF89a";
+/// assert!(matches!(header.as_slice(), bstrmatch!("GIF" [_])));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn bstrmatch(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("bstrmatch", &tokens) {
+        return splice;
+    }
+    if tokens.is_empty() {
+        return quote!([]).into();
     }
+
+    let Alternatives(alternatives) = parse_macro_input!(tokens as Alternatives);
+    alternatives_pattern(&alternatives, |pattern| {
+        pattern.check_binary();
+        slice_pattern(pattern)
+    })
+    .into()
 }
 
-struct MacroInput {
-    literals: Vec<Capture>,
-    end: Option<EndCapture>,
+/// `strmatch_array!` is `strmatch!` for fixed-size arrays, `[u8; N]` or
+/// `&[u8; N]`, so a buffer doesn't have to be turned into a slice first. The
+/// pattern has to match exactly `N` bytes: it can't end in a capture of the
+/// rest, and one of the wrong length is a compile error.
+///
+/// ```rust
+/// # use strmatch::strmatch_array;
+/// let frame: [u8; 4] = *b"OK\r\n";
+/// match frame {
+///     strmatch_array!("OK\r\n") => {}
+///     strmatch_array!("E" code '\r' '\n') => panic!("error {}", code),
+///     _ => {}
+/// }
+/// assert!(matches!(&frame, strmatch_array!("OK" _ _)));
+/// ```
+///
+/// ```compile_fail
+/// # use strmatch::strmatch_array;
+/// let frame = *b"OK\r\n";
+/// assert!(matches!(frame, strmatch_array!("OK")));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_array(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_array", &tokens) {
+        return splice;
+    }
+    if tokens.is_empty() {
+        return quote!([]).into();
+    }
+
+    let macro_input = parse_macro_input!(tokens as Pattern);
+    if let Some(end) = &macro_input.end {
+        abort!(
+            end.bracket.span,
+            "an array has a fixed length, so its pattern can't end in a \
+             capture of the rest";
+            help = "use `_` for each byte to ignore"
+        );
+    }
+    slice_pattern(&macro_input).into()
 }
 
-impl Parse for MacroInput {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let mut literals = vec![];
-        // Try to parse a literal
-        while let Ok(lit) = input.parse::<Capture>() {
-            literals.push(lit);
-        }
-        if input.is_empty() {
-            return Ok(MacroInput {
-                literals,
-                end: None,
-            });
-        }
-        let inner;
-        let _ = bracketed!(inner in input);
-        match inner.parse::<EndCapture>() {
-            Err(e) => Err(e),
-            Ok(end) => Ok(MacroInput {
-                literals,
-                end: Some(end),
-            }),
+/// `strbytes!` builds a byte array out of literal segments, written the same
+/// way as in a pattern, so what a program sends can share its spelling with
+/// what it matches. Only literals (and their repetitions) are allowed, since
+/// everything else matches more than one thing.
+///
+/// ```rust
+/// # use strmatch::{strbytes, strmatch};
+/// const PING: [u8; 6] = strbytes!("PING" '\r' '\n');
+/// assert_eq!(&PING, b"PING\r\n");
+/// assert!(matches!(&PING[..], strmatch!("PING" "\r\n")));
+///
+/// assert_eq!(strbytes!(b'\x00' * 3 "é"), [0, 0, 0, 0xc3, 0xa9]);
+///
+/// // An underline as long as its title
+/// assert_eq!(&strbytes!("Usage" '\n' '=' * len("Usage")), b"Usage\n=====");
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strbytes(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strbytes", &tokens) {
+        return splice;
+    }
+    if tokens.is_empty() {
+        return quote!([0u8; 0]).into();
+    }
+
+    let macro_input = parse_macro_input!(tokens as Pattern);
+    if let Some(whole) = &macro_input.whole {
+        abort!(
+            whole,
+            "`strbytes!` only takes literals, so it can't bind `{}`",
+            whole
+        );
+    }
+    if let Some(end) = &macro_input.end {
+        abort!(end.bracket.span, "`strbytes!` only takes literals");
+    }
+    if let Some((_, span)) = macro_input.min_len {
+        abort!(span, "`strbytes!` builds an array, so it has no `min_len`");
+    }
+    for segment in &macro_input.literals {
+        if segment.literal_bytes().is_none() {
+            abort!(
+                segment.span(),
+                "`strbytes!` only takes literals, since this could stand for \
+                 more than one byte"
+            );
         }
     }
+    let literals = &macro_input.literals;
+    quote!([#(#literals)*]).into()
 }
 
-/// `EndCapture` is meant to represent the last capture that grabs all
-/// remaining characters, as in [, , , end_capture @ ..] or [, , , _]
-enum EndCapture {
-    Ident(Ident),
-    Underscore,
+/// `strbuild!` builds a `Vec<u8>` at runtime out of segments written the same
+/// way as in a pattern, with `{expr}` holes for the parts that vary. A hole
+/// can be anything that's `AsRef<[u8]>`, like a `&str`, a `String` or a
+/// `Vec<u8>`, and it's only borrowed. `#byte` writes a `u8` variable,
+/// `pad(' ', width)` writes the byte `width` times, and `#nl` writes `\n`.
+/// Holes are evaluated in order before anything is written, so the buffer is
+/// allocated once.
+///
+/// ```rust
+/// # use strmatch::{strbuild, strextract};
+/// let path = "/index.html";
+/// let request = strbuild!("GET " {path} " HTTP/1.1" "\r\n");
+/// assert_eq!(request, b"GET /index.html HTTP/1.1\r\n");
+///
+/// // The same spelling matches it again
+/// let matched = strextract!(&request, "GET " [path until ' '] " HTTP/1.1" #nl);
+/// assert_eq!(matched, Some(&b"/index.html"[..]));
+///
+/// let (sep, indent) = (b':', 2);
+/// let line = strbuild!(pad(' ', indent) {format!("{}", 42)} #sep '-'x2 #nl);
+/// assert_eq!(line, b"  42:--\n");
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strbuild(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strbuild", &tokens) {
+        return splice;
+    }
+    let build_input = parse_macro_input!(tokens as BuildInput);
+    build_input.into_token_stream().into()
 }
 
-impl Parse for EndCapture {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let lookahead = input.lookahead1();
-        if lookahead.peek(Token![_]) {
-            input.parse::<Token![_]>().map(|_| EndCapture::Underscore)
-        } else if lookahead.peek(Ident) {
-            input.parse::<Ident>().map(EndCapture::Ident)
-        } else {
-            Err(lookahead.error())
-        }
+/// `assert_strmatches!` asserts that a pattern matches some input. The
+/// pattern can use anything `strextract!` can. If it doesn't match, the
+/// panic message has the pattern, a hexdump of the input, and the offset of
+/// the first byte that doesn't match (as far as the fixed-width start of the
+/// pattern can tell).
+///
+/// ```rust
+/// # use strmatch::assert_strmatches;
+/// assert_strmatches!(b"HTTP/1.1 200 OK", "HTTP/1." _ ' ' [status] ' ' [_]);
+/// ```
+///
+/// ```rust,should_panic
+/// # use strmatch::assert_strmatches;
+/// // assertion failed: input doesn't match `"HTTP/1." _ ' ' [_]`
+/// //   first difference at byte 5
+/// //   input (8 bytes):
+/// //   00000000  48 54 54 50 2f 32 20 32                          |HTTP/2 2|
+/// assert_strmatches!(b"HTTP/2 2", "HTTP/1." _ ' ' [_]);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn assert_strmatches(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("assert_strmatches", &tokens) {
+        return splice;
     }
+    let assert_input = parse_macro_input!(tokens as AssertInput);
+    assert_input.into_token_stream().into()
 }
 
-impl ToTokens for EndCapture {
-    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        match self {
-            EndCapture::Ident(ident) => tokens.append_all(quote!(#ident @ ..,)),
-            EndCapture::Underscore => tokens.append_all(quote!(..,)),
-        }
+/// `strmatch_dbg!` is `strmatch!`, but when the `STRMATCH_DBG` environment
+/// variable is set while compiling, it also prints the slice pattern it
+/// generates, to see what a pattern really matches without expanding the
+/// whole crate. Cargo shows the output when it builds the crate using the
+/// macro. Cargo doesn't know the macro reads the variable, so setting it
+/// doesn't rebuild a crate that's already built; touch the file first.
+///
+/// ```rust
+/// # use strmatch::strmatch_dbg;
+/// // With `STRMATCH_DBG=1`, prints `strmatch_dbg!("one" _ "two"x2 [rest])
+/// // expands to [111u8, 110u8, 101u8, _, 116u8, 119u8, 111u8, 116u8, 119u8,
+/// // 111u8, rest @ ..,]`
+/// match b"one twotwo three".as_slice() {
+///     strmatch_dbg!("one" _ "two"x2 [rest]) => assert_eq!(rest, b" three"),
+///     _ => unreachable!(),
+/// }
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_dbg(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_dbg", &tokens) {
+        return splice;
+    }
+    let input = proc_macro2::TokenStream::from(tokens.clone());
+    let expanded = if tokens.is_empty() {
+        quote!([])
+    } else {
+        slice_pattern(&parse_macro_input!(tokens as Pattern))
+    };
+    if std::env::var_os("STRMATCH_DBG").is_some_and(|var| !var.is_empty()) {
+        // Token streams print with line breaks in odd places
+        let printed = expanded.to_string();
+        let printed = printed.split_whitespace().collect::<Vec<_>>().join(" ");
+        let printed = printed.replace(" ,", ",");
+        eprintln!("strmatch_dbg!({input}) expands to {printed}");
     }
+    expanded.into()
 }
 
-/// Any capture that does grab and arbitrary number of tokens.
-/// Each of the string-style captures can also have a number of repetitions
-/// provided that dictates how many times the proc-macro includes them.
-/// These are possible captures of each type
-/// `ByteStr`:    b"abc"x2 --expands to-> [b'a', b'b', b'c', b'a', b'b', b'c',]
-/// `Byte`:       b'b'x2   --expands to-> [b'b', b'b',]
-/// `Str`:        "abc!"x2 --expands to-> [b'a', b'b', b'c', b'a', b'b', b'c',]
-/// `Char`:       'c'x2    --expands to-> ['c', 'c',]
-/// `Ident`:      abc      --expands to-> [abc @ _,]
-/// `Underscore`: _        --expands to-> [_,]
-enum Capture {
-    ByteStr { lit: LitByteStr, reps: usize },
-    Byte { lit: LitByte, reps: usize },
-    Str { lit: LitStr, reps: usize },
-    Char { lit: LitChar, reps: usize },
-    Ident(Ident),
-    Underscore,
-}
-
-// Return the number of repetitionss from a suffix
-fn process_suffix(suffix: &str) -> Result<usize, String> {
-    if suffix.is_empty() {
-        return Ok(1);
-    }
-    if suffix.starts_with('x') {
-        // We know it starts with x so we can unwrap
-        let (_, rest) = suffix.split_once('x').unwrap();
-        rest.parse::<usize>()
-            .map_err(|_| format!("error parsing {rest} into an integer"))
-    } else {
-        Err("suffix did not start with `x`".into())
-    }
-}
-
-impl Parse for Capture {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let lookahead = input.lookahead1();
-        if lookahead.peek(Ident) {
-            input.parse().map(Capture::Ident)
-        } else if lookahead.peek(Token![_]) {
-            input.parse::<Token![_]>().map(|_| Capture::Underscore)
-        } else if lookahead.peek(LitByte) {
-            match input.parse::<LitByte>() {
-                Ok(lit) => {
-                    let reps = match process_suffix(lit.suffix()) {
-                        Ok(reps) => reps,
-                        Err(e) => abort!(lit.span(), e),
-                    };
-                    return Ok(Capture::Byte { lit, reps });
-                }
-                Err(_) => unreachable!(), // we checked with lookahead
-            }
-        } else if lookahead.peek(LitByteStr) {
-            match input.parse::<LitByteStr>() {
-                Ok(lit) => {
-                    let reps = match process_suffix(lit.suffix()) {
-                        Ok(reps) => reps,
-                        Err(e) => abort!(lit.span(), e),
-                    };
-                    return Ok(Capture::ByteStr { lit, reps });
-                }
-                Err(_) => unreachable!(), // we checked with lookahead
-            }
-        } else if lookahead.peek(LitChar) {
-            match input.parse::<LitChar>() {
-                Ok(lit) => {
-                    let reps = match process_suffix(lit.suffix()) {
-                        Ok(reps) => reps,
-                        Err(e) => abort!(lit.span(), e),
-                    };
-                    return Ok(Capture::Char { lit, reps });
-                }
-                Err(_) => unreachable!(), // we checked with lookahead
-            }
-        } else if lookahead.peek(LitStr) {
-            match input.parse::<LitStr>() {
-                Ok(lit) => {
-                    let reps = match process_suffix(lit.suffix()) {
-                        Ok(reps) => reps,
-                        Err(e) => abort!(lit.span(), e),
-                    };
-                    return Ok(Capture::Str { lit, reps });
-                }
-                Err(_) => unreachable!(), // we checked with lookahead
-            }
-        } else {
-            Err(lookahead.error())
-        }
+/// `strmatch_const_fn!` turns a pattern into a `const fn` taking a `&[u8]`,
+/// for use in constants, statics and const assertions. It's written like a
+/// function whose body is the pattern, `fn name = pattern`, and can have
+/// attributes (doc comments included) and a visibility.
+///
+/// A pattern that binds nothing gives a function returning whether it
+/// matched. Otherwise it returns `Some` of the captures if it matched, with
+/// single bytes as `&u8`s and the tail or whole match as `&[u8]`s, a lone
+/// capture as is and several in a tuple. Only patterns that `strmatch!`
+/// takes can be used, since const fns can't run the other macros' scanning
+/// code.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_const_fn;
+/// strmatch_const_fn!(
+///     /// Whether `input` starts with the PNG signature.
+///     pub fn is_png = b'\x89' "PNG\r\n" ..
+/// );
+/// strmatch_const_fn!(fn split_key = key @ 'a'..='z' '=' value @ ..);
+///
+/// const _: () = assert!(is_png(b"\x89PNG\r\n\x1a\n"));
+/// const VALUE: Option<(&u8, &[u8])> = split_key(b"x=12");
+/// assert_eq!(VALUE, Some((&b'x', &b"12"[..])));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_const_fn(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_const_fn", &tokens) {
+        return splice;
     }
+    let const_fn_input = parse_macro_input!(tokens as ConstFnInput);
+    const_fn_input.into_token_stream().into()
 }
 
-impl ToTokens for Capture {
-    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        match self {
-            Capture::ByteStr { lit, reps } => {
-                for _ in 0..*reps {
-                    let bytes = lit.value();
-                    tokens.append_terminated(bytes.iter(), quote!(,))
-                }
+// One alternative as `slice_pattern` makes it, several as an or-pattern in
+// parentheses, which every alternative has to bind the same names in
+fn alternatives_pattern(
+    alternatives: &[Pattern],
+    slice_pattern: impl Fn(&Pattern) -> proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if let [pattern] = alternatives {
+        return slice_pattern(pattern);
+    }
+    arms::check_bindings(alternatives);
+    let alternatives = alternatives.iter().map(slice_pattern);
+    quote!((#(#alternatives)|*))
+}
+
+fn slice_pattern(pattern: &Pattern) -> proc_macro2::TokenStream {
+    let whole = pattern.whole.as_ref().map(|whole| quote!(#whole @));
+    let end = &pattern.end;
+    let missing = pattern.check_min_len();
+    if let Some(end) = end {
+        // A slice pattern can only ask for more bytes by matching them
+        if missing > 0 && end.name.is_some() {
+            let (_, span) = pattern.min_len.unwrap();
+            abort!(
+                span,
+                "a slice pattern can't check its `min_len` and bind the rest \
+                 too";
+                help = "use `strextract!` or `strmatch_match!`, which check \
+                        the length first"
+            );
+        }
+    }
+    // Without a capture at the end, one at the start can take what comes
+    // before the rest, as in `[stem] ".rs"`
+    let (start, literals) = match pattern.literals.split_first() {
+        Some((Capture::Slice(start), literals))
+            if end.is_none() && start.is_plain() =>
+        {
+            if let Some((_, span)) = pattern.min_len {
+                abort!(
+                    span,
+                    "a slice pattern can't check its `min_len` and bind the \
+                     start too";
+                    help = "use `strextract!` or `strmatch_match!`, which \
+                            check the length first"
+                );
             }
-            Capture::Byte { lit, reps } => {
-                for _ in 0..*reps {
-                    let byte = lit.value();
-                    tokens.append_all(quote!(#byte,))
-                }
+            (Some(start), literals)
+        }
+        _ => (None, pattern.literals.as_slice()),
+    };
+    // A slice pattern for each choice of line ending for each `#nl`, and of
+    // literal for each `{..}`, with how many bytes the choices take
+    let start = start.map(ToTokens::to_token_stream).unwrap_or_default();
+    let mut variants = vec![(start, 0)];
+    for (i, capture) in literals.iter().enumerate() {
+        let choices: Vec<Vec<u8>> = match capture {
+            Capture::Newline { eol: false, .. } => {
+                vec![b"\n".to_vec(), b"\r\n".to_vec()]
             }
-            Capture::Str { lit, reps } => {
-                for _ in 0..*reps {
-                    let string = lit.value();
-                    // We want to display in byte literal form
-                    let chars = string.as_bytes();
-                    tokens.append_terminated(chars, quote!(,))
+            Capture::Newline { keyword, .. } => {
+                if i + 1 < literals.len() || end.is_some() {
+                    abort!(
+                        keyword,
+                        "in a slice pattern, `#eol` can only end the pattern, \
+                         since it can match the end of the input";
+                        help = "use `#nl` instead, or `strmatch_match!` to \
+                                have `#eol` checked where it is"
+                    );
                 }
+                vec![b"\n".to_vec(), b"\r\n".to_vec(), vec![]]
             }
-            Capture::Char { lit, reps } => {
-                for _ in 0..*reps {
-                    // Display as a byte literal
-                    let char = lit.value() as u8;
-                    tokens.append_all(quote!(#char,))
+            Capture::OneOf { options, .. } => options
+                .iter()
+                .map(|option| option.literal_bytes().unwrap())
+                .collect(),
+            capture => {
+                for (variant, _) in &mut variants {
+                    capture.to_tokens(variant);
                 }
+                continue;
             }
-            Capture::Ident(ident) => tokens.append_all(quote!(#ident,)),
-            Capture::Underscore => tokens.append_all(quote!(_,)),
-        }
+        };
+        variants = variants
+            .iter()
+            .flat_map(|(variant, width)| {
+                choices.iter().map(move |choice| {
+                    (quote!(#variant #(#choice,)*), width + choice.len())
+                })
+            })
+            .collect();
+    }
+    let mut variants =
+        variants.into_iter().map(|(literals, width)| match end {
+            Some(end) => {
+                let padding =
+                    (0..missing.saturating_sub(width)).map(|_| quote!(_,));
+                quote!([#literals #(#padding)* #end])
+            }
+            None => quote!([#literals]),
+        });
+    if variants.len() == 1 {
+        let variant = variants.next();
+        return quote!(#whole #variant);
+    }
+    quote!(#whole (#(#variants)|*))
+}
+
+/// `strmatch_match!` wraps a whole `match`, which lets its arms use segments
+/// that a plain slice pattern can't express, like comparing against runtime
+/// values. Each arm's pattern is written in parentheses and uses the same
+/// syntax as `strmatch!`; a final `_` arm catches everything else.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_match;
+/// let sep = b',';
+/// let line = "key,value";
+///
+/// let value = strmatch_match!(line.as_bytes() {
+///     // `#name` matches a byte equal to the runtime value `name` ...
+///     ("key" #sep [value]) => Some(value),
+///
+///     // ... and `#(expression)` works for anything else.
+///     ("key" #(sep + 1) [_]) => None,
+///
+///     // Guards and alternatives work like in a `match`.
+///     ("a" [rest]) | ("b" [rest]) if !rest.is_empty() => Some(rest),
+///
+///     _ => None,
+/// });
+/// assert_eq!(value, Some(&b"value"[..]));
+/// ```
+///
+/// A guard can also go right after the capture it constrains, as in
+/// `d if d.is_ascii_digit()` or `[rest if rest.len() >= 8]`. The guard can
+/// use that capture and the ones before it, and a capture that fails its
+/// guard doesn't match, so the pattern moves on as if the bytes were wrong.
+/// A guard after a bare capture extends as far as an expression can, but
+/// stops before a bracketed capture (wrap indexing in parentheses).
+///
+/// ```rust
+/// # use strmatch::strmatch_match;
+/// let password = strmatch_match!(b"pw=hunter22" {
+///     ("pw=" [pw if pw.len() >= 8]) => Ok(pw),
+///     ("pw=" [_]) => Err("too short"),
+///     _ => Err("not a password"),
+/// });
+/// assert_eq!(password, Ok(&b"hunter22"[..]));
+/// ```
+///
+/// Like `match`, arms are tried in order. An arm that can never match,
+/// because an earlier arm (without a guard) matches everything it does, is
+/// reported as a warning. Stable Rust doesn't let macros emit warnings of
+/// their own, so it shows up as the use of a deprecated item, and can be
+/// silenced with `#[allow(deprecated)]`.
+///
+/// The same goes for a capture named in SCREAMING_CASE, like `SEP`, which
+/// binds a new name where comparing against a const, with `{SEP}`, was most
+/// likely meant. If binding it is what's meant, write it as `r#SEP` or
+/// `_SEP` instead, which doesn't warn.
+///
+/// ```compile_fail
+/// #![deny(deprecated)]
+/// # use strmatch::strextract;
+/// let key = strextract!(b"a=b", [key lazy] SEP [_]);
+/// ```
+///
+/// ```rust
+/// #![deny(deprecated)]
+/// # use strmatch::strextract;
+/// let sep = strextract!(b"a=b", 'a' r#SEP 'b');
+/// assert_eq!(sep, Some(&b'='));
+/// ```
+///
+/// Arms can also use segments matching a varying number of bytes. `#utf8char`
+/// matches one UTF-8 encoded `char`, and `name @ #utf8char` binds it:
+///
+/// ```rust
+/// # use strmatch::strmatch_match;
+/// let first = strmatch_match!("été".as_bytes() {
+///     (c @ #utf8char [_]) => Some(c),
+///     _ => None,
+/// });
+/// assert_eq!(first, Some('é'));
+/// ```
+///
+/// Like presets, these keywords take priority over runtime values of the
+/// same name: `#utf8char` is always the segment, and `#(utf8char)` the value.
+///
+/// With the `grapheme` feature, `#grapheme` matches one extended grapheme
+/// cluster instead, like `e` followed by a combining accent, and binds it as
+/// a `&str`. The generated code segments with `unicode-segmentation`, which
+/// the crate using it needs as a dependency.
+///
+/// `#ws` matches a run of ASCII whitespace, and `#ws?` a run that can also be
+/// empty. The run is always taken whole, and `name @ #ws` binds it:
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let kv = strextract!(b"port =\t8080", [key: String lazy] #ws? '=' #ws? [value: u16]);
+/// assert_eq!(kv, Some(("port".to_string(), 8080)));
+/// assert_eq!(strextract!(b"a  b", 'a' gap @ #ws 'b'), Some(&b"  "[..]));
+/// ```
+///
+/// `#nl` matches a line ending, either `\n` or `\r\n`, so one arm handles
+/// both, and `#eol` also matches the end of the input, for a last line that
+/// doesn't have one. In `strmatch!`, a pattern with them becomes an
+/// or-pattern of each line ending it could have, so `#eol` has to end it
+/// there:
+///
+/// ```rust
+/// # use strmatch::{strextract, strmatch};
+/// for line in [&b"OK 200\n"[..], b"OK 200\r\n", b"OK 200"] {
+///     assert_eq!(strextract!(line, "OK " [code: u16] #eol), Some(200));
+/// }
+/// assert!(matches!(&b"a\r\nb"[..], strmatch!('a' #nl 'b')));
+/// ```
+///
+/// A class of bytes followed by `+` matches a run of one or more of them, or
+/// followed by `*` a run that can also be empty, and `name @ class+` binds
+/// it. The classes are `digit`, `alpha`, `alnum`, `hex`, `upper`, `lower` and
+/// `punct`, or any range like `'a'..='f'`. Like `#ws`, the whole run is always
+/// taken. Classes made of more than one range, like `hex`, are looked up in a
+/// 256-bit table, so each byte of a long run costs one load and a mask.
+///
+/// With the `unicode` feature, `unicode_alpha`, `unicode_digit`,
+/// `unicode_alnum`, `unicode_upper`, `unicode_lower` and `unicode_space` are
+/// classes of characters rather than bytes. Their runs decode UTF-8 as they
+/// go, checking each character against a table of ranges taken from the
+/// Unicode tables of the standard library, and stop at the first byte that
+/// doesn't start a valid character.
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let version = strextract!(b"v10.2", 'v' major @ digit+ '.' minor @ digit+);
+/// assert_eq!(version, Some((&b"10"[..], &b"2"[..])));
+/// assert_eq!(strextract!(b"0xbeef", "0x" 'a'..='f'+), Some(()));
+/// assert_eq!(strextract!(b"id", a @ alpha+ n @ digit*), Some((&b"id"[..], &b""[..])));
+/// ```
+///
+/// `#base64run` matches a run of base64, in the standard alphabet or the
+/// URL-safe one (but not a mix), with the `=` padding that completes its last
+/// group of four if it has any, and `name @ #base64run` binds it, padding and
+/// all. `#base64run(decoded)` also decodes it into a `Vec<u8>`:
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let auth = b"Authorization: Basic dXNlcjpwYXNz";
+/// let credentials = strextract!(auth, "Authorization: Basic " #base64run(decoded));
+/// assert_eq!(credentials, Some(b"user:pass".to_vec()));
+///
+/// let jwt = b"eyJhbGciOiJub25lIn0.e30.";
+/// let header = strextract!(jwt, header @ #base64run '.' [_]);
+/// assert_eq!(header, Some(&b"eyJhbGciOiJub25lIn0"[..]));
+/// ```
+///
+/// In the same way, `#hexrun` matches a run of hex digits of even length, as
+/// checksums and IDs are written. `#hexrun(bytes)` decodes it into a
+/// `Vec<u8>`, and `#hexrun(bytes: [u8; N])` into an array on the stack, which
+/// only matches runs of exactly `2 * N` digits:
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let line = b"commit 1f0a 3e";
+/// assert_eq!(strextract!(line, "commit " id @ #hexrun ' ' [_]), Some(&b"1f0a"[..]));
+/// assert_eq!(strextract!(line, "commit " #hexrun(id) [_]), Some(vec![0x1f, 0x0a]));
+/// assert_eq!(strextract!(line, "commit " #hexrun(id: [u8; 2]) [_]), Some([0x1f, 0x0a]));
+/// assert_eq!(strextract!(line, "commit " #hexrun(id: [u8; 4]) [_]), None);
+/// ```
+///
+/// `peek("...")` checks that the input goes on with some literal bytes, but
+/// leaves them for the segments after it to match, and `not_ahead("...")`
+/// checks that it doesn't:
+///
+/// ```rust
+/// # use strmatch::{strextract, strmatch_match};
+/// let kind = strmatch_match!(b"0x1f" {
+///     ('0' peek('x') [_]) => "hex",
+///     ('0' [_]) => "octal",
+///     _ => "decimal",
+/// });
+/// assert_eq!(kind, "hex");
+///
+/// let scheme = strextract!(b"https://a", [scheme] "://" not_ahead('/') [_]);
+/// assert_eq!(scheme, Some(&b"https"[..]));
+/// ```
+///
+/// Bracketed captures aren't limited to the end of a pattern either. By
+/// default a capture is greedy, taking as much as it can while still letting
+/// the rest of the pattern match; mark it `lazy` to take as little as it
+/// can instead (`greedy` can also be written out).
+///
+/// ```rust
+/// # use strmatch::strmatch_match;
+/// let quoted = strmatch_match!(br#""a" "b""# {
+///     ('"' [q lazy] '"' [_]) => Some(q),
+///     _ => None,
+/// });
+/// assert_eq!(quoted, Some(&b"a"[..]));
+/// ```
+///
+/// A parenthesized group followed by `*` matches its pattern as many times
+/// in a row as it can (it never gives repetitions back to later segments).
+/// Each capture inside the group is bound to a `Vec` of what it captured in
+/// every repetition, and `name @ (..)*` binds how many times it matched.
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let list = strextract!(b"[1,22,333]", '[' ([n: u32] ',')* [last: u32] ']');
+/// assert_eq!(list, Some((vec![1, 22], 333)));
+///
+/// let indent = strextract!(b"    x", depth @ ("  ")* [_]);
+/// assert_eq!(indent, Some(2));
+/// ```
+///
+/// # Presets
+///
+/// Some common patterns are built in, and can be used as segments with
+/// `#name`, or bound with `name @ #preset`, which binds the matched bytes.
+/// Presets match as much as they can, and take priority over runtime
+/// values of the same name (use `#(name)` to refer to those).
+///
+/// | Preset     | Matches                                                 |
+/// |------------|---------------------------------------------------------|
+/// | `#hexbyte` | two ASCII hex digits, either case                       |
+/// | `#u16`     | one to five ASCII digits whose value fits in a `u16`    |
+/// | `#ident`   | an ASCII letter or `_`, then ASCII letters, digits, `_` |
+/// | `#ipv4`    | four `.`-separated octets, each one to three digits and at most 255 |
+/// | `#ipv6`    | eight `:`-separated groups of one to four hex digits, with `::` for a run of zero groups, and an optional IPv4 address for the last two |
+/// | `#kv`      | a `key = value` line, up to (not including) the next `\n` |
+/// | `#http_request` | an HTTP/1.x request line, `METHOD /path HTTP/1.x`, without the line ending |
+/// | `#semver`  | a semantic version, `1.2.3`, with optional `-pre.release` and `+build.metadata` |
+/// | `#uuid`    | a hyphenated UUID, 8-4-4-4-12 hex digits of either case |
+/// | `#mac`     | a MAC address, six hex digit pairs separated by `:` or `-` (the same throughout) |
+/// | `#url`     | a URL, `scheme:` then visible ASCII other than `"`, `<` and `>` |
+/// | `#email`   | an email address, `local@example.com`, whose domain has at least two labels |
+///
+/// `#hexbyte` always matches two bytes, so it can also be used (unbound) in
+/// `strmatch!`.
+///
+/// Some presets hand back parts of what they matched, which you get with
+/// `#name(arg, ..)`, passing a name to bind, `_` to ignore, or a literal the
+/// part has to equal for each of them (a string or byte literal for parts
+/// that are bytes, an integer for numbers). `#kv(key, value)` binds the key and
+/// value with surrounding ASCII whitespace trimmed, and `#kv("port", value)`
+/// only matches lines whose key is `port`.
+/// `#http_request(method, path, version)` binds the method and path bytes
+/// and the minor version (`1` for `HTTP/1.1`) as a `u8`.
+/// `#semver(major, minor, patch, pre, build)` binds the numbers as `u64`s and
+/// the pre-release and build metadata as `Option<&[u8]>`s. `#uuid(bytes)`
+/// and `#mac(bytes)` decode the address into a `[u8; 16]` or `[u8; 6]`, and
+/// `#ipv6(bytes)` into a `[u8; 16]`, which `Ipv6Addr::from` takes.
+/// `#url(scheme, authority, path, query)` splits a URL into its scheme, the
+/// `Option<&[u8]>` after `//`, the path, and the `Option<&[u8]>` after `?`,
+/// leaving out any `#fragment`, without percent-decoding anything.
+/// `#email(local, domain)` binds the parts before and after the `@`.
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let port = strextract!(b"  port = 8080 \r\n", #kv("port", value) [_]);
+/// assert_eq!(port, Some(&b"8080"[..]));
+///
+/// let request = b"GET /index.html HTTP/1.1\r\n";
+/// let path = strextract!(request, #http_request("GET", path, _) "\r\n");
+/// assert_eq!(path, Some(&b"/index.html"[..]));
+///
+/// let version = strextract!(
+///     b"rustc 1.75.0-beta.3 (82e1608df 2023-12-21)",
+///     "rustc " #semver(major, minor, _, pre, _) ' ' [_]
+/// );
+/// assert_eq!(version, Some((1, 75, Some(&b"beta.3"[..]))));
+///
+/// let mac = strextract!(b"ether 00:1A:2b:3c:4d:5e", "ether " #mac(bytes));
+/// assert_eq!(mac, Some([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]));
+///
+/// let url = b"<https://example.com/search?q=rust#top>";
+/// let parts = strextract!(url, '<' #url("https", host, path, query) '>');
+/// assert_eq!(parts, Some((Some(&b"example.com"[..]), &b"/search"[..], Some(&b"q=rust"[..]))));
+///
+/// let domain = strextract!(b"Contact: ops@example.org.", "Contact: " #email(_, domain) [_]);
+/// assert_eq!(domain, Some(&b"example.org"[..]));
+/// ```
+///
+/// ```rust
+/// # use strmatch::strmatch_match;
+/// let host = strmatch_match!(b"connect 127.0.0.1:8080" {
+///     ("connect " addr @ #ipv4 ':' #u16) => Some(addr),
+///     _ => None,
+/// });
+/// assert_eq!(host, Some(&b"127.0.0.1"[..]));
+///
+/// let ip = strmatch_match!(b"from [fe80::1]:443" {
+///     ("from " #ipv4 ':' #u16) => None,
+///     ("from [" #ipv6(bytes) "]:" #u16) => Some(std::net::IpAddr::from(bytes)),
+///     _ => None,
+/// });
+/// assert_eq!(ip, Some("fe80::1".parse().unwrap()));
+/// ```
+///
+/// With four or more arms, a table built at compile time gives the arms that
+/// can match input starting with each byte, so keyword-heavy matches skip
+/// straight past the arms that can't match rather than trying each in turn.
+///
+/// Starting it with `#[reorder]` tries the arms whose patterns start with
+/// longer literals first, which can save comparisons when many arms share
+/// short prefixes. Arms are only reordered when no input could match both an
+/// arm and one it's moved ahead of, since that would change which one wins;
+/// otherwise it's an error.
+///
+/// ```rust
+/// # use strmatch::strmatch_match;
+/// let command = strmatch_match!(#[reorder] b"HELO mx" {
+///     ("QUIT") => 0,
+///     ("HELO " [_]) => 1,
+///     ("EHLO " [_]) => 2,
+///     _ => 3,
+/// });
+/// assert_eq!(command, 1);
+/// ```
+///
+/// ```compile_fail
+/// # use strmatch::strmatch_match;
+/// // `"GET /"` matches some of what `"GET"` does, so it can't move ahead
+/// strmatch_match!(#[reorder] b"GET /" {
+///     ("GET" ..) => 0,
+///     ("GET /" ..) => 1,
+///     _ => 2,
+/// });
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_match(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_match", &tokens) {
+        return splice;
+    }
+    let match_input = parse_macro_input!(tokens as MatchInput);
+    match_input.into_token_stream().into()
+}
+
+/// `strextract!` runs a single pattern against some bytes, evaluating to
+/// `Some` of its captures if it matched and `None` otherwise. A pattern with
+/// one capture gives back just that capture, and one with several gives back
+/// a tuple of them, in order. Patterns use the same syntax as in
+/// `strmatch_match!`.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let line = b"GET /index.html HTTP/1.1";
+///
+/// let path = strextract!(line, "GET " [path until ' '] " HTTP/1." _);
+/// assert_eq!(path, Some(&b"/index.html"[..]));
+///
+/// // `until` splits right before the first occurrence of its delimiter,
+/// // which can be any literal
+/// let fields = strextract!(b"a, b, c", [first until ", "] ", " [rest]);
+/// assert_eq!(fields, Some((&b"a"[..], &b"b, c"[..])));
+///
+/// assert_eq!(strextract!(b"no delimiter", [_ until ','] [_]), None);
+///
+/// // `trim` leaves out the ASCII whitespace around what's bound
+/// let setting = strextract!(b" port = 80 ", [key trim until '='] '=' [v: u16 trim]);
+/// assert_eq!(setting, Some((&b"port"[..], 80)));
+///
+/// // A pipeline runs the capture through each step after a `|`, any of
+/// // which can fail the match: `trim`, `utf8`, `parse::<T>`, or a function
+/// // returning an `Option` or `Result`
+/// let id = strextract!(b"id= 7 ", "id=" [id | trim | parse::<u8> | std::num::NonZeroU8::new]);
+/// assert_eq!(id.map(|id| id.get()), Some(7));
+/// assert_eq!(strextract!(b"id=0", "id=" [id | parse::<u8> | std::num::NonZeroU8::new]), None);
+///
+/// // A capture with a type only matches bytes that parse into it
+/// let size = strextract!(b"1920x1080", [w: u32] 'x' [h: u32]);
+/// assert_eq!(size, Some((1920, 1080)));
+///
+/// // Except `utf8`, which is the `utf8` step: it binds a `&str`, and only
+/// // matches bytes that are valid UTF-8
+/// let word = strextract!("ok: déjà vu".as_bytes(), "ok: " [rest: utf8]);
+/// assert_eq!(word, Some("déjà vu"));
+/// assert_eq!(strextract!(b"ok: \xff", "ok: " [rest: utf8]), None);
+///
+/// // With the `bstr` feature, `[rest: bstr]` (or the `bstr` step) binds a
+/// // `&bstr::BStr`, whose `Debug` output is text even if it isn't UTF-8
+///
+/// // A float capture takes the whole number, sign, fraction and exponent
+/// let temp = strextract!(b"-3.5e2C", [t: f64] 'C');
+/// assert_eq!(temp, Some(-350.0));
+///
+/// // So does a signed integer capture, which can clamp a number that
+/// // overflows instead of failing to match
+/// assert_eq!(strextract!(b"-300", [n: i8]), None);
+/// assert_eq!(strextract!(b"-300", [n: i8 saturating]), Some(-128));
+/// ```
+///
+/// Captures borrow from whatever the input points at, not from the macro's
+/// own copy of it, so they can be handed back out of a function even when
+/// the input is a method call:
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// fn path(line: &str) -> Option<&[u8]> {
+///     strextract!(line.trim().as_bytes(), "GET " [path until ' '] ..)
+/// }
+/// assert_eq!(path("  GET /index.html HTTP/1.1\r\n"), Some(&b"/index.html"[..]));
+/// ```
+///
+/// Runs of fixed-width segments are matched with slice patterns. Starting
+/// `strextract!` or `strmatch_match!` with `#[codegen(if_chain)]` matches
+/// them with a length check and a chain of comparisons instead, which can be
+/// handy for benchmarking which one a hot parser compiles better to.
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let path = strextract!(#[codegen(if_chain)] b"GET /", "GET " [path]);
+/// assert_eq!(path, Some(&b"/"[..]));
+/// ```
+///
+/// `#[codegen(shared_helpers)]` is like `if_chain`, but compares each
+/// literal longer than a byte by calling an `#[inline(never)]` function, one
+/// per distinct literal, which every arm of a `strmatch_match!` shares. It
+/// trades a little speed for less code, for targets short on flash.
+///
+/// ```rust
+/// # use strmatch::strmatch_match;
+/// let method = strmatch_match!(#[codegen(shared_helpers)] &b"POST /"[..] {
+///     ("GET " [_]) => 0,
+///     ("POST " [_]) => 1,
+///     ("POST" [_]) => 2,
+///     _ => 3,
+/// });
+/// assert_eq!(method, 1);
+/// ```
+///
+/// `#[codegen(const_array)]` instead puts each of those literals in a
+/// `const` array, also shared, and compares a slice of the input against
+/// it, which keeps the expansion short and readable.
+///
+/// ```rust
+/// # use strmatch::strextract;
+/// let host = strextract!(#[codegen(const_array)] b"Host: example.com", "Host: " [host]);
+/// assert_eq!(host, Some(&b"example.com"[..]));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strextract(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strextract", &tokens) {
+        return splice;
+    }
+    let extract_input = parse_macro_input!(tokens as ExtractInput);
+    extract_input.into_token_stream().into()
+}
+
+/// `strextract_owned!` is `strextract!` handing back owned copies of the
+/// captures, so they can outlive the input, like a reused read buffer. Each
+/// capture is copied with `ToOwned`, so a `&[u8]` comes back as a `Vec<u8>`,
+/// a `&str` as a `String` and a `&u8` as a `u8`, even inside the `Vec`s of a
+/// repeated group. Parsed values come back as they are.
+///
+/// ```rust
+/// # use strmatch::strextract_owned;
+/// let mut buf = b"key=value".to_vec();
+/// let owned = strextract_owned!(&buf[..], [key until '='] '=' [value]);
+/// buf.clear();
+/// assert_eq!(owned, Some((b"key".to_vec(), b"value".to_vec())));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strextract_owned(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strextract_owned", &tokens) {
+        return splice;
+    }
+    let mut extract_input = parse_macro_input!(tokens as ExtractInput);
+    extract_input.owned = true;
+    extract_input.into_token_stream().into()
+}
+
+/// `strmatch_nom!` turns a pattern into a [`nom`](https://docs.rs/nom)
+/// parser, so it can be used as a leaf among nom's combinators. The parser
+/// matches the start of its input and hands back the rest along with the
+/// captures, as `strextract!` would give them, failing with
+/// `ErrorKind::Verify` if the pattern doesn't match. The crate using it
+/// needs `nom` as a dependency.
+///
+/// ```rust
+/// # use strmatch::strmatch_nom;
+/// use nom::multi::separated_list1;
+/// use nom::bytes::complete::tag;
+///
+/// let pair = strmatch_nom!([key until '='] '=' [value: u32 until ';']);
+/// let mut pairs = separated_list1(tag(";"), pair);
+/// let (rest, parsed) = pairs(&b"a=1;b=22;"[..]).unwrap();
+/// assert_eq!(parsed, [(&b"a"[..], 1), (&b"b"[..], 22)]);
+/// assert_eq!(rest, b";");
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_nom(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_nom", &tokens) {
+        return splice;
+    }
+    let nom_input = parse_macro_input!(tokens as NomInput);
+    nom_input.into_token_stream().into()
+}
+
+/// `strmatch_winnow!` turns a pattern into a
+/// [`winnow`](https://docs.rs/winnow) parser, the same way `strmatch_nom!`
+/// does for nom. It takes a `&mut &[u8]` and moves it past what it matched,
+/// returning a `ModalResult` of the captures that backtracks if the pattern
+/// doesn't match. The crate using it needs `winnow` as a dependency.
+///
+/// ```rust
+/// # use strmatch::strmatch_winnow;
+/// use winnow::combinator::separated;
+/// use winnow::Parser;
+///
+/// let pair = strmatch_winnow!([key until '='] '=' [value: u32 until ';']);
+/// let mut input = &b"a=1;b=22;"[..];
+/// let pairs: Vec<_> = separated(1.., pair, ";").parse_next(&mut input).unwrap();
+/// assert_eq!(pairs, [(&b"a"[..], 1), (&b"b"[..], 22)]);
+/// assert_eq!(input, b";");
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_winnow(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_winnow", &tokens) {
+        return splice;
+    }
+    let winnow_input = parse_macro_input!(tokens as WinnowInput);
+    winnow_input.into_token_stream().into()
+}
+
+/// `strmatch_clap!` turns a pattern into a [`clap`](https://docs.rs/clap)
+/// value parser, for arguments with a syntax of their own like `HOST:PORT`
+/// or `WxH`. It's given the type the parser produces, and an arm like in
+/// `strmatch_match!` building it from the pattern's captures. A value that
+/// doesn't match is rejected with a `ValueValidation` error showing the
+/// pattern. The crate using it needs `clap` as a dependency.
+///
+/// ```rust
+/// # use strmatch::strmatch_clap;
+/// use clap::{Arg, Command};
+///
+/// let size = strmatch_clap!((u32, u32), ([w: u32] 'x' [h: u32]) => (w, h));
+/// let cmd = Command::new("resize").arg(Arg::new("size").long("size").value_parser(size));
+///
+/// let matches = cmd.clone().try_get_matches_from(["resize", "--size", "640x480"]).unwrap();
+/// assert_eq!(matches.get_one::<(u32, u32)>("size"), Some(&(640, 480)));
+/// assert!(cmd.try_get_matches_from(["resize", "--size", "640"]).is_err());
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_clap(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_clap", &tokens) {
+        return splice;
+    }
+    let clap_input = parse_macro_input!(tokens as ClapInput);
+    clap_input.into_token_stream().into()
+}
+
+/// `strmatch_arbitrary!` turns a pattern into a function generating inputs
+/// that it matches, with each random choice, like how long a run is or what
+/// goes in a capture, drawn from an `arbitrary::Unstructured`. It's for
+/// fuzzing the code behind a match arm with inputs that reach it. Several
+/// patterns separated by `|`, as in `strmatch!`, give inputs matching any
+/// of them. Needs the `arbitrary` feature.
+///
+/// Runs and captures of varying length are kept short, and a typed capture
+/// gets a number of its type, or text for a `String`. Segments that depend
+/// on what's around them, like a `#variable`, `peek(..)` or a guard, can't
+/// be generated.
+///
+/// ```rust
+/// # use strmatch::{strextract, strmatch_arbitrary};
+/// let generate = strmatch_arbitrary!("GET /" [path until ' '] " HTTP/1." digit #nl);
+/// let data = [7; 64];
+/// let mut u = arbitrary::Unstructured::new(&data);
+/// let request = generate(&mut u).unwrap();
+/// assert!(strextract!(&request, "GET /" [path until ' '] " HTTP/1." _ #nl).is_some());
+/// ```
+///
+/// Runs and presets take all the bytes they can, so a capture like `_` or
+/// `[rest]` right after one never starts with a byte it would take, and one
+/// followed by something that has to start with such a byte is turned down,
+/// since it would never match:
+///
+/// ```compile_fail
+/// # use strmatch::strmatch_arbitrary;
+/// let generate = strmatch_arbitrary!(digit+ '5');
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_arbitrary(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_arbitrary", &tokens) {
+        return splice;
+    }
+    let arbitrary_input = parse_macro_input!(tokens as ArbitraryInput);
+    arbitrary_input.into_token_stream().into()
+}
+
+/// `strmatch_proptest!` turns a pattern into a `proptest` strategy, an
+/// `impl Strategy<Value = Vec<u8>>` of inputs that it matches, so a property
+/// can be stated for everything a pattern matches. The inputs are made the
+/// same way as by `strmatch_arbitrary!`, from a list of bytes that proptest
+/// generates and shrinks, so a failing input shrinks toward the shortest runs
+/// and smallest bytes. Needs the `proptest` feature.
+///
+/// ```rust
+/// # use strmatch::{strextract, strmatch_proptest};
+/// use proptest::prelude::*;
+///
+/// proptest!(|(line in strmatch_proptest!([key: String until '='] '=' [value: u32]))| {
+///     let (key, value) = strextract!(&line, [key: String until '='] '=' [value: u32]).unwrap();
+///     prop_assert_eq!(format!("{}={}", key, value).into_bytes(), line);
+/// });
+/// ```
+///
+/// As with `strmatch_arbitrary!`, a run or preset followed by something that
+/// has to start with a byte it would take is turned down:
+///
+/// ```compile_fail
+/// # use strmatch::strmatch_proptest;
+/// let strategy = strmatch_proptest!(#ident "x");
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_proptest(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_proptest", &tokens) {
+        return splice;
+    }
+    let proptest_input = parse_macro_input!(tokens as ProptestInput);
+    proptest_input.into_token_stream().into()
+}
+
+/// `strlexer!` splits its input into tokens. It's written like
+/// `strmatch_match!`, with each arm's pattern matching the start of what's
+/// left of the input, and gives back an iterator over the tokens along with
+/// the range of the input each one came from.
+///
+/// Where more than one arm matches, the one taking the most bytes wins, or
+/// the first of them if they take as many, so `"if"` can be a keyword while
+/// `"ifs"` is still an identifier. An arm ending in `=> _` skips what it
+/// matches, like whitespace. A byte that no arm matches gives `Err` of its
+/// offset, and lexing carries on after it.
+///
+/// ```rust
+/// # use strmatch::strlexer;
+/// #[derive(Debug, PartialEq)]
+/// enum Token<'a> {
+///     If,
+///     Ident(&'a [u8]),
+///     Num(u32),
+/// }
+///
+/// let tokens = strlexer!(b"if ifs 42 ?" {
+///     ("if") => Token::If,
+///     (id @ 'a'..='z'+) => Token::Ident(id),
+///     (n @ digit+) => Token::Num(std::str::from_utf8(n).unwrap().parse().unwrap()),
+///     (#ws) => _,
+/// });
+/// assert_eq!(
+///     tokens.collect::<Vec<_>>(),
+///     [
+///         Ok((Token::If, 0..2)),
+///         Ok((Token::Ident(b"ifs"), 3..6)),
+///         Ok((Token::Num(42), 7..9)),
+///         Err(10),
+///     ]
+/// );
+/// ```
+///
+/// An arm that would match nothing at all is treated as not matching, so a
+/// lexer can't get stuck.
+#[proc_macro]
+#[proc_macro_error]
+pub fn strlexer(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strlexer", &tokens) {
+        return splice;
+    }
+    let lexer_input = parse_macro_input!(tokens as LexerInput);
+    lexer_input.into_token_stream().into()
+}
+
+/// `strmatch_find!` finds the first occurrence of a literal in its input,
+/// giving `Some` of its offset. The table the search uses to skip ahead is
+/// built at compile time, so searching for a fixed delimiter in a big
+/// buffer doesn't have to build one on every call.
+///
+/// ```rust
+/// # use strmatch::strmatch_find;
+/// let request = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\nbody";
+/// assert_eq!(strmatch_find!(request, "\r\n\r\n"), Some(33));
+/// assert_eq!(strmatch_find!(request, b'\n'), Some(15));
+/// assert_eq!(strmatch_find!(request, "\n\n"), None);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_find(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let find_input = parse_macro_input!(tokens as FindInput);
+    find_input.into_token_stream().into()
+}
+
+/// `strmatch_split_once!` splits its input around the first occurrence of a
+/// delimiter, giving `Some` of the bytes before and after it, like
+/// `str::split_once` but for bytes and delimiters of any length. The
+/// delimiter is a pattern of fixed bytes, found with the same search as
+/// `strmatch_find!`.
+///
+/// ```rust
+/// # use strmatch::strmatch_split_once;
+/// let path = b"std::collections::HashMap";
+/// assert_eq!(
+///     strmatch_split_once!(path, "::"),
+///     Some((&b"std"[..], &b"collections::HashMap"[..])),
+/// );
+/// assert_eq!(strmatch_split_once!(b"a\r\nb", '\r' '\n'), Some((&b"a"[..], &b"b"[..])));
+/// assert_eq!(strmatch_split_once!(b"a\nb", "\r\n"), None);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_split_once(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let split_input = parse_macro_input!(tokens as SplitOnceInput);
+    split_input.into_token_stream().into()
+}
+
+/// `strmatch_count!` counts the occurrences of some fixed bytes in its
+/// input, as a `usize`, with the same search as `strmatch_find!`. Each
+/// occurrence is looked for after the end of the last one, so they don't
+/// overlap.
+///
+/// ```rust
+/// # use strmatch::strmatch_count;
+/// let csv = b"a,b,c\n1,2,3\n4,5,6\n";
+/// assert_eq!(strmatch_count!(csv, '\n'), 3);
+/// assert_eq!(strmatch_count!(csv, ','), 6);
+/// assert_eq!(strmatch_count!(b"aaaa", "aa"), 2);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_count(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let count_input = parse_macro_input!(tokens as CountInput);
+    count_input.into_token_stream().into()
+}
+
+/// `strmatch_contains!` checks whether a pattern matches anywhere in its
+/// input, giving a `bool`. Fixed bytes at the start of the pattern are
+/// searched for the same way as by `strmatch_find!`, and the rest of it is
+/// only tried where they're found, so a pattern made of literals is just the
+/// search. A pattern that starts with anything else is tried at every offset.
+///
+/// ```rust
+/// # use strmatch::strmatch_contains;
+/// let log = b"12:00:01 INFO started\n12:00:07 ERROR code=503 upstream\n";
+/// assert!(strmatch_contains!(log, "ERROR"));
+/// assert!(strmatch_contains!(log, "code=" digit+ ' '));
+/// assert!(!strmatch_contains!(log, "code=4" _ _ ' '));
+/// assert!(strmatch_contains!(log, digit+ ':' digit+ " ERROR"));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_contains(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_contains", &tokens) {
+        return splice;
+    }
+    let contains_input = parse_macro_input!(tokens as ContainsInput);
+    contains_input.into_token_stream().into()
+}
+
+/// `strmatch_replace!` replaces every match of a pattern, written in
+/// parentheses, in its input, giving back a `Cow<[u8]>` that borrows the
+/// input if nothing matched. The pattern has to start with fixed bytes, which
+/// are searched for the same way as by `strmatch_find!`, and the rest of it
+/// is tried wherever they're found. The replacement is written as literals
+/// and the names of the pattern's captures, which are written out as they
+/// matched.
+///
+/// ```rust
+/// # use strmatch::strmatch_replace;
+/// # use std::borrow::Cow;
+/// let text = b"a\r\nb\r\n";
+/// assert_eq!(strmatch_replace!(text, ("\r\n") => '\n'), &b"a\nb\n"[..]);
+/// assert!(matches!(strmatch_replace!(b"a\nb\n", ("\r\n") => '\n'), Cow::Borrowed(_)));
+///
+/// let template = b"Hi ${name}, it's ${day}!";
+/// let replaced = strmatch_replace!(template, ("${" [key until '}'] '}') => '<' key '>');
+/// assert_eq!(replaced, &b"Hi <name>, it's <day>!"[..]);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_replace(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_replace", &tokens) {
+        return splice;
+    }
+    let replace_input = parse_macro_input!(tokens as ReplaceInput);
+    replace_input.into_token_stream().into()
+}
+
+/// `strkeywords!` defines an enum with a variant for each of a list of
+/// keywords, along with `from_bytes`, which gives the variant a keyword is
+/// spelled with, `as_bytes`, which gives it back, and `ALL`, every variant
+/// in order. `from_bytes` dispatches on the length first, then on the bytes
+/// of just the keywords of that length, all worked out at compile time.
+///
+/// ```rust
+/// # use strmatch::strkeywords;
+/// strkeywords! {
+///     /// The keywords of a tiny language
+///     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///     pub enum Keyword {
+///         If = "if",
+///         Else = "else",
+///         Elif = "elif",
+///         Return = "return",
+///     }
+/// }
+///
+/// assert_eq!(Keyword::from_bytes(b"elif"), Some(Keyword::Elif));
+/// assert_eq!(Keyword::from_bytes(b"iff"), None);
+/// assert_eq!(Keyword::Return.as_bytes(), b"return");
+/// assert_eq!(Keyword::ALL.len(), 4);
+/// ```
+///
+/// With the `phf` feature, an enum marked `#[phf]` looks keywords up in a
+/// `phf::Map` instead, whose perfect hash is found at compile time. That
+/// takes a hash and a single comparison however many keywords there are, and
+/// keeps the code small for big keyword sets, but the crate using it needs
+/// `phf` as a dependency.
+#[proc_macro]
+#[proc_macro_error]
+pub fn strkeywords(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let keywords_input = parse_macro_input!(tokens as KeywordsInput);
+    keywords_input.into_token_stream().into()
+}
+
+/// `strpattern!` names a pattern, so it can be written once and spliced into
+/// others with an `@sub(NAME)` segment. It's written `NAME = pattern`, and
+/// works in any of the macros taking a pattern, `strmatch!` included:
+///
+/// ```rust
+/// # use strmatch::{strextract, strmatch_match, strpattern};
+/// strpattern!(
+///     /// `HH:MM:SS`
+///     TIME = '0'..='2' _ ':' '0'..='5' _ ':' '0'..='5' _
+/// );
+/// strpattern!(STAMP = '[' @sub(TIME) "] ");
+///
+/// let level = strmatch_match!(b"[12:30:00] WARN disk" {
+///     (@sub(STAMP) "WARN " [what]) => Some(what),
+///     (@sub(STAMP) [_]) => None,
+///     _ => None,
+/// });
+/// assert_eq!(level, Some(&b"disk"[..]));
+/// assert_eq!(strextract!(b"12:60:00", @sub(TIME)), None);
+/// ```
+///
+/// The name is defined as a macro, so it follows the same scoping rules: it
+/// can be used after its definition in the same module, and elsewhere in the
+/// crate through its path, as in `@sub(crate::patterns::TIME)`.
+#[proc_macro]
+#[proc_macro_error]
+pub fn strpattern(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let pattern_def = parse_macro_input!(tokens as PatternDef);
+    pattern_def.into_token_stream().into()
+}
+
+/// Splices a pattern defined with `strpattern!` into the input of another
+/// macro, then runs that macro. Only meant to be called by the macros
+/// `strpattern!` defines.
+#[doc(hidden)]
+#[proc_macro]
+#[proc_macro_error]
+pub fn __strmatch_sub(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let sub_input = parse_macro_input!(tokens as SubInput);
+    sub_input.into_token_stream().into()
+}
+
+/// `strmatch_let!` binds the captures of a pattern with a `let`-`else`, for
+/// where a `let` would need a pattern that always matches. It's written
+/// `pattern = input else { .. }`, with the `else` block running (and having
+/// to return, break or panic) when the pattern doesn't match. Patterns use
+/// the same syntax as in `strmatch_match!`.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_let;
+/// fn path(request: &[u8]) -> Option<&[u8]> {
+///     strmatch_let!("GET " [path until ' '] " HTTP/1." _ = request else {
+///         return None;
+///     });
+///     Some(path)
+/// }
+/// assert_eq!(path(b"GET /index.html HTTP/1.1"), Some(&b"/index.html"[..]));
+/// assert_eq!(path(b"POST / HTTP/1.1"), None);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_let(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_let", &tokens) {
+        return splice;
+    }
+    let let_input = parse_macro_input!(tokens as LetInput);
+    let_input.into_token_stream().into()
+}
+
+/// `strmulti!` looks for the first of many literal keywords in its input,
+/// and evaluates to the body of that keyword's arm. It's written like a
+/// `match` whose arms are keywords (or several keywords separated by `|`),
+/// and which ends in a `_` arm for when none of them occur.
+///
+/// The input is scanned from the start, and at each byte the keywords are
+/// checked with a single `match` that dispatches on the bytes one at a time,
+/// rather than trying each keyword in turn. Where several keywords start at
+/// the same byte, the longest one wins.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmulti;
+/// let level = |line: &str| strmulti!(line.as_bytes() {
+///     "ERROR" | "FATAL" => 3,
+///     "WARN" => 2,
+///     "INFO" => 1,
+///     _ => 0,
+/// });
+/// assert_eq!(level("12:00 WARN disk at 91%"), 2);
+/// assert_eq!(level("12:01 FATAL out of disk"), 3);
+/// assert_eq!(level("12:02 ok"), 0);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmulti(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let multi_input = parse_macro_input!(tokens as MultiInput);
+    multi_input.into_token_stream().into()
+}
+
+/// `strglob!` checks whether its input matches a glob, like the ones a shell
+/// expands filenames with, and evaluates to a `bool`. The glob is turned
+/// into a pattern and matched like one:
+///
+/// - `*` matches any run of bytes, `/` included, like a lazy `[_]`
+/// - `?` matches one UTF-8 encoded `char`, like `#utf8char`
+/// - `[abc]` and `[a-z]` match one byte in the class, and `[!a-z]` (or
+///   `[^a-z]`) one that isn't
+/// - `\` makes the character after it match as is, so `\*` matches a `*`
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strglob;
+/// let is_source = |name: &str| strglob!(name.as_bytes(), "*.[ch]");
+/// assert!(is_source("main.c"));
+/// assert!(is_source("include/strmatch.h"));
+/// assert!(!is_source("main.rs"));
+///
+/// assert!(strglob!(b"sensors/7/temp", "sensors/?/*"));
+/// assert!(strglob!(b"log-2024.txt", "log-[0-9][!a-z]*.txt"));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strglob(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let glob_input = parse_macro_input!(tokens as GlobInput);
+    glob_input.into_token_stream().into()
+}
+
+/// `strmatch_regex!` matches its input against a regex, evaluating to `Some`
+/// of what its groups matched, as `&[u8]`s, or `None`. A lone group is
+/// handed back as is, and a regex without groups gives `Some(())`. The regex
+/// is turned into patterns at compile time and matched like one, so only the
+/// parts of regex syntax that patterns can express are there:
+///
+/// - literals, `.` for any byte, classes like `[a-z_]` and `[^"]`, and
+///   `\d`, `\w`, `\s` and their negations, all of them ASCII
+/// - `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`, and their lazy forms like
+///   `*?`, which give back bytes to what follows like in any regex engine.
+///   A repetition of a class or group that what follows can't start with
+///   takes all it can, as `digit+` does, and the rest backtrack like `[_]`
+///   and a lazy `[_]` do
+/// - groups, `(..)` or `(?<name>..)` to capture and `(?:..)` not to, and
+///   `|` between alternatives
+/// - `^` and `$`, without which the match can start or end anywhere
+///
+/// Backreferences, lookarounds and flags are turned down at compile time.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_regex;
+/// fn path(line: &str) -> Option<&[u8]> {
+///     strmatch_regex!(line.as_bytes(), r"^GET (\S+) HTTP/1\.[01]$")
+/// }
+/// assert_eq!(path("GET /index.html HTTP/1.1"), Some(&b"/index.html"[..]));
+/// assert_eq!(path("GET /index.html HTTP/2"), None);
+///
+/// let version = strmatch_regex!(b"strmatch v0.12.3", r"v(\d+)\.(\d+)\.(\d+)");
+/// assert_eq!(version, Some((&b"0"[..], &b"12"[..], &b"3"[..])));
+/// assert!(strmatch_regex!(b"log-2024.txt", r"\.(?:txt|md)$").is_some());
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_regex(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let regex_input = parse_macro_input!(tokens as RegexInput);
+    regex_input.into_token_stream().into()
+}
+
+/// `strmatch_regex_str!` writes a pattern out as a regex, in the syntax that
+/// PCRE, RE2 and Rust's `regex` crate share, so a tool outside of the
+/// program, like `grep -P` or a log pipeline, can use the same filter. It
+/// evaluates to a `&'static str` literal, anchored with `^` and `$` since a
+/// pattern matches its whole input, and alternatives can be given with `|`.
+/// Each binding becomes a capturing group, in order.
+///
+/// Typed captures become the numbers they parse, and presets the regexes
+/// closest to them; what only Rust code can check, like guards, `#var`s and
+/// pipelines with functions in them, is turned down at compile time. Like
+/// any regex, the result matches text a character at a time, so `_` and
+/// bare names, which match a byte, become `(?s:.)`, which matches `\n` too.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_regex_str;
+/// const REQUEST_RE: &str =
+///     strmatch_regex_str!("GET " [path] " HTTP/1." '0'..='1');
+/// assert_eq!(REQUEST_RE, r"^GET ((?s:.*)) HTTP/1\.[01]$");
+///
+/// assert_eq!(
+///     strmatch_regex_str!("id=" [id: u32] #ws? [note until ';'] ';'),
+///     r"^id=(\+?[0-9]+)[\t\n\f\r ]*([^;]*);$"
+/// );
+/// assert_eq!(strmatch_regex_str!("a" | "b" hex+), "^(?:a|b[0-9A-Fa-f]+)$");
+/// ```
+///
+/// A run like `digit+` or `#ws` takes all the bytes it can and never gives
+/// any back, where a regex's `+` backtracks, so `digit+ '3'` never matches
+/// `123` but `[0-9]+3` does. A run that what follows could take bytes from
+/// is turned down too, unless that's a capture like `[rest]`, which makes up
+/// for whatever the run takes:
+///
+/// ```compile_fail
+/// # use strmatch::strmatch_regex_str;
+/// const NOT_THE_SAME: &str = strmatch_regex_str!(digit+ '3');
+/// ```
+///
+/// ```rust
+/// # use strmatch::strmatch_regex_str;
+/// assert_eq!(strmatch_regex_str!(digit+ 'x' [rest]), "^[0-9]+x((?s:.*))$");
+/// assert_eq!(strmatch_regex_str!(digit+ [rest]), "^[0-9]+((?s:.*))$");
+/// ```
+///
+/// The same goes for a group repeated with `*`, which repeats as many times
+/// as it can:
+///
+/// ```compile_fail
+/// # use strmatch::strmatch_regex_str;
+/// const NOT_THE_SAME: &str = strmatch_regex_str!(("ab")* "ab");
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_regex_str(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_regex_str", &tokens) {
+        return splice;
+    }
+    let regex_str_input = parse_macro_input!(tokens as RegexStrInput);
+    regex_str_input.into_token_stream().into()
+}
+
+/// `strcsv!` splits a CSV row into its fields and matches each one, in
+/// order, evaluating to `Some` of the captures if every field matched.
+/// Quoted fields can contain commas, and doubled quotes inside them are
+/// unescaped. Each field is matched by one of:
+///
+/// - `_`, which matches any field.
+/// - `[name]`, which binds the whole field as a `Cow<[u8]>` (borrowed
+///   unless the field had quotes to unescape).
+/// - `[name: Type]`, which only matches if the field is UTF-8 that parses
+///   into `Type`, and binds the parsed value.
+/// - `(pattern)`, which only matches if the field matches the pattern.
+///   These can't bind names, since the field may be a temporary buffer.
+///
+/// The row has to have exactly as many fields as listed, unless the list
+/// ends with `..`. As with `strextract!`, a single capture is given back as
+/// is, and several in a tuple.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strcsv;
+/// let row = br#"42,"Doe, ""JD"" John",admin,2023"#;
+///
+/// let (id, name) = strcsv!(row, [id: u32], [name], ("admin"), ..).unwrap();
+/// assert_eq!(id, 42);
+/// assert_eq!(&name[..], br#"Doe, "JD" John"#);
+///
+/// assert_eq!(strcsv!(row, _, _, ("user"), ..), None);
+/// assert_eq!(strcsv!(row, _, _, _), None);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strcsv(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strcsv", &tokens) {
+        return splice;
+    }
+    let csv_input = parse_macro_input!(tokens as CsvInput);
+    csv_input.into_token_stream().into()
+}
+
+/// `strmatch_fields!` splits its input on a delimiter and matches each field
+/// in order, evaluating to `Some` of the captures if every field matched.
+/// The delimiter can be any literal, and comes after the input, separated
+/// from the fields by a `;`. Each field is matched by one of:
+///
+/// - `_`, which matches any field.
+/// - `[name]` or `[name: Type]`, which binds the field, as bytes or parsed
+///   into `Type`.
+/// - `(pattern)`, which has to match the whole field, and can bind names
+///   like in `strextract!`.
+///
+/// The input has to have exactly as many fields as listed, unless the list
+/// ends with `..`. A single capture is given back as is, and several in a
+/// tuple. Unlike `strcsv!`, fields can't be quoted, so they're always
+/// borrowed from the input.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_fields;
+/// let line = b"root:x:0:0:root:/root:/bin/bash";
+///
+/// let (uid, home) =
+///     strmatch_fields!(line, ':'; ("root"), _, [uid: u32], _, _, [home], ..)
+///         .unwrap();
+/// assert_eq!((uid, home), (0, &b"/root"[..]));
+///
+/// let pair = strmatch_fields!(b"host = example.com", " = "; [key], (_ [rest]));
+/// assert_eq!(pair, Some((&b"host"[..], &b"xample.com"[..])));
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_fields(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_fields", &tokens) {
+        return splice;
+    }
+    let fields_input = parse_macro_input!(tokens as FieldsInput);
+    fields_input.into_token_stream().into()
+}
+
+/// `strmatch_chunks!` splits its input into records as wide as a pattern, and
+/// evaluates to an iterator over the captures of each record that matches.
+/// Every segment of the pattern has to match a fixed number of bytes, which
+/// makes it a fit for files of fixed-size binary records.
+///
+/// Records that don't match are skipped, as are leftover bytes at the end
+/// that don't make up a whole record. Start with `#[strict]` to yield
+/// `Result`s instead, where a record that doesn't match (or a partial one at
+/// the end) is an `Err` holding the offset it starts at. Like the other
+/// matcher macros, it can also start with a `#[codegen(..)]` attribute.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_chunks;
+/// let data = b"A1B2xxC3";
+/// let ids = strmatch_chunks!(data, id @ 'A'..='Z' _).collect::<Vec<_>>();
+/// assert_eq!(ids, [&b'A', &b'B', &b'C']);
+///
+/// let records = strmatch_chunks!(#[strict] b"A1B2x", id @ 'A'..='Z' n @ '0'..='9')
+///     .collect::<Vec<_>>();
+/// assert_eq!(records, [Ok((&b'A', &b'1')), Ok((&b'B', &b'2')), Err(4)]);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatch_chunks(
+    tokens: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatch_chunks", &tokens) {
+        return splice;
+    }
+    let chunks_input = parse_macro_input!(tokens as ChunksInput);
+    chunks_input.into_token_stream().into()
+}
+
+/// `path_match!` matches a path by its components, evaluating to `Some` of
+/// what it binds if it matched and `None` otherwise. The input is anything
+/// `Path::new` takes a reference to, like `&Path`, `&PathBuf` or `&str`, and
+/// the components are separated by `/`, each one of:
+///
+/// - `"name"`, a component spelled exactly so, compared as an `OsStr`.
+/// - `_`, which matches any component.
+/// - `[name]` or `[name: Type]`, which binds the component, as an `&OsStr`
+///   or parsed into `Type` (a component that isn't UTF-8 doesn't parse).
+/// - `(pattern)`, which has to match the whole component's bytes, and can
+///   bind names like in `strextract!`.
+///
+/// A leading `/` matches the root, after any Windows prefix like `C:`, and a
+/// path that doesn't start at the root may start with a `.` that's skipped.
+/// The path has to have exactly as many components as listed, unless the
+/// list ends with `..`, or `rest @ ..` to bind the rest as a `&Path`. Since
+/// the path is split with `Path::components`, repeated separators and `\`
+/// on Windows are taken care of.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::path_match;
+/// use std::path::Path;
+///
+/// let path = Path::new("/usr/share/doc/strmatch/README.md");
+/// let (pkg, file) = path_match!(path, / "usr" / "share" / _ / [pkg] / rest @ ..)
+///     .unwrap();
+/// assert_eq!((pkg.to_str(), file), (Some("strmatch"), Path::new("README.md")));
+///
+/// assert_eq!(path_match!("./src/lib.rs", "src" / ([stem] ".rs")), Some(&b"lib"[..]));
+/// assert_eq!(path_match!("logs/2024/app.log", "logs" / [year: u16] / _), Some(2024));
+/// assert_eq!(path_match!("/usr/bin", "usr" / ..), None);
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn path_match(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("path_match", &tokens) {
+        return splice;
+    }
+    let path_input = parse_macro_input!(tokens as PathInput);
+    path_input.into_token_stream().into()
+}
+
+/// `strmatcher!` compiles a pattern into a `strmatch_runtime::Matcher`, a
+/// value that can be stored in a `static` and passed around. It takes the
+/// same patterns as `strextract!`, as long as they only bind bytes and byte
+/// slices. Use it through the `strmatch-runtime` crate, which re-exports it;
+/// it's behind this crate's `runtime` feature.
+#[cfg(feature = "runtime")]
+#[proc_macro]
+#[proc_macro_error]
+pub fn strmatcher(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strmatcher", &tokens) {
+        return splice;
+    }
+    let matcher_input = parse_macro_input!(tokens as MatcherInput);
+    matcher_input.into_token_stream().into()
+}
+
+/// `bitmatch!` matches the bits of an unsigned integer, evaluating to `Some`
+/// of the fields it binds if it matched and `None` otherwise. The bits are
+/// written from the most significant one down, as a mix of:
+///
+/// - binary literals like `0b110`, which have to match exactly (each digit
+///   is a bit, so leading zeros count), and a lone `0` or `1` for one bit,
+/// - `_`, which matches any single bit,
+/// - `name`, which binds a single bit, and `name: width`, which binds the
+///   next `width` bits, shifted down.
+///
+/// The bits have to add up to 8, 16, 32, 64 or 128, which picks the type of
+/// the input and of the fields. As with `strextract!`, a single field is
+/// given back as is, and several in a tuple.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::bitmatch;
+/// // The first byte of a two byte UTF-8 sequence
+/// assert_eq!(bitmatch!(0xc3, 0b110 high: 5), Some(0b00011));
+/// assert_eq!(bitmatch!(b'a', 0b110 high: 5), None);
+///
+/// // The first byte of an IPv4 header
+/// let (version, ihl) = bitmatch!(0x45, version: 4 ihl: 4).unwrap();
+/// assert_eq!((version, ihl), (4, 5));
+///
+/// // A TCP flags field, checking for a SYN without an ACK
+/// let flags: u16 = 0x5002;
+/// assert!(bitmatch!(flags, offset: 4 _ _ _ _ _ _ _ 0 _ _ 1 _).is_some());
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn bitmatch(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let bit_input = parse_macro_input!(tokens as BitInput);
+    bit_input.into_token_stream().into()
+}
+
+/// `strsniff!` checks whether the start of a `std::io::Read` matches a
+/// pattern, reading only as many bytes as the pattern needs into a buffer on
+/// the stack, which makes it handy for spotting file formats by their magic
+/// bytes. It evaluates to an `io::Result<bool>`.
+///
+/// The reader can be passed by value or as `&mut reader`; either way the
+/// bytes read are consumed. A pattern ending in `[_]` (or `..`) only looks at
+/// the start of the stream, and any other has to match all of it. Since the
+/// buffer is gone once the check is done, patterns can't bind anything, and
+/// only their last capture can match a varying number of bytes.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strsniff;
+/// let mut file: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR...";
+/// assert!(strsniff!(&mut file, b'\x89' "PNG" ..).unwrap());
+///
+/// let gif: &[u8] = b"GIF89a...";
+/// assert!(strsniff!(gif, "GIF8" '7'..='9' 'a' ..).unwrap());
+/// assert!(!strsniff!(b"GIF".as_slice(), "GIF8" _ 'a' ..).unwrap());
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn strsniff(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    if let Some(splice) = sub::splice("strsniff", &tokens) {
+        return splice;
+    }
+    let sniff_input = parse_macro_input!(tokens as SniffInput);
+    sniff_input.into_token_stream().into()
+}
+
+/// `#[strmatch_from_str]` implements `FromStr` for a struct from a pattern
+/// whose captures are named after the struct's fields. The pattern is either
+/// written like in `strmatch_match!`, or as a format string, where `{field}`
+/// (or `{field: Type}`) captures a field and `{{`/`}}` stand for braces.
+///
+/// A bracketed capture without a type of its own is parsed into the type of
+/// its field, and any other capture is assigned to its field as it is. Every
+/// field has to be captured exactly once. Parsing fails with a generated
+/// `Parse{Struct}Error`, which has the struct's visibility.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_from_str;
+/// #[strmatch_from_str("{x},{y}")]
+/// #[derive(Debug, PartialEq)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// assert_eq!("3,-4".parse(), Ok(Point { x: 3, y: -4 }));
+/// assert_eq!("3;-4".parse::<Point>(), Err(ParsePointError));
+///
+/// #[strmatch_from_str("v" [major] '.' [minor] ".x")]
+/// struct Version {
+///     major: u16,
+///     minor: u16,
+/// }
+///
+/// let version: Version = "v1.75.x".parse().unwrap();
+/// assert_eq!((version.major, version.minor), (1, 75));
+/// ```
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn strmatch_from_str(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as syn::ItemStruct);
+    let attr = attribute_pattern(attr);
+    let pattern = parse_macro_input!(attr as Pattern);
+    let from_str = from_str::from_str(pattern, &item);
+    quote!(#item #from_str).into()
+}
+
+/// `#[strmatch_display]` implements `Display` for a struct from the same
+/// kind of pattern or format string as `#[strmatch_from_str]`, writing the
+/// pattern's literals as they are and each captured field with its own
+/// `Display` impl, so that parsing and printing can't drift apart.
+///
+/// Fields are captured with bracketed captures like `[field]` or
+/// `field @ #utf8char`. Segments that don't say what to write, like `_` or a
+/// range, can't be used, and literals have to be UTF-8.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_display;
+/// #[strmatch_display("{x},{y}")]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// assert_eq!(Point { x: 3, y: -4 }.to_string(), "3,-4");
+/// ```
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn strmatch_display(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as syn::ItemStruct);
+    let attr = attribute_pattern(attr);
+    let pattern = parse_macro_input!(attr as Pattern);
+    let display = display::display(pattern, &item);
+    quote!(#item #display).into()
+}
+
+/// `#[strmatch_deserialize]` implements serde's `Deserialize` for a struct
+/// from the same kind of pattern or format string as `#[strmatch_from_str]`,
+/// for config formats that pack a small grammar into a string field. The
+/// string (or bytes) the struct is deserialized from has to match the
+/// pattern, and anything else is an `invalid_value` error.
+///
+/// A newtype like `struct Port(u16)` can be deserialized too, from a pattern
+/// with one capture of any name. The crate using it needs `serde` as a
+/// dependency.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_deserialize;
+/// # use serde::Deserialize;
+/// #[strmatch_deserialize("{w}x{h}")]
+/// #[derive(Debug, PartialEq)]
+/// struct Resolution {
+///     w: u32,
+///     h: u32,
+/// }
+///
+/// #[strmatch_deserialize(":" [port])]
+/// struct Listen(u16);
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     resolution: Resolution,
+///     listen: Listen,
+/// }
+///
+/// let config: Config = serde_json::from_str(
+///     r#"{ "resolution": "1920x1080", "listen": ":8080" }"#,
+/// ).unwrap();
+/// assert_eq!(config.resolution, Resolution { w: 1920, h: 1080 });
+/// assert_eq!(config.listen.0, 8080);
+/// assert!(serde_json::from_str::<Resolution>(r#""1920 by 1080""#).is_err());
+/// ```
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn strmatch_deserialize(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as syn::ItemStruct);
+    let attr = attribute_pattern(attr);
+    let pattern = parse_macro_input!(attr as Pattern);
+    let deserialize = deserialize::deserialize(pattern, &item);
+    quote!(#item #deserialize).into()
+}
+
+/// `#[strmatch_try_from]` implements `TryFrom<&[u8]>` and `TryFrom<&str>`
+/// for a struct from the same kind of pattern or format string as
+/// `#[strmatch_from_str]`. When the input doesn't match, the generated
+/// `TryFrom{Struct}Error` says which segment of the pattern it failed at and
+/// how many bytes in, by trying longer and longer prefixes of the pattern.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_try_from;
+/// #[strmatch_try_from("{w}x{h}")]
+/// #[derive(Debug, PartialEq)]
+/// struct Size {
+///     w: u32,
+///     h: u32,
+/// }
+///
+/// assert_eq!(Size::try_from(&b"640x480"[..]), Ok(Size { w: 640, h: 480 }));
+/// let error = Size::try_from("640*480").unwrap_err();
+/// assert_eq!((error.segment(), error.offset()), (Some("\"x\""), 3));
+/// assert_eq!(error.to_string(), "expected \"x\" at byte 3");
+/// ```
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn strmatch_try_from(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as syn::ItemStruct);
+    let attr = attribute_pattern(attr);
+    let tokens = attr.clone().into();
+    // Parsed here first so a bad pattern is reported like anywhere else
+    let _ = parse_macro_input!(attr as Pattern);
+    let try_from = try_from::try_from(tokens, &item);
+    quote!(#item #try_from).into()
+}
+
+/// `#[strmatch_record]` is `#[strmatch_from_str]` and `#[strmatch_display]`
+/// in one, so a struct's textual format is written down once. Given
+/// `examples = [..]` after the pattern, it also generates a test, named
+/// after the struct (`point_round_trips` for `Point`), checking that each
+/// example parses, and that printing what it parsed into gives a string
+/// that parses and prints the same way again.
+///
+/// # Usage:
+///
+/// ```rust
+/// # use strmatch::strmatch_record;
+/// #[strmatch_record("{key}={value}", examples = ["port=8080", "retries=3"])]
+/// struct Setting {
+///     key: String,
+///     value: u32,
+/// }
+///
+/// let setting: Setting = "port=8080".parse().unwrap();
+/// assert_eq!(setting.to_string(), "port=8080");
+/// ```
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn strmatch_record(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as syn::ItemStruct);
+    let args = parse_macro_input!(attr as record::RecordArgs);
+    let pattern = attribute_pattern(args.pattern.clone().into());
+    // Each impl takes its own copy of the pattern
+    let from_str_pattern = pattern.clone();
+    let from_str = parse_macro_input!(from_str_pattern as Pattern);
+    let from_str = from_str::from_str(from_str, &item);
+    let display = parse_macro_input!(pattern as Pattern);
+    let display = display::display(display, &item);
+    let round_trip = record::round_trip(&args, &item);
+    quote!(#item #from_str #display #round_trip).into()
+}
+
+// The attributes take a pattern either as is or as a format string
+fn attribute_pattern(attr: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match syn::parse::<syn::LitStr>(attr.clone()) {
+        Ok(format) => from_str::format_pattern(&format).into(),
+        Err(_) => attr,
     }
 }
@@ -1,5 +1,7 @@
 use strmatch::strmatch;
 
+// Not every binding in the examples below gets used
+#[allow(unused_variables)]
 fn main() {
     // Convert to bytes so we can use slice pattern matching
     let test = "hello hello strmatch!".as_bytes();
@@ -51,7 +53,19 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use strmatch::strmatch;
+    use std::borrow::Cow;
+
+    use strmatch::{
+        assert_strmatches, bitmatch, bstrmatch, path_match, strbuild, strbytes,
+        strcsv, strextract, strextract_owned, strglob, strkeywords, strlexer,
+        strmatch, strmatch_arbitrary, strmatch_array, strmatch_chunks,
+        strmatch_clap, strmatch_const_fn, strmatch_contains, strmatch_count,
+        strmatch_dbg, strmatch_deserialize, strmatch_display, strmatch_fields,
+        strmatch_find, strmatch_from_str, strmatch_let, strmatch_match,
+        strmatch_nom, strmatch_proptest, strmatch_record, strmatch_regex,
+        strmatch_regex_str, strmatch_replace, strmatch_split_once,
+        strmatch_try_from, strmatch_winnow, strmulti, strpattern, strsniff,
+    };
 
     #[test]
     fn syntax() {
@@ -71,4 +85,2645 @@ mod tests {
         assert!(matches!("hello".as_bytes(), strmatch!("hello")));
         assert!(matches!("hello".as_bytes(), strmatch!(b"hello")));
     }
+
+    #[test]
+    fn star_repetition() {
+        let input = "abab--!".as_bytes();
+        assert!(matches!(input, strmatch!("ab" * 2 '-' * 2 b'!' * 1)));
+        assert!(matches!(input, strmatch!(b"ab" * 2[_])));
+        assert!(!matches!(input, strmatch!("ab" * 3[_])));
+    }
+
+    #[test]
+    fn runtime_values() {
+        fn parse(input: &[u8], sep: u8) -> Option<&[u8]> {
+            strmatch_match!(input {
+                ("key" #sep [value]) => Some(value),
+                _ => None,
+            })
+        }
+        assert_eq!(parse(b"key=value", b'='), Some(&b"value"[..]));
+        assert_eq!(parse(b"key:value", b':'), Some(&b"value"[..]));
+        assert_eq!(parse(b"key:value", b'='), None);
+    }
+
+    #[test]
+    fn match_arms() {
+        let classify = |input: &str| {
+            strmatch_match!(input.as_bytes() {
+                ("") => 0,
+                (_) | ("ab") => 1,
+                ("x" [rest]) if rest.len() > 2 => 2,
+                (first [_]) if *first == b'x' => 3,
+                _ => 4,
+            })
+        };
+        assert_eq!(classify(""), 0);
+        assert_eq!(classify("a"), 1);
+        assert_eq!(classify("ab"), 1);
+        assert_eq!(classify("xyzw"), 2);
+        assert_eq!(classify("xy"), 3);
+        assert_eq!(classify("hello"), 4);
+    }
+
+    #[test]
+    fn char_literals_are_utf8() {
+        assert!(matches!(&[0xc3, 0xa9][..], strmatch!('é')));
+        assert!(!matches!(&[0xe9][..], strmatch!('é')));
+        assert!(matches!(&[0xe9][..], strmatch!(b'\xe9')));
+        assert!(matches!("€".as_bytes(), strmatch!('€')));
+    }
+
+    #[test]
+    fn utf8_chars() {
+        fn first(input: &[u8]) -> Option<char> {
+            strmatch_match!(input {
+                (c @ #utf8char [_]) => Some(c),
+                _ => None,
+            })
+        }
+        assert_eq!(first("a".as_bytes()), Some('a'));
+        assert_eq!(first("été".as_bytes()), Some('é'));
+        assert_eq!(first("🦀!".as_bytes()), Some('🦀'));
+        assert_eq!(first(b"\xff"), None);
+        assert_eq!(first(b"\xc3"), None);
+        // Overlong encoding of '/'
+        assert_eq!(first(b"\xc0\xaf"), None);
+
+        let quoted = strmatch_match!("«ü»".as_bytes() {
+            ('«' #utf8char '»') => true,
+            _ => false,
+        });
+        assert!(quoted);
+        assert!(matches!("«".as_bytes(), strmatch!('«')));
+
+        // `#utf8char` wins over a runtime value of the same name,
+        // which is still there as `#(utf8char)`
+        let utf8char = b'!';
+        let keyword = strmatch_match!("é".as_bytes() {
+            (#utf8char) => true,
+            _ => false,
+        });
+        assert!(keyword);
+        let value = strmatch_match!(b"!" {
+            (#(utf8char)) => true,
+            _ => false,
+        });
+        assert!(value);
+    }
+
+    #[test]
+    fn presets() {
+        fn address(input: &[u8]) -> Option<(&[u8], &[u8])> {
+            strmatch_match!(input {
+                (ip @ #ipv4 ':' port @ #u16) => Some((ip, port)),
+                _ => None,
+            })
+        }
+        assert_eq!(
+            address(b"10.0.255.1:65535"),
+            Some((&b"10.0.255.1"[..], &b"65535"[..]))
+        );
+        assert_eq!(address(b"10.0.256.1:80"), None);
+        assert_eq!(address(b"10.0.1:80"), None);
+        assert_eq!(address(b"10.0.0.1:65536"), None);
+
+        let ident = strmatch_match!(b"let _x1 = 0xfF;" {
+            ("let " name @ #ident " = 0x" #hexbyte ';') => Some(name),
+            _ => None,
+        });
+        assert_eq!(ident, Some(&b"_x1"[..]));
+        assert!(matches!(b"0a".as_slice(), strmatch!(#hexbyte)));
+        assert!(!matches!(b"0g".as_slice(), strmatch!(#hexbyte)));
+    }
+
+    #[test]
+    fn lazy_and_greedy() {
+        let input = br#"say "hi" and "bye""#;
+        let lazy = strmatch_match!(input {
+            ("say " '"' [q lazy] '"' [_]) => Some(q),
+            _ => None,
+        });
+        assert_eq!(lazy, Some(&b"hi"[..]));
+        let greedy = strmatch_match!(input {
+            ("say " '"' [q] '"' [_]) => Some(q),
+            _ => None,
+        });
+        assert_eq!(greedy, Some(&br#"hi" and "bye"#[..]));
+
+        let pair = strmatch_match!(b"a=b=c" {
+            ([key lazy] '=' [value]) => Some((key, value)),
+            _ => None,
+        });
+        assert_eq!(pair, Some((&b"a"[..], &b"b=c"[..])));
+        let pair = strmatch_match!(b"a=b=c" {
+            ([key greedy] '=' [value]) => Some((key, value)),
+            _ => None,
+        });
+        assert_eq!(pair, Some((&b"a=b"[..], &b"c"[..])));
+    }
+
+    #[test]
+    fn until() {
+        let line = b"2023-01-01 WARN disk,87%,/dev/sda";
+        let fields = strextract!(
+            line,
+            [date until ' '] ' ' [level until ' '] ' '
+            [name until ','] ',' [usage until ','] ',' [device]
+        );
+        assert_eq!(
+            fields,
+            Some((
+                &b"2023-01-01"[..],
+                &b"WARN"[..],
+                &b"disk"[..],
+                &b"87%"[..],
+                &b"/dev/sda"[..]
+            ))
+        );
+        // Unlike a greedy capture, `until` stops at the first delimiter
+        assert_eq!(
+            strextract!(b"a::b::c", [a until "::"] "::" [_]),
+            Some(&b"a"[..])
+        );
+        assert_eq!(strextract!(b"abc", [_ until ','] [_]), None);
+        assert_eq!(strextract!(b"", ""), Some(()));
+        assert_eq!(strextract!(b"x", 'x'), Some(()));
+    }
+
+    #[test]
+    fn csv() {
+        type Row<'a> = (Cow<'a, [u8]>, f64, Cow<'a, [u8]>);
+        fn row(input: &[u8]) -> Option<Row<'_>> {
+            strcsv!(input, [name], [score: f64], [_: u32], ("ok" [_]), [note])
+        }
+        let (name, score, note) =
+            row(br#"ada,99.5,3,okay,"says ""hi"", twice""#).unwrap();
+        assert!(matches!(name, Cow::Borrowed(b"ada")));
+        assert_eq!(score, 99.5);
+        assert!(matches!(note, Cow::Owned(_)));
+        assert_eq!(&note[..], br#"says "hi", twice"#);
+
+        assert!(row(b"ada,99.5,3,okay,").is_some());
+        assert!(row(b"ada,99.5,-3,okay,").is_none());
+        assert!(row(b"ada,99.5,3,nope,").is_none());
+        assert!(row(b"ada,99.5,3,okay").is_none());
+        assert!(row(b"ada,99.5,3,okay,,").is_none());
+        assert!(row(br#"ada,99.5,3,okay,"unterminated"#).is_none());
+        assert!(row(br#"ada,99.5,3,okay,"junk"after"#).is_none());
+
+        assert_eq!(strcsv!(b"", [only]), Some(Cow::Borrowed(&b""[..])));
+        assert_eq!(strcsv!(b"a,b,c", _, ..), Some(()));
+    }
+
+    #[test]
+    fn key_value() {
+        fn setting(line: &[u8]) -> Option<(&[u8], &[u8])> {
+            strmatch_match!(line {
+                (#kv(key, value)) => Some((key, value)),
+                _ => None,
+            })
+        }
+        assert_eq!(setting(b"name=x"), Some((&b"name"[..], &b"x"[..])));
+        assert_eq!(
+            setting(b"  log level =\tdebug info  "),
+            Some((&b"log level"[..], &b"debug info"[..]))
+        );
+        assert_eq!(setting(b"empty ="), Some((&b"empty"[..], &b""[..])));
+        assert_eq!(setting(b"a = b = c"), Some((&b"a"[..], &b"b = c"[..])));
+        assert_eq!(setting(b" = value"), None);
+        assert_eq!(setting(b"no equals sign"), None);
+        assert_eq!(setting(b"a = b\nc = d"), None);
+
+        let config = b"host = example.com\nport = 8080\n";
+        let port = strextract!(config, [_ lazy] #kv("port", p) '\n');
+        assert_eq!(port, Some(&b"8080"[..]));
+        let line = strextract!(config, all @ #kv("host", _) [_]);
+        assert_eq!(line, Some(&b"host = example.com"[..]));
+    }
+
+    #[test]
+    fn http_request() {
+        type RequestLine<'a> = (&'a [u8], &'a [u8], u8);
+        fn request(line: &[u8]) -> Option<RequestLine<'_>> {
+            strextract!(line, #http_request(method, path, version) "\r\n")
+        }
+        assert_eq!(
+            request(b"GET /index.html?q=1 HTTP/1.1\r\n"),
+            Some((&b"GET"[..], &b"/index.html?q=1"[..], 1))
+        );
+        assert_eq!(
+            request(b"DELETE * HTTP/1.0\r\n"),
+            Some((&b"DELETE"[..], &b"*"[..], 0))
+        );
+        assert_eq!(request(b"get / HTTP/1.1\r\n"), None);
+        assert_eq!(request(b"GET  / HTTP/1.1\r\n"), None);
+        assert_eq!(request(b"GET / HTTP/2\r\n"), None);
+        assert_eq!(request(b"GET / HTTP/1.1"), None);
+
+        let route = |line: &[u8]| {
+            strmatch_match!(line {
+                (#http_request("GET", path, 1)) => path.len(),
+                _ => 0,
+            })
+        };
+        assert_eq!(route(b"GET /abc HTTP/1.1"), 4);
+        assert_eq!(route(b"POST /abc HTTP/1.1"), 0);
+        assert_eq!(route(b"GET /abc HTTP/1.0"), 0);
+    }
+
+    #[test]
+    fn semver() {
+        type Version<'a> = (u64, u64, u64, Option<&'a [u8]>, Option<&'a [u8]>);
+        fn version(input: &[u8]) -> Option<Version<'_>> {
+            strextract!(input, #semver(major, minor, patch, pre, build))
+        }
+        assert_eq!(version(b"0.1.10"), Some((0, 1, 10, None, None)));
+        assert_eq!(
+            version(b"1.0.0-alpha.1+build.5-x"),
+            Some((1, 0, 0, Some(&b"alpha.1"[..]), Some(&b"build.5-x"[..])))
+        );
+        assert_eq!(
+            version(b"18446744073709551615.0.0+b"),
+            Some((u64::MAX, 0, 0, None, Some(&b"b"[..])))
+        );
+        assert_eq!(version(b"18446744073709551616.0.0"), None);
+        assert_eq!(version(b"01.0.0"), None);
+        assert_eq!(version(b"1.0"), None);
+        assert_eq!(version(b"1.0.0-"), None);
+
+        // Stops before a `-` or `.` that isn't followed by an identifier
+        let rest = strextract!(b"v2.3.4-.x", 'v' #semver(2, _, _, _, _) [r]);
+        assert_eq!(rest, Some(&b"-.x"[..]));
+        let rest = strextract!(b"2.3.4-rc.", #semver(_, 3, _, pre, _) [r]);
+        assert_eq!(rest, Some((Some(&b"rc"[..]), &b"."[..])));
+    }
+
+    #[test]
+    fn uuid_and_mac() {
+        let id = strextract!(
+            b"id=67E55044-10b1-426f-9247-bb680e5fe0c8",
+            "id=" text @ #uuid(bytes)
+        );
+        assert_eq!(
+            id,
+            Some((
+                &b"67E55044-10b1-426f-9247-bb680e5fe0c8"[..],
+                [
+                    0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47,
+                    0xbb, 0x68, 0x0e, 0x5f, 0xe0, 0xc8
+                ]
+            ))
+        );
+        fn uuid(input: &[u8]) -> bool {
+            strextract!(input, #uuid).is_some()
+        }
+        assert!(uuid(b"67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!uuid(b"67e55044-10b1-426f-9247bb680e5fe0c8-"));
+        assert!(!uuid(b"67e55044-10b1-426f-9247-bb680e5fe0c"));
+
+        fn mac(input: &[u8]) -> Option<[u8; 6]> {
+            strextract!(input, #mac(bytes))
+        }
+        assert_eq!(
+            mac(b"ff-ee-dd-cc-bb-aa"),
+            Some([255, 238, 221, 204, 187, 170])
+        );
+        assert_eq!(mac(b"01:02:03:04:05:06"), Some([1, 2, 3, 4, 5, 6]));
+        assert_eq!(mac(b"01:02:03-04:05:06"), None);
+        assert_eq!(mac(b"01:02:03:04:05"), None);
+        assert_eq!(mac(b"01:02:03:04:05:0g"), None);
+    }
+
+    #[test]
+    fn binary() {
+        fn format(header: &[u8]) -> &str {
+            match header {
+                bstrmatch!(b'\x89' b"PNG" b"\r\n\x1a\n" [_]) => "png",
+                bstrmatch!(b"GIF8" version b'a' [_])
+                    if b"79".contains(version) =>
+                {
+                    "gif"
+                }
+                bstrmatch!(b"\xff\xd8"[_]) => "jpeg",
+                bstrmatch!() => "empty",
+                _ => "unknown",
+            }
+        }
+        assert_eq!(format(b"\x89PNG\r\n\x1a\n\0\0"), "png");
+        assert_eq!(format(b"GIF89a..."), "gif");
+        assert_eq!(format(b"GIF88a..."), "unknown");
+        assert_eq!(format(b"\xff\xd8\xff"), "jpeg");
+        assert_eq!(format(b""), "empty");
+    }
+
+    #[test]
+    fn open_tail() {
+        assert!(matches!(b"one two".as_slice(), strmatch!("one"..)));
+        assert!(matches!(b"one".as_slice(), strmatch!("one"..)));
+        assert!(!matches!(b"on".as_slice(), strmatch!("one"..)));
+        assert!(matches!(b"\x89PNG".as_slice(), bstrmatch!(b'\x89'..)));
+
+        // In the middle of a pattern it's a greedy `[_]`
+        let last = strextract!(b"a/b/c", ..'/'[last]);
+        assert_eq!(last, Some(&b"c"[..]));
+    }
+
+    #[test]
+    fn named_tail() {
+        match b"one two".as_slice() {
+            strmatch!("one" _ rest @ ..) => assert_eq!(rest, b"two"),
+            _ => panic!(),
+        }
+        let (name, rest) =
+            strextract!(b"k=v;", name @ .. '=' rest @ ..).unwrap();
+        assert_eq!((name, rest), (&b"k"[..], &b"v;"[..]));
+    }
+
+    #[test]
+    fn ranges() {
+        fn drive(path: &[u8]) -> Option<(u8, u8)> {
+            match path {
+                strmatch!(letter @ b'A'..=b'Z' sep @ ':' ..) => {
+                    Some((*letter, *sep))
+                }
+                _ => None,
+            }
+        }
+        assert_eq!(drive(b"C:\\Windows"), Some((b'C', b':')));
+        assert_eq!(drive(b"c:\\Windows"), None);
+        assert_eq!(drive(b"/usr"), None);
+
+        assert!(matches!(b"7".as_slice(), strmatch!('0'..='9')));
+        assert!(!matches!(b"a".as_slice(), strmatch!('0'..='9')));
+        let digits = strextract!(b"v12", 'v' a @ '0'..='9' b @ '0'..='9');
+        assert_eq!(digits, Some((&b'1', &b'2')));
+    }
+
+    #[test]
+    fn inline_guards() {
+        fn pin(input: &[u8]) -> Option<&[u8]> {
+            strmatch_match!(input {
+                ("pin " [pin if pin.len() == 4] end if *end == b'\n') => Some(pin),
+                _ => None,
+            })
+        }
+        assert_eq!(pin(b"pin 1234\n"), Some(&b"1234"[..]));
+        assert_eq!(pin(b"pin 12345\n"), None);
+        assert_eq!(pin(b"pin 1234;"), None);
+
+        // A failed guard moves on to the next possibility, like bad bytes
+        let digits =
+            strextract!(b"ab12", [_ lazy] d if d.is_ascii_digit() [rest]);
+        assert_eq!(digits, Some((&b'1', &b"2"[..])));
+        let sum = strextract!(
+            b"7+8",
+            a @ '0'..='9' if *a > b'5' '+' b if (*b - b'0') + (*a - b'0') > 9
+        );
+        assert_eq!(sum, Some((&b'7', &b'8')));
+        let word =
+            strextract!(b"hello world", [w lazy if w.ends_with(b"o")] ' ' [_]);
+        assert_eq!(word, Some(&b"hello"[..]));
+
+        let row = strcsv!(b"alice,42", [name if !name.is_empty()], [age: u8 if age >= 18]);
+        assert_eq!(row, Some((Cow::Borrowed(&b"alice"[..]), 42)));
+        assert_eq!(strcsv!(b",42", [_name if !_name.is_empty()], _), None);
+    }
+
+    #[test]
+    fn whole_match() {
+        match b"GET /".as_slice() {
+            strmatch!(line @ ("GET " path @ ..)) => {
+                assert_eq!((line, path), (&b"GET /"[..], &b"/"[..]))
+            }
+            _ => panic!(),
+        }
+        let logged = strmatch_match!(b"id=7;" {
+            (all @ ("id=" [id: u32] ';')) => Some((all, id)),
+            _ => None,
+        });
+        assert_eq!(logged, Some((&b"id=7;"[..], 7)));
+        let ints = strextract!(b"1,2", all @ ([a] ',' [b]));
+        assert_eq!(ints, Some((&b"1,2"[..], &b"1"[..], &b"2"[..])));
+    }
+
+    #[test]
+    // Shadowed arms are reported through the `deprecated` lint
+    #[allow(deprecated)]
+    fn shadowed_arms() {
+        let arm = |input: &[u8]| {
+            strmatch_match!(input {
+                ("abc" [_]) => 1,
+                ("abcd" [_]) => 2,
+                ('a'..='z' "bx") => 3,
+                (b'q' "bx") => 4,
+                _ => 0,
+            })
+        };
+        assert_eq!(arm(b"abcd"), 1);
+        assert_eq!(arm(b"qbx"), 3);
+    }
+
+    #[test]
+    // Without `#[allow(deprecated)]`, so a wrong report fails the build under
+    // `-D warnings`
+    fn min_len_arms_are_not_shadowing() {
+        let arm = |input: &[u8]| {
+            strmatch_match!(input {
+                (min_len(10); "a" ..) => 1,
+                ("ab" ..) => 2,
+                _ => 3,
+            })
+        };
+        assert_eq!(arm(b"ab"), 2);
+        assert_eq!(arm(b"abcdefghij"), 1);
+    }
+
+    #[test]
+    fn dbg() {
+        let rest = match b"one twotwo three".as_slice() {
+            strmatch_dbg!("one" _ "two"x2 [rest]) => rest,
+            _ => panic!(),
+        };
+        assert_eq!(rest, b" three");
+        assert!(matches!(b"".as_slice(), strmatch_dbg!()));
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(bitmatch!(0b1100_0011u8, 0b110 rest: 5), Some(0b00011));
+        assert_eq!(bitmatch!(0b1110_0011u8, 0b110 rest: 5), None);
+        assert_eq!(bitmatch!(0xa5u8, hi: 4 lo: 4), Some((0xa, 0x5)));
+        assert_eq!(bitmatch!(0x80u8, 1 _ _ _ _ _ _ low), Some(0));
+        assert_eq!(bitmatch!(0x01u8, 0 _ _ _ _ _ _ _), Some(()));
+
+        // Fields are shifted down, and the width picks the type
+        let word: u32 = 0xdead_beef;
+        let rest: Option<u32> = bitmatch!(word, 0b1101_1110 rest: 24);
+        assert_eq!(rest, Some(0xad_beef));
+    }
+
+    #[test]
+    fn sniff() {
+        // Hands out a byte at a time, to check that reads are retried
+        struct Trickle<'a>(&'a [u8]);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let mut png = Trickle(b"\x89PNG\r\n\x1a\n rest of the file");
+        assert!(strsniff!(&mut png, b'\x89' "PNG" "\r\n" ..).unwrap());
+        // Only the bytes the pattern needed were read
+        assert_eq!(png.0, b"\x1a\n rest of the file");
+
+        let sep = b':';
+        assert!(strsniff!(Trickle(b"ab:"), "ab" #sep).unwrap());
+        assert!(!strsniff!(Trickle(b"ab:c"), "ab" #sep).unwrap());
+        assert!(!strsniff!(Trickle(b"a"), "ab"..).unwrap());
+    }
+
+    #[test]
+    fn const_fn() {
+        strmatch_const_fn!(fn is_get = "GET " ..);
+        strmatch_const_fn!(fn digit = d @ '0'..='9');
+        strmatch_const_fn!(fn header = line @ (name @ 'A'..='Z' ':' _ value @ ..));
+
+        const GETS: [bool; 2] = [is_get(b"GET /"), is_get(b"PUT /")];
+        assert_eq!(GETS, [true, false]);
+        static LINE: Option<(&[u8], &u8, &[u8])> = header(b"X: 1");
+        assert_eq!(LINE, Some((&b"X: 1"[..], &b'X', &b"1"[..])));
+        assert_eq!(header(b"x: 1"), None);
+        assert_eq!(digit(b"7"), Some(&b'7'));
+    }
+
+    #[test]
+    fn from_str() {
+        #[strmatch_from_str("{{{x}, {y}}}")]
+        #[derive(Debug, PartialEq)]
+        struct Point {
+            x: f64,
+            y: f64,
+        }
+        assert_eq!("{1.5, -2}".parse(), Ok(Point { x: 1.5, y: -2.0 }));
+        assert_eq!("(1.5, -2)".parse::<Point>(), Err(ParsePointError));
+        assert_eq!(
+            ParsePointError.to_string(),
+            "string doesn't match the format of `Point`"
+        );
+
+        #[strmatch_from_str(sigil @ #utf8char [name: String] ' ' [rest])]
+        struct Tagged {
+            sigil: char,
+            name: String,
+            rest: u32,
+        }
+        let tagged: Tagged = "§foo 42".parse().unwrap();
+        assert_eq!(
+            (tagged.sigil, tagged.name.as_str(), tagged.rest),
+            ('§', "foo", 42)
+        );
+        assert!("§foo bar".parse::<Tagged>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        #[strmatch_from_str("{{{name}: {score}}}")]
+        #[strmatch_display("{{{name}: {score}}}")]
+        #[derive(Debug, PartialEq)]
+        struct Entry {
+            name: String,
+            score: u32,
+        }
+        let entry = Entry {
+            name: "ann".into(),
+            score: 7,
+        };
+        assert_eq!(entry.to_string(), "{ann: 7}");
+        assert_eq!(entry.to_string().parse(), Ok(entry));
+
+        #[strmatch_display(initial @ #utf8char ". " [last])]
+        struct Name {
+            initial: char,
+            last: &'static str,
+        }
+        assert_eq!(
+            Name {
+                initial: 'É',
+                last: "Zola"
+            }
+            .to_string(),
+            "É. Zola"
+        );
+    }
+
+    // Also generates the `rgb_round_trips` test
+    #[strmatch_record("rgb({r}, {g}, {b})", examples = ["rgb(0, 128, 255)", "rgb(007, 0, 0)"])]
+    #[derive(Debug, PartialEq)]
+    struct Rgb {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+
+    #[test]
+    fn record() {
+        let teal: Rgb = "rgb(0, 128, 128)".parse().unwrap();
+        assert_eq!(
+            teal,
+            Rgb {
+                r: 0,
+                g: 128,
+                b: 128
+            }
+        );
+        assert_eq!(teal.to_string(), "rgb(0, 128, 128)");
+        assert_eq!("rgb(0, 128, 256)".parse::<Rgb>(), Err(ParseRgbError));
+    }
+
+    #[test]
+    fn if_chain() {
+        let sep = b'=';
+        let inputs = [
+            &b"k=v"[..],
+            b"k=",
+            b"k:v",
+            b"k=ab!c",
+            b"x",
+            b"",
+            b"ab3f!",
+            b"ab3g!",
+        ];
+        for input in inputs {
+            assert_eq!(
+                strextract!(#[codegen(if_chain)] input, key @ 'a'..='z' #sep [value]),
+                strextract!(input, key @ 'a'..='z' #sep [value]),
+            );
+            assert_eq!(
+                strextract!(
+                    #[codegen(if_chain)]
+                    input,
+                    [rest]
+                ),
+                strextract!(input, [rest]),
+            );
+            assert_eq!(
+                strextract!(#[codegen(if_chain)] input, "ab" #hexbyte c if *c == b'!'),
+                strextract!(input, "ab" #hexbyte c if *c == b'!'),
+            );
+            fn arms(input: &[u8]) -> Option<&[u8]> {
+                strmatch_match!(#[codegen(if_chain)] input {
+                    ("k" _ [v lazy] '!' ..) => Some(v),
+                    (_) | () => None,
+                    _ => Some(b"?"),
+                })
+            }
+            fn shared(input: &[u8]) -> Option<&[u8]> {
+                strmatch_match!(#[codegen(shared_helpers)] input {
+                    ("k" _ [v lazy] '!' ..) => Some(v),
+                    ("ab" _ "ab" ..) | ("abc" ..) => Some(b"ab"),
+                    (_) | () => None,
+                    _ => Some(b"?"),
+                })
+            }
+            fn shared_slices(input: &[u8]) -> Option<&[u8]> {
+                strmatch_match!(input {
+                    ("k" _ [v lazy] '!' ..) => Some(v),
+                    ("ab" _ "ab" ..) | ("abc" ..) => Some(b"ab"),
+                    (_) | () => None,
+                    _ => Some(b"?"),
+                })
+            }
+            fn slices(input: &[u8]) -> Option<&[u8]> {
+                strmatch_match!(input {
+                    ("k" _ [v lazy] '!' ..) => Some(v),
+                    (_) | () => None,
+                    _ => Some(b"?"),
+                })
+            }
+            assert_eq!(arms(input), slices(input));
+            assert_eq!(shared(input), shared_slices(input));
+            fn consts(input: &[u8]) -> Option<&[u8]> {
+                strmatch_match!(#[codegen(const_array)] input {
+                    ("k" _ [v lazy] '!' ..) => Some(v),
+                    ("ab" _ "ab" ..) | ("abc" ..) => Some(b"ab"),
+                    (_) | () => None,
+                    _ => Some(b"?"),
+                })
+            }
+            assert_eq!(consts(input), shared_slices(input));
+            assert_eq!(
+                strextract!(#[codegen(shared_helpers)] input, "ab" #hexbyte c if *c == b'!'),
+                strextract!(input, "ab" #hexbyte c if *c == b'!'),
+            );
+        }
+    }
+
+    #[test]
+    fn groups() {
+        let list =
+            strextract!(b"[1,22,333]", '[' ([n: u32] ',')* [last: u32] ']');
+        assert_eq!(list, Some((vec![1, 22], 333)));
+
+        let count = strextract!(b"ababa", n @ ("ab")* [rest]);
+        assert_eq!(count, Some((2, &b"a"[..])));
+        assert_eq!(strextract!(b"", n @ ("ab")*), Some(0));
+
+        // Groups are possessive, so they don't give back repetitions
+        assert_eq!(strextract!(b"abab", ("ab") * "ab"), None);
+
+        let pairs = strmatch_match!(b"a=1;b=2;" {
+            ((k @ 'a'..='z' '=' v ';')*) => Some((k, v)),
+            _ => None,
+        });
+        assert_eq!(pairs, Some((vec![&b'a', &b'b'], vec![&b'1', &b'2'])));
+
+        let nested = strextract!(b"ab.ab.c", (("ab") * '.') * [rest]);
+        assert_eq!(nested, Some(&b"c"[..]));
+    }
+
+    #[test]
+    fn fields() {
+        let line = b"GET /index.html 200";
+        let (path, code) =
+            strmatch_fields!(line, ' '; ("GET"), [path], [code: u16]).unwrap();
+        assert_eq!((path, code), (&b"/index.html"[..], 200));
+
+        // Every field has to be there, unless the list ends with `..`
+        assert_eq!(strmatch_fields!(line, ' '; _, [path]), None);
+        assert_eq!(
+            strmatch_fields!(line, ' '; _, [path], ..),
+            Some(&b"/index.html"[..])
+        );
+        assert_eq!(strmatch_fields!(b"a", ' '; _, _), None);
+
+        // Delimiters can be longer than a byte, and empty fields still count
+        let fields = strmatch_fields!(b"a::b::::c", "::"; [a], (b), (), [c]);
+        assert_eq!(fields, Some((&b"a"[..], &b'b', &b"c"[..])));
+        assert_eq!(strmatch_fields!(b"", ','; [all]), Some(&b""[..]));
+        assert_eq!(strmatch_fields!(b"x", ','; ('y')), None);
+    }
+
+    #[test]
+    fn whitespace() {
+        let words = strextract!(b"mov  eax,\t 1", [op lazy] #ws [dst lazy] ',' #ws? [src]);
+        assert_eq!(words, Some((&b"mov"[..], &b"eax"[..], &b"1"[..])));
+
+        // `#ws` needs at least one byte of whitespace, `#ws?` doesn't
+        assert_eq!(strextract!(b"ab", 'a' #ws 'b'), None);
+        assert_eq!(strextract!(b"ab", 'a' #ws? 'b'), Some(()));
+        assert_eq!(strextract!(b"a \r\n", 'a' gap @ #ws), Some(&b" \r\n"[..]));
+
+        // The whole run is taken, so a later space has nothing left to match
+        assert_eq!(strextract!(b"a  b", 'a' #ws ' ' 'b'), None);
+
+        let matched = strmatch_match!(b"  if x" {
+            (#ws? "if" #ws [cond]) => Some(cond),
+            _ => None,
+        });
+        assert_eq!(matched, Some(&b"x"[..]));
+    }
+
+    #[test]
+    fn runs() {
+        let version =
+            strextract!(b"1.22.333", a @ digit+ '.' b @ digit+ '.' c @ digit+);
+        assert_eq!(version, Some((&b"1"[..], &b"22"[..], &b"333"[..])));
+
+        // A run needs at least one byte, and takes all of them
+        assert_eq!(strextract!(b"x1", 'x' digit+), Some(()));
+        assert_eq!(strextract!(b"x", 'x' digit+), None);
+        assert_eq!(strextract!(b"123", digit + '3'), None);
+
+        let classes = strextract!(b"Ab9f!?", upper+ lower+ n @ alnum+ punct+);
+        assert_eq!(classes, Some(&b"9f"[..]));
+        assert_eq!(strextract!(b"ff00", h @ hex+), Some(&b"ff00"[..]));
+        assert_eq!(
+            strextract!(b"abz", h @ 'a'..='b'+ [rest]),
+            Some((&b"ab"[..], &b"z"[..]))
+        );
+
+        let word = strmatch_match!(b"hello world" {
+            (w @ alpha+ ' ' [_]) => Some(w),
+            _ => None,
+        });
+        assert_eq!(word, Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn optional_runs() {
+        fn ident(input: &[u8]) -> Option<&[u8]> {
+            strextract!(input, alpha + digit * [rest])
+        }
+        assert_eq!(ident(b"abc123;"), Some(&b";"[..]));
+        assert_eq!(ident(b"abc;"), Some(&b";"[..]));
+        assert_eq!(ident(b"1abc"), None);
+
+        let sign = strextract!(b"42", s @ '-'..='-'* n @ digit+);
+        assert_eq!(sign, Some((&b""[..], &b"42"[..])));
+        assert_eq!(strextract!(b"", n @ lower*), Some(&b""[..]));
+    }
+
+    #[test]
+    fn lookahead() {
+        // The peeked bytes are still there for the capture after it
+        let path = strextract!(b"/usr/bin", peek('/')[path]);
+        assert_eq!(path, Some(&b"/usr/bin"[..]));
+        assert_eq!(strextract!(b"usr/bin", peek('/')[path]), None);
+        assert_eq!(strextract!(b"ab", 'a' peek("bc") [rest]), None);
+        assert_eq!(
+            strextract!(b"abc", 'a' peek('b' "c") [rest]),
+            Some(&b"bc"[..])
+        );
+
+        let num = strmatch_match!(b"12px" {
+            (n @ digit+ peek("px") [_]) => Some(n),
+            _ => None,
+        });
+        assert_eq!(num, Some(&b"12"[..]));
+
+        // `peek` is still a name when it's followed by a repeated group
+        assert_eq!(strextract!(b"xabab", peek ("ab")*), Some(&b'x'));
+    }
+
+    #[test]
+    fn negative_lookahead() {
+        fn scheme(url: &[u8]) -> Option<&str> {
+            strmatch_match!(url {
+                ("http" not_ahead('s') ':' [_]) => Some("http"),
+                ("https:" [_]) => Some("https"),
+                _ => None,
+            })
+        }
+        assert_eq!(scheme(b"http://a"), Some("http"));
+        assert_eq!(scheme(b"https://a"), Some("https"));
+
+        let line = |line: &[u8]| {
+            strextract!(line, "//" not_ahead('/') not_ahead('!') [_]).is_some()
+        };
+        assert!(line(b"// comment"));
+        assert!(!line(b"/// doc"));
+        assert!(!line(b"//! doc"));
+        // Running out of input is fine
+        assert!(line(b"//"));
+    }
+
+    #[test]
+    fn chunks() {
+        let data =
+            &[0x01, 0x10, 0xff, 0x02, 0x20, 0xff, 0x03, 0x30, 0x00, 0x04];
+        let records =
+            strmatch_chunks!(data, kind value b'\xff').collect::<Vec<_>>();
+        assert_eq!(records, [(&0x01, &0x10), (&0x02, &0x20)]);
+
+        let strict = strmatch_chunks!(#[strict] data, kind value b'\xff')
+            .collect::<Vec<_>>();
+        assert_eq!(
+            strict,
+            [Ok((&0x01, &0x10)), Ok((&0x02, &0x20)), Err(6), Err(9)]
+        );
+
+        let words =
+            strmatch_chunks!(#[codegen(if_chain)] b"ab:cd:", w @ (_ _ ':'));
+        assert_eq!(words.collect::<Vec<_>>(), [&b"ab:"[..], &b"cd:"[..]]);
+        assert_eq!(strmatch_chunks!(b"", _).count(), 0);
+    }
+
+    #[test]
+    fn arrays() {
+        let parse = |header: [u8; 6]| match header {
+            strmatch_array!("GIF8" version 'a') => Some(version),
+            strmatch_array!(b'\x89' "PNG" _ _) => None,
+            _ => None,
+        };
+        assert_eq!(parse(*b"GIF89a"), Some(b'9'));
+        assert_eq!(parse(*b"GIF87b"), None);
+
+        let by_ref = &[1u8, 2, 3];
+        assert!(
+            matches!(by_ref, strmatch_array!(b'\x01' a b) if (*a, *b) == (2, 3))
+        );
+        assert!(
+            matches!(by_ref, strmatch_array!(all @ (_ _ _)) if all == &[1, 2, 3])
+        );
+        assert!(matches!([0u8; 0], strmatch_array!()));
+    }
+
+    #[test]
+    fn bytes() {
+        const HELLO: [u8; 7] = strbytes!("HELO" ' ' b"x" '\n');
+        assert_eq!(&HELLO, b"HELO x\n");
+        assert_eq!(strbytes!("ab"x2 '-' * 3), *b"abab---");
+        assert_eq!(strbytes!('é'), [0xc3, 0xa9]);
+        assert_eq!(strbytes!(), []);
+
+        // What gets sent matches the same spelling
+        assert!(matches!(&HELLO[..], strmatch!("HELO" ' ' [_])));
+    }
+
+    #[test]
+    fn assertions() {
+        assert_strmatches!(b"key=value", [key lazy] '=' [value]);
+        assert_strmatches!(b"v1.2", 'v' digit+ '.' digit+);
+
+        fn message(run: fn()) -> String {
+            let panic = std::panic::catch_unwind(run).unwrap_err();
+            panic.downcast_ref::<String>().unwrap().clone()
+        }
+        let diverged =
+            message(|| assert_strmatches!(b"GET /", "GET" ' ' 'a'..='z' [_]));
+        assert!(
+            diverged.contains("doesn't match `\"GET\" ' ' 'a' ..= 'z' [_]`")
+        );
+        assert!(diverged.contains("first difference at byte 4"));
+        assert!(diverged.contains("00000000  47 45 54 20 2f"));
+        assert!(diverged.contains("|GET /|"));
+
+        // Input the pattern doesn't have room for is a difference too
+        let too_long = message(|| assert_strmatches!(b"ab\n", "ab"));
+        assert!(too_long.contains("first difference at byte 2"));
+        assert!(too_long.contains("|ab.|"));
+
+        let variable = message(|| assert_strmatches!(b"ab1", "ab" alpha+));
+        assert!(variable.contains("the first 2 bytes match"));
+    }
+
+    #[test]
+    fn keywords() {
+        fn route(line: &[u8]) -> &str {
+            strmulti!(line {
+                "db" => "database",
+                "dbus" | "systemd" => "system",
+                b"\xffkernel" => "kernel",
+                _ => "other",
+            })
+        }
+        // The first keyword in the input wins, and the longest one where
+        // several start at the same place
+        assert_eq!(route(b"[dbus] reloaded"), "system");
+        assert_eq!(route(b"[db] dbus"), "database");
+        assert_eq!(route(b"pid 1: systemd[1]: db"), "system");
+        assert_eq!(route(b"\xffkernel: oops"), "kernel");
+        assert_eq!(route(b"d"), "other");
+        assert_eq!(route(b""), "other");
+
+        let found = strmulti!(b"let x = 1;" {
+            "let" => {
+                let keyword = "let";
+                Some(keyword)
+            }
+            "fn" => Some("fn"),
+            _ => None,
+        });
+        assert_eq!(found, Some("let"));
+    }
+
+    #[test]
+    fn raw_literals() {
+        // Raw literals stand for exactly the bytes between the quotes
+        let path = br"C:\dir\x";
+        assert!(matches!(&path[..], strmatch!(r"C:\" "dir" r"\x")));
+        assert!(matches!(&path[..], bstrmatch!(br"C:\dir" b'\\' b'x')));
+        assert!(matches!(&br#"a"b"#[..], bstrmatch!(br#"a"b"#)));
+
+        // Repetitions work the same as on other literals
+        assert!(matches!(&b"\\\\"[..], strmatch!(r"\"x2)));
+        assert!(matches!(&b"\\\\"[..], strmatch!(r"\" * 2)));
+        assert!(matches!(&b"#!#!"[..], strmatch!(r#"#!"#x2)));
+
+        assert_eq!(strextract!(br"\n", r"\"[n]), Some(&b"n"[..]));
+        assert_eq!(strbytes!(r"\" br"\"), *b"\\\\");
+    }
+
+    #[test]
+    fn integer_bytes() {
+        // Integers stand for single bytes, in any base
+        let frame = b"\x7f\x0a\x0aE\x01rest";
+        assert!(matches!(&frame[..], strmatch!(0x7F 10x2 b'E' 0b1 ..)));
+        assert!(matches!(&frame[..], bstrmatch!(0x7f 0x0a * 2 0x45u8 [_])));
+        assert_eq!(
+            strextract!(&frame[..], _ 0x0a..=0x0d+ kind @ 0x41..=0x5a 1 [_]),
+            Some(&b'E')
+        );
+        assert_eq!(strbytes!(0xde 0xad "!" 0o7), [0xde, 0xad, b'!', 7]);
+    }
+
+    #[test]
+    fn byte_arrays() {
+        const PNG: [u8; 4] = [0x89, b'P', b'N', b'G'];
+        let file = b"\x89PNG\r\n";
+        assert!(matches!(&file[..], strmatch!([0x89, b'P', b'N', b'G']..)));
+        assert!(matches!(
+            &file[..],
+            bstrmatch!([0x89, b'P', b'N', b'G',][_])
+        ));
+        assert_eq!(strbytes!([0x89, b'P', b'N', b'G']), PNG);
+
+        // Arrays can be repeated and bound, just like byte strings
+        assert_eq!(strbytes!([0, 1] * 2), [0, 1, 0, 1]);
+        assert_eq!(
+            strextract!(&file[..], [0x89, b'P'] [rest] [b'\r', 10]),
+            Some(&b"NG"[..])
+        );
+    }
+
+    #[test]
+    fn included_bytes() {
+        let png = include_bytes!("testdata/png_header.bin");
+        assert!(matches!(
+            &png[..],
+            strmatch!(include_bytes!("testdata/png_header.bin"))
+        ));
+
+        // Only part of the file, like indexing the array it would give
+        let signature = b"\x89PNG\r\n\x1a\n";
+        assert!(matches!(
+            &png[..],
+            bstrmatch!(include_bytes!("testdata/png_header.bin")[..8][_])
+        ));
+        assert_eq!(
+            strbytes!(include_bytes!("testdata/png_header.bin")[..8]),
+            *signature
+        );
+        assert_eq!(
+            strextract!(&png[..], [_ lazy] include_bytes!("testdata/png_header.bin")[12..]),
+            Some(())
+        );
+    }
+
+    #[test]
+    fn env_segments() {
+        let hello = concat!(
+            "HELLO ",
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        );
+        assert!(matches!(
+            hello.as_bytes(),
+            strmatch!("HELLO " env!("CARGO_PKG_NAME") '/' env!("CARGO_PKG_VERSION"))
+        ));
+        assert_eq!(
+            strextract!(hello.as_bytes(), "HELLO " env!("CARGO_PKG_NAME") '/' [version]),
+            Some(env!("CARGO_PKG_VERSION").as_bytes())
+        );
+
+        // Unset variables fall back to the default
+        assert_eq!(
+            strbytes!(
+                option_env!("STRMATCH_UNSET_VARIABLE").unwrap_or("dev") * 2
+            ),
+            *b"devdev"
+        );
+    }
+
+    #[test]
+    fn globs() {
+        fn glob(name: &str) -> [bool; 4] {
+            let name = name.as_bytes();
+            [
+                strglob!(name, "*.rs"),
+                strglob!(name, "src/*/mod.rs"),
+                strglob!(name, "?[a-z]_[!0-9]*"),
+                strglob!(name, r"\*[-]*"),
+            ]
+        }
+        assert_eq!(glob("lib.rs"), [true, false, false, false]);
+        assert_eq!(glob("src/engine/mod.rs"), [true, true, false, false]);
+        assert_eq!(glob("éa_b"), [false, false, true, false]);
+        assert_eq!(glob("xa_1"), [false, false, false, false]);
+        assert_eq!(glob("*-x.rs"), [true, false, false, true]);
+        assert_eq!(glob(""), [false, false, false, false]);
+        assert!(strglob!(b"", "*"));
+        assert!(strglob!(b"a]", "[]a][]]"));
+    }
+
+    mod patterns {
+        use strmatch::strpattern;
+
+        strpattern!(HEX_BYTE = "0x" hex+);
+        strpattern!(CRLF = '\r' '\n');
+    }
+
+    #[test]
+    fn sub_patterns() {
+        strpattern!(DATE = "20" _ _ '-' _ _ '-' _ _);
+        strpattern!(STAMP = @sub(DATE) ' ');
+
+        assert!(matches!(&b"2024-01-02 x"[..], strmatch!(@sub(DATE) ' ' _)));
+        assert!(!matches!(&b"1999-01-02 x"[..], strmatch!(@sub(DATE) ..)));
+        let byte = strmatch_match!(&b"2024-01-02 0x1f"[..] {
+            (@sub(STAMP) byte @ ..) if byte.len() > 8 => Some(byte),
+            (@sub(STAMP) [byte] @sub(patterns::CRLF)) => Some(byte),
+            (@sub(STAMP) @sub(patterns::HEX_BYTE)) => Some(&b"hex"[..]),
+            _ => None,
+        });
+        assert_eq!(byte, Some(&b"hex"[..]));
+        assert_eq!(
+            strextract!(b"2024-01-02 0x1f\r\n", @sub(STAMP) [byte] @sub(patterns::CRLF)),
+            Some(&b"0x1f"[..])
+        );
+        assert_eq!(strextract!(b"0xff", @sub(patterns::HEX_BYTE)), Some(()));
+        assert_eq!(strbytes!("ok" @sub(patterns::CRLF)), *b"ok\r\n");
+    }
+
+    #[test]
+    fn let_else() {
+        fn header(line: &str) -> Option<(&[u8], u16)> {
+            strmatch_let!([name lazy] #ws? ':' #ws? [value: u16] = line.as_bytes() else {
+                return None;
+            });
+            Some((name, value))
+        }
+        assert_eq!(
+            header("Content-Length: 42"),
+            Some((&b"Content-Length"[..], 42))
+        );
+        assert_eq!(header("Content-Length: lots"), None);
+
+        let mut lines = vec![];
+        for line in [&b"a=1"[..], b"b<=2", b"c=3"] {
+            strmatch_let!(key @ 'a'..='z' '=' d if d.is_ascii_digit() = line else {
+                continue;
+            });
+            lines.push((*key, *d));
+        }
+        assert_eq!(lines, [(b'a', b'1'), (b'c', b'3')]);
+
+        strmatch_let!("ok" = b"ok" else { unreachable!() });
+    }
+
+    #[test]
+    fn min_len() {
+        let short = &b"HDR\x01"[..];
+        let long = &b"HDR\x01 and a payload"[..];
+        assert!(matches!(long, strmatch!(min_len(18); "HDR" _ ..)));
+        assert!(!matches!(short, strmatch!(min_len(18); "HDR" _ ..)));
+        assert!(matches!(short, strmatch!(min_len(4); "HDR" _ ..)));
+
+        assert_eq!(
+            strextract!(long, min_len(18); "HDR" version [rest]),
+            Some((&1, &b" and a payload"[..]))
+        );
+        assert_eq!(strextract!(short, min_len(20); "HDR" version [rest]), None);
+        assert_eq!(
+            strextract!(long, min_len(19); whole @ ("HDR" [_ lazy] ' ' [_])),
+            None
+        );
+
+        // Without the `;`, it's just a name
+        assert_eq!(strextract!(b"x", min_len), Some(&b'x'));
+        assert!(
+            strsniff!(&b"HDR\x01 and a payload"[..], min_len(18); "HDR" ..)
+                .unwrap()
+        );
+        assert!(!strsniff!(&b"HDR\x01 and"[..], min_len(18); "HDR" ..).unwrap());
+    }
+
+    #[test]
+    fn float_captures() {
+        assert_eq!(
+            strextract!(b"temp=-3.5e2C", "temp=" [t: f64] 'C'),
+            Some(-350.0)
+        );
+        assert_eq!(strextract!(b"x=.5", "x=" [x: f32]), Some(0.5));
+        assert_eq!(strextract!(b"x=+7", "x=" [x: f64]), Some(7.0));
+        assert_eq!(strextract!(b"x=inf", "x=" [x: f64]), None);
+        assert_eq!(strextract!(b"x=NaN", "x=" [x: f64]), None);
+        assert_eq!(strextract!(b"x=.", "x=" [x: f64]), None);
+
+        // A `.` without digits after it is part of the number, as in Rust,
+        // but an exponent without digits isn't
+        assert_eq!(strextract!(b"1.", [x: f64]), Some(1.0));
+        assert_eq!(strextract!(b"5.x", [x: f32] 'x'), Some(5.0));
+        assert_eq!(strextract!(b"5.", [x: f64] '.'), None);
+        assert_eq!(
+            strextract!(b"2em", [x: f64] [unit]),
+            Some((2.0, &b"em"[..]))
+        );
+        assert_eq!(
+            strextract!(b"1.5,2e-3", [a: f64] ',' [b: f64]),
+            Some((1.5, 0.002))
+        );
+    }
+
+    #[test]
+    fn signed_captures() {
+        assert_eq!(
+            strextract!(b"dx=-12,dy=+7", "dx=" [x: i32] ",dy=" [y: i32]),
+            Some((-12, 7))
+        );
+        assert_eq!(strextract!(b"-12-3", [a: i64] [b: i64]), Some((-12, -3)));
+        assert_eq!(strextract!(b"n=-", "n=" [n: i32]), None);
+        assert_eq!(strextract!(b"n=12a", "n=" [n: i32]), None);
+
+        assert_eq!(strextract!(b"99999999999", [n: i32]), None);
+        assert_eq!(
+            strextract!(b"99999999999", [n: i32 saturating]),
+            Some(i32::MAX)
+        );
+        assert_eq!(
+            strextract!(b"-99999999999", [n: i32 saturating]),
+            Some(i32::MIN)
+        );
+        assert_eq!(
+            strextract!(b"t=-1e9;", "t=" [t: i16 saturating until ';'] ';'),
+            None
+        );
+        assert_eq!(
+            strcsv!(b"id,-70000", [_], [n: i16 saturating]),
+            Some(i16::MIN)
+        );
+    }
+
+    #[test]
+    fn alternation() {
+        fn truthy(input: &[u8]) -> bool {
+            matches!(input, strmatch!("yes"[_] | "y"[_] | "true"))
+        }
+        assert!(truthy(b"yes"));
+        assert!(truthy(b"y!"));
+        assert!(truthy(b"true"));
+        assert!(!truthy(b"truer"));
+        assert!(!truthy(b"no"));
+
+        let value = match &b"port=80"[..] {
+            strmatch!("port="[v] | "p="[v]) => v,
+            _ => &[],
+        };
+        assert_eq!(value, b"80");
+
+        assert!(matches!(&b"\x89P"[..], bstrmatch!(b"GIF" .. | 0x89 _)));
+    }
+
+    #[test]
+    // Without `#[allow(deprecated)]`, so a capture that warns fails the build
+    // under `-D warnings`
+    #[allow(non_snake_case)]
+    fn const_like_captures_can_opt_out() {
+        assert_eq!(strextract!(b"a=b", 'a' r#SEP 'b'), Some(&b'='));
+        assert_eq!(strextract!(b"a=b", 'a' _SEP 'b'), Some(&b'='));
+        assert_eq!(strextract!(b"a=b", 'a' sep 'b'), Some(&b'='));
+        assert_eq!(strextract!(b"a=b", 'a' Sep 'b'), Some(&b'='));
+        assert_eq!(strextract!(b"a=b", 'a' S 'b'), Some(&b'='));
+    }
+
+    #[test]
+    // Each capture that looks like a const warns on its own
+    #[allow(deprecated, non_snake_case)]
+    fn several_const_like_captures() {
+        let pair = strmatch_match!(&b"xy!"[..] {
+            (AB CD [_]) => Some((*AB, *CD)),
+            _ => None,
+        });
+        assert_eq!(pair, Some((b'x', b'y')));
+        let sep = strmatch_match!(&b"b;"[..] {
+            ('a' SEP) | ('b' SEP) => Some(*SEP),
+            _ => None,
+        });
+        assert_eq!(sep, Some(b';'));
+    }
+
+    #[test]
+    fn const_segments() {
+        const COLON: u8 = b':';
+        mod bytes {
+            pub const EQ: u8 = b'=';
+        }
+
+        assert!(matches!(&b"a:b"[..], strmatch!('a' {COLON} ..)));
+        assert!(!matches!(&b"a;b"[..], strmatch!('a' {COLON} ..)));
+        assert!(matches!(&b"k=v"[..], strmatch!(_ {bytes::EQ} _)));
+
+        assert_eq!(
+            strextract!(b"key:value", [k until ':'] {COLON} [v]),
+            Some((&b"key"[..], &b"value"[..]))
+        );
+        assert_eq!(strextract!(b"key=value", [k until '='] {COLON} [v]), None);
+        assert_eq!(
+            strmatch_match!(&b"x=1"[..] {
+                (name {bytes::EQ} value) => Some((*name, *value)),
+                _ => None,
+            }),
+            Some((b'x', b'1'))
+        );
+        assert_eq!(
+            strmatch_match!(#[codegen(if_chain)] &b"x:1"[..] {
+                (_ {COLON} value) => Some(*value),
+                _ => None,
+            }),
+            Some(b'1')
+        );
+    }
+
+    #[test]
+    fn len_reps() {
+        assert_eq!(&strbytes!('-' x len("Section Title")), b"-------------");
+        assert_eq!(&strbytes!("ab" * len(b"xyz")), b"ababab");
+        assert_eq!(&strbytes!('-' x len('é')), b"--");
+
+        let heading = &b"Section Title\n-------------"[..];
+        assert!(matches!(
+            heading,
+            strmatch!("Section Title" '\n' '-' x len("Section Title"))
+        ));
+        assert!(!matches!(
+            heading,
+            strmatch!("Section Title" '\n' '-' * len("Section"))
+        ));
+
+        // Without `len(..)` after it, `x` is still a capture
+        assert_eq!(strextract!(b"-y", '-' x), Some(&b'y'));
+    }
+
+    #[test]
+    fn pad() {
+        const WIDTH: usize = 4;
+        assert_eq!(&strbytes!("id" pad(' ', 3) '|'), b"id   |");
+        assert_eq!(&strbytes!(pad('.', len("abc"))), b"...");
+        assert!(matches!(
+            &b"ok    done"[..],
+            strmatch!("ok" pad(' ', 4) "done")
+        ));
+
+        let row = &b"ab    |42"[..];
+        assert_eq!(
+            strextract!(row, "ab" pad(' ', WIDTH) '|' [n: u32]),
+            Some(42)
+        );
+        assert_eq!(
+            strextract!(row, "ab" pad(' ', WIDTH + 1) '|' [n: u32]),
+            None
+        );
+        assert_eq!(strextract!(&b"ab  "[..], "ab" pad(' ', WIDTH)), None);
+        assert_eq!(
+            strextract!(&b"ab"[..], "ab" pad(b' ', WIDTH - 4)),
+            Some(())
+        );
+    }
+
+    #[test]
+    fn nocase() {
+        let header = |line: &[u8]| strextract!(line, nocase("Content-Length") ": " [n: usize]);
+        assert_eq!(header(b"Content-Length: 12"), Some(12));
+        assert_eq!(header(b"content-length: 12"), Some(12));
+        assert_eq!(header(b"CONTENT-LENGTH: 12"), Some(12));
+        assert_eq!(header(b"Content_Length: 12"), None);
+
+        // Only the `nocase` segment ignores case
+        assert!(matches!(&b"get /"[..], strmatch!(nocase("GET") " /")));
+        assert!(!matches!(&b"GET /"[..], strmatch!(nocase("get") " /" 'X')));
+        assert!(!matches!(&b"Get X"[..], strmatch!("GET" nocase(" x"))));
+        assert!(matches!(&b"\x89pNg"[..], bstrmatch!(0x89 nocase(b"PNG"))));
+
+        assert_eq!(
+            strmatch_match!(#[codegen(if_chain)] &b"hOsT:a"[..] {
+                (nocase("host:") host) => Some(*host),
+                _ => None,
+            }),
+            Some(b'a')
+        );
+    }
+
+    #[test]
+    fn trim() {
+        assert_eq!(
+            strextract!(b"  name  : Ada \t", [k trim until ':'] ':' [v trim]),
+            Some((&b"name"[..], &b"Ada"[..]))
+        );
+        assert_eq!(strextract!(b"x= 42 ", "x=" [n: u32 trim]), Some(42));
+        assert_eq!(strextract!(b"x= 4 2 ", "x=" [n: u32 trim]), None);
+        assert_eq!(strextract!(b"   ", [v trim]), Some(&b""[..]));
+        let (n, c) =
+            strcsv!(b"a, 7 ,\" c \"\"\"", [_], [n: u8 trim], [c trim]).unwrap();
+        assert_eq!((n, &c[..]), (7, &b"c \""[..]));
+        assert_eq!(
+            strmatch_fields!(b" root :x", ':'; [user trim], _),
+            Some(&b"root"[..])
+        );
+    }
+
+    #[test]
+    fn pipelines() {
+        fn username(name: &str) -> Result<&str, &'static str> {
+            match name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                true => Ok(name),
+                false => Err("not alphanumeric"),
+            }
+        }
+        fn even(n: u32) -> Option<u32> {
+            n.is_multiple_of(2).then_some(n)
+        }
+
+        assert_eq!(
+            strextract!(b"user= ada ", "user="[name | trim | utf8 | username]),
+            Some("ada")
+        );
+        assert_eq!(
+            strextract!(b"user=a-b", "user="[name | utf8 | username]),
+            None
+        );
+        assert_eq!(strextract!(b"user=\xff", "user="[name | utf8]), None);
+        assert_eq!(
+            strextract!(b"4,7", [a | parse::<u32> | even until ','] ',' [b | parse::<u32>]),
+            Some((4, 7))
+        );
+        assert_eq!(
+            strextract!(b"3,7", [a | parse::<u32> | even until ','] ',' [_]),
+            None
+        );
+
+        // A step that fails lets a greedy capture try a shorter match
+        assert_eq!(
+            strextract!(b"12ab", [n | parse::<u32>][rest]),
+            Some((12, &b"ab"[..]))
+        );
+        assert_eq!(
+            strcsv!(b"x, 8", [_], [n | trim | parse::<u32> | even]),
+            Some(8)
+        );
+    }
+
+    #[test]
+    fn owned_extraction() {
+        fn read(buf: &mut Vec<u8>, line: &[u8]) {
+            buf.clear();
+            buf.extend_from_slice(line);
+        }
+        let mut buf = vec![];
+        read(&mut buf, b"GET /index.html 200");
+        let request = strextract_owned!(&buf[..], [method until ' '] ' ' [path | utf8 until ' '] ' ' [status: u16]);
+        read(&mut buf, b"something else");
+        assert_eq!(
+            request,
+            Some((b"GET".to_vec(), String::from("/index.html"), 200))
+        );
+
+        let first = strextract_owned!(&b"xy"[..], c _);
+        assert_eq!(first, Some(b'x'));
+
+        let list = strextract_owned!(&b"[a,bc,]"[..], '[' n @ ([item until ','] ',')* ']');
+        assert_eq!(list, Some((2, vec![b"a".to_vec(), b"bc".to_vec()])));
+    }
+
+    #[test]
+    fn borrowed_lifetimes() {
+        // Captures have to outlive the expansion, borrowing from `line`
+        fn path(line: &str) -> Option<&[u8]> {
+            strextract!(line.trim().as_bytes(), "GET "[path])
+        }
+        fn pair(buf: &[u8]) -> Option<(&[u8], &u8)> {
+            strextract!(#[codegen(if_chain)] buf.strip_prefix(b">").unwrap_or(buf), [k until '='] '=' v ..)
+        }
+        fn rest(line: &str) -> Option<&[u8]> {
+            strmatch_match!(line.trim_end().as_bytes() {
+                ("x" [rest]) => Some(rest),
+                _ => None,
+            })
+        }
+        fn field(line: &str) -> Option<&[u8]> {
+            strmatch_fields!(line.trim().as_bytes(), ':'; _, [b], ..)
+        }
+        fn values<'a>(lines: &[&'a str]) -> Vec<&'a [u8]> {
+            lines
+                .iter()
+                .filter_map(|l| strextract!(l.as_bytes(), "k="[v]))
+                .collect()
+        }
+
+        assert_eq!(path(" GET / "), Some(&b"/"[..]));
+        assert_eq!(pair(b">a=bc"), Some((&b"a"[..], &b'b')));
+        assert_eq!(rest("xyz\n"), Some(&b"yz"[..]));
+        assert_eq!(field(" a:b:c "), Some(&b"b"[..]));
+        assert_eq!(values(&["k=1", "j=2", "k=3"]), [&b"1"[..], b"3"]);
+
+        // A temporary in the input lives as long as the expression does
+        let len = strextract!(String::from("k=abc").as_bytes(), "k="[v])
+            .map(<[u8]>::len);
+        assert_eq!(len, Some(3));
+    }
+
+    #[test]
+    fn nom_parsers() {
+        use nom::bytes::complete::tag;
+        use nom::sequence::{preceded, tuple};
+
+        // Each parser takes what it matches and leaves the rest
+        let version =
+            strmatch_nom!("HTTP/" [major: u8] '.' [minor: u8 until ' ']);
+        assert_eq!(version(b"HTTP/1.1 200 OK"), Ok((&b" 200 OK"[..], (1, 1))));
+        assert!(version(b"HTTP/x.1 ").is_err());
+
+        let status = strmatch_nom!(' ' _ _ _);
+        let code = strmatch_nom!(' ' code @ digit+);
+        let mut line = tuple((
+            version,
+            preceded(tag(" "), strmatch_nom!(d @ digit+)),
+            code,
+        ));
+        assert!(line(b"HTTP/1.0 200 301").is_ok());
+        assert_eq!(status(b" 200 OK"), Ok((&b" OK"[..], ())));
+        assert_eq!(code(b" 42"), Ok((&b""[..], &b"42"[..])));
+
+        strpattern!(KEY = 'a'..='z'+);
+        let key = strmatch_nom!(k @ @sub(KEY) '=');
+        assert_eq!(key(b"ab=1"), Ok((&b"1"[..], &b"ab"[..])));
+        assert_eq!(
+            key(b"AB=1"),
+            Err(nom::Err::Error(nom::error::Error::new(
+                &b"AB=1"[..],
+                nom::error::ErrorKind::Verify
+            )))
+        );
+    }
+
+    #[test]
+    fn winnow_parsers() {
+        use winnow::combinator::{alt, preceded, repeat};
+        use winnow::Parser;
+
+        // The input is moved past what matched, and left alone on a failure
+        let mut input = &b"HTTP/1.1 200"[..];
+        let mut version =
+            strmatch_winnow!("HTTP/" [major: u8] '.' [minor: u8 until ' ']);
+        assert_eq!(version.parse_next(&mut input), Ok((1, 1)));
+        assert_eq!(input, b" 200");
+        assert!(version.parse_next(&mut input).is_err());
+        assert_eq!(input, b" 200");
+
+        let mut digits =
+            repeat(1.., preceded(' ', strmatch_winnow!(d @ digit+)));
+        let numbers: Vec<&[u8]> =
+            digits.parse_next(&mut &b" 1 22"[..]).unwrap();
+        assert_eq!(numbers, [&b"1"[..], b"22"]);
+
+        let mut sign = alt((strmatch_winnow!('+'), strmatch_winnow!('-')));
+        assert_eq!(sign.parse(&b"-"[..]), Ok(()));
+        assert!(sign.parse(&b"*"[..]).is_err());
+    }
+
+    #[test]
+    fn lexers() {
+        #[derive(Debug, PartialEq)]
+        enum Tok<'a> {
+            Arrow,
+            Op(u8),
+            Word(&'a [u8]),
+            Str(&'a [u8]),
+        }
+        let lex = |input| {
+            strlexer!(input {
+                ("=>") => Tok::Arrow,
+                (op @ '='..='>') | (op @ '+') => Tok::Op(*op),
+                (w @ 'a'..='z'+) => Tok::Word(w),
+                ('"' [s until '"'] '"') => Tok::Str(s),
+                // Matches nothing at all between tokens, so never wins
+                (digit*) => Tok::Op(b'0'),
+                (#ws) => _,
+            })
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lex(b"a => b= \"c d\"+"),
+            [
+                Ok((Tok::Word(b"a"), 0..1)),
+                Ok((Tok::Arrow, 2..4)),
+                Ok((Tok::Word(b"b"), 5..6)),
+                Ok((Tok::Op(b'='), 6..7)),
+                Ok((Tok::Str(b"c d"), 8..13)),
+                Ok((Tok::Op(b'+'), 13..14)),
+            ]
+        );
+        assert_eq!(lex(b""), []);
+        assert_eq!(lex(b"a!!"), [Ok((Tok::Word(b"a"), 0..1)), Err(1), Err(2)]);
+        // An unterminated string isn't a token
+        assert_eq!(lex(b"\"ab"), [Err(0), Ok((Tok::Word(b"ab"), 1..3))]);
+    }
+
+    #[test]
+    fn finds() {
+        fn naive(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+            haystack.windows(needle.len()).position(|w| w == needle)
+        }
+        let haystacks: [&[u8]; 6] =
+            [b"", b"a", b"aaab", b"abaabab", b"xxabcabxabcaby", b"abcab"];
+        for haystack in haystacks {
+            assert_eq!(
+                strmatch_find!(haystack, "aab"),
+                naive(haystack, b"aab")
+            );
+            assert_eq!(
+                strmatch_find!(haystack, "abcaby"),
+                naive(haystack, b"abcaby")
+            );
+            assert_eq!(strmatch_find!(haystack, b"ab"), naive(haystack, b"ab"));
+            assert_eq!(strmatch_find!(haystack, 'b'), naive(haystack, b"b"));
+            assert_eq!(strmatch_find!(haystack, ""), Some(0));
+        }
+        assert_eq!(strmatch_find!("héllo".as_bytes(), "llo"), Some(3));
+
+        // `until` captures search for their delimiters the same way
+        assert_eq!(
+            strextract!(b"k: v: w", [k until ": "] ": " [v]),
+            Some((&b"k"[..], &b"v: w"[..]))
+        );
+    }
+
+    #[test]
+    fn reordered_arms() {
+        fn reordered(input: &[u8], strict: bool) -> u8 {
+            strmatch_match!(#[reorder] input {
+                ('a'..='z' ..) => 0,
+                ("HEAD " ..) | ("HEAP" _) => 1,
+                ("GET" _ ..) if input.len() < 6 => 2,
+                _ if strict => 3,
+                (_ _) => 4,
+                ("POST /" ..) => 5,
+                _ => 6,
+            })
+        }
+        fn in_order(input: &[u8], strict: bool) -> u8 {
+            strmatch_match!(input {
+                ('a'..='z' ..) => 0,
+                ("HEAD " ..) | ("HEAP" _) => 1,
+                ("GET" _ ..) if input.len() < 6 => 2,
+                _ if strict => 3,
+                (_ _) => 4,
+                ("POST /" ..) => 5,
+                _ => 6,
+            })
+        }
+        let inputs = [
+            &b"get"[..],
+            b"HEAD /",
+            b"HEAPS",
+            b"GET /",
+            b"GET /x",
+            b"ab",
+            b"PO",
+            b"POST /a",
+            b"",
+        ];
+        for input in inputs {
+            for strict in [false, true] {
+                assert_eq!(reordered(input, strict), in_order(input, strict));
+            }
+        }
+    }
+
+    #[test]
+    fn dispatch() {
+        // Enough arms to go through a table of which arms each first byte
+        // can start
+        fn command(input: &[u8], admin: bool) -> &'static str {
+            strmatch_match!(input {
+                ("GET " [_]) => "get",
+                ("GETS") => "gets",
+                (nocase("set ") [_]) => "set",
+                (d @ '0'..='9' ..) if *d != b'0' => "number",
+                _ if admin => "admin",
+                ([_ lazy] "!") => "bang",
+                () => "empty",
+                ("DEL " [_]) | ("del " [_]) => "del",
+                _ => "other",
+            })
+        }
+        let cases = [
+            (&b"GET /"[..], "get"),
+            (b"GETS", "gets"),
+            (b"SeT k", "set"),
+            (b"42", "number"),
+            (b"0!", "bang"),
+            (b"", "empty"),
+            (b"del k", "del"),
+            (b"DEL k", "del"),
+            (b"PUT", "other"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(command(input, false), expected);
+        }
+        assert_eq!(command(b"GET /", true), "get");
+        assert_eq!(command(b"0!", true), "admin");
+        assert_eq!(command(b"", true), "admin");
+    }
+
+    #[test]
+    fn keyword_enums() {
+        strkeywords! {
+            #[derive(Debug, PartialEq)]
+            enum Command {
+                Get = "GET",
+                Gets = "GETS",
+                Set = "SET",
+                #[allow(dead_code)]
+                Raw = b"\x00\xff",
+                Del = "DEL",
+            }
+        }
+        for command in Command::ALL {
+            assert_eq!(Command::from_bytes(command.as_bytes()), Some(command));
+        }
+        assert_eq!(Command::from_bytes(b"GETS"), Some(Command::Gets));
+        assert_eq!(Command::from_bytes(b"\x00\xff"), Some(Command::Raw));
+        for other in [&b""[..], b"GE", b"get", b"GETSS", b"DEX", b"\x00"] {
+            assert_eq!(Command::from_bytes(other), None);
+        }
+    }
+
+    #[test]
+    fn keyword_enums_phf() {
+        strkeywords! {
+            #[phf]
+            #[derive(Debug, PartialEq)]
+            enum Month {
+                Jan = "jan", Feb = "feb", Mar = "mar", Apr = "apr",
+                May = "may", Jun = "jun", Jul = "jul", Aug = "aug",
+                Sep = "sep", Oct = "oct", Nov = "nov", Dec = "dec",
+                Sept = "sept", June = "june", July = b"july",
+            }
+        }
+        for month in Month::ALL {
+            assert_eq!(Month::from_bytes(month.as_bytes()), Some(month));
+        }
+        assert_eq!(Month::from_bytes(b"sept"), Some(Month::Sept));
+        for other in [&b""[..], b"ja", b"JAN", b"janu", b"sep\0"] {
+            assert_eq!(Month::from_bytes(other), None);
+        }
+    }
+
+    #[test]
+    fn class_tables() {
+        // Every byte is in exactly the classes the std predicates say
+        for byte in 0..=u8::MAX {
+            let input = [byte];
+            assert_eq!(
+                strextract!(&input, alpha+).is_some(),
+                byte.is_ascii_alphabetic()
+            );
+            assert_eq!(
+                strextract!(&input, alnum+).is_some(),
+                byte.is_ascii_alphanumeric()
+            );
+            assert_eq!(
+                strextract!(&input, hex+).is_some(),
+                byte.is_ascii_hexdigit()
+            );
+            assert_eq!(
+                strextract!(&input, punct+).is_some(),
+                byte.is_ascii_punctuation()
+            );
+        }
+        let long =
+            [b"0123456789abcdefABCDEF".repeat(50), b"g".to_vec()].concat();
+        let (digits, rest) = strextract!(&long, h @ hex+ [rest]).unwrap();
+        assert_eq!((digits.len(), rest), (1100, &b"g"[..]));
+    }
+
+    #[test]
+    fn unicode_runs() {
+        let name = strextract!("Zoë Ångström, 42".as_bytes(), first @ unicode_alpha+ ' ' last @ unicode_alpha+ [_]);
+        assert_eq!(name, Some(("Zoë".as_bytes(), "Ångström".as_bytes())));
+        assert_eq!(
+            strextract!("١٢٣x".as_bytes(), n @ unicode_digit+ 'x'),
+            Some("١٢٣".as_bytes())
+        );
+        assert_eq!(strextract!("ΣΑΣ".as_bytes(), unicode_upper+), Some(()));
+        assert_eq!(strextract!("ΣαΣ".as_bytes(), unicode_upper+), None);
+        assert_eq!(strextract!("日本語".as_bytes(), unicode_alnum+), Some(()));
+        assert_eq!(
+            strextract!("a\u{3000}b".as_bytes(), 'a' unicode_space+ 'b'),
+            Some(())
+        );
+        assert_eq!(
+            strextract!(b"ab", s @ unicode_lower* [_]),
+            Some(&b"ab"[..])
+        );
+
+        // A run stops at bytes that aren't valid UTF-8, even cut-off ones
+        assert_eq!(
+            strextract!(b"ab\xff", s @ unicode_alpha+ [_]),
+            Some(&b"ab"[..])
+        );
+        let cut = &"aé".as_bytes()[..2];
+        assert_eq!(strextract!(cut, s @ unicode_alpha+ [_]), Some(&b"a"[..]));
+        assert_eq!(strextract!(b"\xc0\x80", unicode_alpha * [_]), Some(()));
+    }
+
+    #[test]
+    fn graphemes() {
+        fn first(input: &[u8]) -> Option<(&str, &[u8])> {
+            strextract!(input, g @ #grapheme [rest])
+        }
+        assert_eq!(first(b"ab"), Some(("a", &b"b"[..])));
+        assert_eq!(
+            first("e\u{301}x".as_bytes()),
+            Some(("e\u{301}", &b"x"[..]))
+        );
+        assert_eq!(first("\r\n".as_bytes()), Some(("\r\n", &b""[..])));
+        assert_eq!(first(b""), None);
+        assert_eq!(first(b"\xff"), None);
+
+        // Clusters longer than the first window are still taken whole
+        let family =
+            "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\u{200d}\u{1f466}";
+        let long = format!("{family}!");
+        assert_eq!(first(long.as_bytes()), Some((family, &b"!"[..])));
+        let marks = format!("a{}b", "\u{301}".repeat(40));
+        let (cluster, rest) = first(marks.as_bytes()).unwrap();
+        assert_eq!((cluster.len(), rest), (81, &b"b"[..]));
+
+        // A cluster ends at a byte that can't be UTF-8
+        assert_eq!(first(b"a\xff\xcc\x81"), Some(("a", &b"\xff\xcc\x81"[..])));
+
+        let flag = strmatch_match!("\u{1f1eb}\u{1f1f7}.".as_bytes() {
+            (f @ #grapheme '.') => Some(f),
+            _ => None,
+        });
+        assert_eq!(flag, Some("\u{1f1eb}\u{1f1f7}"));
+    }
+
+    #[test]
+    fn utf8_tails() {
+        fn header(line: &[u8]) -> Option<(&[u8], &str)> {
+            strextract!(line, [name until b':'] ':' #ws? [value: utf8])
+        }
+        assert_eq!(
+            header("Subject: héllo".as_bytes()),
+            Some((&b"Subject"[..], "héllo"))
+        );
+        assert_eq!(header(b"Subject: \xff"), None);
+        assert_eq!(header(b"Subject:"), Some((&b"Subject"[..], "")));
+
+        let greeting = strmatch_match!("hi ünïcode".as_bytes() {
+            ("hi " [rest: utf8]) => Some(rest),
+            _ => None,
+        });
+        assert_eq!(greeting, Some("ünïcode"));
+        let invalid = strmatch_match!(b"hi \xc3" {
+            ("hi " [rest: utf8]) => Some(rest),
+            ("hi " [rest]) => rest.first().map(|_| "not UTF-8"),
+            _ => None,
+        });
+        assert_eq!(invalid, Some("not UTF-8"));
+    }
+
+    #[test]
+    fn bstr_captures() {
+        use bstr::BStr;
+
+        let (user, agent) =
+            strextract!(b"user=r\xf6b agent=curl", "user=" [user: bstr until ' '] " agent=" [agent | bstr]).unwrap();
+        assert_eq!(user, BStr::new(b"r\xf6b"));
+        assert_eq!(format!("{user:?} {agent}"), "\"r\\xf6b\" curl");
+        let trimmed = strextract!(b"[ x ]", '[' [inner | trim | bstr] ']');
+        assert_eq!(trimmed, Some(BStr::new("x")));
+    }
+
+    #[test]
+    fn deserialize_templates() {
+        use serde::de::value::{BytesDeserializer, Error, StrDeserializer};
+        use serde::Deserialize;
+
+        #[strmatch_deserialize("{w}x{h}")]
+        #[derive(Debug, PartialEq)]
+        struct Size<T: std::str::FromStr> {
+            w: T,
+            h: T,
+        }
+
+        #[strmatch_deserialize("v" [version])]
+        #[derive(Debug, PartialEq)]
+        struct Version(u8);
+
+        #[strmatch_deserialize("none")]
+        #[derive(Debug, PartialEq)]
+        struct Nothing;
+
+        fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T, Error> {
+            T::deserialize(StrDeserializer::new(s))
+        }
+        assert_eq!(from_str("640x480"), Ok(Size { w: 640u16, h: 480 }));
+        assert_eq!(from_str("1.5x2"), Ok(Size { w: 1.5f32, h: 2.0 }));
+        assert_eq!(from_str("v2"), Ok(Version(2)));
+        assert_eq!(from_str("none"), Ok(Nothing));
+        let bytes =
+            Version::deserialize(BytesDeserializer::<Error>::new(b"v7"));
+        assert_eq!(bytes, Ok(Version(7)));
+
+        let error = from_str::<Size<u8>>("640x480").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value: string \"640x480\", expected a string in the format of `Size`",
+        );
+        assert!(from_str::<Version>("v").is_err());
+    }
+
+    #[test]
+    fn try_from_templates() {
+        #[strmatch_try_from("{host}:{port}")]
+        #[derive(Debug, PartialEq)]
+        struct Addr {
+            host: String,
+            port: u16,
+        }
+
+        #[strmatch_try_from("#" [r: u8 until ','] ',' [g: u8 until ','] ',' [b: u8])]
+        #[derive(Debug, PartialEq)]
+        struct Rgb {
+            r: u8,
+            g: u8,
+            b: u8,
+        }
+
+        fn parse(input: &[u8]) -> Result<Rgb, TryFromRgbError> {
+            let rgb = Rgb::try_from(input)?;
+            Ok(rgb)
+        }
+
+        let addr = Addr::try_from("localhost:8080");
+        assert_eq!(
+            addr,
+            Ok(Addr {
+                host: "localhost".into(),
+                port: 8080
+            })
+        );
+        let error = Addr::try_from("localhost").unwrap_err();
+        assert_eq!((error.segment(), error.offset()), (Some("\":\""), 9));
+        let error = Addr::try_from(&b"localhost:http"[..]).unwrap_err();
+        assert_eq!((error.segment(), error.offset()), (Some("`port`"), 10));
+        assert_eq!(error.to_string(), "expected `port` at byte 10");
+        let error = Addr::try_from("localhost:80x").unwrap_err();
+        assert_eq!((error.segment(), error.offset()), (None, 12));
+        assert_eq!(
+            error.to_string(),
+            "unexpected bytes after the end at byte 12"
+        );
+
+        assert_eq!(parse(b"#1,2,3"), Ok(Rgb { r: 1, g: 2, b: 3 }));
+        assert_eq!(parse(b"1,2,3").unwrap_err().segment(), Some("\"#\""));
+        let error = parse(b"#1,300,3").unwrap_err();
+        assert_eq!((error.segment(), error.offset()), (Some("`g`"), 3));
+        let error = parse(b"#1;2,3").unwrap_err();
+        assert_eq!((error.segment(), error.offset()), (Some("`r`"), 1));
+    }
+
+    #[test]
+    fn clap_parsers() {
+        use clap::builder::TypedValueParser;
+        use clap::error::ErrorKind;
+        use clap::{Arg, Command};
+
+        let addr = strmatch_clap!((String, u16), ([host | utf8] ':' [port: u16]) => (host.to_owned(), port));
+        let cmd = Command::new("serve")
+            .arg(Arg::new("listen").long("listen").value_parser(addr));
+
+        let matches = cmd
+            .clone()
+            .try_get_matches_from(["serve", "--listen", "localhost:80"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<(String, u16)>("listen"),
+            Some(&("localhost".to_string(), 80))
+        );
+
+        let error = cmd
+            .clone()
+            .try_get_matches_from(["serve", "--listen", "localhost"])
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        assert!(error
+            .to_string()
+            .contains("invalid value 'localhost' for '--listen <listen>': expected the format `[host | utf8] ':' [port: u16]`"));
+
+        // Without an argument, as for an external subcommand
+        let error = addr
+            .parse_ref(&cmd, None, "localhost:http".as_ref())
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("invalid value 'localhost:http': expected the format"));
+    }
+
+    #[test]
+    fn split_once() {
+        fn key_value(line: &str) -> Option<(&[u8], &[u8])> {
+            strmatch_split_once!(line.as_bytes(), " => ")
+        }
+        assert_eq!(key_value("a => b => c"), Some((&b"a"[..], &b"b => c"[..])));
+        assert_eq!(key_value(" => "), Some((&b""[..], &b""[..])));
+        assert_eq!(key_value("a =>b"), None);
+        assert_eq!(key_value(""), None);
+
+        assert_eq!(
+            strmatch_split_once!(b"k=v", '='),
+            Some((&b"k"[..], &b"v"[..]))
+        );
+        assert_eq!(
+            strmatch_split_once!(b"aXYXYb", "XY"x2),
+            Some((&b"a"[..], &b"b"[..]))
+        );
+        let owned = b"one, two".to_vec();
+        assert_eq!(
+            strmatch_split_once!(&owned, ", "),
+            Some((&b"one"[..], &b"two"[..]))
+        );
+    }
+
+    #[test]
+    fn counts() {
+        fn naive(haystack: &[u8], needle: &[u8]) -> usize {
+            let (mut count, mut at) = (0, 0);
+            while at + needle.len() <= haystack.len() {
+                if haystack[at..].starts_with(needle) {
+                    count += 1;
+                    at += needle.len();
+                } else {
+                    at += 1;
+                }
+            }
+            count
+        }
+        for haystack in [
+            &b""[..],
+            b"a",
+            b"abab",
+            b"aaaaa",
+            b"xabcabcabx",
+            b"ab\nab\r\n\r\n",
+        ] {
+            assert_eq!(strmatch_count!(haystack, 'a'), naive(haystack, b"a"));
+            assert_eq!(strmatch_count!(haystack, "aa"), naive(haystack, b"aa"));
+            assert_eq!(
+                strmatch_count!(haystack, "abc"),
+                naive(haystack, b"abc")
+            );
+            assert_eq!(
+                strmatch_count!(haystack, '\r' '\n'),
+                naive(haystack, b"\r\n")
+            );
+        }
+        let text = "one\ntwo\nthree".to_string();
+        assert_eq!(strmatch_count!(text.as_bytes(), b'\n'), 2);
+    }
+
+    #[test]
+    fn replacements() {
+        use std::borrow::Cow;
+
+        let text = b"a\r\nb\r\n\r\nc";
+        assert_eq!(
+            strmatch_replace!(text, ("\r\n") => '\n'),
+            &b"a\nb\n\nc"[..]
+        );
+        assert_eq!(strmatch_replace!(text, ("\r\n") => ""), &b"abc"[..]);
+        assert!(matches!(
+            strmatch_replace!(b"abc", ("\r\n") => '\n'),
+            Cow::Borrowed(b"abc")
+        ));
+
+        let template = "${greeting}, ${name}! $5 ${unclosed";
+        let replaced = strmatch_replace!(template.as_bytes(), ("${" [key until '}'] '}') => '<' key '>');
+        assert_eq!(replaced, &b"<greeting>, <name>! $5 ${unclosed"[..]);
+
+        // A failed match moves on by a byte, so it can find one that overlaps
+        let text = b"**a **b** c";
+        let replaced = strmatch_replace!(text, ("**" bold @ alpha+ "**") => "<b>" bold "</b>");
+        assert_eq!(replaced, &b"**a <b>b</b> c"[..]);
+    }
+
+    #[test]
+    fn line_endings() {
+        for text in [&b"a\nb"[..], b"a\r\nb"] {
+            assert!(matches!(text, strmatch!('a' #nl 'b')));
+            assert_eq!(
+                strextract!(text, [first lazy] #nl [rest]),
+                Some((&b"a"[..], &b"b"[..]))
+            );
+        }
+        assert!(!matches!(&b"a\rb"[..], strmatch!('a' #nl 'b')));
+        assert!(!matches!(&b"ab"[..], strmatch!('a' #nl 'b')));
+        assert!(matches!(&b"ok\r\n!"[..], strmatch!("ok" #nl [_])));
+
+        for text in [&b"ok\n"[..], b"ok\r\n", b"ok"] {
+            assert!(matches!(text, strmatch!("ok" #eol)));
+            assert_eq!(strextract!(text, "ok" #eol), Some(()));
+        }
+        assert!(!matches!(&b"ok\n\n"[..], strmatch!("ok" #eol)));
+        assert!(!matches!(&b"ok\r"[..], strmatch!("ok" #eol)));
+
+        // Each line, however it ends, including the last one
+        let text = b"one\r\ntwo\nthree";
+        let lines = strextract!(text, (line @ alpha+ #eol)*);
+        assert_eq!(lines, Some(vec![&b"one"[..], b"two", b"three"]));
+
+        // `min_len` counts the bytes each line ending takes
+        assert!(matches!(
+            &b"ab\r\nc"[..],
+            strmatch!(min_len(5); "ab" #nl [_])
+        ));
+        assert!(!matches!(
+            &b"ab\nc"[..],
+            strmatch!(min_len(5); "ab" #nl [_])
+        ));
+    }
+
+    #[test]
+    fn containment() {
+        for haystack in [
+            &b""[..],
+            b"ab",
+            b"xxabab",
+            b"a1b22",
+            b"ERR",
+            b"an ERROR: 42\n",
+        ] {
+            let windows = |len: usize| {
+                (0..=haystack.len())
+                    .map(move |at| &haystack[at..haystack.len().min(at + len)])
+            };
+            assert_eq!(
+                strmatch_contains!(haystack, "ab"),
+                windows(2).any(|w| w == b"ab")
+            );
+            assert_eq!(
+                strmatch_contains!(haystack, "ERROR"),
+                windows(5).any(|w| w == b"ERROR")
+            );
+            assert_eq!(
+                strmatch_contains!(haystack, 'b' digit+),
+                windows(2).any(|w| w.len() == 2
+                    && w[0] == b'b'
+                    && w[1].is_ascii_digit()),
+            );
+            assert_eq!(
+                strmatch_contains!(haystack, alpha+ ':' ' '),
+                windows(3).any(|w| w.len() == 3
+                    && w[0].is_ascii_alphabetic()
+                    && w[1..] == *b": "),
+            );
+        }
+        // A prefix that's found, but not followed by the rest, is skipped
+        assert!(
+            strmatch_contains!(b"code=4 code=503", "code=" '0'..='9' '0'..='9' '0'..='9')
+        );
+        assert!(
+            !strmatch_contains!(b"code=4 code=50", "code=" '0'..='9' '0'..='9' '0'..='9')
+        );
+        // A pattern that can match nothing matches at the end of the input
+        assert!(strmatch_contains!(b"", digit*));
+        assert!(strmatch_contains!(b"x", "x" #eol));
+    }
+
+    #[test]
+    fn building() {
+        assert_eq!(strbuild!(), b"");
+        assert_eq!(strbuild!("ab" 'c' b'd' 0x65 b"f" "-"x3), b"abcdef---");
+
+        let name = String::from("Zoë");
+        let bytes = vec![1u8, 2];
+        let greeting = strbuild!("Hi " {name} '!' {bytes} {&b"\xff"[..]} #nl);
+        assert_eq!(greeting, b"Hi Zo\xc3\xab!\x01\x02\xff\n");
+        // The holes are only borrowed
+        assert_eq!(name, "Zoë");
+        assert_eq!(bytes, [1, 2]);
+
+        let (colon, width) = (b':', 3);
+        assert_eq!(
+            strbuild!(#colon pad('.', width) {width.to_string()}),
+            b":...3"
+        );
+        assert_eq!(strbuild!(nocase("Host") ':'), b"Host:");
+
+        // What's built matches the same spelling
+        let (method, path) = ("POST", "/submit");
+        let request = strbuild!({method} ' ' {path} " HTTP/1.1" #nl);
+        let parsed = strextract!(&request, [method until ' '] ' ' [path until ' '] " HTTP/1." _ #nl);
+        assert_eq!(parsed, Some((&b"POST"[..], &b"/submit"[..])));
+    }
+
+    #[test]
+    fn arbitrary_inputs() {
+        let request =
+            strmatch_arbitrary!("GET /" [path until ' '] " HTTP/1." digit+ #nl);
+        let address = strmatch_arbitrary!(#ipv4 ':' [port: u16]);
+        let version =
+            strmatch_arbitrary!('v' digit+ '.' alpha* (',' hex+)* #eol);
+        let mixed = strmatch_arbitrary!(nocase("ok") #ws? name @ unicode_alpha+ ' ' [n: i32] ' ' #utf8char [_]);
+        let either = strmatch_arbitrary!("a" digit+ | "b" upper+ [rest]);
+        // What comes after a run never starts with a byte the run would take
+        let around = strmatch_arbitrary!(hex+ 'a'..='z' [mid] ';' upper+ [word: utf8] ' ' #ident [id] ';' [rest]);
+        for seed in 0..200u32 {
+            let data = (0..256u32)
+                .map(|i| {
+                    (i.wrapping_mul(seed).wrapping_add(seed * 7) >> 2) as u8
+                })
+                .collect::<Vec<_>>();
+            let mut u = arbitrary::Unstructured::new(&data);
+            let input = request(&mut u).unwrap();
+            assert!(strextract!(&input, "GET /" [path until ' '] " HTTP/1." digit+ #nl).is_some(), "{:?}", input);
+            let input = address(&mut u).unwrap();
+            assert!(
+                strextract!(&input, #ipv4 ':' [port: u16]).is_some(),
+                "{:?}",
+                input
+            );
+            let input = version(&mut u).unwrap();
+            assert!(
+                strextract!(&input, 'v' digit+ '.' alpha* (',' hex+)* #eol)
+                    .is_some(),
+                "{:?}",
+                input
+            );
+            let input = mixed(&mut u).unwrap();
+            let matched = strextract!(&input, nocase("ok") #ws? name @ unicode_alpha+ ' ' [n: i32] ' ' #utf8char [_]);
+            assert!(matched.is_some(), "{:?}", input);
+            let input = either(&mut u).unwrap();
+            let matched = strmatch_match!(input.as_slice() {
+                ("a" digit+) | ("b" upper+ [_]) => true,
+                _ => false,
+            });
+            assert!(matched, "{:?}", input);
+            let input = around(&mut u).unwrap();
+            let matched = strextract!(&input, hex+ 'a'..='z' [mid] ';' upper+ [word: utf8] ' ' #ident [id] ';' [rest]);
+            assert!(matched.is_some(), "{:?}", input);
+        }
+        // Out of data, every choice is the smallest it can be
+        let mut u = arbitrary::Unstructured::new(&[]);
+        assert_eq!(request(&mut u).unwrap(), b"GET / HTTP/1.0\n");
+    }
+
+    #[test]
+    fn proptest_strategies() {
+        use proptest::test_runner::{TestError, TestRunner};
+
+        let strategy = strmatch_proptest!("GET /" [path until ' '] " HTTP/1." digit #nl | "PING" #eol);
+        TestRunner::deterministic()
+            .run(&strategy, |input| {
+                let matched = strmatch_match!(input.as_slice() {
+                    ("GET /" [path until ' '] " HTTP/1." _ #nl) => !path.contains(&b' '),
+                    ("PING" #eol) => true,
+                    _ => false,
+                });
+                assert!(matched, "{:?}", input);
+                Ok(())
+            })
+            .unwrap();
+
+        let strategy =
+            strmatch_proptest!(#semver ' ' [n: i64] ' ' nocase("x") hex+);
+        TestRunner::deterministic()
+            .run(&strategy, |input| {
+                assert!(strextract!(&input, #semver ' ' [n: i64] ' ' nocase("x") hex+).is_some(), "{:?}", input);
+                Ok(())
+            })
+            .unwrap();
+
+        // What comes after a run never starts with a byte the run would take
+        let strategy = strmatch_proptest!(hex+ 'a'..='z' [mid] ';' upper+ [word: utf8] ' ' #ident _ (digit+ ',')* #ws? [rest]);
+        TestRunner::deterministic()
+            .run(&strategy, |input| {
+                let matched = strextract!(&input, hex+ 'a'..='z' [mid] ';' upper+ [word: utf8] ' ' #ident _ (digit+ ',')* #ws? [rest]);
+                assert!(matched.is_some(), "{:?}", input);
+                Ok(())
+            })
+            .unwrap();
+
+        // A failing input shrinks to the smallest one that still fails
+        let strategy = strmatch_proptest!('a' digits @ digit+);
+        let result = TestRunner::deterministic().run(&strategy, |input| {
+            proptest::prop_assert!(input.len() < 4);
+            Ok(())
+        });
+        match result {
+            Err(TestError::Fail(_, input)) => assert_eq!(input, b"a000"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn regexes() {
+        fn request(line: &[u8]) -> Option<(&[u8], &[u8])> {
+            strmatch_regex!(line, r"^(GET|POST) (\S+) HTTP/1\.[01]$")
+        }
+        assert_eq!(
+            request(b"GET /a HTTP/1.1"),
+            Some((&b"GET"[..], &b"/a"[..]))
+        );
+        assert_eq!(
+            request(b"POST / HTTP/1.0"),
+            Some((&b"POST"[..], &b"/"[..]))
+        );
+        assert_eq!(request(b"PUT / HTTP/1.0"), None);
+        assert_eq!(request(b"GET /a HTTP/1.1 "), None);
+
+        // Unanchored, the leftmost match is found
+        let key = |line: &'static str| {
+            strmatch_regex!(line.as_bytes(), r"(?<key>[a-z_]+)=(?:\d+|on)")
+        };
+        assert_eq!(key("x; max_len=10"), Some(&b"max_len"[..]));
+        assert_eq!(key("1=on, debug=on"), Some(&b"debug"[..]));
+        assert_eq!(key("debug=off"), None);
+
+        // Repetitions give back bytes when what follows needs them, like in
+        // any regex engine
+        let quoted = strmatch_regex!(br#"say "hi" and "bye""#, r#""(.*)""#);
+        assert_eq!(quoted, Some(&br#"hi" and "bye"#[..]));
+        let quoted = strmatch_regex!(br#"say "hi" and "bye""#, r#""(.*?)""#);
+        assert_eq!(quoted, Some(&b"hi"[..]));
+        let quoted = strmatch_regex!(br#"say "hi" and "bye""#, r#""([^"]*)"$"#);
+        assert_eq!(quoted, Some(&b"bye"[..]));
+        assert_eq!(strmatch_regex!(b"123", r"^\d+3$"), Some(()));
+        assert_eq!(
+            strmatch_regex!(b"123", r"^(\d+)(\d)$"),
+            Some((&b"12"[..], &b"3"[..]))
+        );
+        assert_eq!(
+            strmatch_regex!(b"123", r"^(\d+?)(\d*)$"),
+            Some((&b"1"[..], &b"23"[..]))
+        );
+        assert_eq!(strmatch_regex!(b"123", r"^\d{1,2}3$"), Some(()));
+        assert_eq!(strmatch_regex!(b"123", r"^(\d{2})"), Some(&b"12"[..]));
+        assert_eq!(strmatch_regex!(b"aab", r"^(a*)ab$"), Some(&b"a"[..]));
+        assert_eq!(strmatch_regex!(b"123", r"^\d+4$"), None);
+
+        let ip = |addr: &'static str| {
+            strmatch_regex!(addr.as_bytes(), r"^\d{1,3}(?:\.\d{1,3}){3}$")
+                .is_some()
+        };
+        assert!(ip("10.0.0.255"));
+        assert!(!ip("10.0.0"));
+        assert!(!ip("10.0.0.2555"));
+
+        assert_eq!(strmatch_regex!(b"colour", r"^colou?r$"), Some(()));
+        assert_eq!(strmatch_regex!(b"color", r"^colou?r$"), Some(()));
+        assert_eq!(
+            strmatch_regex!(b"2024-01-31", r"^(\d{4})-(\d\d)-\d\d$"),
+            Some((&b"2024"[..], &b"01"[..]))
+        );
+        assert_eq!(
+            strmatch_regex!(b"ab\tcd", r"^\w+\s(.+)$"),
+            Some(&b"cd"[..])
+        );
+        assert_eq!(
+            strmatch_regex!(b"a.b.c", r"^(?:\w\.)+(\w)$"),
+            Some(&b"c"[..])
+        );
+        assert_eq!(strmatch_regex!(b"", r"x|^$"), Some(()));
+
+        // A repeated group gives back copies when what follows needs them
+        assert_eq!(strmatch_regex!(b"abab", r"^(?:ab)*ab$"), Some(()));
+        assert_eq!(strmatch_regex!(b"ab", r"^(?:ab)*ab$"), Some(()));
+        assert_eq!(strmatch_regex!(b"aba", r"^(?:ab)*ab$"), None);
+        assert_eq!(
+            strmatch_regex!(b"ababc", r"^(?:ab)*?(.*)$"),
+            Some(&b"ababc"[..])
+        );
+    }
+
+    #[test]
+    fn regex_strings() {
+        const LINE_RE: &str =
+            strmatch_regex_str!([level: u8] ':' #ws nocase("warn") [what] #nl);
+        assert_eq!(
+            LINE_RE,
+            r"^(\+?[0-9]+):[\t\n\f\r ]+(?i:warn)((?s:.*))\r?\n$"
+        );
+        assert_eq!(
+            strmatch_regex_str!("ip=" addr @ #ipv4 ", " 'a'..='f' [_ lazy] ".[x]"),
+            r"^ip=((?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9]{1,2})(?:\.(?:25[0-5]|2[0-4][0-9]|[01]?[0-9]{1,2})){3})), [a-f](?s:.*?)\.\[x\]$"
+        );
+        assert_eq!(
+            strmatch_regex_str!(c @ ':' _ #utf8char (digit+ ',')*),
+            r"^(:)(?s:.)(?s:.)(?:[0-9]+,)*$"
+        );
+        assert_eq!(
+            strmatch_regex_str!(@sub(patterns::HEX_BYTE)),
+            "^0x[0-9A-Fa-f]+$"
+        );
+        assert_eq!(
+            strmatch_regex_str!(b"\xff\x00" [rest: f32]),
+            r"^\xff\x00([+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[Ee][+-]?[0-9]+)?)$"
+        );
+
+        // What the regex matches agrees with the pattern it came from
+        assert_eq!(
+            strmatch_regex_str!("GET " [path] " HTTP/1." '0'..='9'),
+            r"^GET ((?s:.*)) HTTP/1\.[0-9]$"
+        );
+        let line = b"GET /a b HTTP/1.1";
+        assert_eq!(
+            strmatch_regex!(line, r"^GET (.*) HTTP/1\.[0-9]$"),
+            strextract!(line, "GET " [path] " HTTP/1." '0'..='9')
+        );
+        // A run that what follows can't start with agrees with `+` too
+        assert_eq!(
+            strmatch_regex_str!(d @ digit+ '.' #ws? [n: u8]),
+            r"^([0-9]+)\.[\t\n\f\r ]*(\+?[0-9]+)$"
+        );
+        // `_` and `[..]` take a `\n` in the regex as they do in the pattern
+        let input = b"a\nb\nc;";
+        let regex = strmatch_regex_str!('a' _ [rest] ';');
+        let regex = regex::bytes::Regex::new(regex).unwrap();
+        let rest = regex.captures(input).and_then(|captures| captures.get(1));
+        assert_eq!(
+            rest.map(|rest| rest.as_bytes()),
+            strextract!(input, 'a' _ [rest] ';')
+        );
+        assert_eq!(rest.map(|rest| rest.as_bytes()), Some(&b"b\nc"[..]));
+    }
+
+    #[test]
+    fn brace_alternatives() {
+        fn image(name: &[u8]) -> Option<&[u8]> {
+            match name {
+                strmatch!([stem] '.' {"png", "jpg", "jpeg"}) => Some(stem),
+                _ => None,
+            }
+        }
+        assert_eq!(image(b"cat.png"), Some(&b"cat"[..]));
+        assert_eq!(image(b"a.b.jpeg"), Some(&b"a.b"[..]));
+        assert_eq!(image(b"cat.gif"), None);
+        assert!(matches!(&b"GET /"[..], strmatch!({"GET", "HEAD"} ' ' ..)));
+        assert!(matches!(&b"HEAD\r\n"[..], strmatch!({"GET", "HEAD"} #nl)));
+
+        // A shorter literal that starts a longer one doesn't hide it
+        let version = strextract!(b"v1.2-rc1", {"v", "v1."} '2' [pre]);
+        assert_eq!(version, Some(&b"-rc1"[..]));
+        assert_eq!(strextract!(b"tar.gz", [_ lazy] '.' {"gz", "xz"}), Some(()));
+        assert!(strmatch_contains!(b"see x.jpg here", {".png", ".jpg"} ' '));
+        assert_eq!(
+            strmatch_regex_str!([_] '.' {"png", "a+b"}),
+            r"^(?s:.*)\.(?:png|a\+b)$"
+        );
+    }
+
+    #[test]
+    fn path_components() {
+        use std::ffi::OsStr;
+        use std::path::{Path, PathBuf};
+
+        let path = PathBuf::from("/usr/share/doc/strmatch");
+        assert_eq!(
+            path_match!(&path, / "usr" / "share" / _ / [pkg]),
+            Some(OsStr::new("strmatch"))
+        );
+        assert_eq!(path_match!(&path, "usr" / ..), None);
+        assert_eq!(path_match!(&path, / "usr" / "share"), None);
+        assert_eq!(path_match!(&path, / "usr" / "share" / ..), Some(()));
+        assert_eq!(
+            path_match!(&path, / "usr" / rest @ ..),
+            Some(Path::new("share/doc/strmatch"))
+        );
+        assert_eq!(
+            path_match!(&path, / _ / _ / _ / _ / rest @ ..),
+            Some(Path::new(""))
+        );
+
+        // Repeated separators and a leading `.` don't count as components
+        assert_eq!(
+            path_match!("./src//bin/main.rs", "src" / "bin" / ([name] ".rs")),
+            Some(&b"main"[..])
+        );
+        assert_eq!(path_match!("src/bin", / "src" / ..), None);
+        assert_eq!(
+            path_match!("v2/items/17", ("v" [v: u8]) / "items" / [id: u64]),
+            Some((2, 17))
+        );
+        assert_eq!(
+            path_match!("v2/items/x", ("v" [v: u8]) / "items" / [id: u64]),
+            None
+        );
+        assert_eq!(path_match!("../up", ".." / [name]), Some(OsStr::new("up")));
+    }
+
+    #[test]
+    fn urls() {
+        type Parts<'a> =
+            (&'a [u8], Option<&'a [u8]>, &'a [u8], Option<&'a [u8]>);
+        fn url(input: &[u8]) -> Option<Parts<'_>> {
+            strextract!(input, #url(scheme, authority, path, query))
+        }
+        assert_eq!(
+            url(b"https://user@example.com:8443/a/b?x=1&y=%20#frag"),
+            Some((
+                &b"https"[..],
+                Some(&b"user@example.com:8443"[..]),
+                &b"/a/b"[..],
+                Some(&b"x=1&y=%20"[..])
+            ))
+        );
+        assert_eq!(
+            url(b"mailto:someone@example.com"),
+            Some((&b"mailto"[..], None, &b"someone@example.com"[..], None))
+        );
+        assert_eq!(
+            url(b"file:///etc/hosts"),
+            Some((&b"file"[..], Some(&b""[..]), &b"/etc/hosts"[..], None))
+        );
+        assert_eq!(
+            url(b"git+ssh://host?"),
+            Some((
+                &b"git+ssh"[..],
+                Some(&b"host"[..]),
+                &b""[..],
+                Some(&b""[..])
+            ))
+        );
+        assert_eq!(url(b"1http://x"), None);
+        assert_eq!(url(b"ht_tp://x"), None);
+        assert_eq!(url(b"no-colon"), None);
+
+        // The URL stops at whitespace and quotes
+        let link = strextract!(b"see \"http://a.io/x\" here", "see \"" link @ #url '"' [_]);
+        assert_eq!(link, Some(&b"http://a.io/x"[..]));
+    }
+
+    #[test]
+    fn emails() {
+        fn email(input: &[u8]) -> Option<(&[u8], &[u8])> {
+            strextract!(input, #email(local, domain))
+        }
+        assert_eq!(
+            email(b"first.last+tag@mail.example.co"),
+            Some((&b"first.last+tag"[..], &b"mail.example.co"[..]))
+        );
+        assert_eq!(
+            email(b"o'brien@x-y.io"),
+            Some((&b"o'brien"[..], &b"x-y.io"[..]))
+        );
+        assert_eq!(email(b"a@localhost"), None);
+        assert_eq!(email(b".a@b.io"), None);
+        assert_eq!(email(b"a..b@b.io"), None);
+        assert_eq!(email(b"a.@b.io"), None);
+        assert_eq!(email(b"a@-b.io"), None);
+        assert_eq!(email(b"@b.io"), None);
+
+        // The domain stops after its last whole label
+        let rest = strextract!(b"a@b.io. Thanks", #email(_, domain) [rest]);
+        assert_eq!(rest, Some((&b"b.io"[..], &b". Thanks"[..])));
+        assert_eq!(strextract!(b"a@b.io-", #email [_]), None);
+    }
+
+    #[test]
+    fn ipv6() {
+        use std::net::Ipv6Addr;
+
+        fn ipv6(input: &[u8]) -> Option<Ipv6Addr> {
+            strextract!(input, #ipv6(bytes)).map(Ipv6Addr::from)
+        }
+        for text in [
+            "::",
+            "::1",
+            "1::",
+            "fe80::1:2",
+            "2001:DB8:0:0:8:800:200C:417A",
+            "1:2:3:4:5:6:7::",
+            "::2:3:4:5:6:7:8",
+            "::ffff:10.0.0.1",
+            "1:2:3:4:5:6:255.255.255.255",
+        ] {
+            assert_eq!(
+                ipv6(text.as_bytes()),
+                Some(text.parse().unwrap()),
+                "{text}"
+            );
+        }
+        for text in [
+            "1:2:3:4:5:6:7",
+            "1::2::3",
+            ":::",
+            "12345::",
+            "10.0.0.1",
+            "1:2:3:4:5:6:7:8:9",
+            ":1::",
+        ] {
+            assert_eq!(ipv6(text.as_bytes()), None, "{text}");
+        }
+
+        // The address as written, and whatever comes after it
+        let rest = strextract!(b"::1:8080", addr @ #ipv6 [rest]);
+        assert_eq!(rest, Some((&b"::1:8080"[..], &b""[..])));
+        let rest =
+            strextract!(b"[::1]:8080", '[' addr @ #ipv6 "]:" [port: u16]);
+        assert_eq!(rest, Some((&b"::1"[..], 8080)));
+        // Eight groups are a whole address, so a `::` after them isn't part
+        // of it
+        let rest = strextract!(b"1:2:3:4:5:6:7:8::", addr @ #ipv6 [rest]);
+        assert_eq!(rest, Some((&b"1:2:3:4:5:6:7:8"[..], &b"::"[..])));
+    }
+
+    #[test]
+    fn base64_runs() {
+        fn decode(input: &[u8]) -> Option<Vec<u8>> {
+            strextract!(input, #base64run(decoded))
+        }
+        assert_eq!(decode(b"aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(decode(b"aGVsbG8"), Some(b"hello".to_vec()));
+        assert_eq!(decode(b"aGk="), Some(b"hi".to_vec()));
+        assert_eq!(decode(b"aA=="), Some(b"h".to_vec()));
+        assert_eq!(decode(b"+/8="), Some(vec![0xfb, 0xff]));
+        assert_eq!(decode(b"-_8"), Some(vec![0xfb, 0xff]));
+        assert_eq!(decode(b"+_8="), None);
+        assert_eq!(decode(b"aGVsb"), None);
+        assert_eq!(decode(b"aA="), None);
+        assert_eq!(decode(b"aGVs="), None);
+        assert_eq!(decode(b"="), None);
+
+        // The padding is part of the run, and anything past it is left
+        let token = strextract!(b"token=aGk=; path=/", "token=" t @ #base64run "; " [_]);
+        assert_eq!(token, Some(&b"aGk="[..]));
+        let rest = strextract!(b"aA===", #base64run [rest]);
+        assert_eq!(rest, Some(&b"="[..]));
+        let (text, decoded) =
+            strextract!(b"sig:AAEC", "sig:" text @ #base64run(decoded))
+                .unwrap();
+        assert_eq!((text, decoded), (&b"AAEC"[..], vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn hex_runs() {
+        assert_eq!(
+            strextract!(b"DEADbeef", #hexrun(bytes)),
+            Some(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(strextract!(b"DEADbee", #hexrun(bytes)), None);
+        assert_eq!(strextract!(b"", #hexrun(bytes)), None);
+        assert_eq!(
+            strextract!(b"00ff", #hexrun(bytes: [u8; 2])),
+            Some([0, 255])
+        );
+        assert_eq!(strextract!(b"00ff00", #hexrun(_: [u8; 2]) [_]), None);
+        assert_eq!(strextract!(b"00ff", #hexrun(_: [u8; 2])), Some(()));
+
+        // The whole run is taken, so an odd one doesn't give back its last digit
+        assert_eq!(strextract!(b"abc", #hexrun [_]), None);
+        let (run, rest) =
+            strextract!(b"c0ffee!", run @ #hexrun [rest]).unwrap();
+        assert_eq!((run, rest), (&b"c0ffee"[..], &b"!"[..]));
+
+        // A parenthesized group that's repeated is still a group
+        let pairs = strextract!(b"ab,cd,", #hexrun (',' #hexrun)* [_]);
+        assert_eq!(pairs, Some(()));
+        assert_eq!(
+            strmatch_regex_str!("id=" #hexrun(id: [u8; 4])),
+            "^id=(?:[0-9A-Fa-f]{8})$"
+        );
+    }
+
+    #[test]
+    fn bare_keyword_names() {
+        // Without the `#`, these are bytes bound to names, as they always were
+        assert_eq!(strextract!(b"a\xc3", 'a' utf8char), Some(&0xc3));
+        assert_eq!(strextract!(b"ab", 'a' grapheme), Some(&b'b'));
+        assert_eq!(strextract!(b"a b", 'a' ws 'b'), Some(&b' '));
+        assert_eq!(strextract!(b"a-b", 'a' nl 'b'), Some(&b'-'));
+        assert_eq!(strextract!(b"ok!", "ok" eol), Some(&b'!'));
+        assert_eq!(strextract!(b"a=", 'a' base64run), Some(&b'='));
+        assert_eq!(strextract!(b"ab", 'a' hexrun), Some(&b'b'));
+    }
 }
@@ -0,0 +1,76 @@
+//! Parsing and expansion for `strmatcher!`, which compiles a pattern into a
+//! `strmatch_runtime::Matcher` value.
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+
+use crate::engine::{hidden, matcher, Codegen};
+use crate::pattern::Pattern;
+use crate::shadow::prefix;
+
+/// `pattern`, optionally preceded by a `#[codegen(..)]` attribute
+pub(crate) struct MatcherInput {
+    codegen: Codegen,
+    pattern: Pattern,
+    /// The pattern as written, for `Matcher::pattern`
+    printed: String,
+}
+
+impl Parse for MatcherInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let printed = input.fork().parse::<TokenStream>()?.to_string();
+        let printed = printed.split_whitespace().collect::<Vec<_>>().join(" ");
+        let pattern = input.parse()?;
+        Ok(MatcherInput {
+            codegen,
+            pattern,
+            printed,
+        })
+    }
+}
+
+impl ToTokens for MatcherInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            self.codegen,
+        );
+        let bindings = self.pattern.bindings();
+        let names = bindings.iter().map(|binding| binding.to_string());
+        let printed = &self.printed;
+        // What a `Stream` checks to give up on input that can't match
+        let (positions, open) = prefix(&self.pattern);
+        let sets = positions.iter().map(|set| quote!([#(#set),*]));
+        let len = positions.len();
+        let fits = (!open).then(|| quote!(#input.len() <= #len &&));
+        let viable = quote! {
+            |#input: &[u8]| {
+                const PREFIX: &[[u64; 4]] = &[#(#sets),*];
+                #fits #input.iter().zip(PREFIX).all(|(byte, set)| {
+                    (set[usize::from(*byte / 64)] >> (*byte % 64)) & 1 == 1
+                })
+            }
+        };
+        tokens.extend(quote! {
+            ::strmatch_runtime::Matcher::__new(
+                #printed,
+                &[#(#names),*],
+                |#input: &[u8]| #matcher.is_some(),
+                |#input: &[u8]| match #matcher {
+                    ::core::option::Option::Some((#(#bindings,)*)) => {
+                        ::core::option::Option::Some(::std::vec![
+                            #(::strmatch_runtime::IntoCapture::into_capture(#bindings)),*
+                        ])
+                    }
+                    ::core::option::Option::None => ::core::option::Option::None,
+                },
+                #viable,
+            )
+        });
+    }
+}
@@ -0,0 +1,140 @@
+//! Parsing and expansion for `strmulti!`, which finds the first of many
+//! literal keywords in its input.
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Lifetime, Token};
+
+use crate::engine::{coerce_input, hidden};
+use crate::pattern::Capture;
+
+/// `input { "keyword" | "keyword" => body, .., _ => body }`
+pub(crate) struct MultiInput {
+    input: Expr,
+    arms: Vec<(Vec<Capture>, Expr)>,
+    default: Expr,
+}
+
+impl Parse for MultiInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = Expr::parse_without_eager_brace(input)?;
+        let inner;
+        let _ = syn::braced!(inner in input);
+        let mut arms = vec![];
+        let mut seen = vec![];
+        loop {
+            if inner.peek(Token![_]) {
+                inner.parse::<Token![_]>()?;
+                inner.parse::<Token![=>]>()?;
+                let default = inner.parse()?;
+                let _ = inner.parse::<Option<Token![,]>>()?;
+                if !inner.is_empty() {
+                    return Err(
+                        inner.error("arms after a `_` arm can never match")
+                    );
+                }
+                return Ok(MultiInput {
+                    input: expr,
+                    arms,
+                    default,
+                });
+            }
+            if inner.is_empty() {
+                return Err(inner.error(
+                    "expected a `_` arm for when none of the keywords occur",
+                ));
+            }
+            let mut keywords = vec![];
+            loop {
+                let keyword = inner.parse::<Capture>()?;
+                match keyword.literal_bytes() {
+                    Some(bytes) if bytes.is_empty() => {
+                        abort!(keyword.span(), "a keyword can't be empty")
+                    }
+                    Some(bytes) if seen.contains(&bytes) => abort!(
+                        keyword.span(),
+                        "this keyword is already in an earlier arm"
+                    ),
+                    Some(bytes) => seen.push(bytes),
+                    None => abort!(
+                        keyword.span(),
+                        "expected a literal keyword";
+                        help = "use `strmatch_match!` to match patterns"
+                    ),
+                }
+                keywords.push(keyword);
+                if !inner.peek(Token![|]) {
+                    break;
+                }
+                inner.parse::<Token![|]>()?;
+            }
+            inner.parse::<Token![=>]>()?;
+            let body = inner.parse::<Expr>()?;
+            // Like in a `match`, block bodies don't need a trailing comma
+            if matches!(body, Expr::Block(_)) {
+                let _ = inner.parse::<Option<Token![,]>>()?;
+            } else {
+                inner.parse::<Token![,]>()?;
+            }
+            arms.push((keywords, body));
+        }
+    }
+}
+
+impl ToTokens for MultiInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let at = hidden("at");
+        let which = hidden("which");
+        let label = Lifetime::new("'__strmatch", Span::mixed_site());
+
+        // Longer keywords go first, so that where several start at the same
+        // place the longest one wins. The match compiles to a dispatch on
+        // the first byte, then the next, and so on.
+        let mut keywords = self
+            .arms
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (keywords, _))| keywords.iter().map(move |k| (i, k)))
+            .collect::<Vec<_>>();
+        keywords.sort_by_key(|(_, keyword)| {
+            std::cmp::Reverse(keyword.literal_bytes().map_or(0, |b| b.len()))
+        });
+        let cases = keywords.iter().map(|(i, keyword)| {
+            quote! {
+                [#keyword ..] => break #label ::core::option::Option::Some(#i),
+            }
+        });
+        let bodies = self.arms.iter().enumerate().map(
+            |(i, (_, body))| quote!(::core::option::Option::Some(#i) => #body,),
+        );
+        let default = &self.default;
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    #[allow(unreachable_code)]
+                    let #which: ::core::option::Option<usize> = #label: {
+                        let mut #at = 0;
+                        while #at < #input.len() {
+                            match &#input[#at..] {
+                                #(#cases)*
+                                _ => {}
+                            }
+                            #at += 1;
+                        }
+                        ::core::option::Option::None
+                    };
+                    match #which {
+                        #(#bodies)*
+                        _ => #default,
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,64 @@
+//! Parsing and expansion for `strmatch_nom!`, which turns a pattern into a
+//! `nom` parser.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+
+use crate::engine::{captured, hidden, matcher, Codegen};
+use crate::pattern::Pattern;
+
+/// `pattern`, optionally preceded by a `#[codegen(..)]` attribute
+pub(crate) struct NomInput {
+    codegen: Codegen,
+    pattern: Pattern,
+}
+
+impl Parse for NomInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let mut pattern: Pattern = input.parse()?;
+        // A parser takes what it can from the start of the input and leaves
+        // the rest for the next one
+        pattern.capture_rest(hidden("rest"), Span::call_site());
+        Ok(NomInput { codegen, pattern })
+    }
+}
+
+impl ToTokens for NomInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            self.codegen,
+        );
+        let mut bindings = self.pattern.bindings();
+        let rest = bindings.pop();
+        let output = captured(&bindings);
+        let parser = hidden("parser");
+        // A closure passed straight to a function takes its signature from
+        // the function's bounds, which tie what it hands back to the input's
+        // lifetime
+        tokens.extend(quote! {{
+            fn #parser<'a, O, F>(parser: F) -> F
+            where
+                F: ::core::ops::Fn(&'a [u8]) -> ::nom::IResult<&'a [u8], O>,
+            {
+                parser
+            }
+            #parser(|#input| match #matcher {
+                ::core::option::Option::Some((#(#bindings,)* #rest,)) => {
+                    ::core::result::Result::Ok((#rest, #output))
+                }
+                ::core::option::Option::None => ::core::result::Result::Err(
+                    ::nom::Err::Error(::nom::error::Error::new(
+                        #input,
+                        ::nom::error::ErrorKind::Verify,
+                    )),
+                ),
+            })
+        }});
+    }
+}
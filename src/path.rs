@@ -0,0 +1,241 @@
+//! Parsing and expansion for `path_match!`, which matches a path by its
+//! components rather than by its bytes.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    bracketed, parenthesized, token, Expr, Lifetime, LitStr, Token, Type,
+};
+
+use crate::engine::{captured, hidden, matcher, with_helpers, Codegen};
+use crate::pattern::Pattern;
+
+/// What one component of the path has to be
+enum Segment {
+    /// `_`, any component
+    Any,
+    /// `"name"`, a component spelled exactly so
+    Literal(LitStr),
+    /// `[name]` or `[name: Type]`, any component, bound as an `&OsStr` or
+    /// parsed
+    Bind(Ident, Option<Type>),
+    /// `(pattern)`, a component whose bytes match a pattern
+    Pattern(Pattern),
+}
+
+/// `input, segment / segment / ..`, optionally preceded by a
+/// `#[codegen(..)]` attribute
+pub(crate) struct PathInput {
+    codegen: Codegen,
+    input: Expr,
+    /// Whether the path has to start at the root, from a leading `/`
+    root: bool,
+    segments: Vec<Segment>,
+    /// `Some` if a trailing `..` allows more components than were listed,
+    /// holding the binding from `rest @ ..`
+    rest: Option<Option<Ident>>,
+}
+
+impl Parse for PathInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let root = input.parse::<Option<Token![/]>>()?.is_some();
+        let mut segments = vec![];
+        let mut rest = None;
+        while !input.is_empty() {
+            if rest.is_some() {
+                return Err(input.error("`..` has to be the last component"));
+            }
+            let lookahead = input.lookahead1();
+            if lookahead.peek(Token![..]) {
+                input.parse::<Token![..]>()?;
+                rest = Some(None);
+            } else if lookahead.peek(Token![_]) {
+                input.parse::<Token![_]>()?;
+                segments.push(Segment::Any);
+            } else if input.peek(syn::Ident) && input.peek2(Token![@]) {
+                let name = input.parse()?;
+                input.parse::<Token![@]>()?;
+                input.parse::<Token![..]>()?;
+                rest = Some(Some(name));
+            } else if lookahead.peek(LitStr) {
+                let literal = input.parse::<LitStr>()?;
+                let value = literal.value();
+                if value.is_empty() || value.contains(['/', '\\']) {
+                    abort!(
+                        literal,
+                        "a component can't be empty or hold a separator";
+                        help = "write each component on its own, like \
+                                `\"usr\" / \"share\"`"
+                    );
+                }
+                segments.push(Segment::Literal(literal));
+            } else if lookahead.peek(token::Bracket) {
+                let inner;
+                bracketed!(inner in input);
+                let name = inner.parse()?;
+                let ty = match inner.parse::<Option<Token![:]>>()? {
+                    Some(_) => Some(inner.parse()?),
+                    None => None,
+                };
+                if !inner.is_empty() {
+                    return Err(inner.error("expected `]`"));
+                }
+                segments.push(Segment::Bind(name, ty));
+            } else if lookahead.peek(token::Paren) {
+                let inner;
+                parenthesized!(inner in input);
+                segments.push(Segment::Pattern(inner.parse()?));
+            } else {
+                return Err(lookahead.error());
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![/]>()?;
+        }
+        Ok(PathInput {
+            codegen,
+            input: expr,
+            root,
+            segments,
+            rest,
+        })
+    }
+}
+
+impl ToTokens for PathInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        // Component patterns share the helper functions and consts of
+        // `shared_helpers` and `const_array` matchers
+        tokens.extend(with_helpers(|| self.expand()));
+    }
+}
+
+impl PathInput {
+    fn expand(&self) -> TokenStream {
+        let input = hidden("input");
+        let components = hidden("components");
+        let first = hidden("first");
+        let label = Lifetime::new("'__strmatch_path", Span::mixed_site());
+        let result = hidden("result");
+        let component = quote!(::std::path::Component);
+
+        // A Windows prefix like `C:` comes before the root, and a relative
+        // path may start with `.`, which the pattern leaves out
+        let start = if self.root {
+            quote! {
+                let mut #first = #components.next();
+                if let ::core::option::Option::Some(#component::Prefix(_)) = #first {
+                    #first = #components.next();
+                }
+                if !::core::matches!(#first, ::core::option::Option::Some(#component::RootDir)) {
+                    break #label ::core::option::Option::None;
+                }
+            }
+        } else {
+            quote! {
+                if let ::core::option::Option::Some(#component::CurDir) =
+                    #components.clone().next()
+                {
+                    #components.next();
+                }
+            }
+        };
+
+        let mut bindings = vec![];
+        let mut steps = vec![];
+        for (i, segment) in self.segments.iter().enumerate() {
+            let value = hidden(&format!("component{i}"));
+            let check = match segment {
+                Segment::Any => quote!(),
+                // Compared as an `OsStr`, the way the platform spells it
+                Segment::Literal(literal) => quote! {
+                    if #value != #literal {
+                        break #label ::core::option::Option::None;
+                    }
+                },
+                Segment::Bind(name, None) => {
+                    bindings.push(name.clone());
+                    quote!(let #name: &::std::ffi::OsStr = #value;)
+                }
+                Segment::Bind(name, Some(ty)) => {
+                    bindings.push(name.clone());
+                    quote! {
+                        let ::core::option::Option::Some(#name) = #value
+                            .to_str()
+                            .and_then(|text| <#ty as ::core::str::FromStr>::from_str(text).ok())
+                        else {
+                            break #label ::core::option::Option::None;
+                        };
+                    }
+                }
+                Segment::Pattern(pattern) => {
+                    let names = pattern.bindings();
+                    bindings.extend(names.iter().cloned());
+                    let bytes = hidden(&format!("bytes{i}"));
+                    let matcher = matcher(
+                        &bytes,
+                        std::slice::from_ref(pattern),
+                        None,
+                        self.codegen,
+                    );
+                    quote! {
+                        let #bytes: &[u8] = #value.as_encoded_bytes();
+                        let ::core::option::Option::Some((#(#names,)*)) = (#matcher) else {
+                            break #label ::core::option::Option::None;
+                        };
+                    }
+                }
+            };
+            steps.push(quote! {
+                let ::core::option::Option::Some(#value) = #components.next() else {
+                    break #label ::core::option::Option::None;
+                };
+                let #value: &::std::ffi::OsStr = #value.as_os_str();
+                #check
+            });
+        }
+        let end = match &self.rest {
+            None => quote! {
+                if #components.next().is_some() {
+                    break #label ::core::option::Option::None;
+                }
+            },
+            Some(None) => quote!(),
+            Some(Some(name)) => {
+                bindings.push(name.clone());
+                quote!(let #name: &::std::path::Path = #components.as_path();)
+            }
+        };
+        let captures = captured(&bindings);
+
+        let expr = &self.input;
+        // Spanned like `coerce_input`, so a path of the wrong type is
+        // pointed out
+        let scrutinee = Ident::new(
+            &input.to_string(),
+            Span::mixed_site().located_at(syn::spanned::Spanned::span(expr)),
+        );
+        quote! {
+            match #expr {
+                #input => {
+                    let mut #components =
+                        ::std::path::Path::new(#scrutinee).components();
+                    #[allow(unreachable_code, irrefutable_let_patterns)]
+                    let #result = #label: {
+                        #start
+                        #(#steps)*
+                        #end
+                        ::core::option::Option::Some(#captures)
+                    };
+                    #result
+                }
+            }
+        }
+    }
+}
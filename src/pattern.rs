@@ -0,0 +1,2151 @@
+use proc_macro2::{Delimiter, Literal, Span, TokenTree};
+use proc_macro_error::abort;
+use quote::TokenStreamExt;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{bracketed, parenthesized, token};
+use syn::{
+    parse::Parse, Expr, Ident, LitByte, LitByteStr, LitChar, LitInt, LitStr,
+    Token, Type,
+};
+
+use crate::presets::{OutputKind, Preset, PresetArg};
+use crate::unicode::Category;
+
+pub(crate) struct Pattern {
+    /// `name` in `name @ (pattern)`, bound to everything the pattern matched
+    pub(crate) whole: Option<Ident>,
+    /// `N` in a leading `min_len(N);`, the fewest bytes the input can have
+    pub(crate) min_len: Option<(usize, Span)>,
+    pub(crate) literals: Vec<Capture>,
+    pub(crate) end: Option<SliceCapture>,
+}
+
+impl Pattern {
+    /// The names this pattern binds, in order.
+    pub(crate) fn bindings(&self) -> Vec<Ident> {
+        let mut bindings = self
+            .whole
+            .iter()
+            .cloned()
+            .chain(self.literals.iter().flat_map(Capture::bindings))
+            .collect::<Vec<_>>();
+        if let Some(SliceCapture {
+            name: Some(ident), ..
+        }) = &self.end
+        {
+            bindings.push(ident.clone());
+        }
+        bindings
+    }
+
+    /// Have the pattern match the start of the input rather than all of it,
+    /// by ending it in a capture of whatever is left, which comes after its
+    /// own end capture if it has one.
+    pub(crate) fn capture_rest(&mut self, rest: Ident, span: Span) {
+        if let Some(end) = self.end.take() {
+            self.literals.push(Capture::Slice(end));
+        }
+        self.end = Some(SliceCapture::dots(Some(rest), span));
+    }
+
+    /// Check that the pattern can be as long as its `min_len`, if it has
+    /// one, and give the number of bytes it needs past its fixed-width
+    /// start to get there.
+    pub(crate) fn check_min_len(&self) -> usize {
+        let Some((min_len, span)) = self.min_len else {
+            return 0;
+        };
+        let widths = self.literals.iter().map(Capture::width);
+        let width = widths.clone().flatten().sum::<usize>();
+        let fixed = widths.into_iter().all(|width| width.is_some());
+        if fixed && self.end.is_none() && width < min_len {
+            abort!(
+                span,
+                "this pattern always matches exactly {} bytes, which is less \
+                 than its `min_len({})`",
+                width,
+                min_len
+            );
+        }
+        min_len.saturating_sub(width)
+    }
+
+    /// Whether any capture in this pattern has a guard of its own.
+    pub(crate) fn has_guards(&self) -> bool {
+        self.literals
+            .iter()
+            .any(|capture| matches!(capture, Capture::Guarded { .. }))
+    }
+
+    /// Reject `str` and `char` literals, for macros matching binary data.
+    pub(crate) fn check_binary(&self) {
+        self.literals.iter().for_each(Capture::check_binary);
+    }
+}
+
+// Parse the guard after a bare capture. It ends before the next top-level
+// bracketed capture, so that `d if d > b'5' [rest]` isn't read as indexing.
+fn parse_guard(input: syn::parse::ParseStream) -> syn::Result<Expr> {
+    let upto_bracket = input.fork().step(|cursor| {
+        let mut tokens = proc_macro2::TokenStream::new();
+        let mut rest = *cursor;
+        while let Some((tt, next)) = rest.token_tree() {
+            match &tt {
+                TokenTree::Group(group)
+                    if group.delimiter() == Delimiter::Bracket =>
+                {
+                    break
+                }
+                _ => tokens.append(tt),
+            }
+            rest = next;
+        }
+        Ok((tokens, rest))
+    })?;
+    let total = upto_bracket.clone().into_iter().count();
+    // The guard is as much of that as parses as an expression
+    let (guard, left) = (|tokens: syn::parse::ParseStream| {
+        let guard = tokens.parse::<Expr>()?;
+        let left = tokens.parse::<proc_macro2::TokenStream>()?;
+        Ok((guard, left.into_iter().count()))
+    })
+    .parse2(upto_bracket)?;
+    input.step(|cursor| {
+        let mut rest = *cursor;
+        for _ in 0..total - left {
+            rest = rest.token_tree().map(|(_, next)| next).unwrap_or(rest);
+        }
+        Ok(((), rest))
+    })?;
+    Ok(guard)
+}
+
+// `min_len(N);` at the start of a pattern. The `;` tells it apart from a
+// byte bound to `min_len` followed by a group.
+fn parse_min_len(
+    input: syn::parse::ParseStream,
+) -> syn::Result<Option<(usize, Span)>> {
+    let is_min_len = input.cursor().ident().is_some_and(|(ident, rest)| {
+        ident == "min_len"
+            && rest
+                .group(Delimiter::Parenthesis)
+                .is_some_and(|(_, _, rest)| {
+                    rest.punct().is_some_and(|(semi, _)| semi.as_char() == ';')
+                })
+    });
+    if !is_min_len {
+        return Ok(None);
+    }
+    let keyword = input.parse::<Ident>()?;
+    let inner;
+    parenthesized!(inner in input);
+    let len = inner.parse::<LitInt>()?;
+    input.parse::<Token![;]>()?;
+    match len.base10_parse::<usize>() {
+        Ok(min_len) => Ok(Some((min_len, keyword.span()))),
+        Err(e) => abort!(len.span(), e),
+    }
+}
+
+/// `pattern | pattern | ..`, complete patterns any of which can match, as
+/// `strmatch!` takes them. The `|`s are the ones outside brackets and
+/// parentheses that aren't part of `||` or `|=`.
+pub(crate) struct Alternatives(pub(crate) Vec<Pattern>);
+
+impl Parse for Alternatives {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        // Each alternative's tokens, and the `|` after it
+        let mut splits = vec![];
+        let mut tokens = proc_macro2::TokenStream::new();
+        let mut joined = false;
+        for token in input.parse::<proc_macro2::TokenStream>()? {
+            if let TokenTree::Punct(punct) = &token {
+                let alone = punct.spacing() == proc_macro2::Spacing::Alone;
+                if punct.as_char() == '|' && alone && !joined {
+                    splits.push((std::mem::take(&mut tokens), punct.span()));
+                    continue;
+                }
+                joined = !alone;
+            } else {
+                joined = false;
+            }
+            tokens.append(token);
+        }
+        if splits.is_empty() {
+            return Ok(Alternatives(vec![syn::parse2(tokens)?]));
+        }
+        let last = splits.last().map(|(_, bar)| *bar);
+        splits.extend(last.map(|bar| (tokens, bar)));
+        splits
+            .into_iter()
+            .map(|(tokens, bar)| {
+                if tokens.is_empty() {
+                    abort!(bar, "expected a pattern on both sides of `|`");
+                }
+                syn::parse2(tokens)
+            })
+            .collect::<syn::Result<_>>()
+            .map(Alternatives)
+    }
+}
+
+impl Parse for Pattern {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let min_len = parse_min_len(input)?;
+        if min_len.is_some() {
+            let mut pattern = input.parse::<Pattern>()?;
+            if let Some((_, span)) = pattern.min_len {
+                abort!(span, "the pattern already has a `min_len`");
+            }
+            pattern.min_len = min_len;
+            return Ok(pattern);
+        }
+        if input.peek(Ident)
+            && input.peek2(Token![@])
+            && input.peek3(token::Paren)
+            && !is_group(input, 2)
+        {
+            // `name @ (pattern)` also binds everything the pattern matched
+            let whole = input.parse::<Ident>()?;
+            input.parse::<Token![@]>()?;
+            let inner;
+            parenthesized!(inner in input);
+            let mut pattern = inner.parse::<Pattern>()?;
+            if let Some(inner) = &pattern.whole {
+                abort!(
+                    inner,
+                    "the whole pattern is already bound to `{}`",
+                    whole
+                );
+            }
+            if !input.is_empty() {
+                abort!(
+                    input.span(),
+                    "`{} @ (..)` has to wrap the whole pattern", whole;
+                    help = "move the rest of the pattern into the parentheses"
+                );
+            }
+            pattern.whole = Some(whole);
+            return Ok(pattern);
+        }
+        let mut literals = vec![];
+        while !input.is_empty() {
+            let capture = input.parse::<Capture>()?;
+            if !input.peek(Token![if]) {
+                literals.push(capture);
+                continue;
+            }
+            let if_token = input.parse::<Token![if]>()?;
+            if capture.bindings().is_empty() {
+                abort!(
+                    if_token.span,
+                    "only captures that bind a name can have a guard"
+                );
+            }
+            literals.push(Capture::Guarded {
+                capture: Box::new(capture),
+                guard: Box::new(parse_guard(input)?),
+            });
+        }
+        // A bracketed capture at the very end grabs everything left
+        let end = match literals.pop() {
+            Some(Capture::Slice(end)) if end.is_plain() => Some(end),
+            Some(other) => {
+                literals.push(other);
+                None
+            }
+            None => None,
+        };
+        Ok(Pattern {
+            whole: None,
+            min_len: None,
+            literals,
+            end,
+        })
+    }
+}
+
+/// `SliceCapture` is a bracketed capture that grabs a run of bytes, bound to
+/// a name or ignored with `_` (`name @ ..` and `..` are short for `[name]`
+/// and `[_]`). As the last capture it grabs all remaining characters, as in
+/// [, , , end_capture @ ..] or [, , , ..]. Anywhere else only the matcher
+/// macros can work out where it ends: a `greedy` capture
+/// (the default) takes as much as it can while letting the rest of the
+/// pattern match, and a `lazy` capture as little. An `until` capture stops
+/// right before the first occurrence of its delimiter instead. A capture
+/// with a type, as in `[n: u32]`, only matches if the bytes are UTF-8 that
+/// parse into that type, and binds the parsed value. Float and signed
+/// integer captures take the whole number at their start instead of trying
+/// every length, and a `saturating` integer capture, as in
+/// `[n: i32 saturating]`, clamps a number that overflows to the type's
+/// `MIN` or `MAX` rather than failing to match. A `trim` capture, as in
+/// `[value trim]`, leaves out ASCII whitespace around what it matched, before
+/// any parsing. A pipeline, as in `[n | trim | parse::<u32>]`, runs the
+/// bytes through each of its `Step`s in turn, and only matches if they all
+/// succeed. A guard, as in
+/// `[rest if rest.len() > 2]`, has to hold for the capture to match.
+pub(crate) struct SliceCapture {
+    pub(crate) bracket: token::Bracket,
+    pub(crate) name: Option<Ident>,
+    pub(crate) ty: Option<Box<Type>>,
+    pub(crate) lazy: bool,
+    pub(crate) until: Option<Vec<u8>>,
+    pub(crate) saturating: bool,
+    pub(crate) trim: bool,
+    pub(crate) steps: Vec<Step>,
+    pub(crate) guard: Option<Box<Expr>>,
+}
+
+/// A step of a capture's pipeline, after a `|`
+pub(crate) enum Step {
+    /// `trim`, which leaves out the ASCII whitespace around the bytes
+    Trim,
+    /// `utf8`, which turns the bytes into a `&str` if they're UTF-8
+    Utf8,
+    /// `bstr`, which turns the bytes into a `&bstr::BStr`, so they print as
+    /// text. Needs the `bstr` feature.
+    BStr,
+    /// `parse::<T>`, which parses UTF-8 bytes into a `T` with `FromStr`
+    Parse(Box<Type>),
+    /// Any other function, which fails the step by returning `None` or an
+    /// `Err`
+    Call(Box<syn::ExprPath>),
+}
+
+impl Parse for Step {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let call = input.parse::<syn::ExprPath>()?;
+        let path = &call.path;
+        if call.qself.is_some() || path.leading_colon.is_some() {
+            return Ok(Step::Call(Box::new(call)));
+        }
+        match path.segments.iter().collect::<Vec<_>>().as_slice() {
+            [step] if step.arguments.is_empty() && step.ident == "trim" => {
+                Ok(Step::Trim)
+            }
+            [step] if step.arguments.is_empty() && step.ident == "utf8" => {
+                Ok(Step::Utf8)
+            }
+            [step] if step.arguments.is_empty() && step.ident == "bstr" => {
+                Ok(bstr(&step.ident))
+            }
+            [step] if step.ident == "parse" => match &step.arguments {
+                syn::PathArguments::AngleBracketed(args)
+                    if args.args.len() == 1 =>
+                {
+                    match &args.args[0] {
+                        syn::GenericArgument::Type(ty) => {
+                            Ok(Step::Parse(Box::new(ty.clone())))
+                        }
+                        _ => abort!(args, "expected a type to parse into"),
+                    }
+                }
+                _ => abort!(
+                    step.ident,
+                    "`parse` needs the type to parse into, as in \
+                     `parse::<u32>`"
+                ),
+            },
+            _ => Ok(Step::Call(Box::new(call))),
+        }
+    }
+}
+
+// The `bstr` step, if the feature it needs is on
+fn bstr(ident: &Ident) -> Step {
+    if !cfg!(feature = "bstr") {
+        abort!(
+            ident,
+            "`bstr` needs the `bstr` feature of strmatch";
+            help = "the generated code uses `bstr::BStr`, so the crate using \
+                    it needs `bstr` as a dependency"
+        );
+    }
+    Step::BStr
+}
+
+impl SliceCapture {
+    /// A `[name]` or `[_]` capture, as spelled by `name @ ..` or `..`.
+    fn dots(name: Option<Ident>, span: proc_macro2::Span) -> SliceCapture {
+        SliceCapture {
+            bracket: token::Bracket(span),
+            name,
+            ty: None,
+            lazy: false,
+            until: None,
+            saturating: false,
+            trim: false,
+            steps: vec![],
+            guard: None,
+        }
+    }
+
+    /// Whether a slice pattern can express this capture when it's the last
+    /// one, as `name @ ..`.
+    pub(crate) fn is_plain(&self) -> bool {
+        self.ty.is_none()
+            && self.until.is_none()
+            && !self.trim
+            && self.steps.is_empty()
+            && self.guard.is_none()
+    }
+
+    /// Whether this is a float capture, `[x: f32]` or `[x: f64]`, which
+    /// takes the whole number at the start of its input rather than trying
+    /// every length.
+    pub(crate) fn is_float(&self) -> bool {
+        matches!(self.ty_name().as_deref(), Some("f32" | "f64"))
+    }
+
+    /// Whether this is a signed integer capture, like `[n: i32]`, which
+    /// takes the whole number at the start of its input, sign and all.
+    pub(crate) fn is_signed(&self) -> bool {
+        matches!(
+            self.ty_name().as_deref(),
+            Some("i8" | "i16" | "i32" | "i64" | "i128" | "isize")
+        )
+    }
+
+    // The name of the capture's type, if it's a plain name like `u32`
+    fn ty_name(&self) -> Option<String> {
+        match self.ty.as_deref() {
+            Some(Type::Path(ty)) => {
+                ty.path.get_ident().map(ToString::to_string)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Parse for SliceCapture {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let inner;
+        let bracket = bracketed!(inner in input);
+        let lookahead = inner.lookahead1();
+        let name = if lookahead.peek(Token![_]) {
+            inner.parse::<Token![_]>()?;
+            None
+        } else if lookahead.peek(Ident) {
+            Some(inner.parse::<Ident>()?)
+        } else {
+            return Err(lookahead.error());
+        };
+        let mut ty = if inner.peek(Token![:]) {
+            inner.parse::<Token![:]>()?;
+            Some(Box::new(inner.parse::<Type>()?))
+        } else {
+            None
+        };
+        let mut steps = vec![];
+        // `[rest: utf8]` and `[rest: bstr]` aren't parsed from a string like
+        // other types, but are the `utf8` and `bstr` steps
+        if let Some(Type::Path(path)) = ty.as_deref() {
+            let step = match path.path.get_ident() {
+                _ if path.qself.is_some() => None,
+                Some(ident) if ident == "utf8" => Some(Step::Utf8),
+                Some(ident) if ident == "bstr" => Some(bstr(ident)),
+                _ => None,
+            };
+            if let Some(step) = step {
+                ty = None;
+                steps.push(step);
+            }
+        }
+        let mut lazy = None;
+        let mut until = None;
+        let mut saturating = false;
+        let mut trim = false;
+        let mut guard = None;
+        while !inner.is_empty() {
+            if inner.peek(Token![if]) {
+                inner.parse::<Token![if]>()?;
+                guard = Some(Box::new(inner.parse()?));
+                break;
+            }
+            if inner.peek(Token![|]) {
+                let bar = inner.parse::<Token![|]>()?;
+                if ty.is_some() {
+                    abort!(
+                        bar.span,
+                        "a capture can't have both a type and a pipeline";
+                        help = "parse it as the pipeline's last step, as in \
+                                `| parse::<u32>`"
+                    );
+                }
+                steps.push(inner.parse::<Step>()?);
+                continue;
+            }
+            let modifier = inner.parse::<Ident>()?;
+            match modifier.to_string().as_str() {
+                "lazy" | "greedy" if lazy.is_none() && until.is_none() => {
+                    lazy = Some(modifier == "lazy")
+                }
+                "until" if lazy.is_none() && until.is_none() => {
+                    let delimiter = inner.parse::<Capture>()?;
+                    match delimiter.literal_bytes() {
+                        Some(bytes) if !bytes.is_empty() => until = Some(bytes),
+                        _ => abort!(
+                            delimiter.span(),
+                            "expected a non-empty literal delimiter"
+                        ),
+                    }
+                }
+                "saturating" if !saturating => saturating = true,
+                "trim" if !trim => trim = true,
+                "lazy" | "greedy" | "until" => abort!(
+                    modifier,
+                    "a capture can only have one of `lazy`, `greedy` or \
+                     `until`"
+                ),
+                _ => abort!(
+                    modifier,
+                    "expected `lazy`, `greedy`, `until`, `saturating`, `trim`, \
+                     `|` or `if`"
+                ),
+            }
+        }
+        let capture = SliceCapture {
+            bracket,
+            name,
+            ty,
+            lazy: lazy.unwrap_or(false),
+            until,
+            saturating,
+            trim,
+            steps,
+            guard,
+        };
+        if saturating && !capture.is_signed() {
+            abort!(
+                bracket.span,
+                "only a signed integer capture, like `[n: i32 saturating]`, \
+                 can be `saturating`"
+            );
+        }
+        Ok(capture)
+    }
+}
+
+impl ToTokens for SliceCapture {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let span = self.bracket.span;
+        match &self.name {
+            Some(ident) => {
+                tokens.append_all(quote_spanned!(span=> #ident @ ..,))
+            }
+            None => tokens.append_all(quote_spanned!(span=> ..,)),
+        }
+    }
+}
+
+/// Any capture that does grab and arbitrary number of tokens.
+/// Each of the string-style captures can also have a number of repetitions
+/// provided that dictates how many times the proc-macro includes them,
+/// either as a suffix (`"ab"x2`) or with a star (`"ab" * 2`). The count can
+/// be the length in bytes of another literal, as in `'-' x len("Title")`.
+/// These are possible captures of each type
+/// `ByteStr`:    b"abc"x2 --expands to-> [b'a', b'b', b'c', b'a', b'b', b'c',]
+/// `Byte`:       b'b'x2   --expands to-> [b'b', b'b',]
+///               0x7f     --expands to-> [b'\x7f',] (any integer up to 255)
+///               [0x89, b'P'] --expands to-> [b'\x89', b'P',] (as a `ByteStr`)
+///               include_bytes!("magic.bin") --a `ByteStr` of the file's bytes
+/// `Str`:        "abc!"x2 --expands to-> [b'a', b'b', b'c', b'a', b'b', b'c',]
+///               env!("VERSION") --a `Str` of the variable's value at build time
+/// `Char`:       'c'x2    --expands to-> [b'c', b'c',] (UTF-8 encoded)
+/// `Ident`:      abc      --expands to-> [abc @ _,]
+/// `Underscore`: _        --expands to-> [_,]
+/// `Var`:        #sep     --expands to-> [__strmatch_var0,] if *__strmatch_var0 == sep
+///               #(utf8char) --for a value named like a keyword or preset
+/// `Const`:      {COLON}  --expands to-> [COLON,] (the value of a `u8` const)
+/// `OneOf`:      {"png", "jpg"} --matches any one of the literals
+/// `Utf8Char`:   #utf8char --matches one UTF-8 encoded `char` (1-4 bytes)
+/// `Grapheme`:   #grapheme --matches one extended grapheme cluster
+/// `Whitespace`: #ws      --matches a run of ASCII whitespace (`#ws?` for
+///                          one that can be empty)
+/// `Newline`:    #nl      --matches `\n` or `\r\n` (`#eol` also matches the
+///                          end of the input)
+/// `Run`:        digit+   --matches a run of bytes in a class (`digit*` for
+///                          one that can be empty)
+/// `NoCase`:     nocase("ab") --expands to-> [(97u8 | 65u8), (98u8 | 66u8),]
+/// `Pad`:        pad(' ', WIDTH) --matches WIDTH spaces, however many that is
+///               at runtime (`pad(' ', 8)` is just `' 'x8`)
+/// `Peek`:       peek("a") --checks that the input goes on with "a", without
+///                          matching it
+///               not_ahead("a") --checks that it doesn't
+/// `Preset`:     #ipv4    --matches one of the built-in presets
+///               #kv(k, v) --binds (or checks) the preset's outputs
+///               #base64run(decoded) --decodes what the preset matched
+///               #hexrun(id: [u8; 16]) --decodes into an array
+/// `Slice`:      [name]   --a `SliceCapture` that isn't at the end (or `..`)
+/// `ByteRange`:  b'0'..=b'9' --expands to-> [48u8..=57u8,]
+///               c @ ':'  --expands to-> [c @ 58u8,]
+/// `Guarded`:    d if d.is_ascii_digit() --any capture that binds, with a guard
+/// Only `strmatch_match!` can emit the guard that a `Var` needs (or that a
+/// `Guarded` capture has), or the
+/// scanning code for variable-width captures like `Utf8Char`.
+pub(crate) enum Capture {
+    ByteStr {
+        lit: LitByteStr,
+        reps: usize,
+    },
+    Byte {
+        lit: LitByte,
+        reps: usize,
+    },
+    Str {
+        lit: LitStr,
+        reps: usize,
+    },
+    Char {
+        lit: LitChar,
+        reps: usize,
+    },
+    Ident(Ident),
+    Underscore(Token![_]),
+    Var {
+        pound: Token![#],
+        expr: Box<Expr>,
+    },
+    /// `{PATH}`, the value of a `u8` const. Unlike a bare name, which binds
+    /// the byte, it's compared against.
+    Const {
+        brace: token::Brace,
+        path: syn::Path,
+    },
+    /// `{"png", "jpg", "gif"}`, any one of the literals, as in a shell's
+    /// brace expansion. The first one that lets the rest of the pattern
+    /// match is taken.
+    OneOf {
+        brace: token::Brace,
+        options: Vec<Capture>,
+    },
+    Utf8Char {
+        keyword: Ident,
+        binding: Option<Ident>,
+    },
+    /// `#grapheme`, one extended grapheme cluster, which is what a reader
+    /// would call one character. Needs the `grapheme` feature.
+    Grapheme {
+        keyword: Ident,
+        binding: Option<Ident>,
+    },
+    /// `class+`, like `digit+` or `'a'..='f'+`, for one or more bytes in a
+    /// class, or `class*` for any number of them. It always takes the whole
+    /// run.
+    Run {
+        span: Span,
+        class: Class,
+        optional: bool,
+        binding: Option<Ident>,
+    },
+    /// `peek("abc")`, which checks what comes next without matching it, or
+    /// `not_ahead("abc")`, which checks that it isn't that
+    Peek {
+        keyword: Ident,
+        bytes: Vec<u8>,
+        negated: bool,
+    },
+    /// `nocase("Content-Length")`, a literal whose ASCII letters can be in
+    /// either case
+    NoCase {
+        keyword: Ident,
+        literal: Box<Capture>,
+    },
+    /// `pad(' ', WIDTH)`, the byte repeated a number of times only known at
+    /// runtime. With a literal count, `pad` is just the repeated literal.
+    Pad {
+        keyword: Ident,
+        fill: Box<Capture>,
+        count: Box<Expr>,
+    },
+    /// `#ws` for one or more ASCII whitespace bytes, or `#ws?` for any number
+    /// of them. It always takes the whole run.
+    Whitespace {
+        keyword: Ident,
+        optional: bool,
+        binding: Option<Ident>,
+    },
+    /// `#nl`, a line ending, either `\n` or `\r\n`. `#eol` is also happy with
+    /// the end of the input, for a last line without one.
+    Newline {
+        keyword: Ident,
+        eol: bool,
+    },
+    Preset {
+        name: Ident,
+        preset: Preset,
+        binding: Option<Ident>,
+        args: Vec<PresetArg>,
+    },
+    Slice(SliceCapture),
+    ByteRange {
+        binding: Option<Ident>,
+        start: Box<Capture>,
+        end: Option<Box<Capture>>,
+    },
+    Guarded {
+        capture: Box<Capture>,
+        guard: Box<Expr>,
+    },
+    /// `(pattern)*`, matching the pattern as many times as it can. Its
+    /// captures are collected into `Vec`s, and `count` is bound to how many
+    /// times it matched. The pattern ends in a hidden capture of whatever is
+    /// left after each repetition.
+    Group {
+        paren: token::Paren,
+        count: Option<Ident>,
+        pattern: Box<Pattern>,
+    },
+}
+
+impl Capture {
+    pub(crate) fn span(&self) -> proc_macro2::Span {
+        match self {
+            Capture::ByteStr { lit, .. } => lit.span(),
+            Capture::Byte { lit, .. } => lit.span(),
+            Capture::Str { lit, .. } => lit.span(),
+            Capture::Char { lit, .. } => lit.span(),
+            Capture::Ident(ident) => ident.span(),
+            Capture::Underscore(underscore) => underscore.span,
+            Capture::Var { pound, .. } => pound.span,
+            Capture::Const { brace, .. } | Capture::OneOf { brace, .. } => {
+                brace.span
+            }
+            Capture::Utf8Char { keyword, .. }
+            | Capture::Grapheme { keyword, .. }
+            | Capture::Whitespace { keyword, .. }
+            | Capture::Newline { keyword, .. }
+            | Capture::Peek { keyword, .. }
+            | Capture::Pad { keyword, .. }
+            | Capture::NoCase { keyword, .. } => keyword.span(),
+            Capture::Preset { name, .. } => name.span(),
+            Capture::Run { span, .. } => *span,
+            Capture::Slice(slice) => slice.bracket.span,
+            Capture::ByteRange { start, .. } => start.span(),
+            Capture::Guarded { capture, .. } => capture.span(),
+            Capture::Group { paren, .. } => paren.span,
+        }
+    }
+
+    /// The names this capture binds, in order.
+    pub(crate) fn bindings(&self) -> Vec<Ident> {
+        match self {
+            Capture::Ident(ident) => vec![ident.clone()],
+            Capture::Utf8Char {
+                binding: Some(ident),
+                ..
+            }
+            | Capture::Grapheme {
+                binding: Some(ident),
+                ..
+            }
+            | Capture::Whitespace {
+                binding: Some(ident),
+                ..
+            }
+            | Capture::Run {
+                binding: Some(ident),
+                ..
+            } => vec![ident.clone()],
+            Capture::Preset { binding, args, .. } => binding
+                .iter()
+                .chain(args.iter().filter_map(|arg| match arg {
+                    PresetArg::Bind(ident) => Some(ident),
+                    _ => None,
+                }))
+                .cloned()
+                .collect(),
+            Capture::Slice(SliceCapture {
+                name: Some(ident), ..
+            })
+            | Capture::ByteRange {
+                binding: Some(ident),
+                ..
+            } => vec![ident.clone()],
+            Capture::Guarded { capture, .. } => capture.bindings(),
+            Capture::Group { count, pattern, .. } => {
+                // Leaving out the hidden capture of what's left
+                let mut bindings = pattern.bindings();
+                bindings.pop();
+                count.iter().cloned().chain(bindings).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The bytes a literal capture matches, repetitions included.
+    pub(crate) fn literal_bytes(&self) -> Option<Vec<u8>> {
+        let (bytes, reps) = match self {
+            Capture::ByteStr { lit, reps } => (lit.value(), reps),
+            Capture::Byte { lit, reps } => (vec![lit.value()], reps),
+            Capture::Str { lit, reps } => (lit.value().into_bytes(), reps),
+            Capture::Char { lit, reps } => {
+                (lit.value().to_string().into_bytes(), reps)
+            }
+            _ => return None,
+        };
+        Some(bytes.repeat(*reps))
+    }
+
+    /// Whether this capture can match a varying number of bytes, in which
+    /// case it can't be part of a slice pattern.
+    pub(crate) fn is_variable(&self) -> bool {
+        match self {
+            Capture::Utf8Char { .. }
+            | Capture::Grapheme { .. }
+            | Capture::Whitespace { .. }
+            | Capture::Newline { .. }
+            | Capture::OneOf { .. }
+            | Capture::Run { .. }
+            | Capture::Peek { .. }
+            | Capture::Pad { .. }
+            | Capture::Slice(_)
+            | Capture::Group { .. } => true,
+            Capture::Guarded { capture, .. } => capture.is_variable(),
+            // Binding a preset binds a subslice, which a slice pattern can
+            // only do at its end
+            Capture::Preset {
+                preset,
+                binding,
+                args,
+                ..
+            } => {
+                binding.is_some()
+                    || !args.is_empty()
+                    || preset.elems(Span::call_site()).is_none()
+            }
+            _ => false,
+        }
+    }
+
+    /// How many bytes this capture matches, unless that can vary.
+    pub(crate) fn width(&self) -> Option<usize> {
+        if self.is_variable() {
+            return None;
+        }
+        Some(match self {
+            Capture::Guarded { capture, .. } => return capture.width(),
+            Capture::NoCase { literal, .. } => return literal.width(),
+            // `#hexbyte` is the only preset with a fixed width
+            Capture::Preset { .. } => 2,
+            Capture::Ident(_)
+            | Capture::Underscore(_)
+            | Capture::Var { .. }
+            | Capture::Const { .. }
+            | Capture::ByteRange { .. } => 1,
+            literal => literal.literal_bytes()?.len(),
+        })
+    }
+
+    /// Reject `str` and `char` literals, for macros matching binary data.
+    fn check_binary(&self) {
+        match self {
+            Capture::Str { lit, .. } => abort!(
+                lit,
+                "string literals aren't allowed when matching binary data";
+                help = "use a byte string literal: b{}", lit.token()
+            ),
+            Capture::Char { lit, .. } => abort!(
+                lit,
+                "char literals aren't allowed when matching binary data";
+                help = "use a byte literal: b{}", lit.token()
+            ),
+            Capture::ByteRange { start, end, .. } => {
+                start.check_binary();
+                end.iter().for_each(|end| end.check_binary());
+            }
+            Capture::Guarded { capture, .. } => capture.check_binary(),
+            Capture::Pad { fill, .. } => fill.check_binary(),
+            Capture::NoCase { literal, .. } => literal.check_binary(),
+            Capture::OneOf { options, .. } => {
+                options.iter().for_each(Capture::check_binary)
+            }
+            Capture::Group { pattern, .. } => pattern.check_binary(),
+            _ => {}
+        }
+    }
+}
+
+// Return the number of repetitions from a suffix
+fn process_suffix(suffix: &str) -> Result<usize, String> {
+    if suffix.is_empty() {
+        return Ok(1);
+    }
+    if suffix.starts_with('x') {
+        // We know it starts with x so we can unwrap
+        let (_, rest) = suffix.split_once('x').unwrap();
+        rest.parse::<usize>()
+            .map_err(|_| format!("error parsing {rest} into an integer"))
+    } else {
+        Err("suffix did not start with `x`".into())
+    }
+}
+
+// Return the number of repetitions of a literal, either from its suffix
+// (`"ab"x2`) or from a following `* N` (`"ab" * 2`). Errors abort right
+// away, pointing at the offending token.
+fn parse_reps(
+    input: syn::parse::ParseStream,
+    suffix: &str,
+    span: proc_macro2::Span,
+) -> usize {
+    let reps = match process_suffix(suffix) {
+        Ok(reps) => reps,
+        Err(e) => abort!(span, e),
+    };
+    // `x len(..)`, which can't be a suffix since `len` isn't a number
+    let is_x_len = input.cursor().ident().is_some_and(|(x, rest)| {
+        x == "x"
+            && rest.ident().is_some_and(|(len, rest)| {
+                len == "len" && rest.group(Delimiter::Parenthesis).is_some()
+            })
+    });
+    if is_x_len {
+        let x = input.parse::<Ident>().unwrap();
+        if !suffix.is_empty() {
+            abort!(x, "literal already has a repetition suffix");
+        }
+        return parse_len(input);
+    }
+    if !input.peek(Token![*]) {
+        return reps;
+    }
+    let star = match input.parse::<Token![*]>() {
+        Ok(star) => star,
+        Err(_) => unreachable!(), // we checked with peek
+    };
+    if !suffix.is_empty() {
+        abort!(star.span, "literal already has a repetition suffix");
+    }
+    if input.peek(Ident) && input.peek2(token::Paren) {
+        return parse_len(input);
+    }
+    match input.parse::<LitInt>() {
+        Ok(count) => match count.base10_parse::<usize>() {
+            Ok(reps) => reps,
+            Err(e) => abort!(count.span(), e),
+        },
+        Err(_) => abort!(star.span, "expected a repetition count after `*`"),
+    }
+}
+
+// `len(literal)`, the number of bytes in a literal, as a repetition count
+fn parse_len(input: syn::parse::ParseStream) -> usize {
+    let keyword = input.parse::<Ident>().unwrap();
+    if keyword != "len" {
+        abort!(keyword, "expected a repetition count or `len(..)`");
+    }
+    let literal = (|input: syn::parse::ParseStream| {
+        let inner;
+        let paren = parenthesized!(inner in input);
+        Ok((paren, inner.parse::<Capture>()?, inner.is_empty()))
+    })(input);
+    match literal {
+        Ok((_, literal, true)) if literal.literal_bytes().is_some() => {
+            literal.literal_bytes().unwrap().len()
+        }
+        Ok((paren, ..)) => {
+            abort!(paren.span, "expected a single literal in `len(..)`")
+        }
+        Err(e) => abort!(e.span(), e),
+    }
+}
+
+// Parse the `(arg, ..)` after a preset name: one name, `_` or literal for
+// each of the preset's outputs.
+fn parse_preset_args(
+    input: syn::parse::ParseStream,
+    name: &Ident,
+    preset: Preset,
+) -> syn::Result<Vec<PresetArg>> {
+    let inner;
+    let paren = parenthesized!(inner in input);
+    let outputs = preset.outputs();
+    let mut args = vec![];
+    while !inner.is_empty() {
+        let arg = if inner.peek(Token![_]) {
+            inner.parse::<Token![_]>()?;
+            PresetArg::Skip
+        } else if inner.peek(Ident) {
+            PresetArg::Bind(inner.parse()?)
+        } else {
+            let kind = outputs.get(args.len()).map(|output| output.kind);
+            let (arg, expected, span) = if inner.peek(LitInt) {
+                let lit = inner.parse::<LitInt>()?;
+                let span = lit.span();
+                let expected = matches!(
+                    kind,
+                    Some(OutputKind::U8 | OutputKind::U64) | None
+                );
+                (PresetArg::Value(lit), expected, span)
+            } else {
+                let literal = inner.parse::<Capture>()?;
+                let bytes = match literal.literal_bytes() {
+                    Some(bytes) => bytes,
+                    None => abort!(
+                        literal.span(),
+                        "expected a name, `_` or a literal"
+                    ),
+                };
+                let expected = matches!(kind, Some(OutputKind::Bytes) | None);
+                let span = literal.span();
+                (PresetArg::Bytes(bytes, span), expected, span)
+            };
+            if !expected {
+                let output = &outputs[args.len()];
+                let checked_by = match output.kind {
+                    OutputKind::Bytes => {
+                        "can only be checked against a string or byte literal"
+                    }
+                    OutputKind::U8 | OutputKind::U64 => {
+                        "can only be checked against an integer literal"
+                    }
+                    OutputKind::MaybeBytes => {
+                        "is optional, so it can't be checked against a literal"
+                    }
+                    OutputKind::Array(_) | OutputKind::Vec => {
+                        "is decoded, so it can't be checked against a literal"
+                    }
+                };
+                abort!(
+                    span,
+                    "`#{}`'s {} output {}", name, output.name,
+                    checked_by;
+                    help = "bind it to a name, or ignore it with `_`"
+                );
+            }
+            arg
+        };
+        args.push(arg);
+        if !inner.is_empty() {
+            inner.parse::<Token![,]>()?;
+        }
+    }
+    if args.len() != outputs.len() {
+        let names = outputs.iter().map(|o| o.name).collect::<Vec<_>>();
+        abort!(
+            paren.span,
+            "`#{}` takes {} arguments, got {}", name,
+            outputs.len(), args.len();
+            help = "pass a name, `_` or a literal for each of: {}",
+                names.join(", ")
+        );
+    }
+    Ok(args)
+}
+
+// Parse the rest of a range if the literal `start` is followed by `..=`.
+fn parse_range(
+    input: syn::parse::ParseStream,
+    start: Capture,
+) -> syn::Result<Capture> {
+    if !input.peek(Token![..=]) {
+        return Ok(start);
+    }
+    let dots = input.parse::<Token![..=]>()?;
+    let end = if input.peek(LitByte) {
+        Capture::Byte {
+            lit: input.parse()?,
+            reps: 1,
+        }
+    } else if input.peek(LitInt) {
+        Capture::Byte {
+            lit: int_byte(&input.parse()?),
+            reps: 1,
+        }
+    } else if input.peek(LitChar) {
+        Capture::Char {
+            lit: input.parse()?,
+            reps: 1,
+        }
+    } else {
+        abort!(
+            dots.spans[0],
+            "expected a byte, char or integer literal after `..=`"
+        )
+    };
+    let (lo, hi) = (single_byte(&start), single_byte(&end));
+    if lo > hi {
+        abort!(
+            end.span(),
+            "this range is empty, since it ends before it starts"
+        );
+    }
+    if peek_quantifier(input) {
+        return Ok(Capture::Run {
+            span: start.span(),
+            class: Class::Range(lo, hi),
+            optional: parse_quantifier(input)?,
+            binding: None,
+        });
+    }
+    Ok(Capture::ByteRange {
+        binding: None,
+        start: Box::new(start),
+        end: Some(Box::new(end)),
+    })
+}
+
+// Whether the parenthesized pattern `skip` tokens ahead is a repeated
+// group, `(..)*`, rather than the whole pattern in `name @ (..)`.
+fn is_group(input: syn::parse::ParseStream, skip: usize) -> bool {
+    let mut cursor = input.cursor();
+    for _ in 0..=skip {
+        match cursor.token_tree() {
+            Some((_, rest)) => cursor = rest,
+            None => return false,
+        }
+    }
+    matches!(cursor.punct(), Some((punct, _)) if punct.as_char() == '*')
+}
+
+// Parse `(pattern)*`, whose repetitions are counted into `count`.
+fn parse_group(
+    input: syn::parse::ParseStream,
+    count: Option<Ident>,
+) -> syn::Result<Capture> {
+    let inner;
+    let paren = parenthesized!(inner in input);
+    let mut pattern = inner.parse::<Pattern>()?;
+    if let Some((_, span)) = pattern.min_len {
+        abort!(
+            span,
+            "`min_len` can only go at the start of the whole pattern"
+        );
+    }
+    if !input.peek(Token![*]) {
+        abort!(
+            paren.span,
+            "a parenthesized group has to be repeated, as in `(..)*`"
+        );
+    }
+    input.parse::<Token![*]>()?;
+    if let Some(whole) = &pattern.whole {
+        abort!(
+            whole,
+            "`{} @ (..)` can't be used in a repeated group", whole;
+            help = "bind the group's captures instead"
+        );
+    }
+    // Each repetition carries on from where the last one stopped, so the
+    // group hands back what's left
+    pattern.capture_rest(crate::engine::hidden("left"), paren.span);
+    Ok(Capture::Group {
+        paren,
+        count,
+        pattern: Box::new(pattern),
+    })
+}
+
+// Literal bytes keep the span of the segment they came from, so errors
+// (and IDE hovers) point at that segment rather than the whole macro call.
+fn spanned_byte(byte: u8, span: Span) -> Literal {
+    let mut literal = Literal::u8_suffixed(byte);
+    literal.set_span(span);
+    literal
+}
+
+fn spanned_bytes(bytes: &[u8], span: Span) -> proc_macro2::TokenStream {
+    let bytes = bytes.iter().map(|&byte| spanned_byte(byte, span));
+    quote_spanned!(span=> #(#bytes,)*)
+}
+
+/// The bytes a run like `digit+` is made of.
+#[derive(Clone, Copy)]
+pub(crate) enum Class {
+    /// `digit`: `0` to `9`
+    Digit,
+    /// `alpha`: ASCII letters
+    Alpha,
+    /// `alnum`: ASCII letters and digits
+    Alnum,
+    /// `hex`: ASCII hex digits, either case
+    Hex,
+    /// `upper`: uppercase ASCII letters
+    Upper,
+    /// `lower`: lowercase ASCII letters
+    Lower,
+    /// `punct`: ASCII punctuation
+    Punct,
+    /// A byte range, like `'a'..='f'`
+    Range(u8, u8),
+    /// Any set of bytes, as a bitset of 4 `u64`s, like a regex's `[^",]`
+    Set([u64; 4]),
+    /// A Unicode class, like `unicode_alpha`, which matches whole characters
+    Unicode(Category),
+}
+
+impl Class {
+    fn from_name(name: &Ident) -> Option<Class> {
+        let class = match name.to_string().as_str() {
+            "digit" => Class::Digit,
+            "alpha" => Class::Alpha,
+            "alnum" => Class::Alnum,
+            "hex" => Class::Hex,
+            "upper" => Class::Upper,
+            "lower" => Class::Lower,
+            "punct" => Class::Punct,
+            name => Class::Unicode(Category::from_name(name)?),
+        };
+        Some(class)
+    }
+
+    /// An expression checking whether `byte`, a `&u8`, is in the class.
+    pub(crate) fn contains(self, byte: &Ident) -> proc_macro2::TokenStream {
+        match self {
+            Class::Digit => quote!(#byte.is_ascii_digit()),
+            Class::Alpha => quote!(#byte.is_ascii_alphabetic()),
+            Class::Alnum => quote!(#byte.is_ascii_alphanumeric()),
+            Class::Hex => quote!(#byte.is_ascii_hexdigit()),
+            Class::Upper => quote!(#byte.is_ascii_uppercase()),
+            Class::Lower => quote!(#byte.is_ascii_lowercase()),
+            Class::Punct => quote!(#byte.is_ascii_punctuation()),
+            Class::Range(lo, hi) => quote!((#lo..=#hi).contains(#byte)),
+            Class::Set(set) => quote! {{
+                static CLASS: [u64; 4] = [#(#set),*];
+                (CLASS[usize::from(*#byte / 64)] >> (*#byte % 64)) & 1 == 1
+            }},
+            Class::Unicode(_) => {
+                unreachable!("Unicode classes aren't checked a byte at a time")
+            }
+        }
+    }
+
+    /// The bytes in the class, in order, unless it's a class of Unicode
+    /// characters.
+    pub(crate) fn bytes(self) -> Option<Vec<u8>> {
+        let test: fn(&u8) -> bool = match self {
+            Class::Digit => u8::is_ascii_digit,
+            Class::Alpha => u8::is_ascii_alphabetic,
+            Class::Alnum => u8::is_ascii_alphanumeric,
+            Class::Hex => u8::is_ascii_hexdigit,
+            Class::Upper => u8::is_ascii_uppercase,
+            Class::Lower => u8::is_ascii_lowercase,
+            Class::Punct => u8::is_ascii_punctuation,
+            Class::Range(lo, hi) => return Some((lo..=hi).collect()),
+            Class::Set(set) => {
+                let bytes = (0..=u8::MAX)
+                    .filter(|byte| {
+                        (set[usize::from(byte / 64)] >> (byte % 64)) & 1 == 1
+                    })
+                    .collect();
+                return Some(bytes);
+            }
+            Class::Unicode(_) => return None,
+        };
+        Some((0..=u8::MAX).filter(test).collect())
+    }
+
+    /// The class as a bitset of 4 `u64`s, for classes that aren't a single
+    /// range. Checking a range is one comparison, which beats a lookup.
+    pub(crate) fn bitmap(self) -> Option<[u64; 4]> {
+        let test: fn(&u8) -> bool = match self {
+            Class::Digit
+            | Class::Upper
+            | Class::Lower
+            | Class::Range(..)
+            | Class::Unicode(_) => return None,
+            Class::Set(set) => return Some(set),
+            Class::Alpha => u8::is_ascii_alphabetic,
+            Class::Alnum => u8::is_ascii_alphanumeric,
+            Class::Hex => u8::is_ascii_hexdigit,
+            Class::Punct => u8::is_ascii_punctuation,
+        };
+        let mut set = [0; 4];
+        for byte in (0..=u8::MAX).filter(test) {
+            set[usize::from(byte / 64)] |= 1 << (byte % 64);
+        }
+        Some(set)
+    }
+}
+
+fn peek_quantifier(input: syn::parse::ParseStream) -> bool {
+    input.peek(Token![+]) || input.peek(Token![*])
+}
+
+// `+` or `*` after a class, returning whether the run can be empty
+fn parse_quantifier(input: syn::parse::ParseStream) -> syn::Result<bool> {
+    if input.parse::<Option<Token![*]>>()?.is_some() {
+        return Ok(true);
+    }
+    input.parse::<Token![+]>()?;
+    Ok(false)
+}
+
+// A segment named by a keyword after `#`, like `#utf8char`. Behind the `#`,
+// it can't be mistaken for a byte bound to a name, the way a bare word would.
+// Like presets, keywords take priority over runtime values of the same name,
+// which `#(name)` still reaches.
+fn keyword(
+    input: syn::parse::ParseStream,
+    name: &Ident,
+) -> syn::Result<Option<Capture>> {
+    let capture = match name.to_string().as_str() {
+        "utf8char" => Capture::Utf8Char {
+            keyword: input.parse()?,
+            binding: None,
+        },
+        "grapheme" => grapheme(input.parse()?, None),
+        "ws" => whitespace(input, input.parse()?, None)?,
+        "nl" | "eol" => Capture::Newline {
+            eol: name == "eol",
+            keyword: input.parse()?,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(capture))
+}
+
+// `#grapheme`, if the feature it needs is on
+fn grapheme(keyword: Ident, binding: Option<Ident>) -> Capture {
+    if !cfg!(feature = "grapheme") {
+        abort!(
+            keyword,
+            "`#grapheme` needs the `grapheme` feature of strmatch";
+            help = "the generated code calls `unicode-segmentation`, which the \
+                    crate using it needs as a dependency"
+        );
+    }
+    Capture::Grapheme { keyword, binding }
+}
+
+// `name+` or `name*`, a run of a named class
+fn named_run(
+    input: syn::parse::ParseStream,
+    name: &Ident,
+    binding: Option<Ident>,
+) -> syn::Result<Capture> {
+    let class = match Class::from_name(name) {
+        Some(class) => class,
+        None => abort!(
+            name,
+            "`{}` isn't a class of bytes", name;
+            help = "the classes are `digit`, `alpha`, `alnum`, `hex`, \
+                    `upper`, `lower` and `punct`, or a range like `'a'..='f'`, \
+                    and with the `unicode` feature `unicode_alpha`, \
+                    `unicode_digit`, `unicode_alnum`, `unicode_upper`, \
+                    `unicode_lower` and `unicode_space`"
+        ),
+    };
+    if let Class::Unicode(_) = class {
+        if !cfg!(feature = "unicode") {
+            abort!(
+                name,
+                "`{}` needs the `unicode` feature of strmatch", name;
+                help = "its tables add to the size of the generated code, so \
+                        it's opt-in"
+            );
+        }
+    }
+    Ok(Capture::Run {
+        span: name.span(),
+        class,
+        optional: parse_quantifier(input)?,
+        binding,
+    })
+}
+
+// A preset whose outputs depend on whether it has arguments, like
+// `#base64run` or `#base64run(decoded)`
+fn keyword_preset(
+    input: syn::parse::ParseStream,
+    name: Ident,
+    binding: Option<Ident>,
+) -> syn::Result<Capture> {
+    // Unless it's followed by a repeated group
+    let args = input.peek(token::Paren) && !is_group(input, 0);
+    let mut preset = Preset::from_keyword(&name, args).unwrap();
+    let args = match args {
+        false => vec![],
+        true => match array_arg(input, preset)? {
+            Some((arg, len)) => {
+                preset = Preset::HexRun {
+                    decode: true,
+                    len: Some(len),
+                };
+                vec![arg]
+            }
+            None => parse_preset_args(input, &name, preset)?,
+        },
+    };
+    Ok(Capture::Preset {
+        name,
+        preset,
+        binding,
+        args,
+    })
+}
+
+// `(name: [u8; N])` after `#hexrun`, which decodes into an array of `N`
+// bytes, returning `None` if the arguments are something else
+fn array_arg(
+    input: syn::parse::ParseStream,
+    preset: Preset,
+) -> syn::Result<Option<(PresetArg, usize)>> {
+    let typed = |input: syn::parse::ParseStream| {
+        let inner;
+        parenthesized!(inner in input);
+        Ok((inner.peek(Ident) || inner.peek(Token![_]))
+            && inner.peek2(Token![:]))
+    };
+    if !matches!(preset, Preset::HexRun { .. }) || !typed(&input.fork())? {
+        return Ok(None);
+    }
+    let inner;
+    parenthesized!(inner in input);
+    let arg = match inner.parse::<Option<Token![_]>>()? {
+        Some(_) => PresetArg::Skip,
+        None => PresetArg::Bind(inner.parse()?),
+    };
+    inner.parse::<Token![:]>()?;
+    let ty = inner.parse::<syn::TypeArray>()?;
+    let len = match (&*ty.elem, &ty.len) {
+        (
+            syn::Type::Path(elem),
+            Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(len),
+                ..
+            }),
+        ) if elem.path.is_ident("u8") => len.base10_parse::<usize>()?,
+        _ => abort!(
+            ty,
+            "expected an array of bytes with a literal length, like \
+             `[u8; 32]`"
+        ),
+    };
+    if len == 0 {
+        abort!(ty.len, "the array has to hold at least one byte");
+    }
+    if !inner.is_empty() {
+        return Err(inner.error("expected `)`"));
+    }
+    Ok(Some((arg, len)))
+}
+
+// `peek(literals)` or `not_ahead(literals)`
+fn peek(
+    input: syn::parse::ParseStream,
+    keyword: Ident,
+) -> syn::Result<Capture> {
+    let inner;
+    parenthesized!(inner in input);
+    let mut bytes = vec![];
+    while !inner.is_empty() {
+        let literal = inner.parse::<Capture>()?;
+        match literal.literal_bytes() {
+            Some(literal) => bytes.extend(literal),
+            None => abort!(
+                literal.span(),
+                "`{}` only takes literals", keyword;
+                help = "use a guard to check something more involved"
+            ),
+        }
+    }
+    Ok(Capture::Peek {
+        negated: keyword == "not_ahead",
+        keyword,
+        bytes,
+    })
+}
+
+// `{"png", "jpg"}`, any one of the literals, each of which can have a
+// repetition of its own, as in `{"ab"x2, "c"}`.
+fn one_of(
+    input: syn::parse::ParseStream,
+    brace: token::Brace,
+) -> syn::Result<Capture> {
+    let options = Punctuated::<Capture, Token![,]>::parse_terminated(input)?;
+    for option in &options {
+        if option.literal_bytes().is_none() {
+            abort!(
+                option.span(),
+                "`{..}` can only hold literals, as in `{\"png\", \"jpg\"}`"
+            );
+        }
+    }
+    Ok(Capture::OneOf {
+        brace,
+        options: options.into_iter().collect(),
+    })
+}
+
+// `pad(byte, count)`, the byte repeated `count` times. A literal count (or
+// `len(..)`) makes it the repeated literal, anything else a `Pad`.
+fn pad(input: syn::parse::ParseStream, keyword: Ident) -> syn::Result<Capture> {
+    let inner;
+    parenthesized!(inner in input);
+    let fill = inner.parse::<Capture>()?;
+    if !matches!(fill.literal_bytes(), Some(bytes) if bytes.len() == 1) {
+        abort!(fill.span(), "`pad` takes a single byte to repeat");
+    }
+    inner.parse::<Token![,]>()?;
+    let count = if inner.peek(LitInt) {
+        let count = inner.parse::<LitInt>()?;
+        match count.base10_parse::<usize>() {
+            Ok(count) => Some(count),
+            Err(e) => abort!(count.span(), e),
+        }
+    } else if inner.peek(Ident) && inner.peek2(token::Paren) {
+        Some(parse_len(&inner))
+    } else {
+        None
+    };
+    let Some(count) = count else {
+        return Ok(Capture::Pad {
+            keyword,
+            fill: Box::new(fill),
+            count: Box::new(inner.parse()?),
+        });
+    };
+    Ok(match fill {
+        Capture::ByteStr { lit, .. } => Capture::ByteStr { lit, reps: count },
+        Capture::Byte { lit, .. } => Capture::Byte { lit, reps: count },
+        Capture::Str { lit, .. } => Capture::Str { lit, reps: count },
+        Capture::Char { lit, .. } => Capture::Char { lit, reps: count },
+        _ => unreachable!("only literals have literal bytes"),
+    })
+}
+
+// `nocase(literal)`, for a literal in any mix of ASCII case
+fn nocase(
+    input: syn::parse::ParseStream,
+    keyword: Ident,
+) -> syn::Result<Capture> {
+    let inner;
+    let paren = parenthesized!(inner in input);
+    let literal = inner.parse::<Capture>()?;
+    if literal.literal_bytes().is_none() || !inner.is_empty() {
+        abort!(paren.span, "`nocase` takes a single literal");
+    }
+    Ok(Capture::NoCase {
+        keyword,
+        literal: Box::new(literal),
+    })
+}
+
+// `#ws`, and `#ws?` for a run that can be empty
+fn whitespace(
+    input: syn::parse::ParseStream,
+    keyword: Ident,
+    binding: Option<Ident>,
+) -> syn::Result<Capture> {
+    let optional = input.parse::<Option<Token![?]>>()?.is_some();
+    Ok(Capture::Whitespace {
+        keyword,
+        optional,
+        binding,
+    })
+}
+
+// `include_bytes!("path")`, which matches the contents of a file, or
+// `include_bytes!("path")[..16]` for only part of them. Like the macro it
+// borrows its name from, the path is relative to the file it's in.
+fn include_bytes(input: syn::parse::ParseStream) -> syn::Result<Capture> {
+    input.parse::<Token![!]>()?;
+    let inner;
+    parenthesized!(inner in input);
+    let path = inner.parse::<LitStr>()?;
+    inner.parse::<Option<Token![,]>>()?;
+    let file = match proc_macro::Span::call_site().local_file() {
+        Some(source) => source.with_file_name(path.value()),
+        // Outside of a file on disk, fall back to the crate's root
+        None => std::path::Path::new(
+            &std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default(),
+        )
+        .join(path.value()),
+    };
+    let mut bytes = match std::fs::read(&file) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            abort!(path.span(), "couldn't read `{}`: {}", file.display(), e)
+        }
+    };
+    if is_bounds(input) {
+        let inner;
+        let bracket = bracketed!(inner in input);
+        let bound = |lit: Option<LitInt>, default| match lit {
+            Some(lit) => lit.base10_parse::<usize>(),
+            None => Ok(default),
+        };
+        let start = bound(inner.parse()?, 0)?;
+        inner.parse::<Token![..]>()?;
+        let end = bound(inner.parse()?, bytes.len())?;
+        if start > end || end > bytes.len() {
+            abort!(
+                bracket.span,
+                "`{}..{}` is out of bounds for `{}`, which is {} bytes long",
+                start,
+                end,
+                path.value(),
+                bytes.len()
+            );
+        }
+        bytes = bytes[start..end].to_vec();
+    }
+    let reps = parse_reps(input, "", path.span());
+    Ok(Capture::ByteStr {
+        lit: LitByteStr::new(&bytes, path.span()),
+        reps,
+    })
+}
+
+// `env!("NAME")`, which matches the value the environment variable had at
+// build time, or `option_env!("NAME").unwrap_or("fallback")` for one that
+// might not be set
+fn env(
+    input: syn::parse::ParseStream,
+    keyword: &Ident,
+) -> syn::Result<Capture> {
+    input.parse::<Token![!]>()?;
+    let inner;
+    parenthesized!(inner in input);
+    let name = inner.parse::<LitStr>()?;
+    inner.parse::<Option<Token![,]>>()?;
+    let fallback = if keyword == "option_env" {
+        let fork = input.fork();
+        let unwrap_or = fork.parse::<Token![.]>().is_ok()
+            && fork
+                .parse::<Ident>()
+                .is_ok_and(|method| method == "unwrap_or");
+        if !unwrap_or {
+            abort!(
+                name.span(),
+                "`option_env!` needs a fallback for when `{}` isn't set",
+                name.value();
+                help = "add one with `.unwrap_or(\"...\")`, or use `env!`"
+            );
+        }
+        input.parse::<Token![.]>()?;
+        input.parse::<Ident>()?;
+        let inner;
+        parenthesized!(inner in input);
+        Some(inner.parse::<LitStr>()?.value())
+    } else {
+        None
+    };
+    let value = match (std::env::var(name.value()), fallback) {
+        (Ok(value), _) => value,
+        (Err(_), Some(fallback)) => fallback,
+        (Err(e), None) => abort!(
+            name.span(),
+            "couldn't read environment variable `{}`: {}",
+            name.value(),
+            e
+        ),
+    };
+    let reps = parse_reps(input, "", name.span());
+    Ok(Capture::Str {
+        lit: LitStr::new(&value, name.span()),
+        reps,
+    })
+}
+
+// Whether the bracketed tokens next are a range like `[..16]` or `[4..]`
+fn is_bounds(input: syn::parse::ParseStream) -> bool {
+    let Some((inner, _, _)) = input.cursor().group(Delimiter::Bracket) else {
+        return false;
+    };
+    let inner = match inner.literal() {
+        Some((_, rest)) => rest,
+        None => inner,
+    };
+    matches!(inner.punct(), Some((dot, _)) if dot.as_char() == '.')
+}
+
+// Whether the bracketed tokens next are an array of bytes, like
+// `[0x89, b'P']`, rather than a `SliceCapture`, which starts with a name
+fn is_byte_array(input: syn::parse::ParseStream) -> bool {
+    match input.cursor().group(Delimiter::Bracket) {
+        Some((inner, _, _)) => inner.literal().is_some(),
+        None => false,
+    }
+}
+
+// `[0x89, b'P', b'N', b'G']`, which matches its bytes in order, like a
+// byte string literal spanning the brackets
+fn byte_array(input: syn::parse::ParseStream) -> syn::Result<Capture> {
+    let inner;
+    let bracket = bracketed!(inner in input);
+    let elements = Punctuated::<Capture, Token![,]>::parse_terminated(&inner)?;
+    let bytes = elements.iter().map(single_byte).collect::<Vec<_>>();
+    let reps = parse_reps(input, "", bracket.span);
+    Ok(Capture::ByteStr {
+        lit: LitByteStr::new(&bytes, bracket.span),
+        reps,
+    })
+}
+
+// The byte an integer literal like `0x7f` stands for, as a byte literal with
+// the same span. Anything over 255 is an error.
+fn int_byte(lit: &LitInt) -> LitByte {
+    match lit.base10_parse::<u8>() {
+        Ok(byte) => {
+            // `LitByte::new` writes the byte as `127u8`, which it can't read
+            // back, so spell it as a byte literal
+            let literal = format!("b'\\x{byte:02x}'");
+            let mut byte = syn::parse_str::<LitByte>(&literal)
+                .expect("a `\\x` escape is a valid byte literal");
+            byte.set_span(lit.span());
+            byte
+        }
+        Err(_) => abort!(
+            lit.span(),
+            "integer literal `{}` doesn't fit in a byte", lit;
+            help = "integer segments stand for a single byte, from 0 to 255"
+        ),
+    }
+}
+
+// The byte a literal in a range (or bound with `@`) stands for.
+fn single_byte(literal: &Capture) -> u8 {
+    match literal.literal_bytes().as_deref() {
+        Some([byte]) => *byte,
+        Some(bytes) => abort!(
+            literal.span(),
+            "expected a single byte, but this literal is {} bytes",
+            bytes.len()
+        ),
+        None => abort!(literal.span(), "expected a literal"),
+    }
+}
+
+impl Parse for Capture {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(token::Bracket) && is_byte_array(input) {
+            byte_array(input)
+        } else if lookahead.peek(token::Bracket) {
+            let mut slice = input.parse::<SliceCapture>()?;
+            Ok(match slice.guard.take() {
+                Some(guard) => Capture::Guarded {
+                    capture: Box::new(Capture::Slice(slice)),
+                    guard,
+                },
+                None => Capture::Slice(slice),
+            })
+        } else if lookahead.peek(token::Brace) {
+            let inner;
+            let brace = syn::braced!(inner in input);
+            if inner.peek(syn::Lit) {
+                return one_of(&inner, brace);
+            }
+            match inner.parse::<syn::Path>() {
+                Ok(path) if inner.is_empty() => {
+                    Ok(Capture::Const { brace, path })
+                }
+                _ => abort!(
+                    brace.span,
+                    "expected the name of a `u8` const, or literals separated \
+                     by commas, in `{..}`"
+                ),
+            }
+        } else if lookahead.peek(token::Paren) {
+            parse_group(input, None)
+        } else if lookahead.peek(Token![..]) {
+            // `..` is short for `[_]`
+            let dots = input.parse::<Token![..]>()?;
+            Ok(Capture::Slice(SliceCapture::dots(None, dots.spans[0])))
+        } else if lookahead.peek(Ident) {
+            let ident = input.parse::<Ident>()?;
+            if ident == "include_bytes" && input.peek(Token![!]) {
+                return include_bytes(input);
+            }
+            if (ident == "env" || ident == "option_env")
+                && input.peek(Token![!])
+            {
+                return env(input, &ident);
+            }
+            if peek_quantifier(input) {
+                return named_run(input, &ident, None);
+            }
+            if ident == "pad" && input.peek(token::Paren) && !is_group(input, 0)
+            {
+                return pad(input, ident);
+            }
+            if ident == "nocase"
+                && input.peek(token::Paren)
+                && !is_group(input, 0)
+            {
+                return nocase(input, ident);
+            }
+            // Unless it's a byte followed by a repeated group
+            if (ident == "peek" || ident == "not_ahead")
+                && input.peek(token::Paren)
+                && !is_group(input, 0)
+            {
+                return peek(input, ident);
+            }
+            if !input.peek(Token![@]) {
+                return Ok(Capture::Ident(ident));
+            }
+            let at = input.parse::<Token![@]>()?;
+            if input.peek(Token![..]) {
+                // `name @ ..` is short for `[name]`
+                let dots = input.parse::<Token![..]>()?;
+                let slice = SliceCapture::dots(Some(ident), dots.spans[0]);
+                return Ok(Capture::Slice(slice));
+            }
+            if input.peek(LitByte)
+                || input.peek(LitInt)
+                || input.peek(LitChar)
+                || input.peek(LitStr)
+                || input.peek(LitByteStr)
+            {
+                return match input.parse::<Capture>()? {
+                    Capture::Run {
+                        span,
+                        class,
+                        optional,
+                        ..
+                    } => Ok(Capture::Run {
+                        span,
+                        class,
+                        optional,
+                        binding: Some(ident),
+                    }),
+                    Capture::ByteRange { start, end, .. } => {
+                        Ok(Capture::ByteRange {
+                            binding: Some(ident),
+                            start,
+                            end,
+                        })
+                    }
+                    literal => {
+                        single_byte(&literal);
+                        Ok(Capture::ByteRange {
+                            binding: Some(ident),
+                            start: Box::new(literal),
+                            end: None,
+                        })
+                    }
+                };
+            }
+            if input.peek(token::Paren) {
+                if is_group(input, 0) {
+                    return parse_group(input, Some(ident));
+                }
+                abort!(
+                    at.span,
+                    "`{} @ (..)` can only wrap a whole pattern",
+                    ident
+                );
+            }
+            if input.peek(Token![#]) {
+                return match input.parse::<Capture>()? {
+                    Capture::Preset {
+                        name, preset, args, ..
+                    } => Ok(Capture::Preset {
+                        name,
+                        preset,
+                        binding: Some(ident),
+                        args,
+                    }),
+                    Capture::Utf8Char { keyword, .. } => {
+                        Ok(Capture::Utf8Char {
+                            keyword,
+                            binding: Some(ident),
+                        })
+                    }
+                    Capture::Grapheme { keyword, .. } => {
+                        Ok(grapheme(keyword, Some(ident)))
+                    }
+                    Capture::Whitespace {
+                        keyword, optional, ..
+                    } => Ok(Capture::Whitespace {
+                        keyword,
+                        optional,
+                        binding: Some(ident),
+                    }),
+                    other => {
+                        abort!(
+                            other.span(),
+                            "expected a preset, or a segment like `#utf8char`, \
+                             after `@`"
+                        )
+                    }
+                };
+            }
+            match input.parse::<Ident>() {
+                Ok(class) if peek_quantifier(input) => {
+                    named_run(input, &class, Some(ident))
+                }
+                _ => abort!(
+                    at.span,
+                    "expected `..`, a literal, a range, `#utf8char`, `#grapheme`, `#ws`, a run \
+                     like `digit+` or `digit*`, or a preset after `@`"
+                ),
+            }
+        } else if lookahead.peek(Token![_]) {
+            input.parse().map(Capture::Underscore)
+        } else if lookahead.peek(Token![#]) {
+            let pound = input.parse::<Token![#]>()?;
+            if input.peek(Ident) {
+                let name = input.fork().parse::<Ident>()?;
+                if let Some(keyword) = keyword(input, &name)? {
+                    return Ok(keyword);
+                }
+                if Preset::from_keyword(&name, false).is_some() {
+                    input.parse::<Ident>()?;
+                    return keyword_preset(input, name, None);
+                }
+                if let Some(preset) = Preset::from_name(&name) {
+                    input.parse::<Ident>()?;
+                    let args = if input.peek(token::Paren) {
+                        parse_preset_args(input, &name, preset)?
+                    } else {
+                        vec![]
+                    };
+                    return Ok(Capture::Preset {
+                        name,
+                        preset,
+                        binding: None,
+                        args,
+                    });
+                }
+            }
+            // Either `#name` or `#(any expression)`
+            let expr = if input.peek(syn::token::Paren) {
+                let inner;
+                let _ = syn::parenthesized!(inner in input);
+                inner.parse::<Expr>()?
+            } else {
+                match input.parse::<Ident>() {
+                    Ok(ident) => syn::parse_quote!(#ident),
+                    Err(_) => abort!(
+                        pound.span,
+                        "expected a variable name or `(expression)` after `#`"
+                    ),
+                }
+            };
+            Ok(Capture::Var {
+                pound,
+                expr: Box::new(expr),
+            })
+        } else if lookahead.peek(LitByte) {
+            let lit = input.parse::<LitByte>()?;
+            let reps = parse_reps(input, lit.suffix(), lit.span());
+            parse_range(input, Capture::Byte { lit, reps })
+        } else if lookahead.peek(LitInt) {
+            let int = input.parse::<LitInt>()?;
+            // A `u8` suffix is allowed, since it's the type the byte has
+            let suffix = match int.suffix() {
+                "u8" => "",
+                suffix => suffix,
+            };
+            let reps = parse_reps(input, suffix, int.span());
+            let lit = int_byte(&int);
+            parse_range(input, Capture::Byte { lit, reps })
+        } else if lookahead.peek(LitByteStr) {
+            let lit = input.parse::<LitByteStr>()?;
+            let reps = parse_reps(input, lit.suffix(), lit.span());
+            Ok(Capture::ByteStr { lit, reps })
+        } else if lookahead.peek(LitChar) {
+            let lit = input.parse::<LitChar>()?;
+            let reps = parse_reps(input, lit.suffix(), lit.span());
+            parse_range(input, Capture::Char { lit, reps })
+        } else if lookahead.peek(LitStr) {
+            let lit = input.parse::<LitStr>()?;
+            let reps = parse_reps(input, lit.suffix(), lit.span());
+            Ok(Capture::Str { lit, reps })
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+impl ToTokens for Capture {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match self {
+            Capture::ByteStr { lit, reps } => {
+                let bytes = lit.value();
+                for _ in 0..*reps {
+                    tokens.append_all(spanned_bytes(&bytes, lit.span()))
+                }
+            }
+            Capture::Byte { lit, reps } => {
+                for _ in 0..*reps {
+                    tokens.append_all(spanned_bytes(&[lit.value()], lit.span()))
+                }
+            }
+            Capture::Str { lit, reps } => {
+                // We want to display in byte literal form
+                let string = lit.value();
+                for _ in 0..*reps {
+                    tokens.append_all(spanned_bytes(
+                        string.as_bytes(),
+                        lit.span(),
+                    ))
+                }
+            }
+            Capture::Char { lit, reps } => {
+                // Display as byte literals
+                let mut buf = [0; 4];
+                let bytes = lit.value().encode_utf8(&mut buf).as_bytes();
+                for _ in 0..*reps {
+                    tokens.append_all(spanned_bytes(bytes, lit.span()))
+                }
+            }
+            Capture::Ident(ident) => tokens.append_all(quote!(#ident,)),
+            Capture::ByteRange {
+                binding,
+                start,
+                end,
+            } => {
+                let span = start.span();
+                let binding = binding.as_ref().map(|ident| quote!(#ident @));
+                let start = spanned_byte(single_byte(start), span);
+                match end {
+                    Some(end) => {
+                        let end = spanned_byte(single_byte(end), end.span());
+                        tokens.append_all(
+                            quote_spanned!(span=> #binding #start..=#end,),
+                        )
+                    }
+                    None => tokens.append_all(quote!(#binding #start,)),
+                }
+            }
+            Capture::Underscore(underscore) => {
+                tokens.append_all(quote!(#underscore,))
+            }
+            Capture::Guarded { capture, .. } => abort!(
+                capture.span(),
+                "a capture with a guard needs the guard checked, which a \
+                 pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get a guard \
+                        generated"
+            ),
+            // A path in a pattern is compared against rather than bound
+            Capture::Const { brace, path } => {
+                tokens.append_all(quote_spanned!(brace.span=> #path,))
+            }
+            Capture::Var { pound, .. } => abort!(
+                pound.span,
+                "`#` segments compare against runtime values, which a \
+                 pattern can't do";
+                help = "use `strmatch_match!` to get a guard generated"
+            ),
+            Capture::Preset {
+                name,
+                preset,
+                binding,
+                args,
+            } => match (preset.elems(name.span()), binding) {
+                (Some(elems), None) if args.is_empty() => {
+                    tokens.append_all(elems)
+                }
+                _ => abort!(
+                    name,
+                    "`#{}` binds or matches a varying number of bytes, which \
+                     a pattern can't do", name;
+                    help = "use `strmatch_match!` or `strextract!` to get scanning code \
+                        generated"
+                ),
+            },
+            Capture::Slice(slice) if slice.ty.is_some() => abort!(
+                slice.bracket.span,
+                "a typed capture has to parse the bytes it matches, which a \
+                 pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get parsing code \
+                        generated"
+            ),
+            Capture::Slice(slice) if !slice.steps.is_empty() => abort!(
+                slice.bracket.span,
+                "a capture with a pipeline has to run it on what it matches, \
+                 which a pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get the \
+                        pipeline generated"
+            ),
+            Capture::Slice(slice) if slice.trim => abort!(
+                slice.bracket.span,
+                "a `trim` capture binds less than it matches, which a \
+                 pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get the \
+                        trimming generated"
+            ),
+            Capture::Slice(slice) if slice.until.is_some() => abort!(
+                slice.bracket.span,
+                "an `until` capture has to search for its delimiter, which a \
+                 pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get scanning code \
+                        generated"
+            ),
+            Capture::Slice(slice) => abort!(
+                slice.bracket.span,
+                "a bracketed capture (or `..`) can only be the last \
+                 segment of a pattern";
+                help = "use `strmatch_match!` or `strextract!` to get scanning code \
+                        generated"
+            ),
+            Capture::Group { paren, .. } => abort!(
+                paren.span,
+                "a repeated group matches a varying number of bytes, which a \
+                 pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get a loop \
+                        generated"
+            ),
+            Capture::Utf8Char { keyword, .. }
+            | Capture::Grapheme { keyword, .. } => abort!(
+                keyword,
+                "`{}` matches a varying number of bytes, which a \
+                 pattern can't do", keyword;
+                help = "use `strmatch_match!` or `strextract!` to get scanning code \
+                        generated"
+            ),
+            Capture::Run { span, .. } => abort!(
+                *span,
+                "a run like `digit+` matches a varying number of bytes, \
+                 which a pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get scanning code \
+                        generated"
+            ),
+            Capture::Peek { keyword, .. } => abort!(
+                keyword,
+                "`{}` checks what comes after it, which a pattern can't do",
+                keyword;
+                help = "use `strmatch_match!` or `strextract!` to get the check \
+                        generated"
+            ),
+            Capture::NoCase { literal, .. } => {
+                for byte in literal.literal_bytes().unwrap() {
+                    let (lower, upper) =
+                        (byte.to_ascii_lowercase(), byte.to_ascii_uppercase());
+                    if lower == upper {
+                        tokens.append_all(quote!(#byte,));
+                    } else {
+                        tokens.append_all(quote!((#lower | #upper),));
+                    }
+                }
+            }
+            Capture::Pad { keyword, .. } => abort!(
+                keyword,
+                "`pad` with a count that isn't a literal matches a number of \
+                 bytes only known at runtime, which a pattern can't do";
+                help = "use `strmatch_match!` or `strextract!` to get the check \
+                        generated"
+            ),
+            Capture::Whitespace { keyword, .. } => abort!(
+                keyword,
+                "`#ws` matches a varying number of bytes, which a pattern \
+                 can't do";
+                help = "use `strmatch_match!` or `strextract!` to get scanning code \
+                        generated"
+            ),
+            Capture::Newline { keyword, .. } => abort!(
+                keyword,
+                "`#{}` matches a varying number of bytes, which only a whole \
+                 pattern can do, as several alternatives", keyword;
+                help = "use `strmatch!` or `strmatch_match!`, which match \
+                        each line ending"
+            ),
+            Capture::OneOf { brace, .. } => abort!(
+                brace.span,
+                "`{..}` matches one of several literals, which only a whole \
+                 pattern can do, as several alternatives";
+                help = "use `strmatch!` or `strmatch_match!`, which match \
+                        each literal"
+            ),
+        }
+    }
+}
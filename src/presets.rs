@@ -0,0 +1,772 @@
+//! Built-in presets, referenced as `#name` segments. Each preset matches as
+//! much as it can at the start of the input; it never gives bytes back to
+//! later segments. Some presets also hand back parts of what they matched,
+//! which `#name(arg, ..)` binds or checks.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::{Ident, LitInt};
+
+use crate::engine::hidden;
+
+#[derive(Clone, Copy)]
+pub(crate) enum Preset {
+    /// `#hexbyte`: exactly two ASCII hex digits, either case.
+    HexByte,
+    /// `#u16`: one to five ASCII digits whose value fits in a `u16`.
+    U16,
+    /// `#ident`: an ASCII letter or `_`, then any number of ASCII letters,
+    /// digits or `_`.
+    Ident,
+    /// `#ipv4`: four decimal octets (one to three digits, at most 255)
+    /// separated by `.`.
+    Ipv4,
+    /// `#kv`: a `key = value` line, up to but not including the next `\n`.
+    /// The key and value are trimmed of ASCII whitespace, and the key can't
+    /// be empty.
+    Kv,
+    /// `#http_request`: an HTTP/1.x request line, `METHOD SP PATH SP
+    /// HTTP/1.x`, without the line ending. The method is one or more ASCII
+    /// uppercase letters, and the path any visible ASCII.
+    HttpRequest,
+    /// `#semver`: a semantic version, `MAJOR.MINOR.PATCH`, then optionally
+    /// `-` and pre-release identifiers and `+` and build identifiers. The
+    /// numbers can't have leading zeros, and have to fit in a `u64`.
+    Semver,
+    /// `#uuid`: a UUID in its hyphenated form, 8-4-4-4-12 hex digits of
+    /// either case.
+    Uuid,
+    /// `#mac`: a MAC address, six pairs of hex digits of either case,
+    /// separated by either `:` or `-` throughout.
+    Mac,
+    /// `#url`: a URL, a scheme of an ASCII letter then letters, digits, `+`,
+    /// `-` or `.`, a `:`, and then visible ASCII other than `"`, `<` and
+    /// `>`, split into an optional `//authority`, a path, an optional
+    /// `?query` and an optional `#fragment`. Nothing is percent-decoded.
+    Url,
+    /// `#email`: an email address, a local part of `.`-separated runs of
+    /// ASCII letters, digits and ``!#$%&'*+-/=?^_`{|}~``, an `@`, and a domain
+    /// of at least two `.`-separated labels, each up to 63 ASCII letters,
+    /// digits and `-`, not starting or ending with `-`.
+    Email,
+    /// `#ipv6`: an IPv6 address, eight groups of one to four hex digits of
+    /// either case separated by `:`, where one run of groups can be left
+    /// out with `::`, and the last two groups can be written as an IPv4
+    /// address.
+    Ipv6,
+    /// `#base64run`: a run of base64, in either the standard alphabet (with
+    /// `+` and `/`) or the URL-safe one (with `-` and `_`) but not a mix of
+    /// the two, then the `=` padding that fills out its last group of four,
+    /// if it's padded. `decode` is whether it's `#base64run(decoded)`, which
+    /// decodes it.
+    Base64Run { decode: bool },
+    /// `#hexrun`: an even-length run of ASCII hex digits, either case.
+    /// `decode` is whether it's `#hexrun(bytes)`, which decodes it into a
+    /// `Vec<u8>`, and `len` is `Some(N)` for `#hexrun(bytes: [u8; N])`, which
+    /// decodes it into an array, and only matches `2 * N` digits.
+    HexRun { decode: bool, len: Option<usize> },
+}
+
+/// Something a preset hands back besides the bytes it matched.
+pub(crate) struct Output {
+    pub(crate) name: &'static str,
+    pub(crate) kind: OutputKind,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum OutputKind {
+    /// A `&[u8]`, which string and byte literal arguments can check
+    Bytes,
+    /// A `u8`, which integer literal arguments can check
+    U8,
+    /// A `u64`, which integer literal arguments can check
+    U64,
+    /// An `Option<&[u8]>` for an optional part, which can only be bound
+    MaybeBytes,
+    /// A decoded `[u8; N]`, which can only be bound
+    Array(usize),
+    /// A decoded `Vec<u8>`, which can only be bound
+    Vec,
+}
+
+impl OutputKind {
+    fn ty(self) -> TokenStream {
+        match self {
+            OutputKind::Bytes => quote!(&[u8]),
+            OutputKind::U8 => quote!(u8),
+            OutputKind::U64 => quote!(u64),
+            OutputKind::MaybeBytes => {
+                quote!(::core::option::Option<&[u8]>)
+            }
+            OutputKind::Array(len) => quote!([u8; #len]),
+            OutputKind::Vec => quote!(::std::vec::Vec<u8>),
+        }
+    }
+}
+
+/// What `#name(arg, ..)` does with one of the preset's outputs.
+pub(crate) enum PresetArg {
+    /// `name`: bind the output
+    Bind(Ident),
+    /// `_`: ignore it
+    Skip,
+    /// A literal: the output has to be exactly these bytes
+    Bytes(Vec<u8>, Span),
+    /// An integer literal: the (numeric) output has to equal it
+    Value(LitInt),
+}
+
+/// A helper for scanners, decoding a pair of ASCII hex digits.
+fn hex() -> TokenStream {
+    quote! {
+        fn hex(pair: &[u8]) -> ::core::option::Option<u8> {
+            let digit = |b: u8| (b as char).to_digit(16);
+            ::core::option::Option::Some((digit(pair[0])? * 16 + digit(pair[1])?) as u8)
+        }
+    }
+}
+
+impl Preset {
+    pub(crate) fn from_name(name: &Ident) -> Option<Preset> {
+        let preset = match name.to_string().as_str() {
+            "hexbyte" => Preset::HexByte,
+            "u16" => Preset::U16,
+            "ident" => Preset::Ident,
+            "ipv4" => Preset::Ipv4,
+            "ipv6" => Preset::Ipv6,
+            "kv" => Preset::Kv,
+            "http_request" => Preset::HttpRequest,
+            "semver" => Preset::Semver,
+            "uuid" => Preset::Uuid,
+            "mac" => Preset::Mac,
+            "url" => Preset::Url,
+            "email" => Preset::Email,
+            _ => return None,
+        };
+        Some(preset)
+    }
+
+    /// The presets that decode what they match when they're followed by
+    /// arguments, given whether they are.
+    pub(crate) fn from_keyword(name: &Ident, args: bool) -> Option<Preset> {
+        match name.to_string().as_str() {
+            "base64run" => Some(Preset::Base64Run { decode: args }),
+            "hexrun" => Some(Preset::HexRun {
+                decode: args,
+                len: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The slice pattern elements for presets that always match the same
+    /// number of bytes, spanned at the `#name` segment.
+    pub(crate) fn elems(self, span: Span) -> Option<TokenStream> {
+        match self {
+            Preset::HexByte => {
+                let hexdig = quote_spanned! {span=>
+                    b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F'
+                };
+                Some(quote!(#hexdig, #hexdig,))
+            }
+            _ => None,
+        }
+    }
+
+    /// A regex matching the same inputs as the preset, as far as a regex
+    /// can tell: numbers that don't fit their type aren't caught.
+    pub(crate) fn regex(self) -> &'static str {
+        match self {
+            Preset::HexByte => "[0-9A-Fa-f]{2}",
+            Preset::U16 => {
+                "[0-9]{1,4}|0[0-9]{4}|[1-5][0-9]{4}|6[0-4][0-9]{3}\
+                 |65[0-4][0-9]{2}|655[0-2][0-9]|6553[0-5]"
+            }
+            Preset::Ident => "[A-Z_a-z][0-9A-Z_a-z]*",
+            Preset::Ipv4 => {
+                "(?:25[0-5]|2[0-4][0-9]|[01]?[0-9]{1,2})\
+                 (?:\\.(?:25[0-5]|2[0-4][0-9]|[01]?[0-9]{1,2})){3}"
+            }
+            Preset::Ipv6 => {
+                "(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}\
+                 |(?:[0-9A-Fa-f]{1,4}:)(?::[0-9A-Fa-f]{1,4}){1,6}\
+                 |(?:[0-9A-Fa-f]{1,4}:){2}(?::[0-9A-Fa-f]{1,4}){1,5}\
+                 |(?:[0-9A-Fa-f]{1,4}:){3}(?::[0-9A-Fa-f]{1,4}){1,4}\
+                 |(?:[0-9A-Fa-f]{1,4}:){4}(?::[0-9A-Fa-f]{1,4}){1,3}\
+                 |(?:[0-9A-Fa-f]{1,4}:){5}(?::[0-9A-Fa-f]{1,4}){1,2}\
+                 |(?:[0-9A-Fa-f]{1,4}:){6}(?::[0-9A-Fa-f]{1,4})\
+                 |(?:[0-9A-Fa-f]{1,4}:){1,7}:\
+                 |:(?::[0-9A-Fa-f]{1,4}){1,7}\
+                 |::\
+                 |(?:(?:[0-9A-Fa-f]{1,4}:){6}\
+                 |(?:[0-9A-Fa-f]{1,4}:):(?:[0-9A-Fa-f]{1,4}:){0,4}\
+                 |(?:[0-9A-Fa-f]{1,4}:){2}:(?:[0-9A-Fa-f]{1,4}:){0,3}\
+                 |(?:[0-9A-Fa-f]{1,4}:){3}:(?:[0-9A-Fa-f]{1,4}:){0,2}\
+                 |(?:[0-9A-Fa-f]{1,4}:){4}:(?:[0-9A-Fa-f]{1,4}:){0,1}\
+                 |(?:[0-9A-Fa-f]{1,4}:){5}:\
+                 |::(?:[0-9A-Fa-f]{1,4}:){0,5})\
+                 (?:25[0-5]|2[0-4][0-9]|[01]?[0-9]{1,2})(?:\\.(?:25[0-5]|2[0-4][0-9]|[01]?[0-9]{1,2})){3}"
+            }
+            Preset::HexRun { .. } => "(?:[0-9A-Fa-f]{2})+",
+            Preset::Base64Run { .. } => {
+                "(?:[+/0-9A-Za-z]{4})*\
+                 (?:[+/0-9A-Za-z]{4}|[+/0-9A-Za-z]{3}=?|[+/0-9A-Za-z]{2}(?:==)?)\
+                 |(?:[-0-9A-Z_a-z]{4})*\
+                 (?:[-0-9A-Z_a-z]{4}|[-0-9A-Z_a-z]{3}=?|[-0-9A-Z_a-z]{2}(?:==)?)"
+            }
+            Preset::Kv => r"[^\n=]*[^\t\n\f\r =][^\n=]*=[^\n]*",
+            Preset::HttpRequest => r"[A-Z]+ [!-~]+ HTTP/1\.[0-9]",
+            Preset::Semver => {
+                "(?:0|[1-9][0-9]*)\\.(?:0|[1-9][0-9]*)\\.(?:0|[1-9][0-9]*)\
+                 (?:-[-0-9A-Za-z]+(?:\\.[-0-9A-Za-z]+)*)?\
+                 (?:\\+[-0-9A-Za-z]+(?:\\.[-0-9A-Za-z]+)*)?"
+            }
+            Preset::Uuid => {
+                "[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}\
+                 -[0-9A-Fa-f]{12}"
+            }
+            Preset::Mac => {
+                "[0-9A-Fa-f]{2}(?::[0-9A-Fa-f]{2}){5}\
+                 |[0-9A-Fa-f]{2}(?:-[0-9A-Fa-f]{2}){5}"
+            }
+            Preset::Url => "[A-Za-z][-+.0-9A-Za-z]*:[!#-;=?-~]*",
+            Preset::Email => {
+                "[-!#-'*+/-9=?A-Z^-~]+(?:\\.[-!#-'*+/-9=?A-Z^-~]+)*@\
+                 [0-9A-Za-z](?:[-0-9A-Za-z]{0,61}[0-9A-Za-z])?\
+                 (?:\\.[0-9A-Za-z](?:[-0-9A-Za-z]{0,61}[0-9A-Za-z])?)+"
+            }
+        }
+    }
+
+    /// The parts of a match this preset hands back, in argument order.
+    pub(crate) fn outputs(self) -> Vec<Output> {
+        let output = |name, kind| Output { name, kind };
+        match self {
+            Preset::Ipv6 => vec![output("bytes", OutputKind::Array(16))],
+            Preset::Base64Run { decode: true } => {
+                vec![output("decoded", OutputKind::Vec)]
+            }
+            Preset::HexRun { decode: true, len } => {
+                let kind = len.map_or(OutputKind::Vec, OutputKind::Array);
+                vec![output("bytes", kind)]
+            }
+            Preset::Kv => vec![
+                output("key", OutputKind::Bytes),
+                output("value", OutputKind::Bytes),
+            ],
+            // A proc-macro crate can't export a method enum, so the method is
+            // handed back as bytes, and checked with a literal argument
+            Preset::HttpRequest => vec![
+                output("method", OutputKind::Bytes),
+                output("path", OutputKind::Bytes),
+                output("version", OutputKind::U8),
+            ],
+            Preset::Semver => vec![
+                output("major", OutputKind::U64),
+                output("minor", OutputKind::U64),
+                output("patch", OutputKind::U64),
+                output("pre", OutputKind::MaybeBytes),
+                output("build", OutputKind::MaybeBytes),
+            ],
+            Preset::Uuid => vec![output("bytes", OutputKind::Array(16))],
+            Preset::Mac => vec![output("bytes", OutputKind::Array(6))],
+            Preset::Url => vec![
+                output("scheme", OutputKind::Bytes),
+                output("authority", OutputKind::MaybeBytes),
+                output("path", OutputKind::Bytes),
+                output("query", OutputKind::MaybeBytes),
+            ],
+            Preset::Email => vec![
+                output("local", OutputKind::Bytes),
+                output("domain", OutputKind::Bytes),
+            ],
+            _ => vec![],
+        }
+    }
+
+    /// An expression evaluating to the number of bytes this preset matches
+    /// at the start of `input` and a tuple of its outputs, or `None` if it
+    /// doesn't match.
+    pub(crate) fn scan(self, input: &Ident) -> TokenStream {
+        let scan = hidden("scan");
+        let outputs = self.outputs();
+        let tys = outputs.iter().map(|output| output.kind.ty());
+        let hex = hex();
+        let body = match self {
+            Preset::HexByte => quote! {
+                match input {
+                    [a, b, ..] if a.is_ascii_hexdigit() && b.is_ascii_hexdigit() => {
+                        ::core::option::Option::Some((2, ()))
+                    }
+                    _ => ::core::option::Option::None,
+                }
+            },
+            Preset::U16 => quote! {
+                let mut len = 0;
+                let mut value = 0u32;
+                while len < input.len() && len < 5 && input[len].is_ascii_digit() {
+                    value = value * 10 + u32::from(input[len] - b'0');
+                    len += 1;
+                }
+                if len > 0 && value <= u32::from(u16::MAX) {
+                    ::core::option::Option::Some((len, ()))
+                } else {
+                    ::core::option::Option::None
+                }
+            },
+            Preset::Ident => quote! {
+                match input.first() {
+                    ::core::option::Option::Some(b) if b.is_ascii_alphabetic() || *b == b'_' => {}
+                    _ => return ::core::option::Option::None,
+                }
+                let rest = input[1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                    .count();
+                ::core::option::Option::Some((1 + rest, ()))
+            },
+            Preset::Ipv4 => quote! {
+                let mut at = 0;
+                for octet in 0..4 {
+                    if octet > 0 {
+                        if input.get(at) != ::core::option::Option::Some(&b'.') {
+                            return ::core::option::Option::None;
+                        }
+                        at += 1;
+                    }
+                    let start = at;
+                    let mut value = 0u32;
+                    while at < input.len() && at - start < 3 && input[at].is_ascii_digit() {
+                        value = value * 10 + u32::from(input[at] - b'0');
+                        at += 1;
+                    }
+                    if at == start || value > 255 {
+                        return ::core::option::Option::None;
+                    }
+                }
+                ::core::option::Option::Some((at, ()))
+            },
+            Preset::Ipv6 => quote! {
+                // An IPv4 address at the start of `input`
+                fn ipv4(input: &[u8]) -> ::core::option::Option<(usize, [u8; 4])> {
+                    let mut octets = [0u8; 4];
+                    let mut at = 0;
+                    for (i, octet) in octets.iter_mut().enumerate() {
+                        if i > 0 {
+                            if input.get(at) != ::core::option::Option::Some(&b'.') {
+                                return ::core::option::Option::None;
+                            }
+                            at += 1;
+                        }
+                        let start = at;
+                        let mut value = 0u32;
+                        while at < input.len() && at - start < 3 && input[at].is_ascii_digit() {
+                            value = value * 10 + u32::from(input[at] - b'0');
+                            at += 1;
+                        }
+                        if at == start || value > 255 {
+                            return ::core::option::Option::None;
+                        }
+                        *octet = value as u8;
+                    }
+                    ::core::option::Option::Some((at, octets))
+                }
+                let mut groups = [0u16; 8];
+                let mut count = 0;
+                // How many groups come before the `::`, if there is one
+                let mut gap = ::core::option::Option::None;
+                let mut at = 0;
+                if input.starts_with(b"::") {
+                    gap = ::core::option::Option::Some(0);
+                    at = 2;
+                }
+                let mut end = at;
+                while count < 8 {
+                    if count <= 6 {
+                        if let ::core::option::Option::Some((len, [a, b, c, d])) = ipv4(&input[at..]) {
+                            groups[count] = u16::from_be_bytes([a, b]);
+                            groups[count + 1] = u16::from_be_bytes([c, d]);
+                            count += 2;
+                            end = at + len;
+                            break;
+                        }
+                    }
+                    let len = input[at..].iter().take_while(|b| b.is_ascii_hexdigit()).count();
+                    if len == 0 {
+                        break;
+                    }
+                    if len > 4 {
+                        return ::core::option::Option::None;
+                    }
+                    let mut group = 0;
+                    for digit in &input[at..at + len] {
+                        group = group * 16 + (*digit as char).to_digit(16).unwrap() as u16;
+                    }
+                    groups[count] = group;
+                    count += 1;
+                    at += len;
+                    end = at;
+                    if gap.is_none() && count < 8 && input[at..].starts_with(b"::") {
+                        gap = ::core::option::Option::Some(count);
+                        at += 2;
+                        end = at;
+                    } else if input.get(at) == ::core::option::Option::Some(&b':')
+                        && input.get(at + 1).is_some_and(|b| b.is_ascii_hexdigit())
+                    {
+                        at += 1;
+                    } else {
+                        break;
+                    }
+                }
+                // Without a `::`, all eight groups have to be there, and with
+                // one, it has to stand for at least one group
+                let mut full = [0u16; 8];
+                match gap {
+                    ::core::option::Option::None if count == 8 => full = groups,
+                    ::core::option::Option::Some(gap) if count < 8 => {
+                        full[..gap].copy_from_slice(&groups[..gap]);
+                        full[8 - (count - gap)..].copy_from_slice(&groups[gap..count]);
+                    }
+                    _ => return ::core::option::Option::None,
+                }
+                let mut bytes = [0u8; 16];
+                for (pair, group) in bytes.chunks_exact_mut(2).zip(full) {
+                    pair.copy_from_slice(&group.to_be_bytes());
+                }
+                ::core::option::Option::Some((end, (bytes,)))
+            },
+            Preset::HexRun { decode, len } => {
+                let fixed = len.map(|len| {
+                    let digits = 2 * len;
+                    quote! {
+                        if len != #digits {
+                            return ::core::option::Option::None;
+                        }
+                    }
+                });
+                let decoded = match (decode, len) {
+                    (false, _) => {
+                        quote!(::core::option::Option::Some((len, ())))
+                    }
+                    (true, ::core::option::Option::None) => quote! {
+                        #hex
+                        let bytes = input[..len]
+                            .chunks_exact(2)
+                            .map(hex)
+                            .collect::<::core::option::Option<::std::vec::Vec<u8>>>()?;
+                        ::core::option::Option::Some((len, (bytes,)))
+                    },
+                    // On the stack, with no allocation
+                    (true, ::core::option::Option::Some(n)) => quote! {
+                        #hex
+                        let mut bytes = [0u8; #n];
+                        for (byte, pair) in bytes.iter_mut().zip(input.chunks_exact(2)) {
+                            *byte = hex(pair)?;
+                        }
+                        ::core::option::Option::Some((len, (bytes,)))
+                    },
+                };
+                quote! {
+                    let len = input.iter().take_while(|b| b.is_ascii_hexdigit()).count();
+                    // Each byte is two digits
+                    if len == 0 || len % 2 == 1 {
+                        return ::core::option::Option::None;
+                    }
+                    #fixed
+                    #decoded
+                }
+            }
+            Preset::Base64Run { decode } => {
+                let decoded = match decode {
+                    true => quote! {
+                        let value = |b: u8| match b {
+                            b'A'..=b'Z' => b - b'A',
+                            b'a'..=b'z' => b - b'a' + 26,
+                            b'0'..=b'9' => b - b'0' + 52,
+                            b'+' | b'-' => 62,
+                            _ => 63,
+                        };
+                        let mut decoded = ::std::vec::Vec::with_capacity(len / 4 * 3 + 2);
+                        // Each group of `n` characters holds `n - 1` bytes
+                        for group in run.chunks(4) {
+                            let mut bits = 0u32;
+                            for (i, b) in group.iter().enumerate() {
+                                bits |= u32::from(value(*b)) << (18 - 6 * i);
+                            }
+                            decoded.extend_from_slice(&bits.to_be_bytes()[1..group.len()]);
+                        }
+                        ::core::option::Option::Some((end, (decoded,)))
+                    },
+                    false => quote!(::core::option::Option::Some((end, ()))),
+                };
+                quote! {
+                    let standard = |b: &u8| b.is_ascii_alphanumeric() || *b == b'+' || *b == b'/';
+                    let url_safe = |b: &u8| b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_';
+                    let len = input.iter().take_while(|b| standard(b) || url_safe(b)).count();
+                    // One character left over doesn't make up a byte
+                    if len == 0 || len % 4 == 1 {
+                        return ::core::option::Option::None;
+                    }
+                    let run = &input[..len];
+                    if !run.iter().all(standard) && !run.iter().all(url_safe) {
+                        return ::core::option::Option::None;
+                    }
+                    let padding = (4 - len % 4) % 4;
+                    let end = match input[len..].iter().take_while(|b| **b == b'=').count() {
+                        0 => len,
+                        n if padding > 0 && n >= padding => len + padding,
+                        _ => return ::core::option::Option::None,
+                    };
+                    #decoded
+                }
+            }
+            Preset::Kv => quote! {
+                fn trim(mut bytes: &[u8]) -> &[u8] {
+                    while let [first, rest @ ..] = bytes {
+                        if !first.is_ascii_whitespace() {
+                            break;
+                        }
+                        bytes = rest;
+                    }
+                    while let [rest @ .., last] = bytes {
+                        if !last.is_ascii_whitespace() {
+                            break;
+                        }
+                        bytes = rest;
+                    }
+                    bytes
+                }
+                let len = input
+                    .iter()
+                    .position(|b| *b == b'\n')
+                    .unwrap_or(input.len());
+                let line = &input[..len];
+                let eq = line.iter().position(|b| *b == b'=')?;
+                let key = trim(&line[..eq]);
+                if key.is_empty() {
+                    return ::core::option::Option::None;
+                }
+                ::core::option::Option::Some((len, (key, trim(&line[eq + 1..]))))
+            },
+            Preset::HttpRequest => quote! {
+                let method = input.iter().take_while(|b| b.is_ascii_uppercase()).count();
+                if method == 0 || input.get(method) != ::core::option::Option::Some(&b' ') {
+                    return ::core::option::Option::None;
+                }
+                let start = method + 1;
+                let path = input[start..].iter().take_while(|b| b.is_ascii_graphic()).count();
+                if path == 0 {
+                    return ::core::option::Option::None;
+                }
+                let end = start + path;
+                match &input[end..] {
+                    [b' ', b'H', b'T', b'T', b'P', b'/', b'1', b'.', minor @ b'0'..=b'9', ..] => {
+                        ::core::option::Option::Some((
+                            end + 9,
+                            (&input[..method], &input[start..end], minor - b'0'),
+                        ))
+                    }
+                    _ => ::core::option::Option::None,
+                }
+            },
+            Preset::Semver => quote! {
+                fn number(input: &[u8], at: &mut usize) -> ::core::option::Option<u64> {
+                    let start = *at;
+                    let mut value = 0u64;
+                    while let ::core::option::Option::Some(digit) =
+                        input.get(*at).filter(|b| b.is_ascii_digit())
+                    {
+                        value = value.checked_mul(10)?.checked_add(u64::from(digit - b'0'))?;
+                        *at += 1;
+                    }
+                    if *at == start || (input[start] == b'0' && *at - start > 1) {
+                        return ::core::option::Option::None;
+                    }
+                    ::core::option::Option::Some(value)
+                }
+                // The end of the `.`-separated identifiers starting at
+                // `start`, leaving off a trailing `.`
+                fn identifiers(input: &[u8], start: usize) -> usize {
+                    let mut end = start;
+                    loop {
+                        let len = input[end..]
+                            .iter()
+                            .take_while(|b| b.is_ascii_alphanumeric() || **b == b'-')
+                            .count();
+                        if len == 0 {
+                            return end.saturating_sub(1).max(start);
+                        }
+                        end += len;
+                        if input.get(end) != ::core::option::Option::Some(&b'.') {
+                            return end;
+                        }
+                        end += 1;
+                    }
+                }
+                // The identifiers after `sigil`, if there are any
+                fn part<'a>(
+                    input: &'a [u8],
+                    at: &mut usize,
+                    sigil: u8,
+                ) -> ::core::option::Option<&'a [u8]> {
+                    if input.get(*at) != ::core::option::Option::Some(&sigil) {
+                        return ::core::option::Option::None;
+                    }
+                    let end = identifiers(input, *at + 1);
+                    if end == *at + 1 {
+                        return ::core::option::Option::None;
+                    }
+                    let part = &input[*at + 1..end];
+                    *at = end;
+                    ::core::option::Option::Some(part)
+                }
+                let mut at = 0;
+                let major = number(input, &mut at)?;
+                if input.get(at) != ::core::option::Option::Some(&b'.') {
+                    return ::core::option::Option::None;
+                }
+                at += 1;
+                let minor = number(input, &mut at)?;
+                if input.get(at) != ::core::option::Option::Some(&b'.') {
+                    return ::core::option::Option::None;
+                }
+                at += 1;
+                let patch = number(input, &mut at)?;
+                let pre = part(input, &mut at, b'-');
+                let build = part(input, &mut at, b'+');
+                ::core::option::Option::Some((at, (major, minor, patch, pre, build)))
+            },
+            Preset::Uuid => quote! {
+                #hex
+                let mut bytes = [0u8; 16];
+                let mut at = 0;
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    if matches!(i, 4 | 6 | 8 | 10) {
+                        if input.get(at) != ::core::option::Option::Some(&b'-') {
+                            return ::core::option::Option::None;
+                        }
+                        at += 1;
+                    }
+                    *byte = hex(input.get(at..at + 2)?)?;
+                    at += 2;
+                }
+                ::core::option::Option::Some((at, (bytes,)))
+            },
+            Preset::Mac => quote! {
+                #hex
+                let separator = *input.get(2)?;
+                if separator != b':' && separator != b'-' {
+                    return ::core::option::Option::None;
+                }
+                let mut bytes = [0u8; 6];
+                let mut at = 0;
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    if i > 0 {
+                        if input.get(at) != ::core::option::Option::Some(&separator) {
+                            return ::core::option::Option::None;
+                        }
+                        at += 1;
+                    }
+                    *byte = hex(input.get(at..at + 2)?)?;
+                    at += 2;
+                }
+                ::core::option::Option::Some((at, (bytes,)))
+            },
+            Preset::Url => quote! {
+                let len = input
+                    .iter()
+                    .take_while(|b| b.is_ascii_graphic() && !matches!(b, b'"' | b'<' | b'>'))
+                    .count();
+                let url = &input[..len];
+                let colon = url.iter().position(|b| *b == b':')?;
+                let scheme = &url[..colon];
+                match scheme.split_first() {
+                    ::core::option::Option::Some((first, rest))
+                        if first.is_ascii_alphabetic()
+                            && rest.iter().all(|b| {
+                                b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.')
+                            }) => {}
+                    _ => return ::core::option::Option::None,
+                }
+                // The bytes `skip` bytes past `at`, up to one of `ends`,
+                // moving `at` past them
+                fn part<'a>(url: &'a [u8], at: &mut usize, skip: usize, ends: &[u8]) -> &'a [u8] {
+                    let start = *at + skip;
+                    let len = url[start..].iter().take_while(|b| !ends.contains(b)).count();
+                    *at = start + len;
+                    &url[start..*at]
+                }
+                let mut at = colon + 1;
+                let authority = match url[at..].starts_with(b"//") {
+                    true => ::core::option::Option::Some(part(url, &mut at, 2, b"/?#")),
+                    false => ::core::option::Option::None,
+                };
+                let path = part(url, &mut at, 0, b"?#");
+                let query = match url.get(at) {
+                    ::core::option::Option::Some(b'?') => {
+                        ::core::option::Option::Some(part(url, &mut at, 1, b"#"))
+                    }
+                    _ => ::core::option::Option::None,
+                };
+                // Anything left is the fragment
+                ::core::option::Option::Some((len, (scheme, authority, path, query)))
+            },
+            Preset::Email => quote! {
+                let atext = |b: &u8| {
+                    b.is_ascii_alphanumeric() || b"!#$%&'*+-/=?^_`{|}~".contains(b)
+                };
+                let mut at = 0;
+                loop {
+                    let len = input[at..].iter().take_while(|b| atext(b)).count();
+                    if len == 0 {
+                        return ::core::option::Option::None;
+                    }
+                    at += len;
+                    if input.get(at) != ::core::option::Option::Some(&b'.') {
+                        break;
+                    }
+                    at += 1;
+                }
+                if input.get(at) != ::core::option::Option::Some(&b'@') {
+                    return ::core::option::Option::None;
+                }
+                let local = &input[..at];
+                at += 1;
+                let start = at;
+                // The domain ends after its last whole label, so a `.`
+                // ending a sentence is left out
+                let mut end = start;
+                let mut labels = 0;
+                loop {
+                    let label = input[at..]
+                        .iter()
+                        .take_while(|b| b.is_ascii_alphanumeric() || **b == b'-')
+                        .count();
+                    let label = &input[at..at + label];
+                    match (label.first(), label.last()) {
+                        (::core::option::Option::Some(first), ::core::option::Option::Some(last))
+                            if *first != b'-' && *last != b'-' && label.len() <= 63 => {}
+                        _ => break,
+                    }
+                    at += label.len();
+                    end = at;
+                    labels += 1;
+                    if input.get(at) != ::core::option::Option::Some(&b'.') {
+                        break;
+                    }
+                    at += 1;
+                }
+                if labels < 2 {
+                    return ::core::option::Option::None;
+                }
+                ::core::option::Option::Some((end, (local, &input[start..end])))
+            },
+        };
+        quote! {{
+            fn #scan(input: &[u8]) -> ::core::option::Option<(usize, (#(#tys,)*))> {
+                #body
+            }
+            #scan(#input)
+        }}
+    }
+}
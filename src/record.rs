@@ -0,0 +1,94 @@
+//! Parsing and expansion for `#[strmatch_record]`, which implements both
+//! `FromStr` and `Display` for a struct from one pattern, and checks that
+//! they agree on some examples.
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Ident, ItemStruct, LitStr, Token};
+
+/// `pattern, examples = ["..", ..]`, where the pattern can also be a format
+/// string.
+pub(crate) struct RecordArgs {
+    pub(crate) pattern: TokenStream,
+    examples: Vec<LitStr>,
+}
+
+impl Parse for RecordArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Patterns never contain a comma, so the first one ends the pattern
+        let pattern = input.step(|cursor| {
+            let mut pattern = TokenStream::new();
+            let mut rest = *cursor;
+            while let Some((tt, next)) = rest.token_tree() {
+                match &tt {
+                    TokenTree::Punct(punct) if punct.as_char() == ',' => break,
+                    _ => pattern.extend([tt]),
+                }
+                rest = next;
+            }
+            Ok((pattern, rest))
+        })?;
+        let mut examples = vec![];
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key = input.parse::<Ident>()?;
+            if key != "examples" {
+                return Err(syn::Error::new(key.span(), "expected `examples`"));
+            }
+            input.parse::<Token![=]>()?;
+            let inner;
+            bracketed!(inner in input);
+            examples =
+                Punctuated::<LitStr, Token![,]>::parse_terminated(&inner)?
+                    .into_iter()
+                    .collect();
+            let _ = input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(RecordArgs { pattern, examples })
+    }
+}
+
+// `MyRecord` becomes `my_record`
+fn snake_case(ident: &Ident) -> String {
+    let mut snake = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Expand to a test checking that each example parses, and that printing
+/// what it parsed into and parsing that again prints the same thing.
+pub(crate) fn round_trip(args: &RecordArgs, item: &ItemStruct) -> TokenStream {
+    if args.examples.is_empty() {
+        return TokenStream::new();
+    }
+    let name = &item.ident;
+    let test = format_ident!("{}_round_trips", snake_case(name));
+    let examples = &args.examples;
+    quote! {
+        #[cfg(test)]
+        #[test]
+        fn #test() {
+            for example in [#(#examples),*] {
+                let parsed = example.parse::<#name>().unwrap_or_else(|_| {
+                    panic!("example {:?} doesn't parse", example)
+                });
+                let printed = parsed.to_string();
+                let reparsed = printed.parse::<#name>().unwrap_or_else(|_| {
+                    panic!("{:?} was printed as {:?}, which doesn't parse", example, printed)
+                });
+                assert_eq!(
+                    reparsed.to_string(),
+                    printed,
+                    "{:?} doesn't print the same after a round trip",
+                    example
+                );
+            }
+        }
+    }
+}
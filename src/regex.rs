@@ -0,0 +1,974 @@
+//! Parsing and expansion for `strmatch_regex!`, which matches its input
+//! against a regex like `^GET (\S+) HTTP/1\.[01]$` by turning the regex into
+//! patterns, and hands back what its groups matched.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use proc_macro2::{Ident, Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{token, Expr, LitByteStr, LitStr, Token};
+
+use crate::engine::{captured, coerce_input, hidden, matcher, Codegen};
+use crate::pattern::{Capture, Class, Pattern, SliceCapture};
+
+/// How many patterns a regex can expand to, since every `?` and `|` inside
+/// a group doubles them or more
+const MAX_ALTERNATIVES: usize = 64;
+
+/// A set of bytes, one bit for each
+type Set = [u64; 4];
+
+/// `input, "regex"`
+pub(crate) struct RegexInput {
+    input: Expr,
+    /// One pattern for each way the regex can match, tried in order
+    alternatives: Vec<Pattern>,
+    /// The captures of the regex's groups, in the order the groups open
+    groups: Vec<Ident>,
+}
+
+impl Parse for RegexInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let regex = input.parse::<LitStr>()?;
+        let (alternatives, groups) = translate(&regex);
+        Ok(RegexInput {
+            input: expr,
+            alternatives,
+            groups,
+        })
+    }
+}
+
+/// A piece of a regex
+#[derive(Clone)]
+enum Node {
+    /// Bytes matched as they are
+    Literal(Vec<u8>),
+    /// One byte in the set
+    Byte(Set),
+    /// `node*`, `node+`, `node?` or `node{min,max}`
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        lazy: bool,
+    },
+    /// `(..)`, with the index of its capture, or `(?:..)`
+    Group {
+        index: Option<usize>,
+        alternatives: Vec<Vec<Node>>,
+    },
+}
+
+/// One of the regex's top-level alternatives
+struct Branch {
+    /// Whether it starts with `^`
+    start: bool,
+    /// Whether it ends with `$`
+    end: bool,
+    nodes: Vec<Node>,
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    span: Span,
+    /// How many capturing groups have been opened
+    groups: usize,
+}
+
+// Turn a regex into the patterns it stands for, one for each way it can
+// match, and the captures of its groups
+fn translate(regex: &LitStr) -> (Vec<Pattern>, Vec<Ident>) {
+    let span = regex.span();
+    let value = regex.value();
+    let mut parser = Parser {
+        chars: value.chars().peekable(),
+        span,
+        groups: 0,
+    };
+    let branches = parser.branches();
+    let groups = (0..parser.groups)
+        .map(|index| hidden(&format!("group{index}")))
+        .collect::<Vec<_>>();
+    let mut lowering = Lowering {
+        span,
+        bytes: 0,
+        groups: vec![],
+    };
+    let mut alternatives = vec![];
+    for branch in branches {
+        for nodes in expand(&branch.nodes, span) {
+            lowering.groups.clear();
+            let mut literals = lowering.sequence(&nodes, branch.end);
+            lowering.groups.sort_unstable();
+            if lowering.groups != (0..groups.len()).collect::<Vec<_>>() {
+                abort!(
+                    span,
+                    "every group in the regex has to match for it to match";
+                    help = "make the group non-capturing, as in `(?:..)`, \
+                            or move it out of the `?` or `|`"
+                );
+            }
+            // Without a `^`, the match can start anywhere, and the leftmost
+            // one is found by skipping as little as possible
+            if !branch.start {
+                literals.insert(0, Capture::Slice(lowering.slice(None, true)));
+            }
+            let mut pattern = Pattern {
+                whole: None,
+                min_len: None,
+                literals,
+                end: None,
+            };
+            if !branch.end {
+                pattern.capture_rest(hidden("rest"), span);
+            } else if let Some(Capture::Slice(_)) = pattern.literals.last() {
+                // A trailing `.*` grabs whatever is left, which a slice
+                // pattern can do
+                let Some(Capture::Slice(last)) = pattern.literals.pop() else {
+                    unreachable!()
+                };
+                pattern.end = Some(last);
+            }
+            alternatives.push(pattern);
+            if alternatives.len() > MAX_ALTERNATIVES {
+                abort!(
+                    span,
+                    "the regex has more than {} ways to match", MAX_ALTERNATIVES;
+                    help = "each `?`, `{n,m}` on a group and `|` in a group \
+                            multiplies them"
+                );
+            }
+        }
+    }
+    (alternatives, groups)
+}
+
+impl Parser<'_> {
+    // The top-level alternatives, each of which can be anchored
+    fn branches(&mut self) -> Vec<Branch> {
+        let mut branches = vec![];
+        loop {
+            let start = self.chars.next_if_eq(&'^').is_some();
+            let nodes = self.sequence(true);
+            let end = self.chars.next_if_eq(&'$').is_some();
+            branches.push(Branch { start, end, nodes });
+            match self.chars.next() {
+                Some('|') => {}
+                None => break branches,
+                Some(')') => {
+                    abort!(self.span, "the regex has an unmatched `)`")
+                }
+                Some(_) => abort!(
+                    self.span,
+                    "`$` can only go at the end of the regex or right before \
+                     a `|`"
+                ),
+            }
+        }
+    }
+
+    // The alternatives inside a group, up to its `)`
+    fn alternatives(&mut self) -> Vec<Vec<Node>> {
+        let mut alternatives = vec![self.sequence(false)];
+        loop {
+            match self.chars.next() {
+                Some('|') => alternatives.push(self.sequence(false)),
+                Some(')') => break alternatives,
+                Some('$') => abort!(
+                    self.span,
+                    "`$` can only go at the end of the regex or right before \
+                     a `|`, outside of groups"
+                ),
+                _ => abort!(
+                    self.span,
+                    "the regex has a `(` without a matching `)`"
+                ),
+            }
+        }
+    }
+
+    // Pieces up to a `|`, a `)`, a `$` or the end of the regex
+    fn sequence(&mut self, top: bool) -> Vec<Node> {
+        let mut nodes: Vec<Node> = vec![];
+        while let Some(&c) = self.chars.peek() {
+            if matches!(c, '|' | ')' | '$') {
+                break;
+            }
+            self.chars.next();
+            let node = match c {
+                '(' => self.group(),
+                '[' => Node::Byte(self.class()),
+                '.' => Node::Byte([u64::MAX; 4]),
+                '\\' => self.escape(),
+                '^' if top => abort!(
+                    self.span,
+                    "`^` can only go at the start of the regex or right after \
+                     a `|`"
+                ),
+                '^' => abort!(self.span, "`^` can't go inside a group"),
+                '*' | '+' | '?' | '{' => {
+                    abort!(
+                        self.span,
+                        "`{}` in the regex has nothing to repeat",
+                        c
+                    )
+                }
+                c => Node::Literal(c.to_string().into_bytes()),
+            };
+            let node = self.quantifier(node);
+            match (nodes.last_mut(), node) {
+                (Some(Node::Literal(last)), Node::Literal(bytes)) => {
+                    last.extend(bytes)
+                }
+                (_, node) => nodes.push(node),
+            }
+        }
+        nodes
+    }
+
+    // What comes after a `(`
+    fn group(&mut self) -> Node {
+        let index = if self.chars.next_if_eq(&'?').is_some() {
+            match self.chars.next() {
+                Some(':') => None,
+                // Named groups are handed back in order like any other
+                Some('<') if !matches!(self.chars.peek(), Some('=' | '!')) => {
+                    self.name();
+                    Some(self.groups)
+                }
+                Some('P') if self.chars.next_if_eq(&'<').is_some() => {
+                    self.name();
+                    Some(self.groups)
+                }
+                Some('=' | '!' | '<') => abort!(
+                    self.span,
+                    "the regex uses a lookaround, which `strmatch_regex!` \
+                     doesn't support";
+                    help = "match what's around as part of the regex instead"
+                ),
+                _ => abort!(
+                    self.span,
+                    "the regex uses a group flag or an atomic group, which \
+                     `strmatch_regex!` doesn't support"
+                ),
+            }
+        } else {
+            Some(self.groups)
+        };
+        if index.is_some() {
+            self.groups += 1;
+        }
+        let alternatives = self.alternatives();
+        Node::Group {
+            index,
+            alternatives,
+        }
+    }
+
+    // The name of a named group, up to its `>`
+    fn name(&mut self) {
+        loop {
+            match self.chars.next() {
+                Some('>') => break,
+                Some(c) if c.is_ascii_alphanumeric() || c == '_' => {}
+                _ => abort!(self.span, "the regex has a group with a bad name"),
+            }
+        }
+    }
+
+    // What comes after a `[`: ASCII characters, ranges like `a-z` and
+    // escapes like `\d`, all of them negated by a leading `^`. A `]` right
+    // at the start is part of the class, as is a `-` at either end.
+    fn class(&mut self) -> Set {
+        let negated = self.chars.next_if_eq(&'^').is_some();
+        let mut set = [0; 4];
+        let mut first = true;
+        loop {
+            let lo = match self.chars.next() {
+                Some(']') if !first => break,
+                Some('\\') => match self.escape() {
+                    Node::Literal(bytes) if bytes.len() == 1 => {
+                        bytes[0] as char
+                    }
+                    Node::Byte(escaped) => {
+                        union(&mut set, &escaped);
+                        first = false;
+                        continue;
+                    }
+                    _ => abort!(
+                        self.span,
+                        "`[..]` in a regex only matches bytes"
+                    ),
+                },
+                Some(c) => c,
+                None => abort!(
+                    self.span,
+                    "the regex has a `[` without a matching `]`"
+                ),
+            };
+            first = false;
+            let hi = match self.chars.peek() {
+                Some('-') => {
+                    self.chars.next();
+                    match self.chars.next() {
+                        // A `-` right before the `]` is just a `-`
+                        Some(']') => {
+                            add(&mut set, lo, self.span);
+                            add(&mut set, '-', self.span);
+                            break;
+                        }
+                        Some('\\') => match self.escape() {
+                            Node::Literal(bytes) if bytes.len() == 1 => {
+                                bytes[0] as char
+                            }
+                            _ => abort!(
+                                self.span,
+                                "a range in the regex has a bad end"
+                            ),
+                        },
+                        Some(hi) => hi,
+                        None => abort!(
+                            self.span,
+                            "the regex has a `[` without a matching `]`"
+                        ),
+                    }
+                }
+                _ => lo,
+            };
+            if lo > hi {
+                abort!(
+                    self.span,
+                    "the range `{}-{}` in the regex is empty",
+                    lo,
+                    hi
+                );
+            }
+            for c in lo..=hi {
+                add(&mut set, c, self.span);
+            }
+        }
+        if negated {
+            set = set.map(|bits| !bits);
+        }
+        set
+    }
+
+    // What comes after a `\`: a class like `\d`, an escaped character or a
+    // control character like `\n`
+    fn escape(&mut self) -> Node {
+        let class = |test: fn(&u8) -> bool, negated: bool| {
+            let mut set = [0; 4];
+            for byte in (0..=u8::MAX).filter(|byte| test(byte) != negated) {
+                set[usize::from(byte / 64)] |= 1 << (byte % 64);
+            }
+            Node::Byte(set)
+        };
+        let word = |byte: &u8| byte.is_ascii_alphanumeric() || *byte == b'_';
+        let byte = match self.chars.next() {
+            Some('d') => return class(u8::is_ascii_digit, false),
+            Some('D') => return class(u8::is_ascii_digit, true),
+            Some('w') => return class(word, false),
+            Some('W') => return class(word, true),
+            Some('s') => return class(u8::is_ascii_whitespace, false),
+            Some('S') => return class(u8::is_ascii_whitespace, true),
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('0') => b'\0',
+            Some('x') => {
+                let hex = (0..2)
+                    .filter_map(|_| self.chars.next())
+                    .collect::<String>();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 => byte,
+                    _ => abort!(
+                        self.span,
+                        "`\\x` in a regex takes two hex digits, as in `\\x7f`"
+                    ),
+                }
+            }
+            Some(c) if c.is_ascii_punctuation() || c == ' ' => c as u8,
+            Some(c @ '1'..='9') => abort!(
+                self.span,
+                "the regex uses the backreference `\\{}`, which \
+                 `strmatch_regex!` doesn't support",
+                c
+            ),
+            Some(c) => abort!(
+                self.span,
+                "`\\{}` in the regex isn't supported", c;
+                help = "`strmatch_regex!` knows `\\d`, `\\w`, `\\s`, their \
+                        negations, `\\n`, `\\r`, `\\t`, `\\0`, `\\x..` and \
+                        escaped punctuation"
+            ),
+            None => abort!(self.span, "the regex ends in a lone `\\`"),
+        };
+        Node::Literal(vec![byte])
+    }
+
+    // A quantifier after `node`, if there is one
+    fn quantifier(&mut self, node: Node) -> Node {
+        let (min, max) = match self.chars.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.chars.next();
+                let mut bounds = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some('}') => break,
+                        Some(c) => bounds.push(c),
+                        None => abort!(
+                            self.span,
+                            "the regex has a `{` without a matching `}`";
+                            help = "write `\\{` to match a `{`"
+                        ),
+                    }
+                }
+                let bound = |bound: &str| match bound.parse::<usize>() {
+                    Ok(bound) => bound,
+                    Err(_) => abort!(
+                        self.span,
+                        "`{{{}}}` in the regex isn't a repetition count", bounds;
+                        help = "write `{n}`, `{n,}` or `{n,m}`, or \
+                                `\\{` to match a `{`"
+                    ),
+                };
+                let (min, max) = match bounds.split_once(',') {
+                    Some((min, "")) => (bound(min), None),
+                    Some((min, max)) => (bound(min), Some(bound(max))),
+                    None => (bound(&bounds), Some(bound(&bounds))),
+                };
+                if max.is_some_and(|max| max < min) {
+                    abort!(self.span, "`{{{}}}` in the regex is empty", bounds);
+                }
+                return self.repeat(node, min, max);
+            }
+            _ => return node,
+        };
+        self.chars.next();
+        self.repeat(node, min, max)
+    }
+
+    fn repeat(&mut self, node: Node, min: usize, max: Option<usize>) -> Node {
+        let lazy = self.chars.next_if_eq(&'?').is_some();
+        if self.chars.next_if_eq(&'+').is_some() {
+            abort!(
+                self.span,
+                "the regex uses a possessive quantifier, which \
+                 `strmatch_regex!` doesn't support";
+                help = "drop the last `+`; a repetition of a class that what \
+                        follows can't start with takes the whole run anyway"
+            );
+        }
+        if matches!(self.chars.peek(), Some('*' | '+' | '?' | '{')) {
+            abort!(self.span, "the regex repeats a repetition");
+        }
+        // A lone byte repeats like a class of one
+        let node = match node {
+            Node::Literal(bytes) if bytes.len() == 1 => {
+                Node::Byte(single(bytes[0]))
+            }
+            node => node,
+        };
+        Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            lazy,
+        }
+    }
+}
+
+fn add(set: &mut Set, c: char, span: Span) {
+    if !c.is_ascii() {
+        abort!(
+            span,
+            "`[..]` in a regex only matches ASCII characters";
+            help = "use `(?:..|..)` to match one of several characters"
+        );
+    }
+    set[usize::from(c as u8 / 64)] |= 1 << (c as u8 % 64);
+}
+
+fn single(byte: u8) -> Set {
+    let mut set = [0; 4];
+    set[usize::from(byte / 64)] |= 1 << (byte % 64);
+    set
+}
+
+fn union(set: &mut Set, other: &Set) {
+    for (bits, other) in set.iter_mut().zip(other) {
+        *bits |= other;
+    }
+}
+
+fn is_disjoint(set: &Set, other: &Set) -> bool {
+    set.iter().zip(other).all(|(bits, other)| bits & other == 0)
+}
+
+// The bytes a match of `nodes` can start with, and whether it can be empty
+fn starts(nodes: &[Node]) -> (Set, bool) {
+    let mut set = [0; 4];
+    for node in nodes {
+        let (first, empty) = match node {
+            Node::Literal(bytes) => match bytes.first() {
+                Some(byte) => (single(*byte), false),
+                None => continue,
+            },
+            Node::Byte(bytes) => (*bytes, false),
+            Node::Repeat { node, min, .. } => {
+                let (first, empty) = starts(std::slice::from_ref(node));
+                (first, empty || *min == 0)
+            }
+            Node::Group { alternatives, .. } => {
+                let mut first = [0; 4];
+                let mut empty = false;
+                for alternative in alternatives {
+                    let (start, can_be_empty) = starts(alternative);
+                    union(&mut first, &start);
+                    empty |= can_be_empty;
+                }
+                (first, empty)
+            }
+        };
+        union(&mut set, &first);
+        if !empty {
+            return (set, false);
+        }
+    }
+    (set, true)
+}
+
+// The runs of consecutive bytes in the set
+fn ranges(set: &Set) -> Vec<(u8, u8)> {
+    let mut ranges: Vec<(u8, u8)> = vec![];
+    for byte in (0..=u8::MAX)
+        .filter(|byte| (set[usize::from(byte / 64)] >> (byte % 64)) & 1 == 1)
+    {
+        match ranges.last_mut() {
+            Some((_, hi)) if *hi + 1 == byte => *hi = byte,
+            _ => ranges.push((byte, byte)),
+        }
+    }
+    ranges
+}
+
+// Spell out the ways a sequence can match, until all that's left are
+// literals, bytes, capturing groups and repetitions with no upper bound.
+// Repetitions of a byte keep their bounds, since they become runs.
+fn expand(nodes: &[Node], span: Span) -> Vec<Vec<Node>> {
+    let mut sequences = vec![vec![]];
+    for node in nodes {
+        let options = match node {
+            Node::Group {
+                index: None,
+                alternatives,
+            } => alternatives
+                .iter()
+                .flat_map(|alternative| expand(alternative, span))
+                .collect(),
+            // A fixed number of bytes matches byte by byte
+            Node::Repeat {
+                node: inner,
+                min,
+                max: Some(max),
+                ..
+            } if min == max && matches!(**inner, Node::Byte(_)) => {
+                vec![vec![(**inner).clone(); *min]]
+            }
+            Node::Repeat {
+                node: inner,
+                min,
+                max,
+                lazy,
+            } if !matches!(**inner, Node::Byte(_)) => {
+                if let Node::Group { index: Some(_), .. } = **inner {
+                    abort!(
+                        span,
+                        "a group in the regex is optional or repeated, which \
+                         `strmatch_regex!` can't capture";
+                        help = "make the group non-capturing, as in `(?:..)`, \
+                                or put it around the whole repetition"
+                    );
+                }
+                let copies = |count: usize| {
+                    expand(&vec![(**inner).clone(); count], span)
+                };
+                match max {
+                    None if *min == 0 => vec![vec![node.clone()]],
+                    None => {
+                        let star = Node::Repeat {
+                            node: inner.clone(),
+                            min: 0,
+                            max: None,
+                            lazy: *lazy,
+                        };
+                        copies(*min)
+                            .into_iter()
+                            .map(|mut sequence| {
+                                sequence.push(star.clone());
+                                sequence
+                            })
+                            .collect()
+                    }
+                    // Greedy repetitions try more copies first
+                    Some(max) => {
+                        let mut counts =
+                            (*min..=*max).rev().collect::<Vec<_>>();
+                        if *lazy {
+                            counts.reverse();
+                        }
+                        counts.into_iter().flat_map(copies).collect()
+                    }
+                }
+            }
+            node => vec![vec![node.clone()]],
+        };
+        sequences = sequences
+            .iter()
+            .flat_map(|sequence| {
+                options.iter().map(move |option| {
+                    let mut sequence = sequence.clone();
+                    sequence.extend(option.iter().cloned());
+                    sequence
+                })
+            })
+            .collect();
+        if sequences.len() > MAX_ALTERNATIVES {
+            abort!(
+                span,
+                "the regex has more than {} ways to match", MAX_ALTERNATIVES;
+                help = "each `?`, `{n,m}` on a group and `|` in a group \
+                        multiplies them"
+            );
+        }
+    }
+    sequences
+}
+
+/// Turns expanded sequences into captures
+struct Lowering {
+    span: Span,
+    /// How many bytes have been given names to check them against a class
+    bytes: usize,
+    /// The groups the current sequence captures
+    groups: Vec<usize>,
+}
+
+impl Lowering {
+    // The captures for `nodes`, which have to reach the end of the input if
+    // `end`, and can be followed by anything otherwise
+    fn sequence(&mut self, nodes: &[Node], end: bool) -> Vec<Capture> {
+        let mut literals = vec![];
+        for (i, node) in nodes.iter().enumerate() {
+            // The bytes that can come right after this node
+            let after = match starts(&nodes[i + 1..]) {
+                (_, true) if !end => [u64::MAX; 4],
+                (after, _) => after,
+            };
+            let capture = match node {
+                Node::Literal(bytes) => Capture::ByteStr {
+                    lit: LitByteStr::new(bytes, self.span),
+                    reps: 1,
+                },
+                Node::Byte(set) if *set == [u64::MAX; 4] => {
+                    Capture::Underscore(Token![_](self.span))
+                }
+                Node::Byte(set) => {
+                    let byte = hidden(&format!("byte{}", self.bytes));
+                    self.bytes += 1;
+                    let ranges = ranges(set)
+                        .into_iter()
+                        .map(|(lo, hi)| quote!(#lo..=#hi));
+                    let guard = syn::parse_quote!(
+                        ::core::matches!(*#byte, #(#ranges)|*)
+                    );
+                    Capture::Guarded {
+                        capture: Box::new(Capture::Ident(byte)),
+                        guard: Box::new(guard),
+                    }
+                }
+                Node::Repeat {
+                    node,
+                    min,
+                    max,
+                    lazy,
+                } => match &**node {
+                    Node::Byte(set) => {
+                        self.run(set, *min, *max, *lazy, None, &after)
+                    }
+                    // A group matches as many times as it can. When it can
+                    // start with what follows, it's a slice instead, which
+                    // gives back copies like a regex engine does, and has to
+                    // hold whole copies of the group.
+                    node => {
+                        let mut options =
+                            expand(std::slice::from_ref(node), self.span);
+                        if options.len() > 1 {
+                            abort!(
+                                self.span,
+                                "a group repeated with no upper bound can't have \
+                                 `|` or `?` in it"
+                            );
+                        }
+                        let mut pattern = Pattern {
+                            whole: None,
+                            min_len: None,
+                            literals: self
+                                .sequence(&options.pop().unwrap(), false),
+                            end: None,
+                        };
+                        let paren = token::Paren(self.span);
+                        pattern.capture_rest(hidden("left"), self.span);
+                        let group = Capture::Group {
+                            paren,
+                            count: None,
+                            pattern: Box::new(pattern),
+                        };
+                        let (first, _) = starts(std::slice::from_ref(node));
+                        if !lazy && is_disjoint(&first, &after) {
+                            group
+                        } else {
+                            self.bytes += 1;
+                            let name = hidden(&format!("run{}", self.bytes));
+                            let copies = Pattern {
+                                whole: None,
+                                min_len: None,
+                                literals: vec![group],
+                                end: None,
+                            };
+                            let matcher = matcher(
+                                &name,
+                                &[copies],
+                                None,
+                                Codegen::default(),
+                            );
+                            let guard = syn::parse_quote!((#matcher).is_some());
+                            let slice = self.slice(Some(name), *lazy);
+                            guarded(Capture::Slice(slice), Some(guard))
+                        }
+                    }
+                },
+                Node::Group {
+                    index: Some(index),
+                    alternatives,
+                } => {
+                    self.groups.push(*index);
+                    self.group(*index, alternatives, &after)
+                }
+                Node::Group { index: None, .. } => {
+                    unreachable!("groups are expanded")
+                }
+            };
+            match (literals.last_mut(), capture) {
+                (
+                    Some(Capture::ByteStr { lit: last, .. }),
+                    Capture::ByteStr { lit, .. },
+                ) => {
+                    let mut joined = last.value();
+                    joined.extend(lit.value());
+                    *last = LitByteStr::new(&joined, self.span);
+                }
+                (_, capture) => literals.push(capture),
+            }
+        }
+        literals
+    }
+
+    // The capture of a group: a run or slice when that's all it holds, and
+    // otherwise a slice that the group's own patterns have to match whole.
+    // `after` is the bytes that can come right after the group.
+    fn group(
+        &mut self,
+        index: usize,
+        alternatives: &[Vec<Node>],
+        after: &Set,
+    ) -> Capture {
+        let name = hidden(&format!("group{index}"));
+        if let [nodes] = alternatives {
+            match nodes.as_slice() {
+                [Node::Repeat {
+                    node,
+                    min,
+                    max,
+                    lazy,
+                }] => {
+                    if let Node::Byte(set) = &**node {
+                        return self.run(
+                            set,
+                            *min,
+                            *max,
+                            *lazy,
+                            Some(name),
+                            after,
+                        );
+                    }
+                }
+                [Node::Byte(set)] => {
+                    return self.run(set, 1, Some(1), false, Some(name), after)
+                }
+                _ => {}
+            }
+        }
+        let outer = std::mem::take(&mut self.groups);
+        let mut patterns = vec![];
+        for alternative in alternatives {
+            for nodes in expand(alternative, self.span) {
+                let literals = self.sequence(&nodes, true);
+                patterns.push(Pattern {
+                    whole: None,
+                    min_len: None,
+                    literals,
+                    end: None,
+                });
+            }
+        }
+        if !self.groups.is_empty() {
+            abort!(
+                self.span,
+                "`strmatch_regex!` can't capture a group inside a group"
+            );
+        }
+        self.groups = outer;
+        let matcher = matcher(&name, &patterns, None, Codegen::default());
+        let guard = syn::parse_quote!((#matcher).is_some());
+        guarded(Capture::Slice(self.slice(Some(name), false)), Some(guard))
+    }
+
+    // A repetition of a byte in `set`, followed by one of the bytes in
+    // `after`. When none of those are in `set`, giving back bytes can't help
+    // what follows match, so it takes the whole run. Otherwise it's a slice,
+    // which backtracks like a regex engine does, checked byte by byte.
+    fn run(
+        &mut self,
+        set: &Set,
+        min: usize,
+        max: Option<usize>,
+        lazy: bool,
+        name: Option<Ident>,
+        after: &Set,
+    ) -> Capture {
+        let any = *set == [u64::MAX; 4];
+        let whole = !any && !lazy && is_disjoint(set, after);
+        // Runs and slices only know whether they can be empty, so other
+        // bounds are checked with a guard, as are the bytes of a slice
+        let bounded = min > 1 || max.is_some() || (!whole && min == 1);
+        let (name, guard) = if bounded || (!whole && !any) {
+            let name = name.unwrap_or_else(|| {
+                self.bytes += 1;
+                hidden(&format!("run{}", self.bytes))
+            });
+            let mut checks: Vec<Expr> = vec![];
+            match max {
+                Some(max) => checks.push(syn::parse_quote!(
+                    (#min..=#max).contains(&#name.len())
+                )),
+                None if bounded => {
+                    checks.push(syn::parse_quote!(#name.len() >= #min))
+                }
+                None => {}
+            }
+            if !whole && !any {
+                let ranges =
+                    ranges(set).into_iter().map(|(lo, hi)| quote!(#lo..=#hi));
+                checks.push(syn::parse_quote!(
+                    #name.iter().all(|byte| ::core::matches!(*byte, #(#ranges)|*))
+                ));
+            }
+            let guard = syn::parse_quote!(#(#checks)&&*);
+            (Some(name), Some(guard))
+        } else {
+            (name, None)
+        };
+        if !whole {
+            return guarded(Capture::Slice(self.slice(name, lazy)), guard);
+        }
+        let class = match ranges(set).as_slice() {
+            [(lo, hi)] => Class::Range(*lo, *hi),
+            _ => Class::Set(*set),
+        };
+        let run = Capture::Run {
+            span: self.span,
+            class,
+            optional: min == 0,
+            binding: name,
+        };
+        guarded(run, guard)
+    }
+
+    fn slice(&self, name: Option<Ident>, lazy: bool) -> SliceCapture {
+        SliceCapture {
+            bracket: token::Bracket(self.span),
+            name,
+            ty: None,
+            lazy,
+            until: None,
+            saturating: false,
+            trim: false,
+            steps: vec![],
+            guard: None,
+        }
+    }
+}
+
+fn guarded(capture: Capture, guard: Option<Expr>) -> Capture {
+    match guard {
+        Some(guard) => Capture::Guarded {
+            capture: Box::new(capture),
+            guard: Box::new(guard),
+        },
+        None => capture,
+    }
+}
+
+impl ToTokens for RegexInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let groups = &self.groups;
+        let output = captured(groups);
+        // Each alternative binds names of its own, so each gets a matcher of
+        // its own, tried in turn
+        let mut chain = quote!(::core::option::Option::None);
+        for pattern in self.alternatives.iter().rev() {
+            let matcher = matcher(
+                &input,
+                std::slice::from_ref(pattern),
+                None,
+                Codegen::default(),
+            );
+            let bindings = pattern.bindings().into_iter().map(|binding| {
+                if groups.contains(&binding) {
+                    quote!(#binding)
+                } else {
+                    quote!(_)
+                }
+            });
+            chain = quote! {
+                match #matcher {
+                    ::core::option::Option::Some((#(#bindings,)*)) => {
+                        ::core::option::Option::Some(#output)
+                    }
+                    ::core::option::Option::None => #chain,
+                }
+            };
+        }
+        let expr = &self.input;
+        let coerce = coerce_input(&input, expr);
+        tokens.extend(quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    #chain
+                }
+            }
+        });
+    }
+}
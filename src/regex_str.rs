@@ -0,0 +1,495 @@
+//! Expansion for `strmatch_regex_str!`, which writes a pattern out as a
+//! regex, for handing the same filter to tools outside of Rust.
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::LitStr;
+
+use crate::pattern::{
+    Alternatives, Capture, Class, Pattern, SliceCapture, Step,
+};
+use crate::presets::{Preset, PresetArg};
+use crate::unicode::Category;
+
+/// The bytes `#ws` matches, inside a `[..]`
+const WS: &str = r"\t\n\f\r ";
+
+/// Any byte, `\n` included, which a bare `.` leaves out
+const ANY: &str = "(?s:.)";
+
+/// Which of the 256 bytes something can start with
+type Starts = [bool; 256];
+
+/// The patterns of a `strmatch_regex_str!`
+pub(crate) struct RegexStrInput {
+    regex: LitStr,
+}
+
+impl Parse for RegexStrInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let Alternatives(patterns) = input.parse()?;
+        let alternatives = patterns.iter().map(pattern).collect::<Vec<_>>();
+        let regex = match alternatives.as_slice() {
+            [alternative] => format!("^{alternative}$"),
+            alternatives => format!("^(?:{})$", alternatives.join("|")),
+        };
+        Ok(RegexStrInput {
+            regex: LitStr::new(&regex, Span::call_site()),
+        })
+    }
+}
+
+impl ToTokens for RegexStrInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let regex = &self.regex;
+        tokens.extend(quote!(#regex));
+    }
+}
+
+// A regex matching what the pattern matches, without anchors. Each binding
+// is a capturing group, so the groups come in the order the bindings do.
+fn pattern(pattern: &Pattern) -> String {
+    if let Some((_, span)) = pattern.min_len {
+        abort!(span, "`min_len` has no regex equivalent");
+    }
+    let end = pattern.end.as_ref().map(|end| slice_starts(end).0);
+    check_runs(&pattern.literals, &end.unwrap_or([false; 256]));
+    let mut regex = String::new();
+    for capture in &pattern.literals {
+        regex += &self::capture(capture);
+    }
+    if let Some(end) = &pattern.end {
+        regex += &slice(end);
+    }
+    match &pattern.whole {
+        Some(_) => format!("({regex})"),
+        None => regex,
+    }
+}
+
+fn capture(capture: &Capture) -> String {
+    let group = |binding: bool, regex: String| match binding {
+        true => format!("({regex})"),
+        false => regex,
+    };
+    match capture {
+        Capture::Ident(_) => format!("({ANY})"),
+        Capture::Underscore(_) => ANY.to_string(),
+        Capture::Utf8Char { binding, .. } => {
+            group(binding.is_some(), ANY.to_string())
+        }
+        Capture::Run {
+            class,
+            optional,
+            binding,
+            ..
+        } => {
+            let quantifier = if *optional { '*' } else { '+' };
+            group(
+                binding.is_some(),
+                format!("{}{quantifier}", self::class(*class)),
+            )
+        }
+        Capture::NoCase { literal, .. } => {
+            format!("(?i:{})", self::capture(literal))
+        }
+        Capture::Whitespace {
+            optional, binding, ..
+        } => {
+            let quantifier = if *optional { '*' } else { '+' };
+            group(binding.is_some(), format!("[{WS}]{quantifier}"))
+        }
+        Capture::Newline { eol: false, .. } => r"\r?\n".to_string(),
+        Capture::Newline { eol: true, .. } => r"(?:\r?\n|$)".to_string(),
+        Capture::Preset {
+            preset,
+            binding,
+            args,
+            ..
+        } => {
+            if args.iter().any(|arg| {
+                matches!(arg, PresetArg::Bytes(..) | PresetArg::Value(_))
+            }) {
+                abort!(
+                    capture.span(),
+                    "a preset's checked outputs have no regex equivalent";
+                    help = "bind the outputs and check them after matching"
+                );
+            }
+            let regex = match preset {
+                Preset::HexRun { len: Some(len), .. } => {
+                    format!("[0-9A-Fa-f]{{{}}}", 2 * len)
+                }
+                preset => preset.regex().to_string(),
+            };
+            group(binding.is_some(), format!("(?:{regex})"))
+        }
+        Capture::OneOf { options, .. } => {
+            let options = options
+                .iter()
+                .map(|option| bytes(&option.literal_bytes().unwrap()))
+                .collect::<Vec<_>>();
+            format!("(?:{})", options.join("|"))
+        }
+        Capture::Slice(slice) => self::slice(slice),
+        Capture::ByteRange {
+            binding,
+            start,
+            end,
+        } => {
+            let start = start.literal_bytes().unwrap()[0];
+            let regex = match end {
+                Some(end) => {
+                    let end = end.literal_bytes().unwrap()[0];
+                    set(&(start..=end).collect::<Vec<_>>())
+                }
+                None => bytes(&[start]),
+            };
+            group(binding.is_some(), regex)
+        }
+        Capture::Group { pattern, .. } => {
+            // Leaving out the hidden capture of what's left
+            let mut regex = String::new();
+            for capture in &pattern.literals {
+                regex += &self::capture(capture);
+            }
+            format!("(?:{regex})*")
+        }
+        Capture::Grapheme { .. } => abort!(
+            capture.span(),
+            "`#grapheme` has no regex equivalent that engines agree on";
+            help = "use `#utf8char`, which is `(?s:.)`"
+        ),
+        Capture::Peek { .. } => abort!(
+            capture.span(),
+            "lookahead has no regex equivalent that engines agree on"
+        ),
+        Capture::Var { .. } | Capture::Const { .. } | Capture::Pad { .. } => {
+            abort!(
+                capture.span(),
+                "this segment's value is only known when the program runs, \
+                 so it can't be written into a regex"
+            )
+        }
+        Capture::Guarded { .. } => abort!(
+            capture.span(),
+            "a guard is Rust code, so it can't be written into a regex";
+            help = "check it after the regex matches instead"
+        ),
+        capture => match capture.literal_bytes() {
+            Some(literal) => bytes(&literal),
+            None => unreachable!("every other capture is a literal"),
+        },
+    }
+}
+
+// Runs take every byte they can, and never give any back, where a regex's
+// `+` or `*` backtracks. The two only agree when what comes after a run,
+// then `after`, can't start with a byte the run could have taken, so runs
+// that could give bytes back are turned down.
+fn check_runs(captures: &[Capture], after: &Starts) {
+    for (i, capture) in captures.iter().enumerate() {
+        let next = starts(&captures[i + 1..], after);
+        let (run, name) = match capture {
+            Capture::Run { span, class, .. } => {
+                (of(&class_bytes(*class)), *span)
+            }
+            Capture::Whitespace { keyword, .. } => {
+                (of(b"\t\n\x0c\r "), keyword.span())
+            }
+            Capture::Group { paren, pattern, .. } => {
+                // The last capture in the group is followed by either the
+                // group again or what comes after it
+                let own = starts(&pattern.literals, &[false; 256]);
+                let mut again = own;
+                union(&mut again, &next);
+                check_runs(&pattern.literals, &again);
+                if own.iter().zip(next).any(|(own, next)| *own && next) {
+                    abort!(
+                        paren.span,
+                        "this group repeats as many times as it can, where a \
+                         regex gives repetitions back to what follows, so the \
+                         regex would match inputs the pattern doesn't";
+                        help = "follow the group with something it can't \
+                                start with, or with a capture like `[rest]` \
+                                that takes anything"
+                    );
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if run.iter().zip(next).any(|(run, next)| *run && next) {
+            abort!(
+                name,
+                "this run takes all the bytes it can, where a regex gives some \
+                 back to what follows, so the regex would match inputs the \
+                 pattern doesn't";
+                help = "follow the run with something it can't match, or \
+                        with a capture like `[rest]` that takes anything"
+            );
+        }
+    }
+}
+
+// The bytes a match of `captures`, then of `after` if they can all be
+// empty, can start with
+fn starts(captures: &[Capture], after: &Starts) -> Starts {
+    let mut starts = [false; 256];
+    for capture in captures {
+        let (first, empty) = first(capture);
+        union(&mut starts, &first);
+        if !empty {
+            return starts;
+        }
+    }
+    union(&mut starts, after);
+    starts
+}
+
+// The bytes a capture can start with, and whether it can be empty. Anything
+// that isn't worth working out can start with any byte.
+fn first(capture: &Capture) -> (Starts, bool) {
+    let first_of = |capture: &Capture| match capture.literal_bytes() {
+        Some(bytes) => (of(&bytes[..bytes.len().min(1)]), bytes.is_empty()),
+        None => ([true; 256], true),
+    };
+    match capture {
+        Capture::Run {
+            class, optional, ..
+        } => (of(&class_bytes(*class)), *optional),
+        Capture::Whitespace { optional, .. } => (of(b"\t\n\x0c\r "), *optional),
+        Capture::Newline { eol, .. } => (of(b"\r\n"), *eol),
+        Capture::NoCase { literal, .. } => {
+            let (mut first, empty) = first_of(literal);
+            for byte in 0..=u8::MAX {
+                if first[usize::from(byte)] {
+                    first[usize::from(byte.to_ascii_lowercase())] = true;
+                    first[usize::from(byte.to_ascii_uppercase())] = true;
+                }
+            }
+            (first, empty)
+        }
+        Capture::ByteRange { start, end, .. } => {
+            let start = start.literal_bytes().unwrap()[0];
+            let end = end
+                .as_ref()
+                .map_or(start, |end| end.literal_bytes().unwrap()[0]);
+            (of(&(start..=end).collect::<Vec<_>>()), false)
+        }
+        Capture::OneOf { options, .. } => {
+            let mut starts = [false; 256];
+            let mut empty = false;
+            for option in options {
+                let (first, can_be_empty) = first_of(option);
+                union(&mut starts, &first);
+                empty |= can_be_empty;
+            }
+            (starts, empty)
+        }
+        Capture::Group { pattern, .. } => {
+            (starts(&pattern.literals, &[false; 256]), true)
+        }
+        Capture::Slice(slice) => slice_starts(slice),
+        capture => first_of(capture),
+    }
+}
+
+// What a bracketed capture can start with. One that isn't parsed can take
+// any bytes a run gives back, so it never minds what it starts with.
+fn slice_starts(slice: &SliceCapture) -> (Starts, bool) {
+    let ty = parsed_type(slice).map(|ty| ty.to_token_stream().to_string());
+    let number: &[u8] = match ty.as_deref() {
+        None => return ([false; 256], false),
+        Some("u8" | "u16" | "u32" | "u64" | "u128" | "usize") => b"+",
+        Some("i8" | "i16" | "i32" | "i64" | "i128" | "isize") => b"+-",
+        Some("f32" | "f64") => b"+-.",
+        Some("bool") => return (of(b"tf"), false),
+        Some(_) => return ([true; 256], false),
+    };
+    let mut starts = of(number);
+    union(&mut starts, &of(b"0123456789"));
+    if slice.trim || slice.steps.iter().any(|step| matches!(step, Step::Trim)) {
+        union(&mut starts, &of(b"\t\n\x0c\r "));
+    }
+    (starts, false)
+}
+
+// The type a bracketed capture is parsed into, if it is
+fn parsed_type(slice: &SliceCapture) -> Option<&syn::Type> {
+    let parsed = slice.steps.iter().rev().find_map(|step| match step {
+        Step::Parse(parsed) => Some(&**parsed),
+        _ => None,
+    });
+    parsed.or(slice.ty.as_deref())
+}
+
+// The bytes a run of the class can start with. A run of a Unicode class can
+// start with its ASCII characters, or with any byte that starts a longer one.
+fn class_bytes(class: Class) -> Vec<u8> {
+    match (class.bytes(), class) {
+        (Some(bytes), _) => bytes,
+        (None, Class::Unicode(category)) => {
+            let ascii = category
+                .ranges()
+                .into_iter()
+                .flat_map(|(lo, hi)| (lo..=hi.min(0x7f)).map(|c| c as u8));
+            ascii.chain(0x80..=u8::MAX).collect()
+        }
+        (None, _) => (0..=u8::MAX).collect(),
+    }
+}
+
+fn of(bytes: &[u8]) -> Starts {
+    let mut starts = [false; 256];
+    for byte in bytes {
+        starts[usize::from(*byte)] = true;
+    }
+    starts
+}
+
+fn union(starts: &mut Starts, other: &Starts) {
+    for (start, other) in starts.iter_mut().zip(other) {
+        *start |= other;
+    }
+}
+
+// A bracketed capture, as the run of bytes it matches: the number a typed
+// capture parses, a `[^..]*` for a one-byte `until`, and otherwise `(?s:.*)`
+fn slice(slice: &SliceCapture) -> String {
+    if slice.guard.is_some() {
+        abort!(
+            slice.bracket.span,
+            "a guard is Rust code, so it can't be written into a regex";
+            help = "check it after the regex matches instead"
+        );
+    }
+    for step in &slice.steps {
+        if let Step::Call(call) = step {
+            abort!(
+                call,
+                "a pipeline's function is Rust code, so it can't be written \
+                 into a regex"
+            );
+        }
+    }
+    let ty = parsed_type(slice);
+    let lazy = if slice.lazy { "?" } else { "" };
+    let trim =
+        slice.trim || slice.steps.iter().any(|step| matches!(step, Step::Trim));
+    let regex = match ty.map(|ty| ty.to_token_stream().to_string()).as_deref() {
+        Some("u8" | "u16" | "u32" | "u64" | "u128" | "usize") => {
+            r"\+?[0-9]+".to_string()
+        }
+        Some("i8" | "i16" | "i32" | "i64" | "i128" | "isize") => {
+            "[+-]?[0-9]+".to_string()
+        }
+        Some("f32" | "f64") => {
+            r"[+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[Ee][+-]?[0-9]+)?"
+                .to_string()
+        }
+        Some("bool") => "true|false".to_string(),
+        Some("char") => ANY.to_string(),
+        _ => match &slice.until {
+            Some(until) if until.len() == 1 => {
+                let others = (0..=u8::MAX)
+                    .filter(|byte| *byte != until[0])
+                    .collect::<Vec<_>>();
+                format!("{}*", set(&others))
+            }
+            Some(_) => "(?s:.*?)".to_string(),
+            // Lazy when trimmed, so the whitespace after it is left out
+            None if trim => "(?s:.*?)".to_string(),
+            None => format!("(?s:.*{lazy})"),
+        },
+    };
+    let regex = match slice.name {
+        Some(_) => format!("({regex})"),
+        None if regex.contains('|') => format!("(?:{regex})"),
+        None => regex,
+    };
+    match trim {
+        true => format!("[{WS}]*{regex}[{WS}]*"),
+        false => regex,
+    }
+}
+
+// The bytes in a class, as a `[..]` or a Unicode category
+fn class(class: Class) -> String {
+    match class {
+        Class::Unicode(category) => match category {
+            Category::Alpha => r"\p{L}",
+            Category::Digit => r"\p{N}",
+            Category::Alnum => r"[\p{L}\p{N}]",
+            Category::Upper => r"\p{Lu}",
+            Category::Lower => r"\p{Ll}",
+            Category::Space => r"\s",
+        }
+        .to_string(),
+        class => set(&class.bytes().unwrap()),
+    }
+}
+
+// A `[..]` matching one of `bytes`, which are in order, with ranges for runs
+// of three or more
+fn set(bytes: &[u8]) -> String {
+    // A set of most bytes is written as the ones it leaves out
+    let (negated, bytes) = match bytes.len() > 128 {
+        true => {
+            let left_out = (0..=u8::MAX).filter(|byte| !bytes.contains(byte));
+            ("^", left_out.collect())
+        }
+        false => ("", bytes.to_vec()),
+    };
+    let mut ranges: Vec<(u8, u8)> = vec![];
+    for byte in bytes {
+        match ranges.last_mut() {
+            Some((_, hi)) if *hi + 1 == byte => *hi = byte,
+            _ => ranges.push((byte, byte)),
+        }
+    }
+    let mut regex = format!("[{negated}");
+    for (lo, hi) in ranges {
+        regex += &escape(lo, true);
+        if hi > lo + 1 {
+            regex.push('-');
+        }
+        if hi > lo {
+            regex += &escape(hi, true);
+        }
+    }
+    regex.push(']');
+    regex
+}
+
+// Literal bytes, escaped. UTF-8 is written as the characters it encodes.
+fn bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text
+            .chars()
+            .map(|c| match u8::try_from(c) {
+                Ok(byte) => escape(byte, false),
+                Err(_) => c.to_string(),
+            })
+            .collect(),
+        Err(_) => bytes.iter().map(|byte| escape(*byte, false)).collect(),
+    }
+}
+
+// A byte as a regex matching it, in a `[..]` or not
+fn escape(byte: u8, bracketed: bool) -> String {
+    let special: &[u8] = match bracketed {
+        true => br"\[]^-",
+        false => br"\.+*?()|[]{}^$",
+    };
+    match byte {
+        b'\n' => r"\n".to_string(),
+        b'\r' => r"\r".to_string(),
+        b'\t' => r"\t".to_string(),
+        _ if special.contains(&byte) => format!("\\{}", byte as char),
+        b' '..=b'~' => (byte as char).to_string(),
+        _ => format!(r"\x{byte:02x}"),
+    }
+}
@@ -0,0 +1,169 @@
+//! Parsing and expansion for `strmatch_replace!`, which replaces every match
+//! of a pattern in its input, giving back a `Cow<[u8]>` that only owns a copy
+//! if something was replaced.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, Expr, Ident, Lit, LitByteStr, Token};
+
+use crate::engine::{coerce_input, hidden, matcher, with_helpers, Codegen};
+use crate::find::position;
+use crate::pattern::Pattern;
+
+/// `input, (pattern) => replacement`
+pub(crate) struct ReplaceInput {
+    input: Expr,
+    pattern: Pattern,
+    /// The fixed bytes every match starts with, which are searched for
+    prefix: Vec<u8>,
+    replacement: Vec<Piece>,
+}
+
+/// A part of the replacement: some bytes, or what a capture matched
+enum Piece {
+    Bytes(LitByteStr),
+    Capture(Ident),
+}
+
+impl Parse for ReplaceInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let inner;
+        let paren = parenthesized!(inner in input);
+        let mut pattern: Pattern = inner.parse()?;
+        let prefix = pattern
+            .literals
+            .iter()
+            .map_while(|capture| capture.literal_bytes())
+            .flatten()
+            .collect::<Vec<_>>();
+        if prefix.is_empty() {
+            abort!(
+                paren.span,
+                "the pattern has to start with fixed bytes, which are what's \
+                 searched for";
+                help = "start it with a literal, like `(\"${{\" [name until '}}'] '}}')`"
+            );
+        }
+        input.parse::<Token![=>]>()?;
+        let bindings = pattern.bindings();
+        let mut replacement = vec![];
+        while !input.is_empty() {
+            if input.peek(Ident) {
+                let ident = input.parse::<Ident>()?;
+                if !bindings.contains(&ident) {
+                    abort!(ident, "`{}` isn't captured by the pattern", ident);
+                }
+                replacement.push(Piece::Capture(ident));
+                continue;
+            }
+            let lit = input.parse::<Lit>()?;
+            let bytes = match &lit {
+                Lit::Str(lit) => lit.value().into_bytes(),
+                Lit::ByteStr(lit) => lit.value(),
+                Lit::Char(lit) => lit.value().to_string().into_bytes(),
+                Lit::Byte(lit) => vec![lit.value()],
+                lit => abort!(
+                    lit,
+                    "expected a string, byte string, char or byte literal, or \
+                     the name of a capture"
+                ),
+            };
+            // Runs of literals are written in one go
+            match replacement.last_mut() {
+                Some(Piece::Bytes(last)) => {
+                    let mut joined = last.value();
+                    joined.extend(bytes);
+                    *last = LitByteStr::new(&joined, last.span());
+                }
+                _ => replacement
+                    .push(Piece::Bytes(LitByteStr::new(&bytes, lit.span()))),
+            }
+        }
+        // Each match takes what it needs from the start of where the
+        // search found its prefix
+        pattern.capture_rest(hidden("rest"), paren.span);
+        Ok(ReplaceInput {
+            input: expr,
+            pattern,
+            prefix,
+            replacement,
+        })
+    }
+}
+
+impl ToTokens for ReplaceInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(with_helpers(|| self.expand()));
+    }
+}
+
+impl ReplaceInput {
+    fn expand(&self) -> TokenStream {
+        let input = hidden("input");
+        let coerce = coerce_input(&input, &self.input);
+        let search = hidden("search");
+        let position = position(&search, &self.prefix);
+        let left = hidden("left");
+        let matcher = matcher(
+            &left,
+            std::slice::from_ref(&self.pattern),
+            None,
+            Codegen::default(),
+        );
+        let mut bindings = self.pattern.bindings();
+        let rest = bindings.pop();
+        let out = hidden("out");
+        let replaced = hidden("replaced");
+        let copied = hidden("copied");
+        let from = hidden("from");
+        let start = hidden("start");
+        let at = hidden("at");
+        let pieces = self.replacement.iter().map(|piece| match piece {
+            Piece::Bytes(bytes) => quote!(#out.extend_from_slice(#bytes);),
+            Piece::Capture(ident) => quote! {
+                #out.extend_from_slice(::core::convert::AsRef::<[u8]>::as_ref(#ident));
+            },
+        });
+        let expr = &self.input;
+        quote! {
+            match #expr {
+                #input => {
+                    #coerce
+                    // Once something's been replaced, `out` holds the new
+                    // copy of everything before `copied`
+                    let mut #out = ::std::vec::Vec::<u8>::new();
+                    let mut #copied = 0usize;
+                    let mut #from = 0usize;
+                    let mut #replaced = false;
+                    let mut #search: &[u8] = #input;
+                    while let ::core::option::Option::Some(#at) = #position {
+                        let #start = #from + #at;
+                        let #left: &[u8] = &#input[#start..];
+                        #[allow(unused_variables)]
+                        match #matcher {
+                            ::core::option::Option::Some((#(#bindings,)* #rest,)) => {
+                                #out.extend_from_slice(&#input[#copied..#start]);
+                                #(#pieces)*
+                                #copied = #input.len() - #rest.len();
+                                #from = #copied;
+                                #replaced = true;
+                            }
+                            ::core::option::Option::None => #from = #start + 1,
+                        }
+                        #search = &#input[#from..];
+                    }
+                    if #replaced {
+                        #out.extend_from_slice(&#input[#copied..]);
+                        ::std::borrow::Cow::<[u8]>::Owned(#out)
+                    } else {
+                        ::std::borrow::Cow::<[u8]>::Borrowed(#input)
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Spotting `strmatch_match!` arms that can never match, because an earlier
+//! arm matches everything they do. Patterns are compared byte by byte, which
+//! only works for their fixed-width parts, so when in doubt an arm counts as
+//! reachable. The same byte-by-byte shape tells a streaming matcher when the
+//! bytes it has so far can't be the start of a match, whether arms can be
+//! reordered without changing which one matches, and which arms are worth
+//! trying for input starting with a given byte.
+
+use crate::pattern::{Capture, Pattern};
+use crate::presets::Preset;
+
+/// A set of byte values.
+#[derive(Clone, Copy)]
+struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    const ALL: ByteSet = ByteSet([u64::MAX; 4]);
+
+    fn range(start: u8, end: u8) -> ByteSet {
+        let mut set = ByteSet([0; 4]);
+        for byte in start..=end {
+            set.0[usize::from(byte / 64)] |= 1 << (byte % 64);
+        }
+        set
+    }
+
+    fn union(self, other: ByteSet) -> ByteSet {
+        let mut set = self;
+        set.0.iter_mut().zip(other.0).for_each(|(a, b)| *a |= b);
+        set
+    }
+
+    fn is_subset(self, other: ByteSet) -> bool {
+        self.0.iter().zip(other.0).all(|(a, b)| a & !b == 0)
+    }
+
+    fn is_disjoint(self, other: ByteSet) -> bool {
+        self.0.iter().zip(other.0).all(|(a, b)| a & b == 0)
+    }
+
+    fn is_single(self) -> bool {
+        self.0.iter().map(|word| word.count_ones()).sum::<u32>() == 1
+    }
+}
+
+/// The bytes each position of a pattern can match, and whether it goes on
+/// to match anything after them.
+struct Shape {
+    positions: Vec<ByteSet>,
+    open: bool,
+}
+
+impl Shape {
+    // Whether every input `other` matches is also matched by `self`.
+    fn covers(&self, other: &Shape) -> bool {
+        let n = self.positions.len();
+        let long_enough = if self.open {
+            other.positions.len() >= n
+        } else {
+            !other.open && other.positions.len() == n
+        };
+        long_enough
+            && other.positions[..n]
+                .iter()
+                .zip(&self.positions)
+                .all(|(theirs, ours)| theirs.is_subset(*ours))
+    }
+
+    // Whether no input is matched by both `self` and `other`, which needs a
+    // byte they can't agree on, or one to need more bytes than the other
+    // can have.
+    fn is_disjoint(&self, other: &Shape) -> bool {
+        let too_short = |a: &Shape, b: &Shape| {
+            !a.open && a.positions.len() < b.positions.len()
+        };
+        too_short(self, other)
+            || too_short(other, self)
+            || self
+                .positions
+                .iter()
+                .zip(&other.positions)
+                .any(|(ours, theirs)| ours.is_disjoint(*theirs))
+    }
+
+    // How many bytes at the start can each only be one thing.
+    fn literal_prefix(&self) -> usize {
+        self.positions
+            .iter()
+            .take_while(|set| set.is_single())
+            .count()
+    }
+}
+
+// The shape of `pattern`. When `covering`, it must not claim more than the
+// pattern matches, so anything unknown gives `None`; otherwise it must not
+// claim less, so unknown bytes can be anything and an unknown width means
+// anything can follow.
+fn shape(pattern: &Pattern, covering: bool) -> Option<Shape> {
+    // A `min_len` turns away inputs the bytes alone would match
+    if covering && pattern.min_len.is_some() {
+        return None;
+    }
+    let mut positions = vec![];
+    for capture in &pattern.literals {
+        let capture = match capture {
+            Capture::Guarded { .. } if covering => return None,
+            Capture::Guarded { capture, .. } => capture,
+            capture => capture,
+        };
+        match capture {
+            _ if capture.is_variable() => {
+                if covering {
+                    return None;
+                }
+                return Some(Shape {
+                    positions,
+                    open: true,
+                });
+            }
+            Capture::Ident(_) | Capture::Underscore(_) => {
+                positions.push(ByteSet::ALL)
+            }
+            Capture::Var { .. } | Capture::Const { .. } if covering => {
+                return None
+            }
+            Capture::Var { .. } | Capture::Const { .. } => {
+                positions.push(ByteSet::ALL)
+            }
+            Capture::NoCase { literal, .. } => {
+                let bytes = literal.literal_bytes()?;
+                positions.extend(bytes.iter().map(|b| {
+                    let lower = b.to_ascii_lowercase();
+                    let upper = b.to_ascii_uppercase();
+                    ByteSet::range(lower, lower)
+                        .union(ByteSet::range(upper, upper))
+                }));
+            }
+            Capture::ByteRange { start, end, .. } => {
+                let start = start.literal_bytes()?[0];
+                let end = match end {
+                    Some(end) => end.literal_bytes()?[0],
+                    None => start,
+                };
+                positions.push(ByteSet::range(start, end));
+            }
+            Capture::Preset {
+                preset: Preset::HexByte,
+                ..
+            } => {
+                let hexdig = ByteSet::range(b'0', b'9')
+                    .union(ByteSet::range(b'a', b'f'))
+                    .union(ByteSet::range(b'A', b'F'));
+                positions.extend([hexdig, hexdig]);
+            }
+            literal => {
+                let bytes = literal.literal_bytes()?;
+                positions.extend(bytes.iter().map(|&b| ByteSet::range(b, b)));
+            }
+        }
+    }
+    Some(Shape {
+        positions,
+        open: pattern.end.is_some(),
+    })
+}
+
+/// The bytes each position at the start of `pattern` can match, as bitsets
+/// of 4 `u64`s, and whether anything can follow them. Every match starts with
+/// bytes in these sets, though not everything that does is a match.
+#[cfg(feature = "runtime")]
+pub(crate) fn prefix(pattern: &Pattern) -> (Vec<[u64; 4]>, bool) {
+    match shape(pattern, false) {
+        Some(shape) => {
+            let positions = shape.positions.iter().map(|set| set.0).collect();
+            (positions, shape.open)
+        }
+        None => (vec![], true),
+    }
+}
+
+/// The bytes a match of any of `patterns` can start with, as a bitset of 4
+/// `u64`s, and whether they can match empty input.
+pub(crate) fn first_bytes(patterns: &[Pattern]) -> ([u64; 4], bool) {
+    patterns
+        .iter()
+        .map(|pattern| match shape(pattern, false) {
+            Some(Shape { positions, open }) => match positions.first() {
+                Some(first) => (*first, false),
+                None if open => (ByteSet::ALL, true),
+                None => (ByteSet([0; 4]), true),
+            },
+            None => (ByteSet::ALL, true),
+        })
+        .fold(([0; 4], false), |(set, empty), (first, can)| {
+            (ByteSet(set).union(first).0, empty || can)
+        })
+}
+
+/// For each arm, given its alternatives (`None` for `_`) and whether it has a
+/// guard, the index of an earlier arm that always matches first, if any.
+pub(crate) fn shadowing(
+    arms: &[(Option<&[Pattern]>, bool)],
+) -> Vec<Option<usize>> {
+    let mut covering: Vec<(usize, Shape)> = vec![];
+    let mut shadowed = vec![];
+    for (index, (patterns, guarded)) in arms.iter().enumerate() {
+        let patterns = match patterns {
+            Some(patterns) => patterns,
+            // Nothing comes after a `_` arm
+            None => {
+                shadowed.push(None);
+                continue;
+            }
+        };
+        // Every alternative has to be covered, and the message names the arm
+        // covering the first one
+        let by = patterns
+            .iter()
+            .map(|pattern| {
+                let shape = shape(pattern, false)?;
+                covering
+                    .iter()
+                    .find(|(_, cover)| cover.covers(&shape))
+                    .map(|(arm, _)| *arm)
+            })
+            .collect::<Option<Vec<_>>>();
+        shadowed.push(by.map(|arms| arms[0]));
+        if !guarded {
+            covering.extend(
+                patterns
+                    .iter()
+                    .filter_map(|pattern| shape(pattern, true))
+                    .map(|shape| (index, shape)),
+            );
+        }
+    }
+    shadowed
+}
+
+/// The order `#[reorder]` tries arms in: the ones starting with longer
+/// literals first, keeping the order of arms that start the same. A `_ if`
+/// arm stays where it is, and arms aren't moved past it. Moving an arm ahead
+/// of one that could match the same input would change what the `match`
+/// does, so that gives the indices of the arm and the one it would pass.
+pub(crate) fn reorder(
+    arms: &[Option<&[Pattern]>],
+) -> Result<Vec<usize>, (usize, usize)> {
+    // The shortest literal start of any alternative, which is how specific
+    // the whole arm is
+    let specificity = |patterns: &[Pattern]| {
+        patterns
+            .iter()
+            .map(|pattern| {
+                shape(pattern, false).map_or(0, |shape| shape.literal_prefix())
+            })
+            .min()
+            .unwrap_or(0)
+    };
+    let overlap = |a: &[Pattern], b: &[Pattern]| {
+        a.iter().any(|a| {
+            b.iter().any(|b| match (shape(a, false), shape(b, false)) {
+                (Some(a), Some(b)) => !a.is_disjoint(&b),
+                _ => true,
+            })
+        })
+    };
+    let mut order = vec![];
+    let mut start = 0;
+    for end in 0..=arms.len() {
+        if end < arms.len() && arms[end].is_some() {
+            continue;
+        }
+        let mut run = (start..end).collect::<Vec<_>>();
+        run.sort_by_key(|&i| std::cmp::Reverse(arms[i].map(specificity)));
+        for (at, &moved) in run.iter().enumerate() {
+            // Every arm after this one that used to come before it
+            for &passed in &run[at + 1..] {
+                if passed < moved
+                    && overlap(arms[moved].unwrap(), arms[passed].unwrap())
+                {
+                    return Err((moved, passed));
+                }
+            }
+        }
+        order.extend(run);
+        order.extend(arms.get(end).map(|_| end));
+        start = end + 1;
+    }
+    Ok(order)
+}
@@ -0,0 +1,98 @@
+//! Parsing and expansion for `strsniff!`, which reads just enough of a
+//! `std::io::Read` into a stack buffer to tell whether a pattern matches.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Token};
+
+use crate::engine::{hidden, matcher, Codegen};
+use crate::pattern::Pattern;
+
+/// `reader, pattern`
+pub(crate) struct SniffInput {
+    reader: Expr,
+    pattern: Pattern,
+}
+
+impl Parse for SniffInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let reader = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let pattern = input.parse::<Pattern>()?;
+        // The buffer is gone once the macro is done, so nothing can borrow it
+        if let Some(binding) = pattern.bindings().first() {
+            abort!(
+                binding,
+                "`strsniff!` can't bind `{}`, since the bytes it reads only \
+                 live while it runs", binding;
+                help = "use `_` to ignore a byte, or `[_]` to ignore the rest"
+            );
+        }
+        Ok(SniffInput { reader, pattern })
+    }
+}
+
+impl ToTokens for SniffInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let mut len = 0;
+        for capture in &self.pattern.literals {
+            match capture.width() {
+                Some(width) => len += width,
+                None => abort!(
+                    capture.span(),
+                    "`strsniff!` has to know how many bytes to read, so only \
+                     the last capture can match a varying number of them"
+                ),
+            }
+        }
+        // Without a tail the pattern has to match everything there is, which
+        // takes reading one byte more to be sure of
+        if self.pattern.end.is_none() {
+            len += 1;
+        }
+        // And a tail might have to be long enough to reach `min_len`
+        len += self.pattern.check_min_len();
+        let input = hidden("input");
+        let buf = hidden("buf");
+        let fill = hidden("fill");
+        let read = hidden("read");
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            Codegen::default(),
+        );
+        let reader = &self.reader;
+        tokens.extend(quote! {{
+            // Reads until `buf` is full or the reader runs out
+            fn #fill<R: ::std::io::Read>(
+                mut reader: R,
+                buf: &mut [u8],
+            ) -> ::std::io::Result<usize> {
+                let mut filled = 0;
+                while filled < buf.len() {
+                    match reader.read(&mut buf[filled..]) {
+                        ::core::result::Result::Ok(0) => break,
+                        ::core::result::Result::Ok(n) => filled += n,
+                        ::core::result::Result::Err(e)
+                            if e.kind() == ::std::io::ErrorKind::Interrupted => {}
+                        ::core::result::Result::Err(e) => {
+                            return ::core::result::Result::Err(e)
+                        }
+                    }
+                }
+                ::core::result::Result::Ok(filled)
+            }
+            let mut #buf = [0u8; #len];
+            match #fill(#reader, &mut #buf) {
+                ::core::result::Result::Ok(#read) => {
+                    let #input: &[u8] = &#buf[..#read];
+                    ::core::result::Result::Ok(#matcher.is_some())
+                }
+                ::core::result::Result::Err(e) => ::core::result::Result::Err(e),
+            }
+        }});
+    }
+}
@@ -0,0 +1,201 @@
+//! `strpattern!` and `@sub(NAME)` segments, which splice a pattern defined
+//! once into the patterns that use it.
+//!
+//! A proc macro only sees its own tokens, so it can't look up `NAME` itself.
+//! Instead, `strpattern!` defines `NAME` as a `macro_rules!` macro that hands
+//! its pattern to a callback. A macro that finds `@sub(NAME)` in its input
+//! expands to a call to `NAME!`, whose callback, `__strmatch_sub!`, splices
+//! the pattern in and runs the macro again on the result.
+
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+use proc_macro_error::abort;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::{braced, bracketed, Attribute, Ident, Path, Token};
+
+use crate::pattern::Pattern;
+
+/// `NAME = pattern`, with any attributes, like doc comments
+pub(crate) struct PatternDef {
+    attrs: Vec<Attribute>,
+    name: Ident,
+    pattern: TokenStream,
+}
+
+impl Parse for PatternDef {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let pattern = input.parse::<TokenStream>()?;
+        // Check the pattern now, so mistakes point here rather than at each
+        // place it's used. One using other sub-patterns can only be checked
+        // once they've been spliced in.
+        if find(&pattern).is_none() {
+            syn::parse2::<Pattern>(pattern.clone())?;
+        }
+        Ok(PatternDef {
+            attrs,
+            name,
+            pattern,
+        })
+    }
+}
+
+impl ToTokens for PatternDef {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let PatternDef {
+            attrs,
+            name,
+            pattern,
+        } = self;
+        tokens.extend(quote! {
+            #(#attrs)*
+            macro_rules! #name {
+                (@callback [$($callback:tt)*] $($args:tt)*) => {
+                    $($callback)*! { { #pattern } $($args)* }
+                };
+            }
+            #[allow(unused_imports)]
+            pub(crate) use #name;
+        });
+    }
+}
+
+/// If `tokens` use a sub-pattern, the expansion that looks it up and runs
+/// the macro called `name` again once it's spliced in.
+pub(crate) fn splice(
+    name: &str,
+    tokens: &proc_macro::TokenStream,
+) -> Option<proc_macro::TokenStream> {
+    let tokens = TokenStream::from(tokens.clone());
+    let path = find(&tokens)?;
+    let name = Ident::new(name, proc_macro2::Span::call_site());
+    // `strmatcher!` is used through `strmatch-runtime`, where `strmatch`
+    // might not be a dependency
+    let krate = if name == "strmatcher" {
+        quote!(::strmatch_runtime)
+    } else {
+        quote!(::strmatch)
+    };
+    Some(
+        quote! {
+            #path! {
+                @callback [#krate::__strmatch_sub]
+                #krate::#name [#path] { #tokens }
+            }
+        }
+        .into(),
+    )
+}
+
+// The path in the first `@sub(path)` in `tokens`, looking inside groups too
+fn find(tokens: &TokenStream) -> Option<Path> {
+    let tokens = tokens.clone().into_iter().collect::<Vec<_>>();
+    for (i, token) in tokens.iter().enumerate() {
+        if let Some(group) = sub_at(&tokens, i) {
+            return match syn::parse2::<Path>(group.stream()) {
+                Ok(path) => Some(path),
+                Err(_) => abort!(
+                    group.span(),
+                    "expected the name of a pattern defined with `strpattern!`"
+                ),
+            };
+        }
+        if let TokenTree::Group(group) = token {
+            if let Some(path) = find(&group.stream()) {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+// The parentheses of the `@sub(..)` starting at `tokens[i]`, if one does. An
+// `@` right after a name binds it rather than starting a sub-pattern.
+fn sub_at(tokens: &[TokenTree], i: usize) -> Option<&Group> {
+    let binds = match i.checked_sub(1).map(|i| &tokens[i]) {
+        Some(TokenTree::Ident(name)) => name != "_",
+        _ => false,
+    };
+    match &tokens[i..] {
+        [TokenTree::Punct(at), TokenTree::Ident(sub), TokenTree::Group(group), ..]
+            if at.as_char() == '@'
+                && sub == "sub"
+                && group.delimiter() == Delimiter::Parenthesis
+                && !binds =>
+        {
+            Some(group)
+        }
+        _ => None,
+    }
+}
+
+// Replace every `@sub(path)` in `tokens` with `pattern`, comparing paths as
+// printed
+fn replace(
+    tokens: TokenStream,
+    path: &str,
+    pattern: &TokenStream,
+) -> TokenStream {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut replaced = TokenStream::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match sub_at(&tokens, i) {
+            Some(group) if group.stream().to_string() == path => {
+                replaced.extend(pattern.clone());
+                i += 3;
+                continue;
+            }
+            _ => {}
+        }
+        match &tokens[i] {
+            TokenTree::Group(group) => {
+                let stream = replace(group.stream(), path, pattern);
+                let mut new = Group::new(group.delimiter(), stream);
+                new.set_span(group.span());
+                replaced.extend([TokenTree::Group(new)]);
+            }
+            token => replaced.extend([token.clone()]),
+        }
+        i += 1;
+    }
+    replaced
+}
+
+/// `{ pattern } macro [path] { tokens }`, which `__strmatch_sub!` is called
+/// with once `path!` has looked up the pattern
+pub(crate) struct SubInput {
+    pattern: TokenStream,
+    callee: Path,
+    path: Path,
+    tokens: TokenStream,
+}
+
+impl Parse for SubInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let pattern;
+        braced!(pattern in input);
+        let callee = input.parse()?;
+        let path;
+        bracketed!(path in input);
+        let tokens;
+        braced!(tokens in input);
+        Ok(SubInput {
+            pattern: pattern.parse()?,
+            callee,
+            path: path.parse()?,
+            tokens: tokens.parse()?,
+        })
+    }
+}
+
+impl ToTokens for SubInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let callee = &self.callee;
+        let path = self.path.to_token_stream().to_string();
+        let replaced = replace(self.tokens.clone(), &path, &self.pattern);
+        tokens.extend(quote!(#callee! { #replaced }));
+    }
+}
@@ -0,0 +1,160 @@
+//! Expansion for `#[strmatch_try_from]`, which implements `TryFrom<&[u8]>`
+//! and `TryFrom<&str>` for a struct from a pattern whose captures are named
+//! after its fields, with an error saying which segment didn't match.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    Fields, GenericParam, Ident, ItemStruct, Lifetime, LifetimeDef, Type,
+};
+
+use crate::engine::{hidden, matcher, Codegen};
+use crate::from_str::{fill_field_types, named_fields, typed_fields};
+use crate::pattern::{Capture, Pattern};
+
+// How the error names a segment: a literal by its bytes, and anything else
+// by what it binds
+fn describe(index: usize, capture: &Capture) -> String {
+    if let Some(bytes) = capture.literal_bytes() {
+        return format!("\"{}\"", bytes.escape_ascii());
+    }
+    let bindings = capture.bindings();
+    if bindings.is_empty() {
+        return format!("segment {index}");
+    }
+    let names = bindings.iter().map(|name| format!("`{name}`"));
+    names.collect::<Vec<_>>().join(", ")
+}
+
+/// Expand to an error type for the struct and its `TryFrom` impls. The
+/// pattern is parsed from `tokens` again for each of its prefixes, which are
+/// tried in turn to find the first segment that fails.
+pub(crate) fn try_from(tokens: TokenStream, item: &ItemStruct) -> TokenStream {
+    let fields = named_fields(item, "strmatch_try_from");
+    let parse = |fields: &[(Ident, Type)]| {
+        let mut pattern = syn::parse2::<Pattern>(tokens.clone())
+            .expect("the pattern was parsed once already");
+        fill_field_types(&mut pattern, fields);
+        pattern
+    };
+    let mut pattern = parse(&fields);
+    typed_fields(&mut pattern, &fields, item);
+    let bindings = pattern.bindings();
+
+    let name = &item.ident;
+    let vis = &item.vis;
+    let error = format_ident!("TryFrom{}Error", name);
+    let error_doc = format!(
+        "The error returned when converting bytes into a `{name}` fails."
+    );
+    let input = hidden("input");
+    let rest = hidden("rest");
+    let offset = hidden("offset");
+    let whole = matcher(
+        &input,
+        std::slice::from_ref(&pattern),
+        None,
+        Codegen::default(),
+    );
+    // Each prefix of the pattern, up to and including the segment it's
+    // checking, matched against the start of the input
+    let prefixes = (0..pattern.literals.len()).map(|index| {
+        let mut prefix = parse(&fields);
+        let segment = describe(index, &prefix.literals[index]);
+        prefix.literals.truncate(index + 1);
+        prefix.whole = None;
+        prefix.min_len = None;
+        prefix.end = None;
+        prefix.capture_rest(rest.clone(), Span::call_site());
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&prefix),
+            None,
+            Codegen::default(),
+        );
+        quote! {
+            match #matcher {
+                ::core::option::Option::Some((.., #rest,)) => {
+                    #offset = #input.len() - #rest.len();
+                }
+                ::core::option::Option::None => {
+                    return ::core::result::Result::Err(#error {
+                        offset: #offset,
+                        segment: ::core::option::Option::Some(#segment),
+                    });
+                }
+            }
+        }
+    });
+    let value = match &item.fields {
+        Fields::Unit => quote!(#name),
+        _ => quote!(#name { #(#bindings,)* }),
+    };
+    let a = Lifetime::new("'__strmatch_a", Span::call_site());
+    let mut generics = item.generics.clone();
+    generics
+        .params
+        .insert(0, GenericParam::Lifetime(LifetimeDef::new(a.clone())));
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = item.generics.split_for_impl();
+    quote! {
+        #[doc = #error_doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #vis struct #error {
+            offset: usize,
+            segment: ::core::option::Option<&'static str>,
+        }
+
+        impl #error {
+            /// How many bytes of the input matched before the failure.
+            #vis fn offset(&self) -> usize {
+                self.offset
+            }
+
+            /// The segment of the pattern that didn't match at `offset`, or
+            /// `None` if the pattern matched but the input went on after it.
+            #vis fn segment(&self) -> ::core::option::Option<&'static str> {
+                self.segment
+            }
+        }
+
+        impl ::core::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self.segment {
+                    ::core::option::Option::Some(segment) => {
+                        ::core::write!(f, "expected {} at byte {}", segment, self.offset)
+                    }
+                    ::core::option::Option::None => {
+                        ::core::write!(f, "unexpected bytes after the end at byte {}", self.offset)
+                    }
+                }
+            }
+        }
+
+        impl ::std::error::Error for #error {}
+
+        impl #impl_generics ::core::convert::TryFrom<&#a [u8]> for #name #ty_generics #where_clause {
+            type Error = #error;
+
+            fn try_from(#input: &#a [u8]) -> ::core::result::Result<Self, Self::Error> {
+                if let ::core::option::Option::Some((#(#bindings,)*)) = #whole {
+                    return ::core::result::Result::Ok(#value);
+                }
+                let mut #offset = 0;
+                #(#prefixes)*
+                ::core::result::Result::Err(#error {
+                    offset: #offset,
+                    segment: ::core::option::Option::None,
+                })
+            }
+        }
+
+        impl #impl_generics ::core::convert::TryFrom<&#a str> for #name #ty_generics #where_clause {
+            type Error = #error;
+
+            fn try_from(#input: &#a str) -> ::core::result::Result<Self, Self::Error> {
+                ::core::convert::TryFrom::try_from(#input.as_bytes())
+            }
+        }
+    }
+}
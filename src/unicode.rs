@@ -0,0 +1,116 @@
+//! Runs of Unicode classes like `unicode_alpha+`, behind the `unicode`
+//! feature. The generated code decodes UTF-8 a character at a time and looks
+//! each one up in a table of ranges, built at compile time from what the
+//! standard library knows about Unicode.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Ident;
+
+use crate::engine::hidden;
+
+/// The characters a run like `unicode_alpha+` is made of.
+#[derive(Clone, Copy)]
+pub(crate) enum Category {
+    /// `unicode_alpha`: characters with the `Alphabetic` property
+    Alpha,
+    /// `unicode_digit`: characters in the number categories, `Nd`, `Nl` and
+    /// `No`
+    Digit,
+    /// `unicode_alnum`: either of those
+    Alnum,
+    /// `unicode_upper`: characters with the `Uppercase` property
+    Upper,
+    /// `unicode_lower`: characters with the `Lowercase` property
+    Lower,
+    /// `unicode_space`: characters with the `White_Space` property
+    Space,
+}
+
+impl Category {
+    pub(crate) fn from_name(name: &str) -> Option<Category> {
+        let category = match name {
+            "unicode_alpha" => Category::Alpha,
+            "unicode_digit" => Category::Digit,
+            "unicode_alnum" => Category::Alnum,
+            "unicode_upper" => Category::Upper,
+            "unicode_lower" => Category::Lower,
+            "unicode_space" => Category::Space,
+            _ => return None,
+        };
+        Some(category)
+    }
+
+    /// The inclusive ranges of code points in the category, in order. Each
+    /// one is all `char`s, since surrogates aren't in any category.
+    pub(crate) fn ranges(self) -> Vec<(u32, u32)> {
+        let test: fn(char) -> bool = match self {
+            Category::Alpha => char::is_alphabetic,
+            Category::Digit => char::is_numeric,
+            Category::Alnum => char::is_alphanumeric,
+            Category::Upper => char::is_uppercase,
+            Category::Lower => char::is_lowercase,
+            Category::Space => char::is_whitespace,
+        };
+        let mut ranges: Vec<(u32, u32)> = vec![];
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            if !test(c) {
+                continue;
+            }
+            let c = c as u32;
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == c => *end = c,
+                _ => ranges.push((c, c)),
+            }
+        }
+        ranges
+    }
+
+    /// An expression giving how many bytes at the start of `input` are
+    /// characters in the category. It stops at the first byte that doesn't
+    /// start a valid UTF-8 character.
+    pub(crate) fn scan(self, index: usize, input: &Ident) -> TokenStream {
+        let ranges = self.ranges();
+        let count = ranges.len();
+        let ranges = ranges.iter().map(|(lo, hi)| quote!((#lo, #hi)));
+        let len = hidden(&format!("len{index}"));
+        let rest = hidden("rest");
+        let width = hidden("width");
+        let str = hidden("str");
+        let char = hidden("char");
+        quote! {{
+            static RANGES: [(u32, u32); #count] = [#(#ranges),*];
+            let mut #len = 0;
+            loop {
+                let #rest = &#input[#len..];
+                let #width = match #rest.first() {
+                    ::core::option::Option::Some(0x00..=0x7f) => 1,
+                    ::core::option::Option::Some(0xc2..=0xdf) => 2,
+                    ::core::option::Option::Some(0xe0..=0xef) => 3,
+                    ::core::option::Option::Some(0xf0..=0xf4) => 4,
+                    _ => break,
+                };
+                let ::core::option::Option::Some(::core::result::Result::Ok(#str)) =
+                    #rest.get(..#width).map(::core::str::from_utf8)
+                else {
+                    break;
+                };
+                let #char = #str.chars().next().unwrap() as u32;
+                let found = RANGES.binary_search_by(|&(lo, hi)| {
+                    if hi < #char {
+                        ::core::cmp::Ordering::Less
+                    } else if lo > #char {
+                        ::core::cmp::Ordering::Greater
+                    } else {
+                        ::core::cmp::Ordering::Equal
+                    }
+                });
+                if found.is_err() {
+                    break;
+                }
+                #len += #width;
+            }
+            #len
+        }}
+    }
+}
@@ -0,0 +1,69 @@
+//! Parsing and expansion for `strmatch_winnow!`, which turns a pattern into
+//! a `winnow` parser.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+
+use crate::engine::{hidden, matcher, Codegen};
+use crate::pattern::Pattern;
+
+/// `pattern`, optionally preceded by a `#[codegen(..)]` attribute
+pub(crate) struct WinnowInput {
+    codegen: Codegen,
+    pattern: Pattern,
+}
+
+impl Parse for WinnowInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let codegen = Codegen::parse_attrs(input)?;
+        let mut pattern: Pattern = input.parse()?;
+        // The parser moves the input past what it matched
+        pattern.capture_rest(hidden("rest"), Span::call_site());
+        Ok(WinnowInput { codegen, pattern })
+    }
+}
+
+impl ToTokens for WinnowInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let input = hidden("input");
+        let matcher = matcher(
+            &input,
+            std::slice::from_ref(&self.pattern),
+            None,
+            self.codegen,
+        );
+        let mut bindings = self.pattern.bindings();
+        let rest = bindings.pop();
+        let output = match bindings.as_slice() {
+            [binding] => quote!(#binding),
+            bindings => quote!((#(#bindings,)*)),
+        };
+        let parser = hidden("parser");
+        let stream = hidden("stream");
+        // Like for `strmatch_nom!`, the function's bounds give the closure a
+        // signature tying its output to the input's lifetime
+        tokens.extend(quote! {{
+            fn #parser<'a, O, F>(parser: F) -> F
+            where
+                F: ::core::ops::Fn(
+                    &mut &'a [u8],
+                ) -> ::winnow::error::ModalResult<O>,
+            {
+                parser
+            }
+            #parser(|#stream| {
+                let #input: &[u8] = *#stream;
+                match #matcher {
+                    ::core::option::Option::Some((#(#bindings,)* #rest,)) => {
+                        *#stream = #rest;
+                        ::core::result::Result::Ok(#output)
+                    }
+                    ::core::option::Option::None => ::core::result::Result::Err(
+                        ::winnow::error::ParserError::from_input(#stream),
+                    ),
+                }
+            })
+        }});
+    }
+}